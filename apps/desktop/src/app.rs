@@ -14,6 +14,7 @@ use crate::playback_selector::{PlaybackSelector, PlaybackSource, ProxyMode};
 use crate::prompt_normalize::normalize_prompt_in_place;
 use crate::proxy_policy::should_proxy;
 use crate::proxy_queue::{ProxyReason, ProxyStatus};
+use project::AutoProxySetting;
 use serde::{Deserialize, Serialize};
 use std::fmt::Write as FmtWrite;
 use std::hash::{Hash, Hasher};
@@ -447,44 +448,21 @@ enum StoryboardPendingInputRefresh {
     Keys(std::collections::HashSet<String>),
 }
 
-#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
-#[serde(rename_all = "kebab-case")]
-enum AutoProxySetting {
-    Off,
-    LargeOnly,
-    All,
-}
-
-impl Default for AutoProxySetting {
-    fn default() -> Self {
-        AutoProxySetting::LargeOnly
-    }
-}
-
-impl AutoProxySetting {
-    fn display_name(self) -> &'static str {
-        match self {
-            AutoProxySetting::Off => "Off",
-            AutoProxySetting::LargeOnly => "Large Only",
-            AutoProxySetting::All => "All Clips",
-        }
-    }
-
-    fn should_queue_proxy(
-        self,
-        is_video: bool,
-        media_info: Option<&MediaInfoData>,
-        hardware: &HardwareCaps,
-    ) -> bool {
-        match self {
-            AutoProxySetting::Off => false,
-            AutoProxySetting::All => is_video,
-            AutoProxySetting::LargeOnly => {
-                is_video
-                    && media_info
-                        .map(|info| should_proxy(info, hardware))
-                        .unwrap_or(false)
-            }
+/// Whether the auto-proxy setting wants a proxy queued for this media.
+fn auto_proxy_wanted(
+    setting: AutoProxySetting,
+    is_video: bool,
+    media_info: Option<&MediaInfoData>,
+    hardware: &HardwareCaps,
+) -> bool {
+    match setting {
+        AutoProxySetting::Off => false,
+        AutoProxySetting::All => is_video,
+        AutoProxySetting::LargeOnly => {
+            is_video
+                && media_info
+                    .map(|info| should_proxy(info, hardware))
+                    .unwrap_or(false)
         }
     }
 }
@@ -514,34 +492,33 @@ impl ViewerScale {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct PersistedProxySettings {
-    #[serde(default)]
-    proxy_mode: ProxyMode,
-    #[serde(default)]
-    auto_proxy: AutoProxySetting,
+struct CachedAssetEntry {
+    asset: project::AssetRow,
+    /// Valid proxy and transcode variants whose files are there.
+    variants: Vec<project::ProxyVariant>,
+    last_refresh: Instant,
 }
 
-impl Default for PersistedProxySettings {
-    fn default() -> Self {
+impl CachedAssetEntry {
+    fn load(db: &ProjectDb, asset: project::AssetRow) -> Self {
+        let variants = match db.proxy_variants(&asset.id) {
+            Ok(variants) => variants
+                .into_iter()
+                .filter(|v| v.valid && std::path::Path::new(&v.path_abs).is_file())
+                .collect(),
+            Err(err) => {
+                tracing::warn!(error = %err, asset = %asset.id, "failed to list proxy variants");
+                Vec::new()
+            }
+        };
         Self {
-            proxy_mode: ProxyMode::OriginalOptimized,
-            auto_proxy: AutoProxySetting::LargeOnly,
+            asset,
+            variants,
+            last_refresh: Instant::now(),
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
-struct PersistedComfySettings {
-    #[serde(default)]
-    api_key: String,
-}
-
-struct CachedAssetEntry {
-    asset: project::AssetRow,
-    last_refresh: Instant,
-}
-
 struct PlaybackPathDecision {
     decode_path: String,
     asset: Option<project::AssetRow>,
@@ -1316,6 +1293,9 @@ struct App {
     audio_out: Option<audio_engine::AudioEngine>,
     selected: Option<(usize, usize)>,
     drag: Option<DragState>,
+    edit_marks: EditMarks,
+    // Track settings being dragged in the Tracks menu, not yet applied.
+    track_props_draft: Option<(timeline_crate::TrackId, timeline_crate::TrackProperties)>,
     export: ExportUiState,
     import_workers: Vec<std::thread::JoinHandle<()>>,
     jobs: Option<jobs_crate::JobsHandle>,
@@ -1333,6 +1313,8 @@ struct App {
     cache_manager: CacheManager,
     cache_events: std::sync::mpsc::Receiver<CacheEvent>,
     cache_job_status: std::collections::HashMap<CacheJobId, crate::cache::job::JobStatus>,
+    /// Asset each optimized media job was queued for, to record its output.
+    cache_job_assets: std::collections::HashMap<CacheJobId, String>,
     viewer_scale: ViewerScale,
     playback_lag_frames: u32,
     playback_stable_frames: u32,
@@ -1505,6 +1487,37 @@ struct App {
     storyboard_batch_busy: bool,
     // Autosave indicator
     last_save_at: Option<Instant>,
+    // Timeline edits journaled since the last full snapshot
+    journal_since_save: usize,
+    // Journal left behind by a session that did not save; awaiting the user
+    pending_recovery: Option<Vec<timeline_crate::JournalEntry>>,
+    // Why the open sequence could not be read; it is then shown read-only and never saved
+    sequence_load_error: Option<String>,
+    // Sequence open in the timeline, and the project's sequences for the picker
+    sequence_id: String,
+    sequences: Vec<project::SequenceRow>,
+    sequence_name_draft: Option<String>,
+    // Project snapshots window
+    show_snapshots: bool,
+    snapshot_name: String,
+    snapshot_comparison: Option<(String, Vec<String>)>,
+    last_autosave_snapshot_at: Option<Instant>,
+    // Project archive window, and the outcome of the last archive or unpack
+    show_archive: bool,
+    archive_options: exporters::archive::ArchiveOptions,
+    archive_status: Option<String>,
+    // Media the last scan found missing or changed, by path, and the relink window
+    offline_media: std::collections::HashMap<String, project::MediaStatus>,
+    show_relink: bool,
+    relink_status: Option<String>,
+    window_focused: bool,
+    // Assets UI: bins, smart collections and the view picked from them
+    asset_view: app_assets::AssetView,
+    bins: Vec<project::BinRow>,
+    smart_collections: Vec<project::SmartCollectionRow>,
+    new_bin_name: String,
+    collection_draft: Option<app_assets::CollectionDraft>,
+    asset_tag_draft: String,
     // Assets UI: cached thumbnail textures
     asset_thumb_textures: std::collections::HashMap<String, egui::TextureHandle>,
     textures_to_free_next_frame: Vec<egui::TextureHandle>,
@@ -1535,6 +1548,9 @@ impl App {
                             output = %path.display(),
                             "optimized media ready"
                         );
+                        if let Some(asset_id) = self.cache_job_assets.remove(&id) {
+                            self.register_optimized_media(&asset_id, path);
+                        }
                     }
                     crate::cache::job::JobStatus::Failed(msg) => {
                         tracing::warn!(
@@ -1543,9 +1559,11 @@ impl App {
                             error = %msg,
                             "optimized media job failed"
                         );
+                        self.cache_job_assets.remove(&id);
                     }
                     crate::cache::job::JobStatus::Canceled => {
                         tracing::info!(target = "cache", id = id.0, "optimized media job canceled");
+                        self.cache_job_assets.remove(&id);
                     }
                     crate::cache::job::JobStatus::Queued
                     | crate::cache::job::JobStatus::InProgress(_) => {}
@@ -1555,6 +1573,33 @@ impl App {
         }
     }
 
+    /// Record finished optimized media as a full-size transcode variant.
+    fn register_optimized_media(&mut self, asset_id: &str, path: &std::path::Path) {
+        let asset = match self.db.get_asset(asset_id) {
+            Ok(asset) => asset,
+            Err(err) => {
+                tracing::warn!(error = %err, asset = %asset_id, "no asset for optimized media");
+                return;
+            }
+        };
+        let settings = serde_json::json!({ "codec": "prores_422", "container": "mov" });
+        let registered = self.db.register_proxy_variant(&project::ProxyVariantInsert {
+            asset_id,
+            kind: project::VariantKind::Transcode,
+            width: asset.width,
+            height: asset.height,
+            codec: Some("prores"),
+            pixel_fmt: None,
+            bitrate_kbps: None,
+            path,
+            settings_hash: &project::variant_settings_hash(&settings),
+        });
+        match registered {
+            Ok(_) => self.refresh_asset_cache_entry(asset_id),
+            Err(err) => tracing::warn!(error = %err, "failed to record optimized media"),
+        }
+    }
+
     fn handle_proxy_event(&mut self, event: crate::proxy_queue::ProxyEvent) {
         let status_clone = event.status.clone();
         self.proxy_status
@@ -1580,10 +1625,7 @@ impl App {
                     );
                     self.asset_cache.insert(
                         asset.src_abs.clone(),
-                        CachedAssetEntry {
-                            asset,
-                            last_refresh: Instant::now(),
-                        },
+                        CachedAssetEntry::load(&self.db, asset),
                     );
                 }
             }
@@ -2396,10 +2438,10 @@ Use one of the keys listed above. Populate the \"inputs\" object with values for
     }
 
     pub(crate) fn load_storyboard_from_settings(&mut self) {
-        match self.db.get_project_settings_json(&self.project_id) {
+        match self.db.get_project_settings(&self.project_id) {
             Ok(settings) => {
-                if let Some(value) = settings.get("storyboard") {
-                    match serde_json::from_value::<PersistedStoryboard>(value.clone()) {
+                if let Some(value) = settings.storyboard {
+                    match serde_json::from_value::<PersistedStoryboard>(value) {
                         Ok(stored) => {
                             self.storyboard_cards = stored.cards;
                             self.storyboard_selected = stored.selected.and_then(|idx| {
@@ -2470,15 +2512,10 @@ Use one of the keys listed above. Populate the \"inputs\" object with values for
                 .map(|(card_id, job)| (*card_id, job.into()))
                 .collect(),
         };
-        let mut settings = self.db.get_project_settings_json(&self.project_id)?;
-        let mut map = match settings {
-            serde_json::Value::Object(map) => map,
-            _ => serde_json::Map::new(),
-        };
-        map.insert("storyboard".to_string(), serde_json::to_value(stored)?);
-        let value = serde_json::Value::Object(map);
+        let mut settings = self.db.get_project_settings(&self.project_id)?;
+        settings.storyboard = Some(serde_json::to_value(stored)?);
         self.db
-            .update_project_settings_json(&self.project_id, &value)?;
+            .update_project_settings(&self.project_id, &settings)?;
         Ok(())
     }
 
@@ -3793,16 +3830,10 @@ Use one of the keys listed above. Populate the \"inputs\" object with values for
     }
 
     fn load_proxy_settings(&mut self) {
-        match self.db.get_project_settings_json(&self.project_id) {
+        match self.db.get_project_settings(&self.project_id) {
             Ok(settings) => {
-                if let Some(value) = settings.get("proxy") {
-                    if let Ok(stored) =
-                        serde_json::from_value::<PersistedProxySettings>(value.clone())
-                    {
-                        self.proxy_mode_user = stored.proxy_mode;
-                        self.auto_proxy_setting = stored.auto_proxy;
-                    }
-                }
+                self.proxy_mode_user = settings.proxy.mode;
+                self.auto_proxy_setting = settings.proxy.auto_proxy;
             }
             Err(err) => {
                 eprintln!("Failed to load proxy settings: {err}");
@@ -3812,18 +3843,8 @@ Use one of the keys listed above. Populate the \"inputs\" object with values for
     }
 
     fn load_comfy_settings(&mut self) {
-        match self.db.get_project_settings_json(&self.project_id) {
-            Ok(settings) => {
-                if let Some(value) = settings.get("comfy") {
-                    if let Ok(stored) =
-                        serde_json::from_value::<PersistedComfySettings>(value.clone())
-                    {
-                        self.comfy_api_key = stored.api_key.clone();
-                        return;
-                    }
-                }
-                self.comfy_api_key.clear();
-            }
+        match self.db.get_project_settings(&self.project_id) {
+            Ok(settings) => self.comfy_api_key = settings.comfy.api_key,
             Err(err) => {
                 eprintln!("Failed to load ComfyUI settings: {err}");
                 self.comfy_api_key.clear();
@@ -3832,36 +3853,22 @@ Use one of the keys listed above. Populate the \"inputs\" object with values for
     }
 
     fn persist_proxy_settings(&mut self) -> anyhow::Result<()> {
-        let mut settings = self.db.get_project_settings_json(&self.project_id)?;
-        let mut map = match settings {
-            serde_json::Value::Object(map) => map,
-            _ => serde_json::Map::new(),
-        };
-        let stored = PersistedProxySettings {
-            proxy_mode: self.proxy_mode_user,
+        let mut settings = self.db.get_project_settings(&self.project_id)?;
+        settings.proxy = project::ProxySettings {
+            mode: self.proxy_mode_user,
             auto_proxy: self.auto_proxy_setting,
         };
-        map.insert("proxy".to_string(), serde_json::to_value(stored)?);
-        let value = serde_json::Value::Object(map);
         self.db
-            .update_project_settings_json(&self.project_id, &value)?;
+            .update_project_settings(&self.project_id, &settings)?;
         Ok(())
     }
 
     #[allow(dead_code)]
     fn persist_comfy_settings(&mut self) -> anyhow::Result<()> {
-        let mut settings = self.db.get_project_settings_json(&self.project_id)?;
-        let mut map = match settings {
-            serde_json::Value::Object(map) => map,
-            _ => serde_json::Map::new(),
-        };
-        let stored = PersistedComfySettings {
-            api_key: self.comfy_api_key.clone(),
-        };
-        map.insert("comfy".to_string(), serde_json::to_value(stored)?);
-        let value = serde_json::Value::Object(map);
+        let mut settings = self.db.get_project_settings(&self.project_id)?;
+        settings.comfy.api_key = self.comfy_api_key.clone();
         self.db
-            .update_project_settings_json(&self.project_id, &value)?;
+            .update_project_settings(&self.project_id, &settings)?;
         Ok(())
     }
 
@@ -3878,13 +3885,8 @@ Use one of the keys listed above. Populate the \"inputs\" object with values for
         }
         match self.db.find_asset_by_path(&self.project_id, path) {
             Ok(Some(asset)) => {
-                self.asset_cache.insert(
-                    key,
-                    CachedAssetEntry {
-                        asset: asset.clone(),
-                        last_refresh: Instant::now(),
-                    },
-                );
+                self.asset_cache
+                    .insert(key, CachedAssetEntry::load(&self.db, asset.clone()));
                 Some(asset)
             }
             Ok(None) => None,
@@ -3897,13 +3899,8 @@ Use one of the keys listed above. Populate the \"inputs\" object with values for
 
     fn refresh_asset_cache_entry(&mut self, asset_id: &str) {
         if let Ok(asset) = self.db.get_asset(asset_id) {
-            self.asset_cache.insert(
-                asset.src_abs.clone(),
-                CachedAssetEntry {
-                    asset,
-                    last_refresh: Instant::now(),
-                },
-            );
+            self.asset_cache
+                .insert(asset.src_abs.clone(), CachedAssetEntry::load(&self.db, asset));
         }
     }
 
@@ -3923,12 +3920,39 @@ Use one of the keys listed above. Populate the \"inputs\" object with values for
                 }
             }
 
-            let optimized_candidate = self.cache_manager.cached_output_path(
-                std::path::Path::new(&asset_row.src_abs),
-                PreferredCodec::ProRes422,
-            );
+            // Scrubbing wants the lightest variant; playing and paused
+            // frames the best one that still decodes smoothly.
+            let context = match self.engine.state {
+                PlayState::Scrubbing | PlayState::Seeking => project::PlaybackContext::Scrub,
+                _ => project::PlaybackContext::Play,
+            };
+            let variants = self
+                .asset_cache
+                .get(original_path)
+                .map(|entry| entry.variants.as_slice())
+                .unwrap_or_default();
+            let variant = project::choose_variant(asset_row, variants, context);
+            let (optimized_candidate, proxy_candidate) = match variant {
+                Some(v) if v.kind == project::VariantKind::Transcode => {
+                    (Some(std::path::PathBuf::from(&v.path_abs)), None)
+                }
+                Some(v) => (None, Some(std::path::PathBuf::from(&v.path_abs))),
+                // Optimized media made before variants were recorded.
+                None => (
+                    self.cache_manager.cached_output_path(
+                        std::path::Path::new(&asset_row.src_abs),
+                        PreferredCodec::ProRes422,
+                    ),
+                    None,
+                ),
+            };
 
-            match PlaybackSelector::select_path(asset_row, mode, optimized_candidate.as_deref()) {
+            match PlaybackSelector::select_path(
+                asset_row,
+                mode,
+                optimized_candidate.as_deref(),
+                proxy_candidate.as_deref(),
+            ) {
                 Some(selection) => {
                     match selection.source {
                         PlaybackSource::Optimized => using_optimized = true,
@@ -3982,7 +4006,7 @@ Use one of the keys listed above. Populate the \"inputs\" object with values for
         self.consider_proxy_for_asset(asset, ProxyReason::Timeline);
     }
 
-    fn queue_optimized_media_for_asset(&self, asset: &project::AssetRow) {
+    fn queue_optimized_media_for_asset(&mut self, asset: &project::AssetRow) {
         if !asset.kind.eq_ignore_ascii_case("video") {
             return;
         }
@@ -3994,6 +4018,7 @@ Use one of the keys listed above. Populate the \"inputs\" object with values for
             source_codec: codec_hint,
         };
         let job_id = self.cache_manager.submit_cache_job(spec);
+        self.cache_job_assets.insert(job_id, asset.id.clone());
         tracing::info!(
             target = "cache",
             asset = %asset.id,
@@ -4215,7 +4240,8 @@ Use one of the keys listed above. Populate the \"inputs\" object with values for
             return;
         }
         let media_info = Self::asset_to_media_info(asset);
-        let needs_proxy = self.auto_proxy_setting.should_queue_proxy(
+        let needs_proxy = auto_proxy_wanted(
+            self.auto_proxy_setting,
             true,
             Some(&media_info),
             self.hardware_caps.as_ref(),
@@ -4633,6 +4659,7 @@ Use one of the keys listed above. Populate the \"inputs\" object with values for
             name: name.to_string(),
             kind,
             node_ids: Vec::new(),
+            properties: Default::default(),
         };
         let track_id = track.id;
         self::app_timeline::apply_timeline_command_impl(
@@ -4654,6 +4681,7 @@ Use one of the keys listed above. Populate the \"inputs\" object with values for
             name,
             kind: timeline_crate::TrackKind::Video,
             node_ids: Vec::new(),
+            properties: Default::default(),
         };
         let overlay_id = overlay_track.id;
         self::app_timeline::apply_timeline_command_impl(
@@ -4778,6 +4806,7 @@ Use one of the keys listed above. Populate the \"inputs\" object with values for
                 name: "Storyboard Video".to_string(),
                 kind: timeline_crate::TrackKind::Video,
                 node_ids: Vec::new(),
+                properties: Default::default(),
             };
             self::app_timeline::apply_timeline_command_impl(
                 self,
@@ -4897,6 +4926,8 @@ Use one of the keys listed above. Populate the \"inputs\" object with values for
                 timeline_range,
                 playback_rate: 1.0,
                 reverse: false,
+                time_remap: None,
+                markers: Vec::new(),
                 metadata: serde_json::Value::Object(clip_metadata_map),
             };
 
@@ -5368,6 +5399,8 @@ Use one of the keys listed above. Populate the \"inputs\" object with values for
             audio_out: audio_engine::AudioEngine::new().ok(),
             selected: None,
             drag: None,
+            edit_marks: EditMarks::default(),
+            track_props_draft: None,
             export: ExportUiState::default(),
             import_workers: Vec::new(),
             jobs: Some(jobs_handle),
@@ -5385,6 +5418,7 @@ Use one of the keys listed above. Populate the \"inputs\" object with values for
             cache_manager,
             cache_events,
             cache_job_status: std::collections::HashMap::new(),
+            cache_job_assets: std::collections::HashMap::new(),
             viewer_scale: ViewerScale::Full,
             playback_lag_frames: 0,
             playback_stable_frames: 0,
@@ -5533,6 +5567,32 @@ Use one of the keys listed above. Populate the \"inputs\" object with values for
             storyboard_workflows: Vec::new(),
             storyboard_batch_busy: false,
             last_save_at: None,
+            journal_since_save: 0,
+            pending_recovery: None,
+            sequence_load_error: None,
+            sequence_id: String::new(),
+            sequences: Vec::new(),
+            sequence_name_draft: None,
+            show_snapshots: false,
+            snapshot_name: String::new(),
+            snapshot_comparison: None,
+            last_autosave_snapshot_at: None,
+            show_archive: false,
+            archive_options: exporters::archive::ArchiveOptions {
+                handles_seconds: 1.0,
+                ..Default::default()
+            },
+            archive_status: None,
+            offline_media: std::collections::HashMap::new(),
+            show_relink: false,
+            relink_status: None,
+            window_focused: true,
+            asset_view: app_assets::AssetView::All,
+            bins: Vec::new(),
+            smart_collections: Vec::new(),
+            new_bin_name: String::new(),
+            collection_draft: None,
+            asset_tag_draft: String::new(),
             asset_thumb_textures: std::collections::HashMap::new(),
             textures_to_free_next_frame: Vec::new(),
             dragging_asset: None,
@@ -6747,6 +6807,18 @@ impl eframe::App for App {
             return;
         }
 
+        app_ui::timeline_recovery_window(self, ctx);
+        app_ui::project_snapshots_window(self, ctx);
+        app_ui::project_archive_window(self, ctx);
+        app_ui::relink_media_window(self, ctx);
+
+        // Media may have been moved or replaced while the window was in the background.
+        let focused = ctx.input(|i| i.focused);
+        if focused && !self.window_focused {
+            app_project::refresh_media_status(self);
+        }
+        self.window_focused = focused;
+
         self.poll_jobs();
 
         if matches!(self.workspace_view, WorkspaceView::Timeline) {
@@ -6800,7 +6872,10 @@ impl eframe::App for App {
             app_ui::drag_overlay(self, ctx);
 
             self.handle_external_file_drops(ctx);
-            self.process_pending_timeline_drops();
+            if !self.timeline_read_only() {
+                // Files dropped on the timeline wait until it can be edited.
+                self.process_pending_timeline_drops();
+            }
         } else {
             self.assets_drop_rect = None;
             self.timeline_drop_rect = None;
@@ -6813,4 +6888,11 @@ impl eframe::App for App {
 
         self.jobs_window(ctx);
     }
+
+    fn on_exit(&mut self) {
+        // A clean exit leaves a snapshot, so the next open has nothing to recover.
+        if !self.project_id.is_empty() && self.journal_since_save > 0 {
+            let _ = self.save_project_timeline();
+        }
+    }
 }
@@ -49,6 +49,9 @@ fn track_end(graph: &timeline_crate::TimelineGraph, binding: &timeline_crate::Tr
         .filter_map(|id| graph.nodes.get(id))
         .filter_map(|node| match &node.kind {
             timeline_crate::TimelineNodeKind::Clip(clip) => Some(clip.timeline_range.end()),
+            timeline_crate::TimelineNodeKind::Compound(compound) => {
+                Some(compound.timeline_range.end())
+            }
             timeline_crate::TimelineNodeKind::Generator { timeline_range, .. } => {
                 Some(timeline_range.end())
             }
@@ -72,7 +75,7 @@ where
         .tracks
         .iter()
         .enumerate()
-        .filter(|(_, track)| predicate(&track.kind))
+        .filter(|(_, track)| predicate(&track.kind) && !track.properties.locked)
         .min_by_key(|(idx, _)| ((*idx as isize - pref).abs() as usize))
         .map(|(idx, track)| (idx, track.clone()))
 }
@@ -215,6 +218,10 @@ impl App {
     pub(crate) fn add_asset_to_timeline(&mut self, asset: &AssetRow) {
         self::add_asset_to_timeline(self, asset)
     }
+
+    pub(crate) fn three_point_edit(&mut self, overwrite: bool) {
+        self::three_point_edit(self, overwrite)
+    }
 }
 
 pub(super) fn insert_asset_at(
@@ -239,6 +246,8 @@ pub(super) fn insert_asset_at(
         timeline_range,
         playback_rate: 1.0,
         reverse: false,
+        time_remap: None,
+        markers: Vec::new(),
         metadata,
     };
     let node = timeline_crate::TimelineNode {
@@ -248,21 +257,7 @@ pub(super) fn insert_asset_at(
         locked: false,
         metadata: serde_json::Value::Null,
     };
-    let placements: Vec<_> = targets
-        .iter()
-        .map(|(_, binding)| timeline_crate::TrackPlacement {
-            track_id: binding.id,
-            position: None,
-        })
-        .collect();
-    if let Err(err) = super::app_timeline::apply_timeline_command_impl(
-        app,
-        timeline_crate::TimelineCommand::InsertNode {
-            node,
-            placements,
-            edges: Vec::new(),
-        },
-    ) {
+    if let Err(err) = insert_linked_clips(app, node, &targets) {
         eprintln!("timeline insert failed: {err}");
         return;
     }
@@ -312,6 +307,14 @@ pub(super) fn import_files_for(
         let metadata_map = metadata_map.clone();
         let h = std::thread::spawn(move || {
             let db = project::ProjectDb::open_or_create(&db_path).expect("open db");
+            if let Ok(Some(existing)) = db.find_duplicate_asset(&project_id, &f) {
+                eprintln!(
+                    "Skipping {}: same content as {}",
+                    f.to_string_lossy(),
+                    existing.src_abs
+                );
+                return;
+            }
             match crate::media_info::probe_media_info(&f) {
                 Ok(info) => {
                     let kind = match info.kind {
@@ -352,6 +355,9 @@ pub(super) fn import_files_for(
                             meta_json.as_deref(),
                         )
                         .unwrap_or_default();
+                    if let Ok(Some(donor)) = db.reuse_derived_media(&asset_id) {
+                        reuse_thumbnail(&donor.id, &asset_id);
+                    }
                 }
                 Err(err) => {
                     eprintln!("Import probe failed {}: {}", f.to_string_lossy(), err);
@@ -363,10 +369,221 @@ pub(super) fn import_files_for(
     Ok(())
 }
 
+/// Copy the thumbnail of an asset with the same content to a new asset.
+fn reuse_thumbnail(from_asset: &str, to_asset: &str) {
+    let dir = project::app_data_dir().join("cache").join("thumbnails");
+    let from = dir.join(format!("{}-thumb.jpg", from_asset));
+    let to = dir.join(format!("{}-thumb.jpg", to_asset));
+    if from.exists() && !to.exists() {
+        let _ = std::fs::copy(&from, &to);
+    }
+}
+
 pub(super) fn assets(app: &App) -> Vec<project::AssetRow> {
     app.db.list_assets(&app.project_id).unwrap_or_default()
 }
 
+/// What the assets grid shows: everything, one bin and its sub-bins, the
+/// matches of a smart collection, or assets no sequence uses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum AssetView {
+    All,
+    Bin(String),
+    Collection(String),
+    Unused,
+}
+
+/// Smart collection being created (`id` is `None`) or edited.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CollectionDraft {
+    pub id: Option<String>,
+    pub name: String,
+    pub query: project::SmartQuery,
+    pub codecs: String,
+    pub tags: String,
+}
+
+impl CollectionDraft {
+    pub(crate) fn edit(row: &project::SmartCollectionRow) -> Self {
+        Self {
+            id: Some(row.id.clone()),
+            name: row.name.clone(),
+            codecs: row.query.codecs.join(", "),
+            tags: row.query.tags.join(", "),
+            query: row.query.clone(),
+        }
+    }
+}
+
+fn split_list(text: &str) -> Vec<String> {
+    text.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Reload the project's bins and smart collections, falling back to showing
+/// all assets if the current view went away.
+pub(super) fn refresh_organization(app: &mut App) {
+    app.bins = app.db.list_bins(&app.project_id).unwrap_or_default();
+    app.smart_collections = app
+        .db
+        .list_smart_collections(&app.project_id)
+        .unwrap_or_default();
+    let exists = match &app.asset_view {
+        AssetView::All | AssetView::Unused => true,
+        AssetView::Bin(id) => app.bins.iter().any(|b| &b.id == id),
+        AssetView::Collection(id) => app.smart_collections.iter().any(|c| &c.id == id),
+    };
+    if !exists {
+        app.asset_view = AssetView::All;
+    }
+}
+
+/// `bin_id` and every bin nested below it.
+pub(super) fn bin_with_descendants(bins: &[project::BinRow], bin_id: &str) -> Vec<String> {
+    let mut out = vec![bin_id.to_string()];
+    let mut i = 0;
+    while i < out.len() {
+        let parent = out[i].clone();
+        out.extend(
+            bins.iter()
+                .filter(|b| b.parent_id.as_deref() == Some(parent.as_str()))
+                .map(|b| b.id.clone()),
+        );
+        i += 1;
+    }
+    out
+}
+
+/// The assets the current [`AssetView`] selects out of `assets`.
+pub(super) fn visible_assets(app: &App, assets: &[AssetRow]) -> Vec<AssetRow> {
+    match &app.asset_view {
+        AssetView::All => assets.to_vec(),
+        AssetView::Bin(id) => {
+            let bins = bin_with_descendants(&app.bins, id);
+            assets
+                .iter()
+                .filter(|a| a.bin_id.as_ref().is_some_and(|b| bins.contains(b)))
+                .cloned()
+                .collect()
+        }
+        AssetView::Collection(id) => app
+            .smart_collections
+            .iter()
+            .find(|c| &c.id == id)
+            .and_then(|c| app.db.query_assets(&app.project_id, &c.query).ok())
+            .unwrap_or_default(),
+        AssetView::Unused => {
+            let open = open_sequence_media(app);
+            app.db
+                .unused_assets(&app.project_id)
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|a| !open.contains(a.src_abs.as_str()))
+                .collect()
+        }
+    }
+}
+
+/// Media paths the open sequence uses, including edits not saved yet, which
+/// the project's usages do not know about.
+pub(super) fn open_sequence_media(app: &App) -> std::collections::HashSet<&str> {
+    app.seq
+        .graph
+        .nodes
+        .values()
+        .chain(
+            app.seq
+                .graph
+                .sequences
+                .values()
+                .flat_map(|g| g.nodes.values()),
+        )
+        .filter_map(|node| match &node.kind {
+            timeline_crate::TimelineNodeKind::Clip(clip) => clip.asset_id.as_deref(),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Save the open sequence and remove an asset no sequence uses from the
+/// project.
+pub(super) fn delete_asset(app: &mut App, asset_id: &str) {
+    if let Err(err) = app
+        .save_project_timeline()
+        .and_then(|_| app.db.delete_asset(asset_id))
+    {
+        tracing::warn!(error = %err, "failed to delete asset");
+        return;
+    }
+    if app
+        .edit_marks
+        .source
+        .as_ref()
+        .is_some_and(|a| a.id == asset_id)
+    {
+        app.edit_marks.source = None;
+    }
+}
+
+/// Create a bin inside the one being shown, or at the top level.
+pub(super) fn create_bin(app: &mut App, name: &str) {
+    let name = name.trim();
+    if name.is_empty() {
+        return;
+    }
+    let parent = match &app.asset_view {
+        AssetView::Bin(id) => Some(id.clone()),
+        _ => None,
+    };
+    match app.db.create_bin(&app.project_id, parent.as_deref(), name) {
+        Ok(id) => {
+            refresh_organization(app);
+            app.asset_view = AssetView::Bin(id);
+        }
+        Err(err) => tracing::warn!(error = %err, "failed to create bin"),
+    }
+}
+
+pub(super) fn delete_bin(app: &mut App, bin_id: &str) {
+    if let Err(err) = app.db.delete_bin(bin_id) {
+        tracing::warn!(error = %err, "failed to delete bin");
+    }
+    refresh_organization(app);
+}
+
+pub(super) fn save_collection(app: &mut App, mut draft: CollectionDraft) {
+    draft.query.codecs = split_list(&draft.codecs);
+    draft.query.tags = split_list(&draft.tags);
+    let name = draft.name.trim();
+    let name = if name.is_empty() { "Collection" } else { name };
+    let saved = match &draft.id {
+        Some(id) => app
+            .db
+            .update_smart_collection(id, name, &draft.query)
+            .map(|_| id.clone()),
+        None => app
+            .db
+            .create_smart_collection(&app.project_id, name, &draft.query),
+    };
+    match saved {
+        Ok(id) => {
+            refresh_organization(app);
+            app.asset_view = AssetView::Collection(id);
+        }
+        Err(err) => tracing::warn!(error = %err, "failed to save smart collection"),
+    }
+}
+
+pub(super) fn delete_collection(app: &mut App, collection_id: &str) {
+    if let Err(err) = app.db.delete_smart_collection(collection_id) {
+        tracing::warn!(error = %err, "failed to delete smart collection");
+    }
+    refresh_organization(app);
+}
+
 pub(super) fn add_asset_to_timeline(app: &mut App, asset: &project::AssetRow) {
     use crate::timeline_crate::{ClipNode, FrameRange, TimelineNode, TimelineNodeKind};
     let targets = collect_target_tracks(app, asset, None);
@@ -391,6 +608,8 @@ pub(super) fn add_asset_to_timeline(app: &mut App, asset: &project::AssetRow) {
         timeline_range,
         playback_rate: 1.0,
         reverse: false,
+        time_remap: None,
+        markers: Vec::new(),
         metadata,
     };
     let node = TimelineNode {
@@ -400,21 +619,7 @@ pub(super) fn add_asset_to_timeline(app: &mut App, asset: &project::AssetRow) {
         locked: false,
         metadata: serde_json::Value::Null,
     };
-    let placements: Vec<_> = targets
-        .iter()
-        .map(|(_, binding)| timeline_crate::TrackPlacement {
-            track_id: binding.id,
-            position: None,
-        })
-        .collect();
-    let _ = super::app_timeline::apply_timeline_command_impl(
-        app,
-        timeline_crate::TimelineCommand::InsertNode {
-            node,
-            placements,
-            edges: Vec::new(),
-        },
-    );
+    let _ = insert_linked_clips(app, node, &targets);
 
     super::app_timeline::sync_tracks_from_graph_impl(app);
 
@@ -425,8 +630,8 @@ pub(super) fn add_asset_to_timeline(app: &mut App, asset: &project::AssetRow) {
         .map(|(idx, _)| *idx);
 
     if let Some(track_idx) = selected_track_index {
-        if let Some(track) = app.seq.tracks.get(track_idx) {
-            let item_idx = track.items.len().saturating_sub(1);
+        if let Some(track) = app.seq.graph.tracks.get(track_idx) {
+            let item_idx = track.node_ids.len().saturating_sub(1);
             app.selected = Some((track_idx, item_idx));
         }
 
@@ -434,6 +639,110 @@ pub(super) fn add_asset_to_timeline(app: &mut App, asset: &project::AssetRow) {
     }
 }
 
+/// Insert or overwrite the edit source onto the targeted tracks. The source
+/// marks and the timeline in/out resolve the edit; with no timeline marks the
+/// clip lands on the playhead.
+pub(super) fn three_point_edit(app: &mut App, overwrite: bool) {
+    use crate::timeline_crate::{ClipNode, EditPoints, FrameRange, TimelineCommand};
+    let Some(asset) = app.edit_marks.source.clone() else {
+        return;
+    };
+    let marks = &app.edit_marks;
+    let record_in = marks
+        .timeline_in
+        .or_else(|| marks.timeline_out.is_none().then_some(app.playhead));
+    let points = EditPoints {
+        source_in: marks.source_in,
+        source_out: marks.source_out,
+        record_in,
+        record_out: marks.timeline_out,
+    };
+    let source_len = asset_timeline_duration(&asset, app.seq.fps);
+    let Some((source, record_in)) = points.resolve(source_len) else {
+        eprintln!("three-point edit: marks do not describe a clip");
+        return;
+    };
+
+    // Source marks are in sequence frames; the clip reads media frames.
+    let media_frames = asset.duration_frames.unwrap_or(source_len).max(1);
+    let scale = media_frames as f64 / source_len as f64;
+    let media_range = FrameRange::new(
+        (source.start as f64 * scale).round() as i64,
+        ((source.duration as f64 * scale).round() as i64).max(1),
+    );
+    let clip = ClipNode {
+        asset_id: Some(asset.src_abs.clone()),
+        media_range,
+        timeline_range: FrameRange::new(record_in, source.duration),
+        playback_rate: 1.0,
+        reverse: false,
+        time_remap: None,
+        markers: Vec::new(),
+        metadata: clip_metadata_for_asset(&asset),
+    };
+    let node = timeline_crate::TimelineNode {
+        id: timeline_crate::NodeId::new(),
+        label: Some(asset.id.clone()),
+        kind: timeline_crate::TimelineNodeKind::Clip(clip),
+        locked: false,
+        metadata: Value::Null,
+    };
+
+    let mut track_ids = app.targeted_track_ids();
+    if track_ids.is_empty() {
+        let preferred = app.selected.map(|(track, _)| track);
+        track_ids = collect_target_tracks(app, &asset, preferred)
+            .into_iter()
+            .map(|(_, binding)| binding.id)
+            .collect();
+    }
+    let node_id = node.id;
+    let command = if overwrite {
+        TimelineCommand::OverwriteEdit { node, track_ids }
+    } else {
+        TimelineCommand::InsertEdit { node, track_ids }
+    };
+    if let Err(err) = super::app_timeline::apply_timeline_command_impl(app, command) {
+        eprintln!("timeline three-point edit failed: {err}");
+        return;
+    }
+    app.update_selection_for_node(node_id);
+    app.prime_asset_for_timeline(&asset);
+}
+
+/// Put a copy of `node` on each target track and link the copies so picture
+/// and sound edit together.
+fn insert_linked_clips(
+    app: &mut App,
+    node: timeline_crate::TimelineNode,
+    targets: &[(usize, timeline_crate::TrackBinding)],
+) -> Result<(), timeline_crate::TimelineError> {
+    let mut commands = Vec::with_capacity(targets.len());
+    let mut anchor = None;
+    for (_, binding) in targets {
+        let mut copy = node.clone();
+        copy.id = timeline_crate::NodeId::new();
+        let edges = anchor
+            .map(|from| timeline_crate::TimelineEdge {
+                from,
+                to: copy.id,
+                kind: timeline_crate::EdgeKind::Link,
+            })
+            .into_iter()
+            .collect();
+        anchor.get_or_insert(copy.id);
+        commands.push(timeline_crate::TimelineCommand::InsertNode {
+            node: copy,
+            placements: vec![timeline_crate::TrackPlacement {
+                track_id: binding.id,
+                position: None,
+            }],
+            edges,
+        });
+    }
+    super::app_timeline::apply_timeline_batch_impl(app, "Insert clip", commands)
+}
+
 fn clip_metadata_for_asset(asset: &AssetRow) -> Value {
     let mut map = Map::new();
     if let Some(num) = asset.fps_num {
@@ -1,5 +1,14 @@
 use super::App;
 
+/// Timeline edits between full snapshots. The journal covers the ones in
+/// between, so a crash loses nothing that was applied.
+const SNAPSHOT_EVERY: usize = 25;
+
+/// Saves at least this far apart also leave an unnamed project snapshot,
+/// of which the newest `AUTOSAVE_SNAPSHOTS_KEPT` are kept.
+const AUTOSAVE_SNAPSHOT_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10 * 60);
+const AUTOSAVE_SNAPSHOTS_KEPT: usize = 12;
+
 pub(super) fn ensure_baseline_tracks(app: &mut App) {
     if app.seq.graph.tracks.is_empty() {
         for i in 1..=3 {
@@ -8,6 +17,7 @@ pub(super) fn ensure_baseline_tracks(app: &mut App) {
                 name: format!("V{}", i),
                 kind: timeline_crate::TrackKind::Video,
                 node_ids: Vec::new(),
+                properties: Default::default(),
             };
             let _ = app.apply_timeline_command(timeline_crate::TimelineCommand::UpsertTrack {
                 track: binding,
@@ -19,6 +29,7 @@ pub(super) fn ensure_baseline_tracks(app: &mut App) {
                 name: format!("A{}", i),
                 kind: timeline_crate::TrackKind::Audio,
                 node_ids: Vec::new(),
+                properties: Default::default(),
             };
             let _ = app.apply_timeline_command(timeline_crate::TimelineCommand::UpsertTrack {
                 track: binding,
@@ -28,70 +39,531 @@ pub(super) fn ensure_baseline_tracks(app: &mut App) {
     }
 }
 
+/// Empty sequence with the default three video and three audio tracks.
+fn default_sequence(
+    name: &str,
+    fps: timeline_crate::Fps,
+    width: u32,
+    height: u32,
+) -> timeline_crate::Sequence {
+    let mut seq = timeline_crate::Sequence::new(name, width, height, fps, 600);
+    for i in 1..=3 {
+        seq.add_track(timeline_crate::Track {
+            name: format!("V{}", i),
+            items: vec![],
+        });
+    }
+    for i in 1..=3 {
+        seq.add_track(timeline_crate::Track {
+            name: format!("A{}", i),
+            items: vec![],
+        });
+    }
+    seq
+}
+
+fn sequence_format(seq: &timeline_crate::Sequence) -> project::SequenceFormat {
+    project::SequenceFormat {
+        fps_num: seq.fps.num as i64,
+        fps_den: seq.fps.den as i64,
+        width: seq.width as i64,
+        height: seq.height as i64,
+        duration_frames: seq.duration_in_frames,
+    }
+}
+
+fn refresh_sequences(app: &mut App) {
+    match app.db.list_sequences(&app.project_id) {
+        Ok(rows) => app.sequences = rows,
+        Err(err) => tracing::warn!(error = %err, "failed to list sequences"),
+    }
+}
+
+/// Open the project's most recently saved sequence, creating one for a new
+/// project.
 pub(super) fn load_project_timeline(app: &mut App) {
-    if let Ok(Some(json)) = app.db.get_project_timeline_json(&app.project_id) {
-        if let Ok(seq) = serde_json::from_str::<timeline_crate::Sequence>(&json) {
-            app.seq = seq;
-        } else {
-            let mut seq = timeline_crate::Sequence::new(
-                "Main",
-                1920,
-                1080,
-                timeline_crate::Fps::new(30, 1),
-                600,
-            );
-            for i in 1..=3 {
-                seq.add_track(timeline_crate::Track {
-                    name: format!("V{}", i),
-                    items: vec![],
-                });
-            }
-            for i in 1..=3 {
-                seq.add_track(timeline_crate::Track {
-                    name: format!("A{}", i),
-                    items: vec![],
-                });
+    app.sequences.clear();
+    refresh_sequences(app);
+    super::app_assets::refresh_organization(app);
+    if app.sequences.is_empty() {
+        let format = match app.db.get_project_settings(&app.project_id) {
+            Ok(settings) => settings.sequence,
+            Err(err) => {
+                tracing::warn!(error = %err, "failed to read project settings");
+                project::SequenceDefaults::default()
             }
-            app.seq = seq;
-        }
-    } else {
-        let mut seq =
-            timeline_crate::Sequence::new("Main", 1920, 1080, timeline_crate::Fps::new(30, 1), 600);
-        for i in 1..=3 {
-            seq.add_track(timeline_crate::Track {
-                name: format!("V{}", i),
-                items: vec![],
-            });
-        }
-        for i in 1..=3 {
-            seq.add_track(timeline_crate::Track {
-                name: format!("A{}", i),
-                items: vec![],
+        };
+        let fps = timeline_crate::Fps::new(format.fps_num, format.fps_den);
+        let seq = default_sequence("Main", fps, format.width, format.height);
+        let created = serde_json::to_string(&seq)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| {
+                app.db
+                    .create_sequence(&app.project_id, &seq.name, &sequence_format(&seq), &json)
             });
+        if let Err(err) = created {
+            tracing::warn!(error = %err, "failed to create sequence");
         }
-        app.seq = seq;
+        refresh_sequences(app);
     }
+    let latest = app
+        .sequences
+        .iter()
+        .max_by_key(|row| row.updated_at)
+        .map(|row| row.id.clone())
+        .unwrap_or_default();
+    open_sequence(app, &latest);
+    app.refresh_storyboard_workflows();
+    app.load_storyboard_from_settings();
+    refresh_media_status(app);
+}
+
+fn open_sequence(app: &mut App, sequence_id: &str) {
+    app.sequence_id = sequence_id.to_string();
+    app.sequence_name_draft = None;
+    let row = app.sequences.iter().find(|row| row.id == sequence_id);
+    let fallback = match row {
+        Some(row) => default_sequence(
+            &row.name,
+            timeline_crate::Fps::new(row.fps_num.max(1) as u32, row.fps_den.max(1) as u32),
+            row.width.max(1) as u32,
+            row.height.max(1) as u32,
+        ),
+        None => default_sequence("Main", timeline_crate::Fps::new(30, 1), 1920, 1080),
+    };
+    // A stored timeline that fails to load, for example one written by a newer
+    // build, is never replaced: the fallback is shown read-only and not saved.
+    let name = fallback.name.clone();
+    let unreadable = |err: String| {
+        tracing::warn!(error = %err, "failed to read sequence timeline");
+        format!("\"{name}\" could not be opened: {err}")
+    };
+    app.sequence_load_error = None;
+    app.seq = match app.db.get_sequence_timeline_json(sequence_id) {
+        Ok(Some(json)) => match serde_json::from_str::<timeline_crate::Sequence>(&json) {
+            Ok(seq) => seq,
+            Err(err) => {
+                app.sequence_load_error = Some(unreadable(err.to_string()));
+                fallback
+            }
+        },
+        Ok(None) => fallback,
+        Err(err) => {
+            app.sequence_load_error = Some(unreadable(err.to_string()));
+            fallback
+        }
+    };
     if app.seq.graph.tracks.is_empty() {
         app.seq.graph = timeline_crate::migrate_sequence_tracks(&app.seq);
     }
+    for issue in app.seq.graph.repair() {
+        tracing::warn!(%issue, "repaired timeline graph on load");
+    }
+    for issue in app.seq.graph.validate() {
+        tracing::warn!(%issue, severity = ?issue.severity(), "timeline graph problem");
+    }
     super::app_timeline::sync_tracks_from_graph_impl(app);
-    ensure_baseline_tracks(app);
+    app.journal_since_save = 0;
+    app.pending_recovery = None;
+    if app.sequence_load_error.is_none() {
+        // The journal stays untouched until the sequence itself can be read.
+        app.pending_recovery = pending_journal(app);
+    }
+    if !app.timeline_read_only() {
+        ensure_baseline_tracks(app);
+        if app.journal_since_save > 0 {
+            let _ = save_project_timeline_impl(app);
+        }
+    }
     app.timeline_history = timeline_crate::CommandHistory::default();
     app.selected = None;
     app.drag = None;
-    app.refresh_storyboard_workflows();
-    app.load_storyboard_from_settings();
+    app.edit_marks = Default::default();
+    app.playhead = app.playhead.min(app.seq.duration_in_frames.max(0));
+}
+
+/// Save the open sequence and open another one of the project.
+pub(super) fn switch_sequence(app: &mut App, sequence_id: &str) {
+    if sequence_id == app.sequence_id || app.pending_recovery.is_some() {
+        return;
+    }
+    let _ = save_project_timeline_impl(app);
+    refresh_sequences(app);
+    open_sequence(app, sequence_id);
+}
+
+/// Add an empty sequence in the open sequence's format and switch to it.
+pub(super) fn create_sequence(app: &mut App) {
+    let name = format!("Sequence {}", app.sequences.len() + 1);
+    let seq = default_sequence(&name, app.seq.fps, app.seq.width, app.seq.height);
+    let created = serde_json::to_string(&seq)
+        .map_err(anyhow::Error::from)
+        .and_then(|json| {
+            app.db
+                .create_sequence(&app.project_id, &name, &sequence_format(&seq), &json)
+        });
+    match created {
+        Ok(id) => switch_sequence(app, &id),
+        Err(err) => tracing::warn!(error = %err, "failed to create sequence"),
+    }
+}
+
+/// Save the open sequence, copy it and switch to the copy.
+pub(super) fn duplicate_sequence(app: &mut App) {
+    if save_project_timeline_impl(app).is_err() || app.pending_recovery.is_some() {
+        return;
+    }
+    let name = format!("{} copy", app.seq.name);
+    match app.db.duplicate_sequence(&app.sequence_id, &name) {
+        Ok(id) => {
+            // The copy still carries the original's name inside its timeline.
+            refresh_sequences(app);
+            open_sequence(app, &id);
+            app.seq.name = name;
+            let _ = save_project_timeline_impl(app);
+        }
+        Err(err) => tracing::warn!(error = %err, "failed to duplicate sequence"),
+    }
+}
+
+pub(super) fn rename_sequence(app: &mut App, name: &str) {
+    let name = name.trim();
+    if name.is_empty() {
+        return;
+    }
+    if let Err(err) = app.db.rename_sequence(&app.sequence_id, name) {
+        tracing::warn!(error = %err, "failed to rename sequence");
+        return;
+    }
+    app.seq.name = name.to_string();
+    app.sequence_name_draft = None;
+    let _ = save_project_timeline_impl(app);
+    refresh_sequences(app);
+}
+
+/// Delete the open sequence and open another. The last sequence of a
+/// project is kept.
+pub(super) fn delete_sequence(app: &mut App) {
+    let Some(next) = app
+        .sequences
+        .iter()
+        .find(|row| row.id != app.sequence_id)
+        .map(|row| row.id.clone())
+    else {
+        return;
+    };
+    if let Err(err) = app.db.delete_sequence(&app.sequence_id) {
+        tracing::warn!(error = %err, "failed to delete sequence");
+        return;
+    }
+    app.pending_recovery = None;
+    app.journal_since_save = 0;
+    refresh_sequences(app);
+    open_sequence(app, &next);
+}
+
+/// Journal entries a previous session left behind, if any. Reading stops at
+/// the first entry that no longer parses: the ones after it were recorded
+/// against a state the replay could not reach.
+fn pending_journal(app: &App) -> Option<Vec<timeline_crate::JournalEntry>> {
+    let rows = match app.db.list_timeline_journal(&app.sequence_id) {
+        Ok(rows) => rows,
+        Err(err) => {
+            tracing::warn!(error = %err, "failed to read timeline journal");
+            return None;
+        }
+    };
+    if rows.is_empty() {
+        return None;
+    }
+    let mut entries = Vec::with_capacity(rows.len());
+    for row in &rows {
+        match serde_json::from_str(&row.entry_json) {
+            Ok(entry) => entries.push(entry),
+            Err(err) => {
+                tracing::warn!(
+                    id = row.id,
+                    dropped = rows.len() - entries.len(),
+                    error = %err,
+                    "unreadable journal entry; recovering only the edits before it"
+                );
+                break;
+            }
+        }
+    }
+    Some(entries)
+}
+
+/// Append an applied edit to the journal and write a full snapshot every
+/// `SNAPSHOT_EVERY` edits, or straight away if journaling fails.
+pub(super) fn record_timeline_edit(app: &mut App, entry: timeline_crate::JournalEntry) {
+    if app.pending_recovery.is_some() {
+        // The old journal is still waiting on the recovery prompt.
+        return;
+    }
+    let journaled = serde_json::to_string(&entry)
+        .map_err(anyhow::Error::from)
+        .and_then(|json| {
+            app.db
+                .append_timeline_journal(&app.project_id, &app.sequence_id, &json)
+        });
+    let snapshot_now = match journaled {
+        Ok(_) => {
+            app.journal_since_save += 1;
+            app.journal_since_save >= SNAPSHOT_EVERY
+        }
+        Err(err) => {
+            tracing::warn!(error = %err, "failed to journal timeline edit");
+            true
+        }
+    };
+    if snapshot_now {
+        let _ = save_project_timeline_impl(app);
+    }
+}
+
+/// Answer the recovery prompt: replay the journal on top of the loaded
+/// snapshot, or drop it. Either way a fresh snapshot replaces the journal.
+pub(super) fn resolve_timeline_recovery(app: &mut App, recover: bool) {
+    let Some(entries) = app.pending_recovery.take() else {
+        return;
+    };
+    if recover {
+        let total = entries.len();
+        let graph = &mut app.seq.graph;
+        match timeline_crate::replay_journal(graph, &mut app.timeline_history, entries) {
+            Ok(applied) => tracing::info!(applied, "recovered unsaved timeline edits"),
+            Err((applied, err)) => tracing::warn!(
+                applied,
+                total,
+                error = %err,
+                "timeline recovery stopped at an entry that no longer applies"
+            ),
+        }
+        super::app_timeline::sync_tracks_from_graph_impl(app);
+    }
+    ensure_baseline_tracks(app);
+    let _ = save_project_timeline_impl(app);
 }
 
 pub(super) fn save_project_timeline_impl(app: &mut App) -> anyhow::Result<()> {
-    let json = serde_json::to_string(&app.seq)?;
-    app.db
-        .upsert_project_timeline_json(&app.project_id, &json)?;
+    if !app.timeline_read_only() && !app.sequence_id.is_empty() {
+        // Writing the snapshot also clears the journal it supersedes.
+        let json = serde_json::to_string(&app.seq)?;
+        app.db
+            .save_sequence_timeline(&app.sequence_id, &sequence_format(&app.seq), &json)?;
+        app.journal_since_save = 0;
+    }
     app.persist_storyboard_to_settings()?;
     app.last_save_at = Some(std::time::Instant::now());
+    let snapshot_due = match app.last_autosave_snapshot_at {
+        Some(at) => at.elapsed() >= AUTOSAVE_SNAPSHOT_INTERVAL,
+        None => true,
+    };
+    if snapshot_due && !app.sequence_id.is_empty() {
+        match app
+            .db
+            .autosave_project_snapshot(&app.project_id, AUTOSAVE_SNAPSHOTS_KEPT)
+        {
+            Ok(_) => app.last_autosave_snapshot_at = Some(std::time::Instant::now()),
+            Err(err) => tracing::warn!(error = %err, "failed to autosave project snapshot"),
+        }
+    }
     Ok(())
 }
 
+/// Save the open sequence and record the whole project under `name`.
+pub(super) fn take_project_snapshot(app: &mut App, name: &str) {
+    let name = name.trim();
+    if name.is_empty() || save_project_timeline_impl(app).is_err() {
+        return;
+    }
+    if let Err(err) = app.db.create_project_snapshot(&app.project_id, Some(name)) {
+        tracing::warn!(error = %err, "failed to take project snapshot");
+    }
+}
+
+/// Describe how the project differs from a snapshot, one line per change.
+/// Changed sequences also say how many timeline edits apart they are.
+pub(super) fn compare_project_snapshot(app: &mut App, snapshot_id: &str) -> Vec<String> {
+    let _ = save_project_timeline_impl(app);
+    let diff = match app.db.diff_project_snapshot(snapshot_id) {
+        Ok(diff) => diff,
+        Err(err) => return vec![format!("Could not compare: {err}")],
+    };
+    if diff.is_empty() {
+        return vec!["No changes since this snapshot.".to_string()];
+    }
+    let then = app
+        .db
+        .get_project_snapshot(snapshot_id)
+        .ok()
+        .flatten()
+        .unwrap_or_default();
+    let graph = |json: &str| {
+        serde_json::from_str::<timeline_crate::Sequence>(json)
+            .ok()
+            .map(|seq| seq.graph)
+    };
+    let verb = |change: project::SnapshotChange| match change {
+        project::SnapshotChange::Added => "added",
+        project::SnapshotChange::Removed => "removed",
+        project::SnapshotChange::Modified => "changed",
+    };
+    let mut lines = Vec::new();
+    if diff.settings_changed {
+        lines.push("Project settings changed".to_string());
+    }
+    for item in &diff.sequences {
+        let mut line = format!("Sequence \"{}\" {}", item.name, verb(item.change));
+        if item.change == project::SnapshotChange::Modified {
+            let old = then
+                .sequences
+                .iter()
+                .find(|s| s.id == item.id)
+                .and_then(|s| graph(&s.timeline_json));
+            let new = app
+                .db
+                .get_sequence_timeline_json(&item.id)
+                .ok()
+                .flatten()
+                .and_then(|json| graph(&json));
+            if let (Some(old), Some(new)) = (old, new) {
+                let edits = timeline_crate::diff_graphs(&old, &new).len();
+                line.push_str(&format!(" ({edits} timeline edits)"));
+            }
+        }
+        lines.push(line);
+    }
+    for item in &diff.assets {
+        lines.push(format!("Asset {} {}", item.name, verb(item.change)));
+    }
+    lines
+}
+
+/// Put the project back to a snapshot and reload it, staying on the open
+/// sequence if the snapshot has it.
+pub(super) fn restore_project_snapshot(app: &mut App, snapshot_id: &str) {
+    if app.pending_recovery.is_some() || save_project_timeline_impl(app).is_err() {
+        return;
+    }
+    if let Err(err) = app.db.restore_project_snapshot(snapshot_id) {
+        tracing::warn!(error = %err, "failed to restore project snapshot");
+        return;
+    }
+    let open = app.sequence_id.clone();
+    load_project_timeline(app);
+    if open != app.sequence_id && app.sequences.iter().any(|row| row.id == open) {
+        open_sequence(app, &open);
+    }
+    app.load_proxy_settings();
+    app.load_comfy_settings();
+}
+
+/// Save the open sequence and archive the project into `output` with the
+/// options picked in the archive window.
+pub(super) fn archive_project(app: &mut App, output: &std::path::Path) {
+    if save_project_timeline_impl(app).is_err() {
+        app.archive_status = Some("Could not save the project before archiving".to_string());
+        return;
+    }
+    let result =
+        exporters::archive::archive_project(&app.db, &app.project_id, output, &app.archive_options);
+    app.archive_status = Some(match result {
+        Ok(report) => {
+            let mut status = format!(
+                "Archived {} media files ({} trimmed) and {} proxies to {}",
+                report.media,
+                report.trimmed,
+                report.proxies,
+                report.output.display()
+            );
+            if !report.missing.is_empty() {
+                status.push_str(&format!("; {} files were missing", report.missing.len()));
+            }
+            status
+        }
+        Err(err) => {
+            tracing::warn!(error = %err, "failed to archive project");
+            format!("Archive failed: {err}")
+        }
+    });
+}
+
+/// Add the project in an archive to the app database and open it.
+pub(super) fn unarchive_project(app: &mut App, archive: &std::path::Path) {
+    match exporters::archive::unarchive_project(&app.db, archive, None) {
+        Ok(report) => {
+            app.archive_status = (!report.failed.is_empty())
+                .then(|| format!("{} media files could not be relinked", report.failed.len()));
+            app.project_id = report.project_id;
+            app.selected = None;
+            app.drag = None;
+            load_project_timeline(app);
+            app.mode = super::AppMode::Editor;
+        }
+        Err(err) => {
+            tracing::warn!(error = %err, "failed to unpack project archive");
+            app.archive_status = Some(format!("Could not unpack archive: {err}"));
+        }
+    }
+}
+
+/// Check the project's media on disk and remember which files are offline.
+pub(super) fn refresh_media_status(app: &mut App) {
+    match app.db.scan_media_status(&app.project_id, false) {
+        Ok(statuses) => {
+            app.offline_media = statuses
+                .into_iter()
+                .filter(|s| s.status != project::MediaStatus::Online)
+                .map(|s| (s.asset.src_abs, s.status))
+                .collect();
+        }
+        Err(err) => tracing::warn!(error = %err, "failed to check project media"),
+    }
+}
+
+/// Look for the project's missing media under `folder`, relink what is
+/// found and reopen the sequence so its clips use the new paths.
+pub(super) fn relink_media(app: &mut App, folder: &std::path::Path) {
+    if save_project_timeline_impl(app).is_err() {
+        app.relink_status = Some("Could not save the project before relinking".to_string());
+        return;
+    }
+    let probe = |path: &std::path::Path| {
+        crate::media_info::probe_media_info(path)
+            .ok()?
+            .duration_seconds
+    };
+    let result = app
+        .db
+        .find_relink_candidates(&app.project_id, &[folder.to_path_buf()], probe)
+        .and_then(|found| {
+            let relinks: Vec<_> = found
+                .iter()
+                .map(|c| (c.asset_id.clone(), c.new_path.clone()))
+                .collect();
+            app.db.relink_assets(&app.project_id, &relinks)?;
+            Ok(found.len())
+        });
+    match result {
+        Ok(relinked) => {
+            let open = app.sequence_id.clone();
+            refresh_sequences(app);
+            open_sequence(app, &open);
+            refresh_media_status(app);
+            app.relink_status = Some(match app.offline_media.len() {
+                0 => format!("Relinked {relinked} files; all media is online"),
+                left => format!("Relinked {relinked} files; {left} still offline"),
+            });
+        }
+        Err(err) => {
+            tracing::warn!(error = %err, "failed to relink media");
+            app.relink_status = Some(format!("Relink failed: {err}"));
+        }
+    }
+}
+
 pub(super) fn save_project_timeline(app: &mut App) -> anyhow::Result<()> {
     save_project_timeline_impl(app)
 }
@@ -110,6 +582,16 @@ impl App {
         self::save_project_timeline(self)
     }
 
+    pub(crate) fn resolve_timeline_recovery(&mut self, recover: bool) {
+        self::resolve_timeline_recovery(self, recover)
+    }
+
+    /// The timeline cannot be edited or saved: a recovery prompt is open, or
+    /// the sequence failed to load.
+    pub(crate) fn timeline_read_only(&self) -> bool {
+        self.pending_recovery.is_some() || self.sequence_load_error.is_some()
+    }
+
     pub(crate) fn delete_project_and_cleanup(&mut self, project_id: &str) -> anyhow::Result<()> {
         let delete_result = self.db.delete_project(project_id)?;
 
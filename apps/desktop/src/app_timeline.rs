@@ -1,171 +1,123 @@
 use std::path::Path;
+use std::sync::Arc;
 
-use crate::audio_engine::ActiveAudioClip;
+use crate::audio_engine::{ActiveAudioClip, AudioBuffer};
 use crate::timeline_crate::{
-    Fps, Item, ItemKind, TimelineCommand, TimelineError, TimelineNode, TimelineNodeKind, Track,
-    TrackKind,
+    resolve_command, ClipNode, Fps, Item, JournalEntry, TimelineCommand, TimelineError,
+    TimelineGraph, TimelineNode, TimelineNodeKind, TrackKind,
 };
 use serde_json::Value;
 
 use super::app_project;
 use super::App;
 
+/// Guards against sequences that (directly or indirectly) contain themselves.
+pub(crate) use crate::timeline_crate::MAX_COMPOUND_DEPTH;
+
 pub(super) fn apply_timeline_command_impl(
     app: &mut App,
     command: TimelineCommand,
 ) -> Result<(), TimelineError> {
-    app.timeline_history.apply(&mut app.seq.graph, command)?;
+    ensure_timeline_editable(app)?;
+    // Journal the resolved form so replay recreates the same new ids.
+    let label = command.label();
+    let command = resolve_command(&app.seq.graph, command)?;
+    let entry = JournalEntry::apply(label, command.clone());
+    app.timeline_history
+        .apply_labeled(&mut app.seq.graph, label, command)?;
     sync_tracks_from_graph_impl(app);
-    // Autosave timeline after each edit (best-effort)
-    let _ = app_project::save_project_timeline_impl(app);
+    refresh_playing_audio(app);
+    // Journal every edit; full snapshots are written periodically (best-effort)
+    app_project::record_timeline_edit(app, entry);
     Ok(())
 }
 
-pub(super) fn sync_tracks_from_graph_impl(app: &mut App) {
-    let mut tracks: Vec<Track> = Vec::with_capacity(app.seq.graph.tracks.len());
-    let mut max_end: i64 = 0;
-    for binding in &app.seq.graph.tracks {
-        let mut items = Vec::with_capacity(binding.node_ids.len());
-        for node_id in &binding.node_ids {
-            if let Some(node) = app.seq.graph.nodes.get(node_id) {
-                if let Some(item) = item_from_node_impl(node, &binding.kind, app.seq.fps) {
-                    max_end = max_end.max(item.from + item.duration_in_frames);
-                    items.push(item);
-                }
-            }
-        }
-        tracks.push(Track {
-            name: binding.name.clone(),
-            items,
-        });
+pub(super) fn apply_timeline_batch_impl(
+    app: &mut App,
+    label: &str,
+    commands: Vec<TimelineCommand>,
+) -> Result<(), TimelineError> {
+    ensure_timeline_editable(app)?;
+    let command = resolve_command(&app.seq.graph, TimelineCommand::Batch { commands })?;
+    let entry = JournalEntry::apply(label, command.clone());
+    app.timeline_history
+        .apply_labeled(&mut app.seq.graph, label, command)?;
+    sync_tracks_from_graph_impl(app);
+    refresh_playing_audio(app);
+    app_project::record_timeline_edit(app, entry);
+    Ok(())
+}
+
+pub(super) fn undo_timeline_impl(app: &mut App) -> Result<(), TimelineError> {
+    ensure_timeline_editable(app)?;
+    app.timeline_history.undo(&mut app.seq.graph)?;
+    app.selected = None;
+    sync_tracks_from_graph_impl(app);
+    refresh_playing_audio(app);
+    app_project::record_timeline_edit(app, JournalEntry::Undo);
+    Ok(())
+}
+
+pub(super) fn redo_timeline_impl(app: &mut App) -> Result<(), TimelineError> {
+    ensure_timeline_editable(app)?;
+    app.timeline_history.redo(&mut app.seq.graph)?;
+    app.selected = None;
+    sync_tracks_from_graph_impl(app);
+    refresh_playing_audio(app);
+    app_project::record_timeline_edit(app, JournalEntry::Redo);
+    Ok(())
+}
+
+/// Edits made while the recovery prompt is open would be neither journaled
+/// nor saved, and Recover would replay the old journal on top of them. A
+/// sequence that failed to load is never saved, so it is not edited either.
+fn ensure_timeline_editable(app: &App) -> Result<(), TimelineError> {
+    if app.pending_recovery.is_some() {
+        return Err(TimelineError::InvalidOp(
+            "recover or discard unsaved changes first".to_string(),
+        ));
+    }
+    match &app.sequence_load_error {
+        Some(err) => Err(TimelineError::InvalidOp(err.clone())),
+        None => Ok(()),
     }
-    app.seq.tracks = tracks;
-    app.seq.duration_in_frames = max_end;
 }
 
-pub(super) fn item_from_node_impl(
-    node: &TimelineNode,
-    track_kind: &TrackKind,
-    fps: Fps,
-) -> Option<Item> {
-    let id = node.id.to_string();
-    match (&node.kind, track_kind) {
-        (TimelineNodeKind::Clip(clip), TrackKind::Audio) => {
-            let src = clip.asset_id.clone().unwrap_or_default();
-            Some(Item {
-                id,
-                from: clip.timeline_range.start,
-                duration_in_frames: clip.timeline_range.duration,
-                kind: ItemKind::Audio {
-                    src,
-                    in_offset_sec: crate::timeline::ui::frames_to_seconds(
-                        clip.media_range.start,
-                        fps,
-                    ),
-                    rate: clip.playback_rate,
-                },
-            })
-        }
-        (TimelineNodeKind::Clip(clip), _) => {
-            let src = clip.asset_id.clone().unwrap_or_default();
-            let is_image = std::path::Path::new(&src)
-                .extension()
-                .and_then(|e| e.to_str())
-                .map(|s| {
-                    s.eq_ignore_ascii_case("png")
-                        || s.eq_ignore_ascii_case("jpg")
-                        || s.eq_ignore_ascii_case("jpeg")
-                        || s.eq_ignore_ascii_case("gif")
-                        || s.eq_ignore_ascii_case("webp")
-                        || s.eq_ignore_ascii_case("bmp")
-                        || s.eq_ignore_ascii_case("tif")
-                        || s.eq_ignore_ascii_case("tiff")
-                        || s.eq_ignore_ascii_case("exr")
-                })
-                .unwrap_or(false);
-            if is_image {
-                Some(Item {
-                    id,
-                    from: clip.timeline_range.start,
-                    duration_in_frames: clip.timeline_range.duration,
-                    kind: ItemKind::Image { src },
-                })
-            } else {
-                Some(Item {
-                    id,
-                    from: clip.timeline_range.start,
-                    duration_in_frames: clip.timeline_range.duration,
-                    kind: ItemKind::Video {
-                        src,
-                        frame_rate: Some(fps.num as f32 / fps.den.max(1) as f32),
-                        in_offset_sec: crate::timeline::ui::frames_to_seconds(
-                            clip.media_range.start,
-                            fps,
-                        ),
-                        rate: clip.playback_rate,
-                    },
-                })
-            }
+/// Hand the mixer a fresh clip set if audio is playing, so edits such as a
+/// mute or a volume change are heard straight away.
+fn refresh_playing_audio(app: &mut App) {
+    if !app.playback_clock.playing {
+        return;
+    }
+    if let Ok(clips) = build_audio_clips_impl(app) {
+        if let Some(engine) = &app.audio_out {
+            engine.set_clips(clips);
         }
-        (
-            TimelineNodeKind::Generator {
-                generator_id,
-                timeline_range,
-                metadata,
-            },
-            _,
-        ) => match generator_id.as_str() {
-            "solid" => {
-                let color = metadata
-                    .get("color")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("#4c4c4c")
-                    .to_string();
-                Some(Item {
-                    id,
-                    from: timeline_range.start,
-                    duration_in_frames: timeline_range.duration,
-                    kind: ItemKind::Solid { color },
-                })
-            }
-            "text" => {
-                let text = metadata
-                    .get("text")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("")
-                    .to_string();
-                let color = metadata
-                    .get("color")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("#ffffff")
-                    .to_string();
-                Some(Item {
-                    id,
-                    from: timeline_range.start,
-                    duration_in_frames: timeline_range.duration,
-                    kind: ItemKind::Text { text, color },
-                })
-            }
-            _ => None,
-        },
-        _ => None,
     }
 }
 
+pub(super) fn sync_tracks_from_graph_impl(app: &mut App) {
+    app.seq.sync_tracks_from_graph();
+}
+
 pub(super) fn build_audio_clips_impl(app: &mut App) -> anyhow::Result<Vec<ActiveAudioClip>> {
     let seq_fps = app.seq.fps;
     let mut clips = Vec::new();
-    for binding in &app.seq.graph.tracks {
-        if !matches!(binding.kind, TrackKind::Audio) {
+    let graph = &app.seq.graph;
+    for (index, binding) in graph.tracks.iter().enumerate() {
+        if !graph.track_audible(index) {
             continue;
         }
-        for node_id in &binding.node_ids {
-            let node = match app.seq.graph.nodes.get(node_id) {
-                Some(n) => n,
-                None => continue,
-            };
-            let clip = match &node.kind {
-                TimelineNodeKind::Clip(c) => c,
+        let gain = binding.properties.stereo_gain();
+        // Compound clips on any track can carry sound from their nested tracks.
+        let flat = binding
+            .node_ids
+            .iter()
+            .filter_map(|id| graph.nodes.get(id))
+            .flat_map(|node| graph.flatten_node(node, &binding.kind));
+        for flat in flat {
+            let clip = match &flat.node.kind {
+                TimelineNodeKind::Clip(c) if matches!(flat.track_kind, TrackKind::Audio) => c,
                 _ => continue,
             };
             let path_str = match &clip.asset_id {
@@ -179,6 +131,10 @@ pub(super) fn build_audio_clips_impl(app: &mut App) -> anyhow::Result<Vec<Active
             let mut timeline_dur =
                 crate::timeline::ui::frames_to_seconds(clip.timeline_range.duration, seq_fps);
             let media_fps = clip_media_fps(app, clip);
+            if clip.time_remap.is_some() {
+                clips.extend(remapped_audio_pieces(clip, seq_fps, media_fps, &buf, gain));
+                continue;
+            }
             let mut media_start =
                 crate::timeline::ui::frames_to_seconds(clip.media_range.start, media_fps);
             let media_range_duration =
@@ -198,6 +154,7 @@ pub(super) fn build_audio_clips_impl(app: &mut App) -> anyhow::Result<Vec<Active
                 start_media_sec: media_start,
                 duration_sec: clip_duration,
                 buf: buf.clone(),
+                gain,
             });
         }
     }
@@ -210,6 +167,119 @@ pub(super) fn build_audio_clips_impl(app: &mut App) -> anyhow::Result<Vec<Active
     Ok(clips)
 }
 
+/// A stretch of a remapped clip that plays its media at one speed.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct MediaRun {
+    pub(crate) timeline_sec: f64,
+    pub(crate) media_sec: f64,
+    pub(crate) duration_sec: f64,
+    /// Media seconds per timeline second; 0 holds a frame, negative plays backwards.
+    pub(crate) speed: f64,
+}
+
+/// Split the timeline frames `from..to` of a clip into runs of constant
+/// speed, sampling `media_time_at` once per frame. Preview, audio and export
+/// all derive remapped playback from these runs.
+pub(crate) fn media_runs(
+    clip: &ClipNode,
+    from: i64,
+    to: i64,
+    seq_fps: Fps,
+    media_fps: Fps,
+) -> Vec<MediaRun> {
+    let frame_sec = crate::timeline::ui::frames_to_seconds(1, seq_fps);
+    let media_sec = |frame| {
+        clip.media_time_at(frame)
+            .map(|m| media_frames_to_seconds(m, media_fps))
+    };
+    let mut runs: Vec<MediaRun> = Vec::new();
+    for frame in from.max(clip.timeline_range.start)..to.min(clip.timeline_range.end()) {
+        let Some(media) = media_sec(frame) else {
+            continue;
+        };
+        let speed = match media_sec(frame + 1) {
+            Some(next) => (next - media) / frame_sec,
+            None => runs.last().map(|r| r.speed).unwrap_or(0.0),
+        };
+        if let Some(last) = runs.last_mut() {
+            let expected = last.media_sec + last.duration_sec * last.speed;
+            if (last.speed - speed).abs() < 1e-3 && (expected - media).abs() < frame_sec * 0.01 {
+                last.duration_sec += frame_sec;
+                continue;
+            }
+        }
+        runs.push(MediaRun {
+            timeline_sec: crate::timeline::ui::frames_to_seconds(frame, seq_fps),
+            media_sec: media,
+            duration_sec: frame_sec,
+            speed,
+        });
+    }
+    runs
+}
+
+/// Audio for a remapped clip. Runs at normal speed play straight through;
+/// faster, slower or reversed runs play one frame-long grain per timeline
+/// frame from where the curve puts it, and held frames stay silent.
+fn remapped_audio_pieces(
+    clip: &ClipNode,
+    seq_fps: Fps,
+    media_fps: Fps,
+    buf: &Arc<AudioBuffer>,
+    gain: [f32; 2],
+) -> Vec<ActiveAudioClip> {
+    let frame_sec = crate::timeline::ui::frames_to_seconds(1, seq_fps);
+    let media_len = buf.duration_sec as f64;
+    let mut pieces = Vec::new();
+    let runs = media_runs(
+        clip,
+        clip.timeline_range.start,
+        clip.timeline_range.end(),
+        seq_fps,
+        media_fps,
+    );
+    for run in runs {
+        if run.speed.abs() < 1e-6 {
+            continue;
+        }
+        let grains: Vec<(f64, f64, f64)> = if (run.speed - 1.0).abs() < 1e-3 {
+            vec![(run.timeline_sec, run.media_sec, run.duration_sec)]
+        } else {
+            let count = (run.duration_sec / frame_sec).round() as i64;
+            (0..count)
+                .map(|i| {
+                    let offset = i as f64 * frame_sec;
+                    (
+                        run.timeline_sec + offset,
+                        run.media_sec + offset * run.speed,
+                        frame_sec,
+                    )
+                })
+                .collect()
+        };
+        for (timeline_sec, media_sec, duration_sec) in grains {
+            if media_sec < 0.0 || media_sec >= media_len {
+                continue;
+            }
+            pieces.push(ActiveAudioClip {
+                start_tl_sec: timeline_sec,
+                start_media_sec: media_sec,
+                duration_sec: duration_sec.min(media_len - media_sec),
+                buf: buf.clone(),
+                gain,
+            });
+        }
+    }
+    pieces
+}
+
+pub(crate) fn media_frames_to_seconds(frames: f64, fps: Fps) -> f64 {
+    if fps.num == 0 {
+        return 0.0;
+    }
+    frames * fps.den.max(1) as f64 / fps.num as f64
+}
+
 fn clip_media_fps(app: &App, clip: &crate::timeline_crate::ClipNode) -> Fps {
     if let Value::Object(map) = &clip.metadata {
         let num = map
@@ -249,73 +319,87 @@ pub(super) fn active_video_media_time_graph_impl(
     app: &App,
     timeline_sec: f64,
 ) -> Option<(String, f64)> {
-    let seq_fps = (app.seq.fps.num.max(1) as f64) / (app.seq.fps.den.max(1) as f64);
-    let playhead = (timeline_sec * seq_fps).round() as i64;
-    // Priority: lower-numbered (top-most) video tracks first
-    for binding in app.seq.graph.tracks.iter() {
-        if matches!(binding.kind, TrackKind::Audio) {
-            continue;
-        }
-        for node_id in &binding.node_ids {
-            let Some(node) = app.seq.graph.nodes.get(node_id) else {
-                continue;
-            };
-            let clip = match &node.kind {
-                TimelineNodeKind::Clip(c) => c,
-                _ => continue,
-            };
-            if playhead < clip.timeline_range.start || playhead >= clip.timeline_range.end() {
-                continue;
-            }
-            let Some(path) = clip.asset_id.clone() else {
-                continue;
-            };
-            let start_on_timeline_sec = clip.timeline_range.start as f64 / seq_fps;
-            let local_t = (timeline_sec - start_on_timeline_sec).max(0.0);
-            let media_fps = clip_media_fps(app, clip);
-            let media_sec =
-                crate::timeline::ui::frames_to_seconds(clip.media_range.start, media_fps)
-                    + local_t * clip.playback_rate as f64;
-            return Some((path, media_sec));
-        }
-    }
-    None
+    active_media_time_impl(app, timeline_sec, false)
 }
 
 pub(super) fn active_audio_media_time_graph_impl(
     app: &App,
     timeline_sec: f64,
 ) -> Option<(String, f64)> {
+    active_media_time_impl(app, timeline_sec, true)
+        .or_else(|| active_video_media_time_graph_impl(app, timeline_sec))
+}
+
+fn active_media_time_impl(app: &App, timeline_sec: f64, audio: bool) -> Option<(String, f64)> {
     let seq_fps = (app.seq.fps.num.max(1) as f64) / (app.seq.fps.den.max(1) as f64);
     let playhead = (timeline_sec * seq_fps).round() as i64;
-    for binding in app.seq.graph.tracks.iter().rev() {
-        if !matches!(binding.kind, TrackKind::Audio) {
+    let graph = &app.seq.graph;
+    let (clip, offset) = active_clip_at(graph, graph, playhead, audio, 0)?;
+    let path = clip.asset_id.clone()?;
+    let media_fps = clip_media_fps(app, clip);
+    if clip.time_remap.is_some() {
+        let media_frame = clip.media_time_at(playhead - offset)?;
+        return Some((path, media_frames_to_seconds(media_frame, media_fps)));
+    }
+    // `offset` maps the clip's (possibly nested) timeline back onto the sequence
+    let start_on_timeline_sec = (clip.timeline_range.start + offset) as f64 / seq_fps;
+    let local_t = (timeline_sec - start_on_timeline_sec).max(0.0);
+    let media_sec = crate::timeline::ui::frames_to_seconds(clip.media_range.start, media_fps)
+        + local_t * clip.playback_rate as f64;
+    Some((path, media_sec))
+}
+
+/// Clip under `playhead`, looking through compound clips. Video tracks are
+/// searched top-most first, audio tracks bottom-most first. Returns the clip
+/// and the offset from its graph's frames to the root timeline.
+fn active_clip_at<'a>(
+    root: &'a TimelineGraph,
+    graph: &'a TimelineGraph,
+    playhead: i64,
+    audio: bool,
+    depth: usize,
+) -> Option<(&'a ClipNode, i64)> {
+    if depth > MAX_COMPOUND_DEPTH {
+        return None;
+    }
+    let mut bindings: Vec<_> = graph.tracks.iter().enumerate().collect();
+    if audio {
+        bindings.reverse();
+    }
+    for (track_index, binding) in bindings {
+        if matches!(binding.kind, TrackKind::Audio) != audio {
             continue;
         }
-        for node_id in &binding.node_ids {
-            let Some(node) = app.seq.graph.nodes.get(node_id) else {
+        let enabled = if audio {
+            graph.track_audible(track_index)
+        } else {
+            graph.track_visible(track_index)
+        };
+        if !enabled {
+            continue;
+        }
+        for node_id in graph.nodes_at(track_index, playhead) {
+            let Some(node) = graph.nodes.get(&node_id) else {
                 continue;
             };
-            let clip = match &node.kind {
-                TimelineNodeKind::Clip(c) => c,
-                _ => continue,
-            };
-            if playhead < clip.timeline_range.start || playhead >= clip.timeline_range.end() {
-                continue;
+            match &node.kind {
+                TimelineNodeKind::Clip(clip) => return Some((clip, 0)),
+                TimelineNodeKind::Compound(compound) => {
+                    let Some(inner) = root.sequence(&compound.sequence_id) else {
+                        continue;
+                    };
+                    let inner_frame = compound.inner_frame(playhead);
+                    if let Some((clip, offset)) =
+                        active_clip_at(root, inner, inner_frame, audio, depth + 1)
+                    {
+                        return Some((clip, offset + playhead - inner_frame));
+                    }
+                }
+                _ => {}
             }
-            let Some(path) = clip.asset_id.clone() else {
-                continue;
-            };
-            let start_on_timeline_sec = clip.timeline_range.start as f64 / seq_fps;
-            let local_t = (timeline_sec - start_on_timeline_sec).max(0.0);
-            let media_fps = clip_media_fps(app, clip);
-            let media_sec =
-                crate::timeline::ui::frames_to_seconds(clip.media_range.start, media_fps)
-                    + local_t * clip.playback_rate as f64;
-            return Some((path, media_sec));
         }
     }
-    active_video_media_time_graph_impl(app, timeline_sec)
+    None
 }
 
 pub(super) fn request_audio_peaks_impl(_app: &mut App, _path: &std::path::Path) {
@@ -334,6 +418,22 @@ impl App {
         self::apply_timeline_command_impl(self, command)
     }
 
+    pub(crate) fn apply_timeline_batch(
+        &mut self,
+        label: &str,
+        commands: Vec<TimelineCommand>,
+    ) -> Result<(), TimelineError> {
+        self::apply_timeline_batch_impl(self, label, commands)
+    }
+
+    pub(crate) fn undo_timeline(&mut self) -> Result<(), TimelineError> {
+        self::undo_timeline_impl(self)
+    }
+
+    pub(crate) fn redo_timeline(&mut self) -> Result<(), TimelineError> {
+        self::redo_timeline_impl(self)
+    }
+
     pub(crate) fn sync_tracks_from_graph(&mut self) {
         self::sync_tracks_from_graph_impl(self)
     }
@@ -343,7 +443,7 @@ impl App {
         track_kind: &TrackKind,
         fps: Fps,
     ) -> Option<Item> {
-        self::item_from_node_impl(node, track_kind, fps)
+        crate::timeline_crate::item_from_node(node, track_kind, fps)
     }
 
     pub(crate) fn build_audio_clips(&mut self) -> anyhow::Result<Vec<ActiveAudioClip>> {
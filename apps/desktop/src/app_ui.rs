@@ -118,7 +118,63 @@ pub(super) fn top_toolbar(app: &mut App, ctx: &egui::Context, _frame: &mut efram
     });
 }
 
+/// Picker for the project's sequences plus new, duplicate, rename and delete.
+fn sequence_picker(app: &mut App, ui: &mut egui::Ui) {
+    ui.label("Sequence:");
+    let mut chosen = None;
+    ui.add_enabled_ui(app.pending_recovery.is_none(), |ui| {
+        ComboBox::from_id_salt("sequence_picker")
+            .selected_text(app.seq.name.clone())
+            .show_ui(ui, |ui| {
+                for row in &app.sequences {
+                    if ui.selectable_label(row.id == app.sequence_id, &row.name).clicked() {
+                        chosen = Some(row.id.clone());
+                    }
+                }
+            });
+        ui.menu_button("⋯", |ui| {
+            if ui.button("New sequence").clicked() {
+                super::app_project::create_sequence(app);
+                ui.close_menu();
+            }
+            if ui.button("Duplicate").clicked() {
+                super::app_project::duplicate_sequence(app);
+                ui.close_menu();
+            }
+            ui.separator();
+            let name = app.seq.name.clone();
+            let draft = app.sequence_name_draft.get_or_insert(name);
+            let mut rename = false;
+            ui.horizontal(|ui| {
+                let response = ui.add(egui::TextEdit::singleline(&mut *draft).desired_width(140.0));
+                rename = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                rename |= ui.button("Rename").clicked();
+            });
+            if rename {
+                let name = draft.clone();
+                super::app_project::rename_sequence(app, &name);
+                ui.close_menu();
+            }
+            ui.separator();
+            let deletable = app.sequences.len() > 1;
+            if ui
+                .add_enabled(deletable, egui::Button::new("Delete sequence"))
+                .on_disabled_hover_text("A project keeps at least one sequence")
+                .clicked()
+            {
+                super::app_project::delete_sequence(app);
+                ui.close_menu();
+            }
+        });
+    });
+    if let Some(id) = chosen {
+        super::app_project::switch_sequence(app, &id);
+    }
+    ui.separator();
+}
+
 fn timeline_toolbar(app: &mut App, ui: &mut egui::Ui) {
+    sequence_picker(app, ui);
     ui.label("Import path:");
     ui.text_edit_singleline(&mut app.import_path);
     if ui.button("Add").clicked() {
@@ -140,6 +196,23 @@ fn timeline_toolbar(app: &mut App, ui: &mut egui::Ui) {
     if ui.button("Settings").clicked() {
         app.show_settings = !app.show_settings;
     }
+    if ui.button("Snapshots").clicked() {
+        app.show_snapshots = !app.show_snapshots;
+    }
+    if ui.button("Archive").clicked() {
+        app.show_archive = !app.show_archive;
+    }
+    if !app.offline_media.is_empty() {
+        let label = egui::RichText::new(format!("{} offline", app.offline_media.len()))
+            .color(egui::Color32::from_rgb(230, 90, 70));
+        if ui
+            .button(label)
+            .on_hover_text("Media that moved or changed since it was imported")
+            .clicked()
+        {
+            app.show_relink = true;
+        }
+    }
     ui.separator();
     if ui
         .button(if app.engine.state == super::decode::PlayState::Playing {
@@ -267,6 +340,251 @@ pub(super) fn preview_settings_window(app: &mut App, ctx: &egui::Context) {
     }
 }
 
+/// Offers to replay timeline edits that a previous session journaled but
+/// never wrote into a snapshot.
+pub(super) fn timeline_recovery_window(app: &mut App, ctx: &egui::Context) {
+    let Some(entries) = &app.pending_recovery else {
+        return;
+    };
+    let count = entries.len();
+    let mut choice = None;
+    egui::Window::new("Recover unsaved changes")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+        .show(ctx, |ui| {
+            ui.label(format!(
+                "This project has {} timeline edit{} that were not saved before it was last closed.",
+                count,
+                if count == 1 { "" } else { "s" }
+            ));
+            ui.small("Recovering replays them on top of the last saved timeline.");
+            ui.horizontal(|ui| {
+                if ui.button("Recover").clicked() {
+                    choice = Some(true);
+                }
+                if ui.button("Discard").clicked() {
+                    choice = Some(false);
+                }
+            });
+        });
+    if let Some(recover) = choice {
+        app.resolve_timeline_recovery(recover);
+    }
+}
+
+/// Named and autosaved snapshots of the project: take, compare, restore.
+pub(super) fn project_snapshots_window(app: &mut App, ctx: &egui::Context) {
+    if !app.show_snapshots {
+        return;
+    }
+    let snapshots = app
+        .db
+        .list_project_snapshots(&app.project_id)
+        .unwrap_or_default();
+    let mut open = true;
+    let mut take = false;
+    let mut compare = None;
+    let mut restore = None;
+    let mut delete = None;
+    egui::Window::new("Project snapshots")
+        .open(&mut open)
+        .default_width(360.0)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut app.snapshot_name)
+                        .hint_text("Snapshot name")
+                        .desired_width(200.0),
+                );
+                take = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                take |= ui
+                    .add_enabled(
+                        !app.snapshot_name.trim().is_empty(),
+                        egui::Button::new("Take snapshot"),
+                    )
+                    .clicked();
+            });
+            ui.small(
+                "Restoring brings back the timelines, settings and asset list. \
+                 Media files are not touched.",
+            );
+            ui.separator();
+            if snapshots.is_empty() {
+                ui.small("No snapshots yet.");
+            }
+            ScrollArea::vertical().max_height(240.0).show(ui, |ui| {
+                for snap in &snapshots {
+                    ui.horizontal(|ui| {
+                        let when = Local
+                            .timestamp_opt(snap.created_at, 0)
+                            .single()
+                            .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+                            .unwrap_or_default();
+                        match &snap.name {
+                            Some(name) => ui.label(RichText::new(name).strong()),
+                            None => ui.label(RichText::new("Autosave").italics()),
+                        };
+                        ui.small(when);
+                        if ui.small_button("Compare").clicked() {
+                            compare = Some(snap.id.clone());
+                        }
+                        if ui
+                            .small_button("Restore")
+                            .on_hover_text("The current state is snapshotted first")
+                            .clicked()
+                        {
+                            restore = Some(snap.id.clone());
+                        }
+                        if ui.small_button("🗑").on_hover_text("Delete snapshot").clicked() {
+                            delete = Some(snap.id.clone());
+                        }
+                    });
+                }
+            });
+            if let Some((id, lines)) = &app.snapshot_comparison {
+                ui.separator();
+                let name = snapshots
+                    .iter()
+                    .find(|s| &s.id == id)
+                    .and_then(|s| s.name.clone())
+                    .unwrap_or_else(|| "autosave".to_string());
+                ui.label(format!("Changes since \"{}\":", name));
+                for line in lines {
+                    ui.small(line);
+                }
+            }
+        });
+    if take {
+        let name = std::mem::take(&mut app.snapshot_name);
+        super::app_project::take_project_snapshot(app, &name);
+    }
+    if let Some(id) = compare {
+        let lines = super::app_project::compare_project_snapshot(app, &id);
+        app.snapshot_comparison = Some((id, lines));
+    }
+    if let Some(id) = restore {
+        super::app_project::restore_project_snapshot(app, &id);
+        app.snapshot_comparison = None;
+    }
+    if let Some(id) = delete {
+        if let Err(err) = app.db.delete_project_snapshot(&id) {
+            tracing::warn!(error = %err, "failed to delete project snapshot");
+        }
+        if app.snapshot_comparison.as_ref().is_some_and(|(c, _)| *c == id) {
+            app.snapshot_comparison = None;
+        }
+    }
+    if !open {
+        app.show_snapshots = false;
+    }
+}
+
+pub(super) fn project_archive_window(app: &mut App, ctx: &egui::Context) {
+    let mut open = app.show_archive;
+    let mut output = None;
+    egui::Window::new("Archive project")
+        .open(&mut open)
+        .resizable(false)
+        .show(ctx, |ui| {
+            let options = &mut app.archive_options;
+            ui.checkbox(&mut options.trim, "Trim media to the used ranges");
+            ui.add_enabled_ui(options.trim, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Handles");
+                    ui.add(
+                        egui::DragValue::new(&mut options.handles_seconds)
+                            .range(0.0..=60.0)
+                            .speed(0.1)
+                            .suffix(" s"),
+                    );
+                });
+            });
+            ui.checkbox(&mut options.include_proxies, "Include proxies");
+            ui.checkbox(&mut options.zip, "Write a zip file");
+            ui.small(
+                "Trimmed media is re-encoded to ProRes or PCM and leaves its proxy behind. \
+                 Snapshots and job history are not archived.",
+            );
+            ui.separator();
+            if ui.button("Archive...").clicked() {
+                let name = app
+                    .db
+                    .list_projects()
+                    .ok()
+                    .and_then(|list| list.into_iter().find(|p| p.id == app.project_id))
+                    .map(|p| format!("{} Archive", p.name))
+                    .unwrap_or_else(|| "Project Archive".to_string());
+                output = if app.archive_options.zip {
+                    app.file_dialog()
+                        .add_filter("Zip", &["zip"])
+                        .set_file_name(format!("{name}.zip"))
+                        .save_file()
+                } else {
+                    app.file_dialog().pick_folder().map(|dir| dir.join(&name))
+                };
+            }
+            if let Some(status) = &app.archive_status {
+                ui.small(status);
+            }
+        });
+    if let Some(output) = output {
+        super::app_project::archive_project(app, &output);
+    }
+    app.show_archive = open;
+}
+
+pub(super) fn relink_media_window(app: &mut App, ctx: &egui::Context) {
+    let mut open = app.show_relink;
+    let mut folder = None;
+    let mut rescan = false;
+    egui::Window::new("Offline media")
+        .open(&mut open)
+        .default_width(420.0)
+        .show(ctx, |ui| {
+            if app.offline_media.is_empty() {
+                ui.label("All media is online.");
+            }
+            let mut offline: Vec<_> = app.offline_media.iter().collect();
+            offline.sort();
+            egui::ScrollArea::vertical().max_height(240.0).show(ui, |ui| {
+                for (path, status) in offline {
+                    let state = match status {
+                        project::MediaStatus::Changed => "changed",
+                        _ => "missing",
+                    };
+                    ui.horizontal(|ui| {
+                        ui.colored_label(egui::Color32::from_rgb(230, 90, 70), state);
+                        ui.label(path);
+                    });
+                }
+            });
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui.button("Search folder...").clicked() {
+                    folder = app.file_dialog().pick_folder();
+                }
+                if ui.button("Rescan").clicked() {
+                    rescan = true;
+                }
+            });
+            ui.small(
+                "Missing files are found by content under any name, or by name and duration. \
+                 Changed files have to be put back or imported again.",
+            );
+            if let Some(status) = &app.relink_status {
+                ui.small(status);
+            }
+        });
+    if rescan {
+        super::app_project::refresh_media_status(app);
+    }
+    if let Some(folder) = folder {
+        super::app_project::relink_media(app, &folder);
+    }
+    app.show_relink = open;
+}
+
 pub(super) fn show_project_picker_if_needed(app: &mut App, ctx: &egui::Context) -> bool {
     if matches!(app.mode, super::AppMode::ProjectPicker) {
         egui::CentralPanel::default().show(ctx, |ui| {
@@ -420,6 +738,24 @@ pub(super) fn show_project_picker_if_needed(app: &mut App, ctx: &egui::Context)
                 app.load_project_timeline();
                 app.mode = super::AppMode::Editor;
             }
+            ui.separator();
+            ui.heading("Open Archive");
+            ui.small("Adds an archived project and relinks it to the media inside the archive.");
+            let mut archive = None;
+            ui.horizontal(|ui| {
+                if ui.button("From zip...").clicked() {
+                    archive = app.file_dialog().add_filter("Zip", &["zip"]).pick_file();
+                }
+                if ui.button("From folder...").clicked() {
+                    archive = app.file_dialog().pick_folder();
+                }
+            });
+            if let Some(status) = &app.archive_status {
+                ui.small(status);
+            }
+            if let Some(archive) = archive {
+                super::app_project::unarchive_project(app, &archive);
+            }
         });
         return true;
     }
@@ -1256,11 +1592,16 @@ fn handle_embedded_comfy_paste(ui: &egui::Ui, rect: egui::Rect, app: &mut App) {
 }
 
 pub(super) fn assets_scroll_section(app: &mut App, ui: &mut egui::Ui, assets: &[AssetRow]) {
+    asset_view_picker(app, ui);
+    smart_collection_window(app, &ui.ctx().clone());
+    let shown = super::app_assets::visible_assets(app, assets);
     egui::ScrollArea::vertical()
         .auto_shrink([false, false])
         .show(ui, |ui| {
             if assets.is_empty() {
                 ui.small("No assets in this project yet. Use Import… to add media.");
+            } else if shown.is_empty() {
+                ui.small("No assets match this view.");
             } else {
                 let cell = 80.0f32; // fixed square thumbnails
                 let card_w = cell + 4.0; // small horizontal gap
@@ -1269,7 +1610,7 @@ pub(super) fn assets_scroll_section(app: &mut App, ui: &mut egui::Ui, assets: &[
                     .num_columns(cols)
                     .spacing([2.0, 8.0])
                     .show(ui, |ui| {
-                        for (i, a) in assets.iter().enumerate() {
+                        for (i, a) in shown.iter().enumerate() {
                             ui.vertical(|ui| {
                                 // Square slot
                                 let (r, resp) = ui.allocate_exact_size(
@@ -1336,6 +1677,15 @@ pub(super) fn assets_scroll_section(app: &mut App, ui: &mut egui::Ui, assets: &[
                                         egui::Color32::WHITE,
                                     );
                                 }
+                                if let Some(color) =
+                                    a.color_label.as_deref().and_then(color_label_color)
+                                {
+                                    ui.painter().circle_filled(
+                                        r.left_top() + egui::vec2(8.0, 8.0),
+                                        5.0,
+                                        color,
+                                    );
+                                }
                                 // Interactions
                                 if resp.drag_started() {
                                     app.dragging_asset = Some(a.clone());
@@ -1343,19 +1693,34 @@ pub(super) fn assets_scroll_section(app: &mut App, ui: &mut egui::Ui, assets: &[
                                 if resp.clicked() {
                                     app.add_asset_to_timeline(a);
                                 }
+                                resp.context_menu(|ui| {
+                                    if ui.button("Use as edit source").clicked() {
+                                        app.edit_marks.source = Some(a.clone());
+                                        app.edit_marks.source_in = None;
+                                        app.edit_marks.source_out = None;
+                                        ui.close_menu();
+                                    }
+                                    ui.separator();
+                                    asset_organize_menu(app, ui, a);
+                                    ui.separator();
+                                    asset_usage_menu(app, ui, a);
+                                });
                                 ui.add_space(2.0);
                                 proxy_status_badge(ui, app, a, cell);
                                 let name = asset_display_name(a);
                                 let lbl = egui::Label::new(egui::RichText::new(name).small());
                                 ui.add_sized([cell, 14.0], lbl);
                                 ui.small(&a.kind);
+                                if a.rating > 0 {
+                                    ui.small("★".repeat(a.rating as usize));
+                                }
                             });
                             if (i + 1) % cols == 0 {
                                 ui.end_row();
                             }
                         }
                         // Ensure the last row ends
-                        if assets.len() % cols != 0 {
+                        if shown.len() % cols != 0 {
                             ui.end_row();
                         }
                     });
@@ -1528,6 +1893,397 @@ fn proxy_status_badge(ui: &mut egui::Ui, app: &App, asset: &AssetRow, width: f32
     }
 }
 
+const COLOR_LABELS: [(&str, egui::Color32); 6] = [
+    ("red", egui::Color32::from_rgb(220, 70, 70)),
+    ("orange", egui::Color32::from_rgb(230, 140, 50)),
+    ("yellow", egui::Color32::from_rgb(220, 200, 60)),
+    ("green", egui::Color32::from_rgb(80, 180, 90)),
+    ("blue", egui::Color32::from_rgb(70, 130, 220)),
+    ("purple", egui::Color32::from_rgb(160, 90, 210)),
+];
+
+fn color_label_color(label: &str) -> Option<egui::Color32> {
+    COLOR_LABELS
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(label))
+        .map(|(_, color)| *color)
+}
+
+/// Bins in depth-first order, each with its nesting depth.
+fn bin_tree(bins: &[project::BinRow]) -> Vec<(&project::BinRow, usize)> {
+    fn visit<'a>(
+        bins: &'a [project::BinRow],
+        parent: Option<&str>,
+        depth: usize,
+        out: &mut Vec<(&'a project::BinRow, usize)>,
+    ) {
+        for bin in bins.iter().filter(|b| b.parent_id.as_deref() == parent) {
+            out.push((bin, depth));
+            visit(bins, Some(&bin.id), depth + 1, out);
+        }
+    }
+    let mut out = Vec::new();
+    visit(bins, None, 0, &mut out);
+    out
+}
+
+/// Picks what the assets grid shows and manages bins and smart collections.
+fn asset_view_picker(app: &mut App, ui: &mut egui::Ui) {
+    use super::app_assets::{AssetView, CollectionDraft};
+    let current = match &app.asset_view {
+        AssetView::All => "All assets".to_string(),
+        AssetView::Bin(id) => app
+            .bins
+            .iter()
+            .find(|b| &b.id == id)
+            .map(|b| format!("📁 {}", b.name))
+            .unwrap_or_default(),
+        AssetView::Collection(id) => app
+            .smart_collections
+            .iter()
+            .find(|c| &c.id == id)
+            .map(|c| format!("🔎 {}", c.name))
+            .unwrap_or_default(),
+        AssetView::Unused => "Unused assets".to_string(),
+    };
+    ui.horizontal(|ui| {
+        ui.label("Show:");
+        let mut view = app.asset_view.clone();
+        ComboBox::from_id_salt("asset_view")
+            .selected_text(current)
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut view, AssetView::All, "All assets");
+                ui.selectable_value(&mut view, AssetView::Unused, "Unused assets");
+                if !app.bins.is_empty() {
+                    ui.separator();
+                }
+                for (bin, depth) in bin_tree(&app.bins) {
+                    let label = format!("{}📁 {}", "   ".repeat(depth), bin.name);
+                    ui.selectable_value(&mut view, AssetView::Bin(bin.id.clone()), label);
+                }
+                if !app.smart_collections.is_empty() {
+                    ui.separator();
+                }
+                for c in &app.smart_collections {
+                    let label = format!("🔎 {}", c.name);
+                    ui.selectable_value(&mut view, AssetView::Collection(c.id.clone()), label);
+                }
+            });
+        app.asset_view = view;
+        ui.menu_button("⋯", |ui| {
+            let mut create = false;
+            ui.horizontal(|ui| {
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut app.new_bin_name)
+                        .hint_text("Bin name")
+                        .desired_width(120.0),
+                );
+                create = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                create |= ui.button("New bin").clicked();
+            });
+            if create {
+                let name = std::mem::take(&mut app.new_bin_name);
+                super::app_assets::create_bin(app, &name);
+                ui.close_menu();
+            }
+            if ui.button("New smart collection…").clicked() {
+                app.collection_draft = Some(CollectionDraft::default());
+                ui.close_menu();
+            }
+            match app.asset_view.clone() {
+                AssetView::All | AssetView::Unused => {}
+                AssetView::Bin(id) => {
+                    ui.separator();
+                    if ui
+                        .add_enabled(
+                            !app.new_bin_name.trim().is_empty(),
+                            egui::Button::new("Rename bin"),
+                        )
+                        .on_disabled_hover_text("Type the new name above")
+                        .clicked()
+                    {
+                        let name = std::mem::take(&mut app.new_bin_name);
+                        if let Err(err) = app.db.rename_bin(&id, name.trim()) {
+                            tracing::warn!(error = %err, "failed to rename bin");
+                        }
+                        super::app_assets::refresh_organization(app);
+                        ui.close_menu();
+                    }
+                    if ui
+                        .button("Delete bin")
+                        .on_hover_text("Its assets and bins move up a level")
+                        .clicked()
+                    {
+                        super::app_assets::delete_bin(app, &id);
+                        ui.close_menu();
+                    }
+                }
+                AssetView::Collection(id) => {
+                    ui.separator();
+                    if ui.button("Edit collection…").clicked() {
+                        app.collection_draft = app
+                            .smart_collections
+                            .iter()
+                            .find(|c| c.id == id)
+                            .map(CollectionDraft::edit);
+                        ui.close_menu();
+                    }
+                    if ui.button("Delete collection").clicked() {
+                        super::app_assets::delete_collection(app, &id);
+                        ui.close_menu();
+                    }
+                }
+            }
+        });
+    });
+}
+
+/// Where an asset is used, and removing it once nothing uses it, for its
+/// context menu.
+fn asset_usage_menu(app: &mut App, ui: &mut egui::Ui, asset: &AssetRow) {
+    let mut used_in: Vec<String> = app
+        .db
+        .sequences_using_asset(&asset.id)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|row| row.id != app.sequence_id)
+        .map(|row| row.name)
+        .collect();
+    if super::app_assets::open_sequence_media(app).contains(asset.src_abs.as_str()) {
+        used_in.insert(0, app.seq.name.clone());
+    }
+    if used_in.is_empty() {
+        ui.label("Not used in any sequence");
+    } else {
+        ui.label(format!("Used in {}", used_in.join(", ")));
+    }
+    if ui
+        .add_enabled(used_in.is_empty(), egui::Button::new("Remove from project"))
+        .on_disabled_hover_text("Remove it from its sequences first")
+        .clicked()
+    {
+        super::app_assets::delete_asset(app, &asset.id);
+        ui.close_menu();
+    }
+}
+
+/// Rating, colour label, bin and tags of an asset, for its context menu.
+fn asset_organize_menu(app: &mut App, ui: &mut egui::Ui, asset: &AssetRow) {
+    ui.horizontal(|ui| {
+        ui.label("Rating:");
+        for star in 1..=5 {
+            let text = if star <= asset.rating { "★" } else { "☆" };
+            if ui.add(egui::Button::new(text).frame(false)).clicked() {
+                // Clicking the current rating clears it
+                let rating = if star == asset.rating { 0 } else { star };
+                let _ = app.db.set_asset_rating(&asset.id, rating);
+            }
+        }
+    });
+    ui.menu_button("Colour label", |ui| {
+        if ui
+            .selectable_label(asset.color_label.is_none(), "None")
+            .clicked()
+        {
+            let _ = app.db.set_asset_color_label(&asset.id, None);
+            ui.close_menu();
+        }
+        for (name, color) in COLOR_LABELS {
+            let selected = asset.color_label.as_deref() == Some(name);
+            if ui
+                .selectable_label(selected, RichText::new(format!("● {name}")).color(color))
+                .clicked()
+            {
+                let _ = app.db.set_asset_color_label(&asset.id, Some(name));
+                ui.close_menu();
+            }
+        }
+    });
+    ui.menu_button("Move to bin", |ui| {
+        let mut target = None;
+        if ui.selectable_label(asset.bin_id.is_none(), "No bin").clicked() {
+            target = Some(None);
+        }
+        for (bin, depth) in bin_tree(&app.bins) {
+            let label = format!("{}📁 {}", "   ".repeat(depth), bin.name);
+            let selected = asset.bin_id.as_deref() == Some(bin.id.as_str());
+            if ui.selectable_label(selected, label).clicked() {
+                target = Some(Some(bin.id.clone()));
+            }
+        }
+        if let Some(bin_id) = target {
+            let _ = app.db.set_asset_bin(&asset.id, bin_id.as_deref());
+            ui.close_menu();
+        }
+    });
+    ui.menu_button("Tags", |ui| {
+        for tag in app.db.list_asset_tags(&asset.id).unwrap_or_default() {
+            ui.horizontal(|ui| {
+                ui.label(&tag);
+                if ui.small_button("✕").clicked() {
+                    let _ = app.db.remove_asset_tag(&asset.id, &tag);
+                }
+            });
+        }
+        let mut add = false;
+        ui.horizontal(|ui| {
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut app.asset_tag_draft)
+                    .hint_text("New tag")
+                    .desired_width(100.0),
+            );
+            add = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            add |= ui.button("Add").clicked();
+        });
+        if add && !app.asset_tag_draft.trim().is_empty() {
+            let tag = std::mem::take(&mut app.asset_tag_draft);
+            let _ = app.db.add_asset_tag(&asset.id, &tag);
+        }
+    });
+}
+
+/// A lower and an upper bound, each switched on by its checkbox.
+fn bound_fields<T: egui::emath::Numeric>(
+    ui: &mut egui::Ui,
+    min: &mut Option<T>,
+    max: &mut Option<T>,
+    default: T,
+) {
+    ui.horizontal(|ui| {
+        for (label, bound) in [("min", min), ("max", max)] {
+            let mut on = bound.is_some();
+            if ui.checkbox(&mut on, label).changed() {
+                *bound = on.then_some(default);
+            }
+            if let Some(value) = bound.as_mut() {
+                ui.add(egui::DragValue::new(value));
+            }
+        }
+    });
+}
+
+/// Editor for the smart collection being created or changed.
+fn smart_collection_window(app: &mut App, ctx: &egui::Context) {
+    let Some(draft) = app.collection_draft.as_mut() else {
+        return;
+    };
+    let title = if draft.id.is_some() {
+        "Edit smart collection"
+    } else {
+        "New smart collection"
+    };
+    let mut open = true;
+    let mut save = false;
+    egui::Window::new(title)
+        .id(egui::Id::new("smart_collection_editor"))
+        .open(&mut open)
+        .collapsible(false)
+        .resizable(false)
+        .show(ctx, |ui| {
+            let query = &mut draft.query;
+            egui::Grid::new("smart_collection_fields")
+                .num_columns(2)
+                .show(ui, |ui| {
+                    ui.label("Name");
+                    ui.text_edit_singleline(&mut draft.name);
+                    ui.end_row();
+
+                    ui.label("Kind");
+                    ui.horizontal(|ui| {
+                        for kind in ["video", "image", "audio"] {
+                            let mut on = query.kinds.iter().any(|k| k == kind);
+                            if ui.checkbox(&mut on, kind).changed() {
+                                query.kinds.retain(|k| k != kind);
+                                if on {
+                                    query.kinds.push(kind.to_string());
+                                }
+                            }
+                        }
+                    });
+                    ui.end_row();
+
+                    ui.label("Codecs");
+                    ui.add(egui::TextEdit::singleline(&mut draft.codecs).hint_text("h264, prores"));
+                    ui.end_row();
+
+                    ui.label("Width");
+                    bound_fields(ui, &mut query.min_width, &mut query.max_width, 1920);
+                    ui.end_row();
+
+                    ui.label("Height");
+                    bound_fields(ui, &mut query.min_height, &mut query.max_height, 1080);
+                    ui.end_row();
+
+                    ui.label("Duration (s)");
+                    bound_fields(
+                        ui,
+                        &mut query.min_duration_seconds,
+                        &mut query.max_duration_seconds,
+                        10.0,
+                    );
+                    ui.end_row();
+
+                    ui.label("Tags");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut draft.tags)
+                            .hint_text("all of, comma separated"),
+                    );
+                    ui.end_row();
+
+                    ui.label("Proxy");
+                    let proxy_text = match query.proxy_ready {
+                        None => "Any",
+                        Some(true) => "Ready",
+                        Some(false) => "Not ready",
+                    };
+                    ComboBox::from_id_salt("smart_collection_proxy")
+                        .selected_text(proxy_text)
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut query.proxy_ready, None, "Any");
+                            ui.selectable_value(&mut query.proxy_ready, Some(true), "Ready");
+                            ui.selectable_value(&mut query.proxy_ready, Some(false), "Not ready");
+                        });
+                    ui.end_row();
+
+                    ui.label("Rating");
+                    ui.horizontal(|ui| {
+                        let mut on = query.min_rating.is_some();
+                        if ui.checkbox(&mut on, "at least").changed() {
+                            query.min_rating = on.then_some(3);
+                        }
+                        if let Some(rating) = query.min_rating.as_mut() {
+                            ui.add(egui::DragValue::new(rating).range(1..=5).suffix(" ★"));
+                        }
+                    });
+                    ui.end_row();
+
+                    ui.label("Colour");
+                    ComboBox::from_id_salt("smart_collection_color")
+                        .selected_text(query.color_label.as_deref().unwrap_or("Any"))
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut query.color_label, None, "Any");
+                            for (name, color) in COLOR_LABELS {
+                                ui.selectable_value(
+                                    &mut query.color_label,
+                                    Some(name.to_string()),
+                                    RichText::new(format!("● {name}")).color(color),
+                                );
+                            }
+                        });
+                    ui.end_row();
+                });
+            ui.separator();
+            save = ui.button("Save").clicked();
+        });
+    if save {
+        if let Some(draft) = app.collection_draft.take() {
+            super::app_assets::save_collection(app, draft);
+        }
+    } else if !open {
+        app.collection_draft = None;
+    }
+}
+
 fn asset_display_name(asset: &AssetRow) -> String {
     Path::new(&asset.src_abs)
         .file_name()
@@ -1582,9 +2338,17 @@ pub(super) fn properties_panel(app: &mut App, ctx: &egui::Context) {
     egui::SidePanel::right("properties")
         .default_width(280.0)
         .show(ctx, |ui| {
+            if app.timeline_read_only() {
+                ui.disable();
+            }
             ui.heading("Properties");
             if let Some((ti, ii)) = app.selected {
-                if ti < app.seq.tracks.len() && ii < app.seq.tracks[ti].items.len() {
+                // `selected` indexes graph nodes; a compound clip yields several items.
+                let node_id = app.seq.graph.tracks.get(ti).and_then(|t| t.node_ids.get(ii));
+                let item_index = node_id.map(|id| id.to_string()).and_then(|id| {
+                    app.seq.tracks.get(ti)?.items.iter().position(|it| it.id == id)
+                });
+                if let Some(ii) = item_index {
                     let item = &mut app.seq.tracks[ti].items[ii];
                     let clip_type_label = match &item.kind {
                         super::timeline_crate::ItemKind::Video { .. } => "Video",
@@ -1642,7 +2406,12 @@ pub(super) fn properties_panel(app: &mut App, ctx: &egui::Context) {
                             if pending_rate.is_some() || pending_offset_frames.is_some() {
                                 if let Ok(uuid) = uuid::Uuid::parse_str(&item.id) {
                                     let node_id = super::timeline_crate::NodeId(uuid);
-                                    if let Some(mut node) = app.seq.graph.nodes.get(&node_id).cloned() {
+                                    // Linked clips get the same offset and rate so they stay in sync.
+                                    let mut node_ids = vec![node_id];
+                                    node_ids.extend(app.seq.graph.linked_nodes(node_id));
+                                    let mut commands = Vec::new();
+                                    for id in node_ids {
+                                        let Some(mut node) = app.seq.graph.nodes.get(&id).cloned() else { continue };
                                         if let super::timeline_crate::TimelineNodeKind::Clip(mut clip) = node.kind.clone() {
                                             if let Some(frames) = pending_offset_frames {
                                                 clip.media_range.start = frames;
@@ -1652,9 +2421,12 @@ pub(super) fn properties_panel(app: &mut App, ctx: &egui::Context) {
                                                 clip.playback_rate = new_rate as f64 as f32;
                                             }
                                             node.kind = super::timeline_crate::TimelineNodeKind::Clip(clip);
-                                            let _ = app.apply_timeline_command(super::timeline_crate::TimelineCommand::UpdateNode { node });
+                                            commands.push(super::timeline_crate::TimelineCommand::UpdateNode { node });
                                         }
                                     }
+                                    if !commands.is_empty() {
+                                        let _ = app.apply_timeline_batch("Edit clip", commands);
+                                    }
                                 }
                             }
                         }
@@ -1733,6 +2505,19 @@ pub(super) fn properties_panel(app: &mut App, ctx: &egui::Context) {
                             })
                             .unwrap_or_else(|| "Not generated".to_string());
                         ui.label(format!("Status: {}", status_label));
+                        for variant in app.db.proxy_variants(&asset.id).unwrap_or_default() {
+                            let size = match (variant.width, variant.height) {
+                                (Some(w), Some(h)) => format!("{}x{}", w, h),
+                                _ => "?".to_string(),
+                            };
+                            ui.label(format!(
+                                "{} {} {}{}",
+                                variant.kind.as_str(),
+                                size,
+                                variant.codec.as_deref().unwrap_or("-"),
+                                if variant.valid { "" } else { " (stale)" }
+                            ));
+                        }
 
                         let proxy_running = matches!(
                             app.proxy_status.get(&asset.id),
@@ -1959,6 +2744,15 @@ pub(super) fn center_editor(app: &mut App, ctx: &egui::Context, frame: &mut efra
             });
         ui.add_space(4.0);
         ui.separator();
+        if let Some(err) = &app.sequence_load_error {
+            ui.colored_label(egui::Color32::from_rgb(230, 90, 70), err);
+            ui.small("The sequence is shown empty and read-only, and will not be saved.");
+        }
+        // The timeline stays read-only until the recovery prompt is answered,
+        // so nothing is edited underneath the journal it would replay.
+        if app.timeline_read_only() {
+            ui.disable();
+        }
 
         ui.horizontal(|ui| {
             ui.heading("Timeline");
@@ -1968,8 +2762,10 @@ pub(super) fn center_editor(app: &mut App, ctx: &egui::Context, frame: &mut efra
                     name: String::new(),
                     kind: timeline_crate::TrackKind::Video,
                     node_ids: Vec::new(),
+                    properties: Default::default(),
                 };
                 app.seq.graph.tracks.insert(0, binding);
+                app.seq.graph.invalidate_index();
                 app.sync_tracks_from_graph();
                 let _ = app.save_project_timeline();
             }
@@ -1979,6 +2775,7 @@ pub(super) fn center_editor(app: &mut App, ctx: &egui::Context, frame: &mut efra
                     name: String::new(),
                     kind: timeline_crate::TrackKind::Audio,
                     node_ids: Vec::new(),
+                    properties: Default::default(),
                 };
                 let _ = app.apply_timeline_command(timeline_crate::TimelineCommand::UpsertTrack {
                     track: binding,
@@ -2028,11 +2825,14 @@ pub(super) fn center_editor(app: &mut App, ctx: &egui::Context, frame: &mut efra
             ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                 if let Some(t) = app.last_save_at {
                     let ago = std::time::Instant::now().saturating_duration_since(t);
-                    let label = if ago.as_secs_f32() < 2.0 {
+                    let mut label = if ago.as_secs_f32() < 2.0 {
                         "Saved".to_string()
                     } else {
                         format!("Autosave {}s ago", ago.as_secs())
                     };
+                    if app.journal_since_save > 0 {
+                        label.push_str(&format!(" · {} edits journaled", app.journal_since_save));
+                    }
                     ui.small(label);
                 }
                 let cache_stats = format!(
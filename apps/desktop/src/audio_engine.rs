@@ -15,6 +15,7 @@ pub struct ActiveAudioClip {
     pub start_media_sec: f64,
     pub duration_sec: f64,
     pub buf: Arc<AudioBuffer>,
+    pub gain: [f32; 2], // left/right, from track volume and pan
 }
 
 struct Mixer {
@@ -67,8 +68,8 @@ impl AudioEngine {
                             if t >= c.start_tl_sec && t < c.start_tl_sec + (c.duration_sec as f64) {
                                 let ct = (t - c.start_tl_sec + c.start_media_sec) as f32;
                                 let (sl, sr) = sample_stereo(&c.buf, ct, m.device_sr);
-                                l += sl;
-                                r += sr;
+                                l += sl * c.gain[0];
+                                r += sr * c.gain[1];
                             }
                         }
                         m.device_frame_cursor += 1;
@@ -97,6 +98,12 @@ impl AudioEngine {
         m.device_frame_cursor = 0;
         m.playing = true;
     }
+    // Swap the clip set without moving the playhead, e.g. after a mute or
+    // volume change while playing.
+    pub fn set_clips(&self, active: Vec<ActiveAudioClip>) {
+        let mut m = self.mixer.lock().unwrap();
+        m.clips = active;
+    }
     pub fn pause(&self, timeline_now_sec: f64) {
         let mut m = self.mixer.lock().unwrap();
         m.anchor_timeline_sec = timeline_now_sec;
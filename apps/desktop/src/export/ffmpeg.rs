@@ -4,7 +4,10 @@ use std::{
     sync::{Arc, Mutex},
 };
 
-use crate::timeline_crate::{Item, ItemKind, Sequence};
+use crate::app_timeline::{media_runs, MediaRun};
+use crate::timeline_crate::{
+    ClipNode, Fps, Item, ItemKind, NodeId, Sequence, TimelineNodeKind, TrackProperties,
+};
 
 use super::{ExportCodec, ExportProgress};
 
@@ -13,12 +16,21 @@ struct VideoSegment {
     kind: VideoSegKind,
     start_sec: f32,
     duration: f32,
+    /// Track opacity, faded against black since segments are not layered.
+    opacity: f32,
 }
 
 #[derive(Clone)]
 enum VideoSegKind {
-    Video { path: String, start_sec: f32 },
-    Image { path: String },
+    /// `speed` is media seconds per output second; 0 holds `start_sec`.
+    Video {
+        path: String,
+        start_sec: f32,
+        speed: f32,
+    },
+    Image {
+        path: String,
+    },
     Black,
 }
 
@@ -27,6 +39,10 @@ struct AudioClip {
     path: String,
     offset_sec: f32,
     duration: f32,
+    media_start_sec: f32,
+    speed: f32,
+    /// Left/right gain from the track's volume and pan.
+    gain: [f32; 2],
 }
 
 struct ExportTimeline {
@@ -54,11 +70,23 @@ pub(crate) fn run_ffmpeg_timeline(
     let mut video_labels: Vec<String> = Vec::new();
     for seg in &timeline.video_segments {
         match &seg.kind {
-            VideoSegKind::Video { path, start_sec } => {
+            VideoSegKind::Video {
+                path,
+                start_sec,
+                speed,
+            } => {
+                // Read only the media this segment covers; reversed runs read
+                // backwards from `start_sec`.
+                let read = (seg.duration * speed.abs()).max(1.0 / fps.max(1.0));
+                let from = if *speed < 0.0 {
+                    (start_sec - read).max(0.0)
+                } else {
+                    *start_sec
+                };
                 args.push("-ss".into());
-                args.push(format!("{:.3}", start_sec));
+                args.push(format!("{:.3}", from));
                 args.push("-t".into());
-                args.push(format!("{:.3}", seg.duration));
+                args.push(format!("{:.3}", read));
                 args.push("-i".into());
                 args.push(path.clone());
             }
@@ -87,6 +115,12 @@ pub(crate) fn run_ffmpeg_timeline(
 
     let audio_input_start = input_index;
     for clip in &timeline.audio_clips {
+        if clip.media_start_sec > 0.0 {
+            args.push("-ss".into());
+            args.push(format!("{:.3}", clip.media_start_sec));
+            args.push("-t".into());
+            args.push(format!("{:.3}", clip.duration * clip.speed.abs()));
+        }
         args.push("-i".into());
         args.push(clip.path.clone());
         input_index += 1;
@@ -94,15 +128,38 @@ pub(crate) fn run_ffmpeg_timeline(
 
     let mut filters: Vec<String> = Vec::new();
     let mut vouts: Vec<String> = Vec::new();
-    for (i, _seg) in timeline.video_segments.iter().enumerate() {
+    for (i, seg) in timeline.video_segments.iter().enumerate() {
         let label_in = format!("{}:v", i);
         let label_out = format!("v{}o", i);
+        let retime = match &seg.kind {
+            VideoSegKind::Video { speed, .. } if *speed == 0.0 => format!(
+                "loop=loop=-1:size=1,trim=duration={:.3},setpts=PTS-STARTPTS,",
+                seg.duration
+            ),
+            VideoSegKind::Video { speed, .. } if *speed < 0.0 => {
+                format!("reverse,setpts=(PTS-STARTPTS)/{:.6},", -speed)
+            }
+            VideoSegKind::Video { speed, .. } if *speed != 1.0 => {
+                format!("setpts=(PTS-STARTPTS)/{:.6},", speed)
+            }
+            _ => String::new(),
+        };
+        let fade = if seg.opacity < 1.0 {
+            format!(
+                "colorchannelmixer=rr={0:.4}:gg={0:.4}:bb={0:.4},",
+                seg.opacity.max(0.0)
+            )
+        } else {
+            String::new()
+        };
         filters.push(format!(
-            "[{}]scale={}x{}:flags=lanczos,fps={},format=yuv420p[{}]",
+            "[{}]{}scale={}x{}:flags=lanczos,fps={},{}format=yuv420p[{}]",
             label_in,
+            retime,
             w,
             h,
             fps.max(1.0) as i32,
+            fade,
             label_out
         ));
         vouts.push(format!("[{}]", label_out));
@@ -122,9 +179,22 @@ pub(crate) fn run_ffmpeg_timeline(
         let label_out = format!("a{}o", j);
         let delay_ms = (clip.offset_sec * 1000.0).round() as u64;
         let total_s = total_ms as f32 / 1000.0;
+        let mut retime = String::new();
+        if clip.speed < 0.0 {
+            retime.push_str("areverse,");
+        }
+        if clip.speed.abs() != 1.0 {
+            retime.push_str(&atempo_chain(clip.speed.abs()));
+        }
+        if clip.gain != [1.0, 1.0] {
+            retime.push_str(&format!(
+                "aformat=channel_layouts=stereo,pan=stereo|c0={:.4}*c0|c1={:.4}*c1,",
+                clip.gain[0], clip.gain[1]
+            ));
+        }
         filters.push(format!(
-            "[{}]adelay={}|{},atrim=0:{:.3},aresample=async=1[{}]",
-            label_in, delay_ms, delay_ms, total_s, label_out
+            "[{}]{}adelay={}|{},atrim=0:{:.3},aresample=async=1[{}]",
+            label_in, retime, delay_ms, delay_ms, total_s, label_out
         ));
         aouts.push(format!("[{}]", label_out));
     }
@@ -261,7 +331,27 @@ fn build_export_timeline(seq: &Sequence) -> ExportTimeline {
         if b <= a {
             continue;
         }
-        let (item_opt, _ti) = topmost_item_covering(seq, a);
+        let (item_opt, ti) = topmost_item_covering(seq, a);
+        let opacity = ti.map_or(1.0, |ti| track_properties(seq, ti).opacity);
+        if let Some(item) = item_opt {
+            if let (ItemKind::Video { src, .. }, Some(clip)) =
+                (&item.kind, remapped_clip(seq, item))
+            {
+                for run in export_runs(seq, clip, a, b) {
+                    video_segments.push(VideoSegment {
+                        kind: VideoSegKind::Video {
+                            path: src.clone(),
+                            start_sec: run.media_sec as f32,
+                            speed: run.speed as f32,
+                        },
+                        start_sec: run.timeline_sec as f32,
+                        duration: run.duration_sec as f32,
+                        opacity,
+                    });
+                }
+                continue;
+            }
+        }
         let kind = if let Some(item) = item_opt {
             match &item.kind {
                 ItemKind::Video { src, .. } => {
@@ -269,6 +359,7 @@ fn build_export_timeline(seq: &Sequence) -> ExportTimeline {
                     VideoSegKind::Video {
                         path: src.clone(),
                         start_sec: start_into,
+                        speed: 1.0,
                     }
                 }
                 ItemKind::Image { src } => VideoSegKind::Image { path: src.clone() },
@@ -281,18 +372,44 @@ fn build_export_timeline(seq: &Sequence) -> ExportTimeline {
             kind,
             start_sec: a as f32 / fps,
             duration: (b - a) as f32 / fps,
+            opacity,
         };
         video_segments.push(seg);
     }
 
     let mut audio_clips: Vec<AudioClip> = Vec::new();
-    for track in &seq.tracks {
+    for (ti, track) in seq.tracks.iter().enumerate() {
+        if mirrors_graph(seq) && !seq.graph.track_audible(ti) {
+            continue;
+        }
+        let gain = track_properties(seq, ti).stereo_gain();
         for it in &track.items {
             if let ItemKind::Audio { src, .. } = &it.kind {
+                if let Some(clip) = remapped_clip(seq, it) {
+                    let runs = export_runs(seq, clip, it.from, it.from + it.duration_in_frames);
+                    for run in runs.into_iter().filter(|r| r.speed != 0.0) {
+                        audio_clips.push(AudioClip {
+                            path: src.clone(),
+                            offset_sec: run.timeline_sec as f32,
+                            duration: run.duration_sec as f32,
+                            media_start_sec: if run.speed < 0.0 {
+                                (run.media_sec + run.duration_sec * run.speed).max(0.0) as f32
+                            } else {
+                                run.media_sec as f32
+                            },
+                            speed: run.speed as f32,
+                            gain,
+                        });
+                    }
+                    continue;
+                }
                 audio_clips.push(AudioClip {
                     path: src.clone(),
                     offset_sec: it.from as f32 / fps,
                     duration: it.duration_in_frames as f32 / fps,
+                    media_start_sec: 0.0,
+                    speed: 1.0,
+                    gain,
                 });
             }
         }
@@ -304,7 +421,81 @@ fn build_export_timeline(seq: &Sequence) -> ExportTimeline {
     }
 }
 
+/// Graph clip behind a legacy item, if it carries a time-remap curve.
+fn remapped_clip<'a>(seq: &'a Sequence, item: &Item) -> Option<&'a ClipNode> {
+    let uuid = uuid::Uuid::parse_str(&item.id).ok()?;
+    match &seq.graph.nodes.get(&NodeId(uuid))?.kind {
+        TimelineNodeKind::Clip(clip) if clip.time_remap.is_some() => Some(clip),
+        _ => None,
+    }
+}
+
+fn export_runs(seq: &Sequence, clip: &ClipNode, from: i64, to: i64) -> Vec<MediaRun> {
+    let media_fps = clip
+        .metadata
+        .get("media_fps_num")
+        .and_then(|n| n.as_u64())
+        .zip(clip.metadata.get("media_fps_den").and_then(|d| d.as_u64()))
+        .filter(|(n, d)| *n > 0 && *d > 0)
+        .map(|(num, den)| Fps::new(num as u32, den as u32))
+        .unwrap_or(seq.fps);
+    media_runs(clip, from, to, seq.fps, media_fps)
+}
+
+/// `atempo` only accepts factors from 0.5 to 100, so larger changes are chained.
+fn atempo_chain(mut speed: f32) -> String {
+    let mut out = String::new();
+    while speed < 0.5 {
+        out.push_str("atempo=0.5,");
+        speed /= 0.5;
+    }
+    while speed > 100.0 {
+        out.push_str("atempo=100,");
+        speed /= 100.0;
+    }
+    out.push_str(&format!("atempo={:.6},", speed));
+    out
+}
+
+/// Legacy tracks mirror the graph one-to-one, with item ids taken from node
+/// ids, whenever the track counts agree.
+fn mirrors_graph(seq: &Sequence) -> bool {
+    !seq.graph.tracks.is_empty() && seq.graph.tracks.len() == seq.tracks.len()
+}
+
+/// Mix and composite settings of legacy track `ti`, or the defaults when the
+/// graph does not mirror the legacy tracks.
+fn track_properties(seq: &Sequence, ti: usize) -> TrackProperties {
+    match seq.graph.tracks.get(ti) {
+        Some(binding) if mirrors_graph(seq) => binding.properties.clone(),
+        _ => TrackProperties::default(),
+    }
+}
+
 fn topmost_item_covering<'a>(seq: &'a Sequence, frame: i64) -> (Option<&'a Item>, Option<usize>) {
+    // With a mirrored graph its interval index can answer without a full scan.
+    if mirrors_graph(seq) {
+        for (ti, track) in seq.tracks.iter().enumerate().rev() {
+            if !seq.graph.track_visible(ti) {
+                continue;
+            }
+            for node_id in seq.graph.nodes_at(ti, frame) {
+                let id = node_id.to_string();
+                // Items flattened out of a compound clip carry its id as a prefix.
+                let nested = format!("{}/", id);
+                if let Some(it) = track.items.iter().find(|it| {
+                    (it.id == id
+                        || (it.id.starts_with(&nested)
+                            && frame >= it.from
+                            && frame < it.from + it.duration_in_frames))
+                        && !matches!(it.kind, ItemKind::Audio { .. })
+                }) {
+                    return (Some(it), Some(ti));
+                }
+            }
+        }
+        return (None, None);
+    }
     for (ti, track) in seq.tracks.iter().enumerate().rev() {
         for it in &track.items {
             if frame >= it.from && frame < it.from + it.duration_in_frames {
@@ -7,7 +7,7 @@ use std::{
 use eframe::egui::{self, Widget};
 use project::ProjectDb;
 
-use crate::timeline_crate::Sequence;
+use crate::timeline_crate::{ItemKind, Sequence};
 
 use super::{ffmpeg, ExportCodec, ExportPreset, ExportProgress};
 
@@ -226,7 +226,7 @@ impl ExportUiState {
                                 self.running = true;
                                 self.status.clear();
                                 let selected_encoder = self.selected_encoder.clone();
-                                let seq_owned = seq.clone();
+                                let seq_owned = with_export_media(db, project_id, seq);
 
                                 self.worker = Some(thread::spawn(move || {
                                     ffmpeg::run_ffmpeg_timeline(
@@ -283,6 +283,29 @@ fn default_export_source_and_duration(
     (src, total_ms)
 }
 
+/// `seq` with each video clip reading the full-size transcode of its media
+/// when one was made, since it decodes faster than most camera codecs.
+fn with_export_media(db: &ProjectDb, project_id: &str, seq: &Sequence) -> Sequence {
+    let mut seq = seq.clone();
+    for item in seq.tracks.iter_mut().flat_map(|t| t.items.iter_mut()) {
+        if let ItemKind::Video { src, .. } = &mut item.kind {
+            let variant = db
+                .find_asset_by_path(project_id, src)
+                .ok()
+                .flatten()
+                .and_then(|asset| {
+                    db.best_proxy_variant(&asset.id, project::PlaybackContext::Export)
+                        .ok()
+                        .flatten()
+                });
+            if let Some(variant) = variant {
+                *src = variant.path_abs;
+            }
+        }
+    }
+    seq
+}
+
 fn adjust_extension(path: &str, ext: &str) -> String {
     let mut p = PathBuf::from(path);
     p.set_extension(ext);
@@ -1,4 +1,4 @@
-use timeline::{NodeId, TimelineNode, TrackId};
+use timeline::{NodeId, TimelineCommand, TimelineNode, TrackId};
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum DragMode {
@@ -21,6 +21,13 @@ pub struct DragState {
     pub original_position: usize,
     pub asset_id: Option<String>,
     pub linked: Vec<LinkedDragNode>,
+    /// Trims started with Alt held ripple downstream clips on release.
+    pub ripple: bool,
+    /// Trims started with Ctrl held roll the edit point shared with the clip
+    /// touching the dragged edge.
+    pub roll: bool,
+    /// Trim commands the preview has applied to the graph, and their inverse.
+    pub trim_preview: Option<(Vec<TimelineCommand>, TimelineCommand)>,
 }
 
 #[derive(Clone, Debug)]
@@ -35,3 +42,17 @@ pub struct LinkedDragNode {
     pub orig_dur: i64,
     pub orig_media_start: i64,
 }
+
+/// Marks for three-point edits: the timeline in/out, the source asset with
+/// its own in/out (in sequence frames from the head of the asset), and the
+/// tracks edits land on. An empty target list falls back to the tracks an
+/// asset would normally be placed on.
+#[derive(Clone, Debug, Default)]
+pub struct EditMarks {
+    pub timeline_in: Option<i64>,
+    pub timeline_out: Option<i64>,
+    pub source: Option<project::AssetRow>,
+    pub source_in: Option<i64>,
+    pub source_out: Option<i64>,
+    pub target_tracks: Vec<TrackId>,
+}
@@ -28,7 +28,7 @@ use clock::PlaybackClock;
 use decode::{
     DecodeCmd, DecodeManager, EngineState, FramePayload, PlayState, VideoFrameOut, VideoProps,
 };
-use interaction::{DragMode, DragState};
+use interaction::{DragMode, DragState, EditMarks};
 mod audio_decode;
 mod audio_engine;
 mod cache;
@@ -173,6 +173,9 @@ fn nearest_common_ancestor(paths: &[PathBuf]) -> Option<PathBuf> {
 struct VisualSource {
     path: String,
     is_image: bool,
+    // Product of the opacities of the tracks it sits on; the preview fades
+    // towards its dark background. Blend modes are not composited.
+    opacity: f32,
 }
 
 #[derive(Clone, Debug)]
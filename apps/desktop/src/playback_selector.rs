@@ -8,8 +8,8 @@ use gstreamer as gst;
 use gstreamer::prelude::*;
 use native_decoder::{create_decoder, DecoderConfig};
 use once_cell::sync::Lazy;
+pub use project::ProxyMode;
 use project::AssetRow;
-use serde::{Deserialize, Serialize};
 use tracing::{debug, info, warn};
 
 /// Global registry that tracks proxies on disk for each original asset.
@@ -18,29 +18,6 @@ static PROXY_REGISTRY: Lazy<RwLock<HashMap<PathBuf, PathBuf>>> =
 static DECODE_PROBE_CACHE: Lazy<RwLock<HashMap<PathBuf, bool>>> =
     Lazy::new(|| RwLock::new(HashMap::new()));
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
-pub enum ProxyMode {
-    OriginalOptimized,
-    ProxyPreferred,
-    ProxyOnly,
-}
-
-impl ProxyMode {
-    pub fn display_name(self) -> &'static str {
-        match self {
-            ProxyMode::OriginalOptimized => "Original (Optimized)",
-            ProxyMode::ProxyPreferred => "Proxy Preferred",
-            ProxyMode::ProxyOnly => "Proxy Only",
-        }
-    }
-}
-
-impl Default for ProxyMode {
-    fn default() -> Self {
-        ProxyMode::OriginalOptimized
-    }
-}
-
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PlaybackSource {
     Original,
@@ -70,6 +47,7 @@ impl PlaybackSelector {
         asset: &AssetRow,
         mode: ProxyMode,
         optimized: Option<&Path>,
+        proxy: Option<&Path>,
     ) -> Option<PlaybackSelection> {
         let mut optimized_info: Option<(PathBuf, bool)> =
             optimized.map(|p| (p.to_path_buf(), p.exists()));
@@ -98,10 +76,9 @@ impl PlaybackSelector {
             }
         }
 
-        let proxy_path = asset
-            .proxy_path
-            .as_ref()
-            .map(PathBuf::from)
+        let proxy_path = proxy
+            .map(Path::to_path_buf)
+            .or_else(|| asset.proxy_path.as_ref().map(PathBuf::from))
             .or_else(|| lookup_proxy(Path::new(&asset.src_abs)));
 
         let mut selection =
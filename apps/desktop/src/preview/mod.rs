@@ -4,35 +4,39 @@ pub mod ui;
 pub(crate) use state::{PreviewShaderMode, PreviewState, StreamMetadata, StreamSlot};
 
 use serde_json::Value;
-use timeline_crate::{
-    ClipNode, FrameRange, TimelineGraph, TimelineNode, TimelineNodeKind, TrackKind,
-};
+use timeline_crate::{ClipNode, TimelineGraph, TimelineNodeKind, TrackKind};
 
 use crate::VisualSource;
 use tracing::trace;
 
 pub(crate) fn visual_source_at(graph: &TimelineGraph, playhead: i64) -> Option<VisualSource> {
+    visual_source_in(graph, graph, playhead, 0)
+}
+
+fn visual_source_in(
+    root: &TimelineGraph,
+    graph: &TimelineGraph,
+    playhead: i64,
+    depth: usize,
+) -> Option<VisualSource> {
+    if depth > crate::app_timeline::MAX_COMPOUND_DEPTH {
+        return None;
+    }
     // Priority: lower-numbered tracks first (top-most rows in UI)
-    for binding in graph.tracks.iter() {
-        if matches!(binding.kind, TrackKind::Audio) {
+    for (track_index, binding) in graph.tracks.iter().enumerate() {
+        if matches!(binding.kind, TrackKind::Audio) || !graph.track_visible(track_index) {
             continue;
         }
-        for node_id in binding.node_ids.iter() {
-            // Skip missing nodes instead of aborting the entire search.
-            let Some(node) = graph.nodes.get(node_id) else {
+        let opacity = binding.properties.opacity.clamp(0.0, 1.0);
+        for node_id in graph.nodes_at(track_index, playhead) {
+            let Some(node) = graph.nodes.get(&node_id) else {
                 continue;
             };
-            let Some(range) = node_frame_range(node) else {
-                continue;
-            };
-            if playhead < range.start || playhead >= range.end() {
-                continue;
-            }
             match &node.kind {
                 TimelineNodeKind::Clip(clip) => {
                     let asset = clip.asset_id.as_deref().unwrap_or("<unknown>");
                     trace!(node_id = ?node_id, asset, playhead, "preview resolver matched clip");
-                    if let Some(src) = clip_source(binding, clip) {
+                    if let Some(src) = clip_source(binding, clip, opacity) {
                         return Some(src);
                     }
                 }
@@ -41,7 +45,17 @@ pub(crate) fn visual_source_at(graph: &TimelineGraph, playhead: i64) -> Option<V
                     metadata,
                     ..
                 } => {
-                    if let Some(src) = generator_source(generator_id, metadata) {
+                    if let Some(src) = generator_source(generator_id, metadata, opacity) {
+                        return Some(src);
+                    }
+                }
+                TimelineNodeKind::Compound(compound) => {
+                    let Some(inner) = root.sequence(&compound.sequence_id) else {
+                        continue;
+                    };
+                    let inner_frame = compound.inner_frame(playhead);
+                    if let Some(mut src) = visual_source_in(root, inner, inner_frame, depth + 1) {
+                        src.opacity *= opacity;
                         return Some(src);
                     }
                 }
@@ -52,15 +66,11 @@ pub(crate) fn visual_source_at(graph: &TimelineGraph, playhead: i64) -> Option<V
     None
 }
 
-fn node_frame_range(node: &TimelineNode) -> Option<FrameRange> {
-    match &node.kind {
-        TimelineNodeKind::Clip(clip) => Some(clip.timeline_range.clone()),
-        TimelineNodeKind::Generator { timeline_range, .. } => Some(timeline_range.clone()),
-        _ => None,
-    }
-}
-
-fn clip_source(binding: &timeline_crate::TrackBinding, clip: &ClipNode) -> Option<VisualSource> {
+fn clip_source(
+    binding: &timeline_crate::TrackBinding,
+    clip: &ClipNode,
+    opacity: f32,
+) -> Option<VisualSource> {
     let path = clip.asset_id.clone()?;
     // Detect images by extension or by explicit image track kind
     let ext_is_image = std::path::Path::new(&path)
@@ -80,10 +90,14 @@ fn clip_source(binding: &timeline_crate::TrackBinding, clip: &ClipNode) -> Optio
         .unwrap_or(false);
     let track_hint_image = matches!(binding.kind, TrackKind::Custom(ref id) if id == "image");
     let is_image = ext_is_image || track_hint_image;
-    Some(VisualSource { path, is_image })
+    Some(VisualSource {
+        path,
+        is_image,
+        opacity,
+    })
 }
 
-fn generator_source(generator_id: &str, metadata: &Value) -> Option<VisualSource> {
+fn generator_source(generator_id: &str, metadata: &Value, opacity: f32) -> Option<VisualSource> {
     match generator_id {
         "solid" => {
             let color = metadata
@@ -93,11 +107,13 @@ fn generator_source(generator_id: &str, metadata: &Value) -> Option<VisualSource
             Some(VisualSource {
                 path: format!("solid:{}", color),
                 is_image: true,
+                opacity,
             })
         }
         "text" => Some(VisualSource {
             path: "text://generator".into(),
             is_image: true,
+            opacity,
         }),
         _ => None,
     }
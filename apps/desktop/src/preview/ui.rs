@@ -54,6 +54,9 @@ impl App {
         self.playhead = playhead_frame;
         let _target_ts = (playhead_frame as f64) / fps;
         let source = visual_source_at(&self.seq.graph, self.playhead);
+        let tint = source.as_ref().map_or(egui::Color32::WHITE, |src| {
+            egui::Color32::WHITE.gamma_multiply(src.opacity)
+        });
 
         // Debug: shader mode toggle for YUV preview
         ui.horizontal(|ui| {
@@ -142,7 +145,7 @@ impl App {
                 let hex = src.path.trim_start_matches("solid:");
                 let color = crate::timeline::ui::parse_hex_color(hex)
                     .unwrap_or(egui::Color32::from_rgb(80, 80, 80));
-                painter.rect_filled(rect, 4.0, color);
+                painter.rect_filled(rect, 4.0, color.gamma_multiply(src.opacity));
                 return;
             }
             if src.path.starts_with("text://") {
@@ -248,7 +251,7 @@ impl App {
                 let size = tex.size();
                 let dest = fit_rect_to_content(video_rect, size[0] as f32, size[1] as f32);
                 let uv = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
-                painter.image(tex.id(), dest, uv, egui::Color32::WHITE);
+                painter.image(tex.id(), dest, uv, tint);
             }
             return;
         }
@@ -589,7 +592,7 @@ impl App {
                                     frame_out.props.w as f32,
                                     frame_out.props.h as f32,
                                 );
-                                painter.image(id, dest, uv_rect, egui::Color32::WHITE);
+                                painter.image(id, dest, uv_rect, tint);
                                 trace!("preview presented frame");
                                 // Update last presented pts for hybrid clearing heuristic
                                 self.last_present_pts = Some((active_path.clone(), frame_out.pts));
@@ -611,7 +614,7 @@ impl App {
                     let dest = fit_rect_to_content(video_rect, w as f32, h as f32);
                     let uv_rect =
                         egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
-                    painter.image(id, dest, uv_rect, egui::Color32::WHITE);
+                    painter.image(id, dest, uv_rect, tint);
                     drew_previous = true;
                 }
             }
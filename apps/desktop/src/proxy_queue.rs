@@ -8,7 +8,10 @@ use std::time::{Duration, Instant, SystemTime};
 
 use anyhow::{anyhow, Context, Result};
 use crossbeam_channel::{self, Receiver, Sender};
-use project::{AssetMediaDetails, AssetRow, ProjectDb, ProxyJobInsert};
+use project::{
+    variant_settings_hash, AssetMediaDetails, AssetRow, ProjectDb, ProxyJobInsert,
+    ProxyVariantInsert, VariantKind,
+};
 
 use crate::media_info::{probe_media_info, HardwareCaps, MediaInfo};
 use crate::playback_selector::register_proxy;
@@ -358,12 +361,14 @@ fn execute_job(
         _ => ProxyPreset::DnxhrLb,
     };
 
+    let config_width = job.width.unwrap_or(TARGET_PROXY_HEIGHT as i64) as u32;
+    let config_height = job.height.unwrap_or(TARGET_PROXY_HEIGHT as i64) as u32;
     let config = ProxyPipelineConfig {
         source: source.clone(),
         destination: destination.clone(),
         preset,
-        width: job.width.unwrap_or(TARGET_PROXY_HEIGHT as i64) as u32,
-        height: job.height.unwrap_or(TARGET_PROXY_HEIGHT as i64) as u32,
+        width: config_width,
+        height: config_height,
         bitrate_kbps: job.bitrate_kbps.unwrap_or(0) as u32,
         decoder: select_decoder(hardware_caps),
     };
@@ -434,10 +439,26 @@ fn execute_job(
     let now = chrono::Utc::now().timestamp();
     db.update_proxy_job_status(&job.id, "done", Some(1.0), None, None, Some(now))?;
 
-    let mut details = AssetMediaDetails::default();
-    details.proxy_path = Some(destination.as_path());
-    details.is_proxy_ready = Some(true);
-    db.update_asset_media_details(&job.asset_id, &details)?;
+    let settings = serde_json::json!({
+        "preset": job.preset,
+        "width": config_width,
+        "height": config_height,
+        "bitrate_kbps": job.bitrate_kbps,
+    });
+    db.register_proxy_variant(&ProxyVariantInsert {
+        asset_id: &job.asset_id,
+        kind: VariantKind::Proxy,
+        width: Some(config_width as i64),
+        height: Some(config_height as i64),
+        codec: Some(match preset {
+            ProxyPreset::MacProRes => "prores_proxy",
+            ProxyPreset::DnxhrLb => "dnxhr_lb",
+        }),
+        pixel_fmt: None,
+        bitrate_kbps: job.bitrate_kbps,
+        path: &destination,
+        settings_hash: &variant_settings_hash(&settings),
+    })?;
 
     register_proxy(source.clone(), destination.clone());
 
@@ -555,6 +576,15 @@ fn compute_target_dimensions(info: &MediaInfo) -> (u32, u32) {
 }
 
 fn prune_proxy_storage(db: &ProjectDb, project_id: &str, limit_bytes: u64) -> Result<()> {
+    // Variants made from media that has since changed go first.
+    for path in db.take_invalid_proxy_variants(project_id)? {
+        if let Err(err) = std::fs::remove_file(&path) {
+            if err.kind() != std::io::ErrorKind::NotFound {
+                tracing::warn!(path = %path.display(), "failed to remove stale proxy: {err}");
+            }
+        }
+    }
+
     let assets = db.list_assets(project_id)?;
     let mut total_size: u64 = 0;
     let mut tracked: Vec<(PathBuf, u64, SystemTime, String)> = Vec::new();
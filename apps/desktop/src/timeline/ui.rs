@@ -1,8 +1,9 @@
 use std::path::Path;
 
 use crate::timeline_crate::{
-    ClipNode, Fps, FrameRange, ItemKind, NodeId, TimelineCommand, TimelineNode, TimelineNodeKind,
-    TrackId, TrackKind, TrackPlacement,
+    apply_command, BlendMode, ClipNode, Fps, FrameRange, ItemKind, Marker, MarkerColor, MarkerKind,
+    NodeId, TimeRemap, TimelineCommand, TimelineNode, TimelineNodeKind, TrackId, TrackKind,
+    TrackPlacement, TrackProperties, TrimEdge,
 };
 use eframe::egui::{self, Color32, Rect, Shape, Stroke};
 use serde_json::Value;
@@ -33,6 +34,34 @@ pub(crate) fn parse_hex_color(hex: &str) -> Option<Color32> {
     None
 }
 
+/// Drag value for an optional source mark; the button clears it.
+fn source_mark_ui(ui: &mut egui::Ui, mark: &mut Option<i64>) {
+    let mut value = mark.unwrap_or(0);
+    if ui
+        .add(egui::DragValue::new(&mut value).range(0..=i64::MAX))
+        .changed()
+    {
+        *mark = Some(value);
+    }
+    if mark.is_some() && ui.small_button("x").clicked() {
+        *mark = None;
+    }
+}
+
+pub(crate) fn marker_color32(color: MarkerColor) -> Color32 {
+    match color {
+        MarkerColor::Red => Color32::from_rgb(230, 70, 70),
+        MarkerColor::Orange => Color32::from_rgb(240, 150, 50),
+        MarkerColor::Yellow => Color32::from_rgb(235, 210, 60),
+        MarkerColor::Green => Color32::from_rgb(80, 200, 110),
+        MarkerColor::Cyan => Color32::from_rgb(70, 200, 220),
+        MarkerColor::Blue => Color32::from_rgb(80, 140, 240),
+        MarkerColor::Purple => Color32::from_rgb(150, 100, 230),
+        MarkerColor::Magenta => Color32::from_rgb(220, 80, 200),
+        MarkerColor::White => Color32::from_rgb(235, 235, 235),
+    }
+}
+
 pub(crate) fn frames_to_seconds(frames: i64, fps: Fps) -> f64 {
     if fps.num == 0 {
         return 0.0;
@@ -43,54 +72,40 @@ pub(crate) fn frames_to_seconds(frames: i64, fps: Fps) -> f64 {
 }
 
 impl App {
-    fn gather_linked_drag_nodes(
-        &self,
-        primary_id: NodeId,
-        primary_clip: &ClipNode,
-    ) -> Vec<LinkedDragNode> {
-        let asset_id = primary_clip.asset_id.clone();
-        let orig_from = primary_clip.timeline_range.start;
-        let orig_dur = primary_clip.timeline_range.duration;
-        let orig_media_start = primary_clip.media_range.start;
-
-        let mut linked = Vec::new();
-        if asset_id.is_none() {
-            return linked;
-        }
-
-        for (ti, binding) in self.seq.graph.tracks.iter().enumerate() {
-            for (idx, node_id) in binding.node_ids.iter().enumerate() {
-                if *node_id == primary_id {
-                    continue;
-                }
-                if let Some(node) = self.seq.graph.nodes.get(node_id) {
-                    if let TimelineNodeKind::Clip(clip) = &node.kind {
-                        if clip.asset_id == asset_id
-                            && clip.timeline_range.start == orig_from
-                            && clip.timeline_range.duration == orig_dur
-                            && clip.media_range.start == orig_media_start
-                        {
-                            linked.push(LinkedDragNode {
-                                node_id: *node_id,
-                                original_node: node.clone(),
-                                original_track_id: binding.id,
-                                original_track_index: ti,
-                                current_track_index: ti,
-                                original_position: idx,
-                                orig_from: clip.timeline_range.start,
-                                orig_dur: clip.timeline_range.duration,
-                                orig_media_start: clip.media_range.start,
-                            });
-                        }
-                    }
-                }
-            }
-        }
-
-        linked
+    /// Drag state for every node in `primary_id`'s link group.
+    fn linked_drag_nodes(&self, primary_id: NodeId) -> Vec<LinkedDragNode> {
+        let graph = &self.seq.graph;
+        graph
+            .linked_nodes(primary_id)
+            .into_iter()
+            .filter_map(|node_id| {
+                let node = graph.nodes.get(&node_id)?;
+                let range = node.timeline_range()?;
+                let (ti, binding) = graph
+                    .tracks
+                    .iter()
+                    .enumerate()
+                    .find(|(_, b)| b.node_ids.contains(&node_id))?;
+                let idx = binding.node_ids.iter().position(|id| *id == node_id)?;
+                let orig_media_start = match &node.kind {
+                    TimelineNodeKind::Clip(clip) => clip.media_range.start,
+                    _ => 0,
+                };
+                Some(LinkedDragNode {
+                    node_id,
+                    original_node: node.clone(),
+                    original_track_id: binding.id,
+                    original_track_index: ti,
+                    current_track_index: ti,
+                    original_position: idx,
+                    orig_from: range.start,
+                    orig_dur: range.duration,
+                    orig_media_start,
+                })
+            })
+            .collect()
     }
 
-
     fn clip_media_fps(&self, clip: &ClipNode) -> Fps {
         if let Value::Object(map) = &clip.metadata {
             let num = map
@@ -180,6 +195,16 @@ impl App {
                     media_src: None,
                 })
             }
+            TimelineNodeKind::Compound(compound) => Some(NodeDisplayInfo {
+                start: compound.timeline_range.start,
+                duration: compound.timeline_range.duration,
+                label: node
+                    .label
+                    .clone()
+                    .unwrap_or_else(|| "Compound clip".to_string()),
+                color: egui::Color32::from_rgb(110, 80, 160),
+                media_src: None,
+            }),
             TimelineNodeKind::Transition(_) | TimelineNodeKind::Effect { .. } => None,
         }
     }
@@ -187,6 +212,7 @@ impl App {
     pub(crate) fn node_frame_range(node: &TimelineNode) -> Option<FrameRange> {
         match &node.kind {
             TimelineNodeKind::Clip(clip) => Some(clip.timeline_range.clone()),
+            TimelineNodeKind::Compound(compound) => Some(compound.timeline_range.clone()),
             TimelineNodeKind::Generator { timeline_range, .. } => Some(timeline_range.clone()),
             _ => None,
         }
@@ -234,6 +260,7 @@ impl App {
         if let Some(binding) = self.seq.graph.tracks.get_mut(target_track) {
             binding.node_ids.push(drag.node_id);
         }
+        self.seq.graph.reindex_node(drag.node_id);
         drag.current_track_index = target_track;
     }
 
@@ -260,6 +287,7 @@ impl App {
             binding.node_ids.insert(pos, node_id);
         }
         self.seq.graph.nodes.insert(node_id, original_node.clone());
+        self.seq.graph.reindex_node(node_id);
     }
 
     pub(crate) fn restore_drag_preview(&mut self, drag: &DragState) {
@@ -279,6 +307,14 @@ impl App {
         }
     }
 
+    /// Refresh the timeline index for the nodes a drag preview just moved.
+    fn reindex_drag_nodes(&mut self, drag: &DragState) {
+        self.seq.graph.reindex_node(drag.node_id);
+        for linked in &drag.linked {
+            self.seq.graph.reindex_node(linked.node_id);
+        }
+    }
+
     pub(crate) fn preview_move_node(&mut self, drag: &DragState, new_from: i64) {
         let delta = new_from - drag.orig_from;
         if let Some(node) = self.seq.graph.nodes.get_mut(&drag.node_id) {
@@ -313,80 +349,81 @@ impl App {
                 }
             }
         }
+        self.reindex_drag_nodes(drag);
     }
 
-    pub(crate) fn preview_trim_start_node(
-        &mut self,
-        drag: &DragState,
-        new_from: i64,
-        new_duration: i64,
-        delta_frames: i64,
-    ) {
-        if let Some(node) = self.seq.graph.nodes.get_mut(&drag.node_id) {
-            match (&mut node.kind, &drag.original_node.kind) {
-                (TimelineNodeKind::Clip(clip), TimelineNodeKind::Clip(orig_clip)) => {
-                    let media_start = orig_clip.media_range.start + delta_frames;
-                    clip.timeline_range.start = new_from;
-                    clip.timeline_range.duration = new_duration;
-                    clip.media_range.start = media_start;
-                    clip.media_range.duration = new_duration;
-                }
-                (
-                    TimelineNodeKind::Generator { timeline_range, .. },
-                    TimelineNodeKind::Generator { .. },
-                ) => {
-                    timeline_range.start = new_from;
-                    timeline_range.duration = new_duration;
-                }
-                _ => {}
-            }
-        }
+    /// The clip sharing `node_id`'s `edge` on its track, if one touches it.
+    fn roll_neighbour(&self, node_id: NodeId, edge: TrimEdge) -> Option<NodeId> {
+        let graph = &self.seq.graph;
+        let range = graph.nodes.get(&node_id)?.timeline_range()?.clone();
+        let track = graph.track_for_node(node_id)?;
+        track.node_ids.iter().copied().find(|id| {
+            graph
+                .nodes
+                .get(id)
+                .and_then(|n| n.timeline_range())
+                .is_some_and(|r| match edge {
+                    TrimEdge::Start => r.end() == range.start,
+                    TrimEdge::End => r.start == range.end(),
+                })
+        })
+    }
 
-        for linked in &drag.linked {
-            let target_start = (linked.orig_from + delta_frames).max(0);
-            let target_duration = (linked.orig_dur - delta_frames).max(1);
-            if let Some(node) = self.seq.graph.nodes.get_mut(&linked.node_id) {
-                if let (TimelineNodeKind::Clip(clip), TimelineNodeKind::Clip(orig_clip)) =
-                    (&mut node.kind, &linked.original_node.kind)
-                {
-                    let media_start = orig_clip.media_range.start + delta_frames;
-                    clip.timeline_range.start = target_start;
-                    clip.timeline_range.duration = target_duration;
-                    clip.media_range.start = media_start;
-                    clip.media_range.duration = target_duration;
-                }
+    /// Commands for a trim drag whose edge has moved `delta` frames: a ripple
+    /// trim with Alt, a roll with the touching clip with Ctrl, a plain trim
+    /// otherwise. The commands trim linked partners themselves.
+    fn trim_drag_commands(&self, drag: &DragState, delta: i64) -> Vec<TimelineCommand> {
+        let edge = match drag.mode {
+            DragMode::TrimStart => TrimEdge::Start,
+            DragMode::TrimEnd => TrimEdge::End,
+            DragMode::Move => return Vec::new(),
+        };
+        if delta == 0 {
+            return Vec::new();
+        }
+        if drag.roll {
+            if let Some(neighbour) = self.roll_neighbour(drag.node_id, edge) {
+                let (left, right) = match edge {
+                    TrimEdge::Start => (neighbour, drag.node_id),
+                    TrimEdge::End => (drag.node_id, neighbour),
+                };
+                return vec![TimelineCommand::RollEdit { left, right, delta }];
             }
         }
+        let node_id = drag.node_id;
+        vec![if drag.ripple {
+            TimelineCommand::RippleTrim {
+                node_id,
+                edge,
+                delta,
+            }
+        } else {
+            TimelineCommand::TrimNode {
+                node_id,
+                edge,
+                delta,
+            }
+        }]
     }
 
-    pub(crate) fn preview_trim_end_node(&mut self, drag: &DragState, new_duration: i64) {
-        if let Some(node) = self.seq.graph.nodes.get_mut(&drag.node_id) {
-            match &mut node.kind {
-                TimelineNodeKind::Clip(clip) => {
-                    clip.timeline_range.duration = new_duration;
-                    clip.media_range.duration = new_duration;
-                }
-                TimelineNodeKind::Generator { timeline_range, .. } => {
-                    timeline_range.duration = new_duration;
-                }
-                _ => {}
-            }
+    /// Show a trim drag by applying its commands to the graph. The previous
+    /// preview is undone first, so the commands always start from the clips
+    /// as they were when the drag began.
+    fn preview_trim(&mut self, drag: &mut DragState, delta: i64) {
+        if let Some((_, inverse)) = drag.trim_preview.take() {
+            let _ = apply_command(&mut self.seq.graph, inverse);
         }
-
-        for linked in &drag.linked {
-            if let Some(node) = self.seq.graph.nodes.get_mut(&linked.node_id) {
-                match &mut node.kind {
-                    TimelineNodeKind::Clip(clip) => {
-                        let clamped = new_duration.max(1);
-                        clip.timeline_range.duration = clamped;
-                        clip.media_range.duration = clamped;
-                    }
-                    TimelineNodeKind::Generator { timeline_range, .. } => {
-                        timeline_range.duration = new_duration.max(1);
-                    }
-                    _ => {}
-                }
-            }
+        let commands = self.trim_drag_commands(drag, delta);
+        if commands.is_empty() {
+            return;
+        }
+        // A trim the engine refuses, such as one running into a neighbour,
+        // leaves the clips as they were.
+        let batch = TimelineCommand::Batch {
+            commands: commands.clone(),
+        };
+        if let Ok(inverse) = apply_command(&mut self.seq.graph, batch) {
+            drag.trim_preview = Some((commands, inverse));
         }
     }
 
@@ -400,7 +437,15 @@ impl App {
         let target_track = ((pointer.y - rect.top()) / track_h).floor() as isize;
         let track_count = self.seq.graph.tracks.len() as isize;
         let clamped_track = target_track.clamp(0, track_count.saturating_sub(1)) as usize;
-        self.move_node_between_tracks(drag, clamped_track);
+        // Locked tracks take no drops; the clip stays where it was.
+        let clamped_track = match self.seq.graph.tracks.get(clamped_track) {
+            Some(track) if track.properties.locked => drag.current_track_index,
+            _ => clamped_track,
+        };
+        // Trims keep the clip on its track.
+        if drag.mode == DragMode::Move {
+            self.move_node_between_tracks(drag, clamped_track);
+        }
 
         let mx = pointer.x;
         let dx_px = mx - drag.start_mouse_x;
@@ -424,9 +469,7 @@ impl App {
                 if (secf - new_from as f32).abs() <= eps {
                     new_from = secf as i64;
                 }
-                let delta_frames = (new_from - drag.orig_from).max(0);
-                let new_duration = (drag.orig_dur - delta_frames).max(1);
-                self.preview_trim_start_node(drag, new_from, new_duration, delta_frames);
+                self.preview_trim(drag, new_from - drag.orig_from);
             }
             DragMode::TrimEnd => {
                 let mut new_duration = (drag.orig_dur + df).max(1);
@@ -435,7 +478,7 @@ impl App {
                 if (secf - end as f32).abs() <= eps {
                     new_duration = (secf as i64 - drag.orig_from).max(1);
                 }
-                self.preview_trim_end_node(drag, new_duration);
+                self.preview_trim(drag, new_duration - drag.orig_dur);
             }
         }
 
@@ -443,7 +486,28 @@ impl App {
         self.update_selection_for_node(drag.node_id);
     }
 
+    /// Undo a trim drag's preview and apply its commands through the
+    /// history, so the trim is journaled and undoable like any other edit.
+    fn finish_trim_drag(&mut self, mut drag: DragState) {
+        let Some((commands, inverse)) = drag.trim_preview.take() else {
+            self.sync_tracks_from_graph();
+            return;
+        };
+        let _ = apply_command(&mut self.seq.graph, inverse);
+        let label = commands[0].label();
+        if let Err(err) = self.apply_timeline_batch(label, commands) {
+            eprintln!("timeline edit failed: {err}");
+            self.sync_tracks_from_graph();
+            return;
+        }
+        self.update_selection_for_node(drag.node_id);
+    }
+
     pub(crate) fn finish_drag(&mut self, drag: DragState) {
+        if drag.mode != DragMode::Move {
+            self.finish_trim_drag(drag);
+            return;
+        }
         let target_track_id = self
             .seq
             .graph
@@ -462,215 +526,347 @@ impl App {
             .collect();
         self.restore_drag_preview(&drag);
 
-        if let Some(node) = final_node {
-            let track_changed = drag.current_track_index != drag.original_track_index;
-            if !track_changed && node == drag.original_node {
-                self.sync_tracks_from_graph();
-                self.update_selection_for_node(drag.node_id);
-                return;
-            }
-
-            if track_changed {
-                let target_id = target_track_id.unwrap_or(drag.original_track_id);
-                if let Err(err) = self.apply_timeline_command(TimelineCommand::RemoveNode {
-                    node_id: drag.node_id,
-                }) {
-                    eprintln!("timeline remove failed: {err}");
-                    return;
-                }
-                if let Err(err) = self.apply_timeline_command(TimelineCommand::InsertNode {
-                    node,
-                    placements: vec![TrackPlacement {
-                        track_id: target_id,
-                        position: None,
-                    }],
-                    edges: Vec::new(),
-                }) {
-                    eprintln!("timeline insert failed: {err}");
-                    return;
-                }
-            } else {
-                if let Err(err) = self.apply_timeline_command(TimelineCommand::UpdateNode { node })
-                {
-                    eprintln!("timeline update failed: {err}");
-                    return;
-                }
-            }
-            self.update_selection_for_node(drag.node_id);
-        } else {
+        let Some(node) = final_node else {
+            self.sync_tracks_from_graph();
+            return;
+        };
+        let track_changed = drag.current_track_index != drag.original_track_index;
+        if !track_changed && node == drag.original_node {
             self.sync_tracks_from_graph();
+            self.update_selection_for_node(drag.node_id);
+            return;
         }
 
+        // Primary and linked nodes go into one batch so a single undo reverts the whole drag.
+        let mut commands = Vec::new();
+        if track_changed {
+            let target_id = target_track_id.unwrap_or(drag.original_track_id);
+            // Re-inserting keeps the node's edges so its link group survives the move.
+            let edges = self
+                .seq
+                .graph
+                .edges
+                .iter()
+                .filter(|e| e.from == drag.node_id || e.to == drag.node_id)
+                .cloned()
+                .collect();
+            commands.push(TimelineCommand::RemoveNode {
+                node_id: drag.node_id,
+            });
+            commands.push(TimelineCommand::InsertNode {
+                node,
+                placements: vec![TrackPlacement {
+                    track_id: target_id,
+                    position: None,
+                }],
+                edges,
+            });
+        } else {
+            commands.push(TimelineCommand::UpdateNode { node });
+        }
         for (linked, final_node) in linked_finals {
             if let Some(node) = final_node {
-                if node == linked.original_node {
-                    continue;
-                }
-                if let Err(err) = self.apply_timeline_command(TimelineCommand::UpdateNode { node })
-                {
-                    eprintln!("timeline update failed: {err}");
+                if node != linked.original_node {
+                    commands.push(TimelineCommand::UpdateNode { node });
                 }
             }
         }
+
+        if let Err(err) = self.apply_timeline_batch("Move clip", commands) {
+            eprintln!("timeline edit failed: {err}");
+            self.sync_tracks_from_graph();
+            return;
+        }
+        self.update_selection_for_node(drag.node_id);
     }
 
     pub(crate) fn split_clip_at_frame(&mut self, track: usize, item: usize, split_frame: i64) {
+        let Some(node_id) = self
+            .seq
+            .graph
+            .tracks
+            .get(track)
+            .and_then(|binding| binding.node_ids.get(item))
+            .copied()
+        else {
+            return;
+        };
+        let command = TimelineCommand::SplitNode {
+            node_id,
+            frame: split_frame,
+        };
+        if let Err(err) = self.apply_timeline_command(command) {
+            eprintln!("timeline split failed: {err}");
+            return;
+        }
+        self.selected = Some((track, item + 1));
+    }
+
+    pub(crate) fn remove_clip(&mut self, track: usize, item: usize) {
         let track_binding = match self.seq.graph.tracks.get(track) {
-            Some(binding) => binding.clone(),
+            Some(binding) => binding,
             None => return,
         };
         let node_id = match track_binding.node_ids.get(item) {
             Some(id) => *id,
             None => return,
         };
-        let node = match self.seq.graph.nodes.get(&node_id) {
-            Some(n) => n.clone(),
-            None => return,
-        };
-
-        match node.kind {
-            TimelineNodeKind::Clip(ref clip) => {
-                let mut targets: Vec<(usize, TrackId, usize, NodeId, TimelineNode)> = Vec::new();
-                targets.push((track, track_binding.id, item, node_id, node.clone()));
-                for ln in self.gather_linked_drag_nodes(node_id, clip) {
-                    targets.push((
-                        ln.original_track_index,
-                        ln.original_track_id,
-                        ln.original_position,
-                        ln.node_id,
-                        ln.original_node.clone(),
-                    ));
-                }
-
-                for (target_track, target_track_id, target_pos, target_node_id, target_node) in
-                    targets
-                {
-                    if let TimelineNodeKind::Clip(ref clip) = target_node.kind {
-                        let start = clip.timeline_range.start;
-                        let end = clip.timeline_range.end();
-                        if split_frame <= start || split_frame >= end {
-                            continue;
-                        }
-                        let left_dur = split_frame - start;
-                        let right_dur = end - split_frame;
+        if let Err(err) = self.apply_timeline_command(TimelineCommand::RemoveLinked { node_id }) {
+            eprintln!("timeline remove failed: {err}");
+        } else {
+            self.selected = None;
+        }
+    }
 
-                        let mut left_clip = clip.clone();
-                        left_clip.timeline_range = FrameRange::new(start, left_dur);
-                        left_clip.media_range = FrameRange::new(clip.media_range.start, left_dur);
+    fn selected_node_id(&self) -> Option<NodeId> {
+        let (t, iidx) = self.selected?;
+        self.seq.graph.tracks.get(t)?.node_ids.get(iidx).copied()
+    }
 
-                        let mut updated_node = target_node.clone();
-                        updated_node.kind = TimelineNodeKind::Clip(left_clip);
-                        if let Err(err) = self.apply_timeline_command(TimelineCommand::UpdateNode {
-                            node: updated_node,
-                        }) {
-                            eprintln!("timeline update failed: {err}");
-                            continue;
-                        }
+    /// Collapse the selected clip and its linked partners into a compound clip.
+    fn collapse_selection_to_compound(&mut self) {
+        let Some(node_id) = self.selected_node_id() else {
+            return;
+        };
+        let mut node_ids = vec![node_id];
+        node_ids.extend(self.seq.graph.linked_nodes(node_id));
+        let compound_id = NodeId::new();
+        let command = TimelineCommand::CollapseToCompound {
+            node_ids,
+            compound_id,
+            sequence_id: uuid::Uuid::new_v4().to_string(),
+        };
+        match self.apply_timeline_command(command) {
+            Ok(()) => self.update_selection_for_node(compound_id),
+            Err(err) => eprintln!("timeline collapse failed: {err}"),
+        }
+    }
 
-                        let right_media_start = clip.media_range.start + left_dur;
-                        let mut right_clip = clip.clone();
-                        right_clip.timeline_range = FrameRange::new(split_frame, right_dur);
-                        right_clip.media_range = FrameRange::new(right_media_start, right_dur);
+    fn expand_selected_compound(&mut self) {
+        let Some(node_id) = self.selected_node_id() else {
+            return;
+        };
+        match self.apply_timeline_command(TimelineCommand::ExpandCompound { node_id }) {
+            Ok(()) => self.selected = None,
+            Err(err) => eprintln!("timeline expand failed: {err}"),
+        }
+    }
 
-                        let right_node = TimelineNode {
-                            id: NodeId::new(),
-                            label: target_node.label.clone(),
-                            kind: TimelineNodeKind::Clip(right_clip),
-                            locked: target_node.locked,
-                            metadata: target_node.metadata.clone(),
-                        };
-                        let placement = TrackPlacement {
-                            track_id: target_track_id,
-                            position: Some(target_pos + 1),
-                        };
-                        if let Err(err) = self.apply_timeline_command(TimelineCommand::InsertNode {
-                            node: right_node,
-                            placements: vec![placement],
-                            edges: Vec::new(),
-                        }) {
-                            eprintln!("timeline insert failed: {err}");
-                            continue;
-                        }
+    fn add_marker_at_playhead(&mut self, on_clip: bool) {
+        let frame = self.playhead;
+        let command = if on_clip {
+            let Some(node) = self.selected.and_then(|(t, iidx)| {
+                let binding = self.seq.graph.tracks.get(t)?;
+                self.seq.graph.nodes.get(binding.node_ids.get(iidx)?)
+            }) else {
+                return;
+            };
+            let TimelineNodeKind::Clip(clip) = &node.kind else {
+                return;
+            };
+            let Some(media_frame) = clip.media_time_at(frame) else {
+                return;
+            };
+            TimelineCommand::AddClipMarker {
+                node_id: node.id,
+                marker: Marker::new(media_frame.floor() as i64, MarkerKind::Standard),
+            }
+        } else {
+            TimelineCommand::AddMarker {
+                marker: Marker::new(frame, MarkerKind::Standard),
+            }
+        };
+        if let Err(err) = self.apply_timeline_command(command) {
+            eprintln!("timeline marker failed: {err}");
+        }
+    }
 
-                        if target_node_id == node_id {
-                            self.selected = Some((target_track, target_pos + 1));
-                        }
-                    }
-                }
-                if self.selected.is_none() {
-                    self.selected = Some((track, item + 1));
-                }
+    /// Hold the frame under the playhead for one second on the selected clip
+    /// and its linked partners, rippling later clips to make room.
+    fn freeze_frame_at_playhead(&mut self) {
+        let Some(selected_id) = self.selected_node_id() else {
+            return;
+        };
+        let frame = self.playhead;
+        let hold = (self.seq.fps.num.max(1) as f64 / self.seq.fps.den.max(1) as f64).round() as i64;
+        let mut node_ids = vec![selected_id];
+        node_ids.extend(self.seq.graph.linked_nodes(selected_id));
+        // The ripple trim lengthens the whole link group at once.
+        let mut commands = vec![TimelineCommand::RippleTrim {
+            node_id: selected_id,
+            edge: TrimEdge::End,
+            delta: hold,
+        }];
+        for node_id in node_ids {
+            let Some(TimelineNodeKind::Clip(clip)) =
+                self.seq.graph.nodes.get(&node_id).map(|n| &n.kind)
+            else {
+                continue;
+            };
+            if frame <= clip.timeline_range.start || frame >= clip.timeline_range.end() {
+                continue;
             }
-            TimelineNodeKind::Generator {
-                ref generator_id,
-                ref timeline_range,
-                ref metadata,
-            } => {
-                let start = timeline_range.start;
-                let end = timeline_range.end();
-                if split_frame <= start || split_frame >= end {
-                    return;
-                }
-                let left_dur = split_frame - start;
-                let right_dur = end - split_frame;
+            let mut remap = clip
+                .time_remap
+                .clone()
+                .unwrap_or_else(|| TimeRemap::from_clip(clip));
+            remap.insert_freeze(frame - clip.timeline_range.start, hold);
+            commands.push(TimelineCommand::SetTimeRemap {
+                node_id,
+                remap: Some(remap),
+            });
+        }
+        if commands.len() == 1 {
+            return;
+        }
+        if let Err(err) = self.apply_timeline_batch("Freeze frame", commands) {
+            eprintln!("timeline freeze frame failed: {err}");
+        }
+    }
 
-                let mut updated_node = node.clone();
-                if let TimelineNodeKind::Generator {
-                    ref mut timeline_range,
-                    ..
-                } = updated_node.kind
-                {
-                    *timeline_range = FrameRange::new(start, left_dur);
-                }
-                if let Err(err) =
-                    self.apply_timeline_command(TimelineCommand::UpdateNode { node: updated_node })
-                {
-                    eprintln!("timeline update failed: {err}");
-                    return;
-                }
+    /// Targeted tracks that still exist, in track order.
+    pub(crate) fn targeted_track_ids(&self) -> Vec<TrackId> {
+        self.seq
+            .graph
+            .tracks
+            .iter()
+            .map(|binding| binding.id)
+            .filter(|id| self.edit_marks.target_tracks.contains(id))
+            .collect()
+    }
 
-                let right_node = TimelineNode {
-                    id: NodeId::new(),
-                    label: node.label.clone(),
-                    kind: TimelineNodeKind::Generator {
-                        generator_id: generator_id.clone(),
-                        timeline_range: FrameRange::new(split_frame, right_dur),
-                        metadata: metadata.clone(),
-                    },
-                    locked: node.locked,
-                    metadata: node.metadata.clone(),
-                };
-                let placement = TrackPlacement {
-                    track_id: track_binding.id,
-                    position: Some(item + 1),
-                };
-                if let Err(err) = self.apply_timeline_command(TimelineCommand::InsertNode {
-                    node: right_node,
-                    placements: vec![placement],
-                    edges: Vec::new(),
-                }) {
-                    eprintln!("timeline insert failed: {err}");
-                    return;
+    /// Lift or extract the span between the timeline in and out marks on the
+    /// targeted tracks, or on every unlocked track when none are targeted.
+    fn remove_marked_range(&mut self, extract: bool) {
+        let (Some(start), Some(end)) = (self.edit_marks.timeline_in, self.edit_marks.timeline_out)
+        else {
+            return;
+        };
+        let mut track_ids = self.targeted_track_ids();
+        if track_ids.is_empty() {
+            track_ids = self
+                .seq
+                .graph
+                .tracks
+                .iter()
+                .filter(|b| !b.properties.locked)
+                .map(|b| b.id)
+                .collect();
+        }
+        let range = FrameRange::new(start, end - start);
+        let command = if extract {
+            TimelineCommand::ExtractRange { range, track_ids }
+        } else {
+            TimelineCommand::LiftRange { range, track_ids }
+        };
+        match self.apply_timeline_command(command) {
+            Ok(()) => {
+                self.selected = None;
+                if extract {
+                    self.edit_marks.timeline_out = None;
                 }
-                self.selected = Some((track, item + 1));
             }
-            _ => {}
+            Err(err) => eprintln!("timeline lift/extract failed: {err}"),
         }
     }
 
-    pub(crate) fn remove_clip(&mut self, track: usize, item: usize) {
-        let track_binding = match self.seq.graph.tracks.get(track) {
-            Some(binding) => binding,
-            None => return,
-        };
-        let node_id = match track_binding.node_ids.get(item) {
-            Some(id) => *id,
-            None => return,
-        };
-        if let Err(err) = self.apply_timeline_command(TimelineCommand::RemoveNode { node_id }) {
-            eprintln!("timeline remove failed: {err}");
-        } else {
-            self.selected = None;
+    /// Per-track mute, solo, lock and hide toggles plus volume/pan or
+    /// opacity/blend. Toggles apply at once; slider drags live in
+    /// `track_props_draft` and land as one undo step on release.
+    fn track_properties_menu(&mut self, ui: &mut egui::Ui) {
+        let tracks: Vec<(TrackId, String, bool, TrackProperties)> = self
+            .seq
+            .graph
+            .tracks
+            .iter()
+            .map(|b| {
+                let props = match &self.track_props_draft {
+                    Some((id, draft)) if *id == b.id => draft.clone(),
+                    _ => b.properties.clone(),
+                };
+                (
+                    b.id,
+                    b.name.clone(),
+                    matches!(b.kind, TrackKind::Audio),
+                    props,
+                )
+            })
+            .collect();
+        let mut commit = None;
+        ui.menu_button("Tracks", |ui| {
+            for (id, name, audio, mut props) in tracks {
+                let mut toggled = false;
+                let mut sliders = Vec::new();
+                ui.horizontal(|ui| {
+                    ui.label(name);
+                    toggled |= ui.toggle_value(&mut props.muted, "M").changed();
+                    toggled |= ui.toggle_value(&mut props.solo, "S").changed();
+                    toggled |= ui.toggle_value(&mut props.locked, "L").changed();
+                    if audio {
+                        sliders.push(
+                            ui.add(egui::Slider::new(&mut props.volume, 0.0..=2.0).text("Vol")),
+                        );
+                        sliders.push(
+                            ui.add(egui::Slider::new(&mut props.pan, -1.0..=1.0).text("Pan")),
+                        );
+                    } else {
+                        toggled |= ui.toggle_value(&mut props.hidden, "H").changed();
+                        sliders.push(
+                            ui.add(
+                                egui::Slider::new(&mut props.opacity, 0.0..=1.0).text("Opacity"),
+                            ),
+                        );
+                        egui::ComboBox::from_id_salt(("blend_mode", id))
+                            .selected_text(format!("{:?}", props.blend_mode))
+                            .show_ui(ui, |ui| {
+                                for mode in [
+                                    BlendMode::Normal,
+                                    BlendMode::Multiply,
+                                    BlendMode::Screen,
+                                    BlendMode::Overlay,
+                                    BlendMode::Add,
+                                ] {
+                                    toggled |= ui
+                                        .selectable_value(
+                                            &mut props.blend_mode,
+                                            mode,
+                                            format!("{mode:?}"),
+                                        )
+                                        .changed();
+                                }
+                            });
+                    }
+                });
+                let released = sliders
+                    .iter()
+                    .any(|r| r.drag_stopped() || (r.changed() && !r.dragged()));
+                if toggled || released {
+                    commit = Some((id, props));
+                } else if sliders.iter().any(|r| r.changed()) {
+                    self.track_props_draft = Some((id, props));
+                }
+            }
+            ui.label(
+                "M mute · S solo · L lock · H hide. Blend modes are saved but not composited yet.",
+            );
+        });
+        if let Some((track_id, properties)) = commit {
+            self.track_props_draft = None;
+            let unchanged = self
+                .seq
+                .graph
+                .tracks
+                .iter()
+                .any(|t| t.id == track_id && t.properties == properties);
+            if unchanged {
+                return;
+            }
+            if let Err(err) = self.apply_timeline_command(TimelineCommand::SetTrackProperties {
+                track_id,
+                properties,
+            }) {
+                eprintln!("track edit failed: {err}");
+            }
         }
     }
 
@@ -684,6 +880,85 @@ impl App {
                 self.zoom_px_per_frame =
                     (width / (self.seq.duration_in_frames.max(1) as f32)).max(0.1);
             }
+            ui.separator();
+            let undo_label = self.timeline_history.undo_label().map(str::to_string);
+            let redo_label = self.timeline_history.redo_label().map(str::to_string);
+            let undo = ui.add_enabled(undo_label.is_some(), egui::Button::new("Undo"));
+            let undo = match &undo_label {
+                Some(label) => undo.on_hover_text(format!("Undo {label}")),
+                None => undo,
+            };
+            if undo.clicked() {
+                if let Err(err) = self.undo_timeline() {
+                    eprintln!("timeline undo failed: {err}");
+                }
+            }
+            let redo = ui.add_enabled(redo_label.is_some(), egui::Button::new("Redo"));
+            let redo = match &redo_label {
+                Some(label) => redo.on_hover_text(format!("Redo {label}")),
+                None => redo,
+            };
+            if redo.clicked() {
+                if let Err(err) = self.redo_timeline() {
+                    eprintln!("timeline redo failed: {err}");
+                }
+            }
+            let history: Vec<String> = self
+                .timeline_history
+                .undo_entries()
+                .iter()
+                .rev()
+                .map(|entry| entry.label.clone())
+                .collect();
+            ui.menu_button("History", |ui| {
+                if history.is_empty() {
+                    ui.label("No edits yet");
+                }
+                for label in &history {
+                    ui.label(label);
+                }
+            });
+            ui.separator();
+            self.track_properties_menu(ui);
+            let tracks: Vec<(TrackId, String)> = self
+                .seq
+                .graph
+                .tracks
+                .iter()
+                .map(|b| (b.id, b.name.clone()))
+                .collect();
+            let marks = &mut self.edit_marks;
+            ui.menu_button("Targets", |ui| {
+                for (id, name) in tracks {
+                    let mut on = marks.target_tracks.contains(&id);
+                    if ui.checkbox(&mut on, name).changed() {
+                        marks.target_tracks.retain(|t| *t != id);
+                        if on {
+                            marks.target_tracks.push(id);
+                        }
+                    }
+                }
+            });
+            if let Some(source) = &marks.source {
+                let name = Path::new(&source.src_abs)
+                    .file_name()
+                    .map(|s| s.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| source.src_abs.clone());
+                ui.label(format!("Source: {name}"));
+                ui.label("In");
+                source_mark_ui(ui, &mut marks.source_in);
+                ui.label("Out");
+                source_mark_ui(ui, &mut marks.source_out);
+            }
+            let mark_text = |mark: Option<i64>| mark.map_or("--".to_string(), |f| f.to_string());
+            ui.label(format!(
+                "Marks {} / {}",
+                mark_text(marks.timeline_in),
+                mark_text(marks.timeline_out)
+            ))
+            .on_hover_text(
+                "I/O mark in/out, Alt+X clears. , insert  . overwrite  ; lift  ' extract",
+            );
         });
 
         let track_h = 48.0;
@@ -818,17 +1093,35 @@ impl App {
                             format!("V{}", v_idx)
                         }
                     };
-                    let name = if binding.name.trim().is_empty() {
+                    let mut name = if binding.name.trim().is_empty() {
                         track_label.clone()
                     } else {
                         format!("{}  ·  {}", track_label, binding.name)
                     };
+                    let props = &binding.properties;
+                    for (on, flag) in [
+                        (props.muted, "M"),
+                        (props.solo, "S"),
+                        (props.locked, "L"),
+                        (props.hidden, "H"),
+                    ] {
+                        if on {
+                            name.push_str("  [");
+                            name.push_str(flag);
+                            name.push(']');
+                        }
+                    }
+                    let enabled = if matches!(binding.kind, TrackKind::Audio) {
+                        self.seq.graph.track_audible(ti)
+                    } else {
+                        self.seq.graph.track_visible(ti)
+                    };
                     painter.text(
                         egui::pos2(rect.left() + 8.0, y + 14.0),
                         egui::Align2::LEFT_TOP,
                         name,
                         egui::FontId::monospace(12.0),
-                        egui::Color32::from_gray(210),
+                        egui::Color32::from_gray(if enabled { 210 } else { 110 }),
                     );
                     // items
                     for (ii, node_id) in binding.node_ids.iter().enumerate() {
@@ -868,7 +1161,15 @@ impl App {
                             }
                         }
                         let label = display.label.clone();
-                        let color = display.color;
+                        let offline = display
+                            .media_src
+                            .as_ref()
+                            .and_then(|src| self.offline_media.get(src))
+                            .copied();
+                        let color = match offline {
+                            Some(_) => egui::Color32::from_rgb(140, 36, 36),
+                            None => display.color,
+                        };
                         painter.rect_filled(r, 4.0, color);
                         painter.rect_stroke(r, 4.0, border);
                         painter.text(
@@ -878,9 +1179,22 @@ impl App {
                             egui::FontId::monospace(12.0),
                             egui::Color32::WHITE,
                         );
+                        if let Some(status) = offline {
+                            painter.text(
+                                r.center_bottom() - egui::vec2(0.0, 4.0),
+                                egui::Align2::CENTER_BOTTOM,
+                                match status {
+                                    project::MediaStatus::Changed => "MEDIA CHANGED",
+                                    _ => "MEDIA OFFLINE",
+                                },
+                                egui::FontId::proportional(11.0),
+                                egui::Color32::from_rgb(255, 210, 200),
+                            );
+                        }
 
                         // Optional lightweight waveform lane under clips (audio or video)
-                        if let Some(src_path) = display.media_src.as_deref() {
+                        let media_src = display.media_src.as_deref().filter(|_| offline.is_none());
+                        if let Some(src_path) = media_src {
                             let pbuf = std::path::PathBuf::from(src_path);
                             if let Some(peaks) = self.audio_cache.map.get(&pbuf) {
                                 let rect_lane = r.shrink2(egui::vec2(2.0, 6.0));
@@ -941,15 +1255,22 @@ impl App {
                                         };
                                         let range = Self::node_frame_range(node)
                                             .unwrap_or(FrameRange::new(0, 0));
-                                        let (asset_id, linked) = match &node.kind {
-                                            TimelineNodeKind::Clip(clip) => (
-                                                clip.asset_id.clone(),
-                                                self.gather_linked_drag_nodes(*node_id, clip),
-                                            ),
-                                            _ => (None, Vec::new()),
+                                        let asset_id = match &node.kind {
+                                            TimelineNodeKind::Clip(clip) => clip.asset_id.clone(),
+                                            _ => None,
                                         };
+                                        let linked = self.linked_drag_nodes(*node_id);
                                         self.selected = Some((ti, ii));
-                                        self.drag = Some(DragState {
+                                        let locked = std::iter::once(ti)
+                                            .chain(linked.iter().map(|l| l.original_track_index))
+                                            .any(|t| {
+                                                self.seq
+                                                    .graph
+                                                    .tracks
+                                                    .get(t)
+                                                    .is_some_and(|b| b.properties.locked)
+                                            });
+                                        self.drag = (!locked).then(|| DragState {
                                             original_track_index: ti,
                                             current_track_index: ti,
                                             mode,
@@ -962,6 +1283,10 @@ impl App {
                                             original_position: ii,
                                             asset_id,
                                             linked,
+                                            ripple: ui.input(|i| i.modifiers.alt),
+                                            roll: ui
+                                                .input(|i| i.modifiers.command && !i.modifiers.alt),
+                                            trim_preview: None,
                                         });
                                     }
                                 }
@@ -974,6 +1299,79 @@ impl App {
                         }
                     }
                 }
+                // Markers: timeline markers span the full height, clip markers sit in their row
+                for marker in &self.seq.graph.markers {
+                    let x0 = rect.left() + marker.frame as f32 * self.zoom_px_per_frame;
+                    let color = marker_color32(marker.color);
+                    if let Some(duration) = marker.duration {
+                        let x1 = x0 + duration as f32 * self.zoom_px_per_frame;
+                        painter.rect_filled(
+                            Rect::from_min_max(
+                                egui::pos2(x0, rect.top()),
+                                egui::pos2(x1, rect.top() + 6.0),
+                            ),
+                            0.0,
+                            color.gamma_multiply(0.6),
+                        );
+                    }
+                    painter.line_segment(
+                        [egui::pos2(x0, rect.top()), egui::pos2(x0, rect.bottom())],
+                        Stroke::new(1.0, color.gamma_multiply(0.7)),
+                    );
+                    painter.add(Shape::convex_polygon(
+                        vec![
+                            egui::pos2(x0 - 5.0, rect.top()),
+                            egui::pos2(x0 + 5.0, rect.top()),
+                            egui::pos2(x0, rect.top() + 7.0),
+                        ],
+                        color,
+                        Stroke::NONE,
+                    ));
+                }
+                for (node_id, marker) in self.seq.graph.clip_markers_on_timeline() {
+                    let Some(ti) = self
+                        .seq
+                        .graph
+                        .tracks
+                        .iter()
+                        .position(|b| b.node_ids.contains(&node_id))
+                    else {
+                        continue;
+                    };
+                    let x = rect.left() + marker.frame as f32 * self.zoom_px_per_frame;
+                    let y1 = rect.top() + (ti as f32 + 1.0) * track_h - 4.0;
+                    painter.line_segment(
+                        [egui::pos2(x, y1 - 10.0), egui::pos2(x, y1)],
+                        Stroke::new(2.0, marker_color32(marker.color)),
+                    );
+                }
+
+                // Timeline in/out marks
+                if let (Some(mark_in), Some(mark_out)) =
+                    (self.edit_marks.timeline_in, self.edit_marks.timeline_out)
+                {
+                    let x0 = rect.left() + mark_in as f32 * self.zoom_px_per_frame;
+                    let x1 = rect.left() + mark_out as f32 * self.zoom_px_per_frame;
+                    painter.rect_filled(
+                        egui::Rect::from_min_max(
+                            egui::pos2(x0, rect.top()),
+                            egui::pos2(x1, rect.bottom()),
+                        ),
+                        0.0,
+                        egui::Color32::from_rgba_unmultiplied(120, 160, 255, 24),
+                    );
+                }
+                for mark in [self.edit_marks.timeline_in, self.edit_marks.timeline_out]
+                    .into_iter()
+                    .flatten()
+                {
+                    let x = rect.left() + mark as f32 * self.zoom_px_per_frame;
+                    painter.line_segment(
+                        [egui::pos2(x, rect.top()), egui::pos2(x, rect.bottom())],
+                        egui::Stroke::new(1.0, egui::Color32::from_rgb(120, 160, 255)),
+                    );
+                }
+
                 // Playhead
                 let phx = rect.left() + self.playhead as f32 * self.zoom_px_per_frame;
                 painter.line_segment(
@@ -1034,26 +1432,141 @@ impl App {
                     }
                 }
 
-                // Timeline hotkeys: split/delete
-                let pressed_split = ui.input(|i| {
-                    i.key_pressed(egui::Key::K)
-                        || (i.modifiers.command && i.key_pressed(egui::Key::S))
-                });
-                let pressed_delete = ui.input(|i| {
-                    i.key_pressed(egui::Key::Delete) || i.key_pressed(egui::Key::Backspace)
-                });
-                if pressed_split {
-                    if let Some((t, iidx)) = self.selected {
-                        let fps =
-                            (self.seq.fps.num.max(1) as f64) / (self.seq.fps.den.max(1) as f64);
-                        let t_sec = self.playback_clock.now();
-                        let split_frame = (t_sec * fps).round() as i64;
-                        self.split_clip_at_frame(t, iidx, split_frame);
+                // Shortcuts stay off while the recovery prompt disables the timeline.
+                if ui.is_enabled() {
+                    // Timeline hotkeys: split/delete
+                    let pressed_split = ui.input(|i| {
+                        i.key_pressed(egui::Key::K)
+                            || (i.modifiers.command && i.key_pressed(egui::Key::S))
+                    });
+                    let pressed_delete = ui.input(|i| {
+                        i.key_pressed(egui::Key::Delete) || i.key_pressed(egui::Key::Backspace)
+                    });
+                    if pressed_split {
+                        if let Some((t, iidx)) = self.selected {
+                            let fps =
+                                (self.seq.fps.num.max(1) as f64) / (self.seq.fps.den.max(1) as f64);
+                            let t_sec = self.playback_clock.now();
+                            let split_frame = (t_sec * fps).round() as i64;
+                            self.split_clip_at_frame(t, iidx, split_frame);
+                        }
                     }
-                }
-                if pressed_delete {
-                    if let Some((t, iidx)) = self.selected.take() {
-                        self.remove_clip(t, iidx);
+                    if pressed_delete {
+                        if let Some((t, iidx)) = self.selected.take() {
+                            self.remove_clip(t, iidx);
+                        }
+                    }
+                    // Cmd+Z undoes the last timeline edit; Cmd+Shift+Z redoes it
+                    let (pressed_undo, pressed_redo) = ui.input(|i| {
+                        let z = i.modifiers.command && i.key_pressed(egui::Key::Z);
+                        (z && !i.modifiers.shift, z && i.modifiers.shift)
+                    });
+                    if pressed_undo {
+                        if let Err(err) = self.undo_timeline() {
+                            eprintln!("timeline undo failed: {err}");
+                        }
+                    } else if pressed_redo {
+                        if let Err(err) = self.redo_timeline() {
+                            eprintln!("timeline redo failed: {err}");
+                        }
+                    }
+                    // Cmd+G collapses the selection into a compound clip; Cmd+Shift+G expands it
+                    let pressed_compound = ui.input(|i| {
+                        (i.modifiers.command && i.key_pressed(egui::Key::G))
+                            .then_some(i.modifiers.shift)
+                    });
+                    match pressed_compound {
+                        Some(false) => self.collapse_selection_to_compound(),
+                        Some(true) => self.expand_selected_compound(),
+                        None => {}
+                    }
+                    // M drops a marker at the playhead; Shift+M puts it on the selected clip
+                    let pressed_marker = ui.input(|i| {
+                        (!i.modifiers.command && i.key_pressed(egui::Key::M))
+                            .then_some(i.modifiers.shift)
+                    });
+                    if let Some(on_clip) = pressed_marker {
+                        self.add_marker_at_playhead(on_clip);
+                    }
+                    // Shift+F freezes the selected clip on the playhead frame for a second
+                    if ui.input(|i| {
+                        i.modifiers.shift && !i.modifiers.command && i.key_pressed(egui::Key::F)
+                    }) {
+                        self.freeze_frame_at_playhead();
+                    }
+                    // I/O mark the timeline in/out at the playhead; Alt+X clears both
+                    let plain = |i: &egui::InputState| !i.modifiers.command && !i.modifiers.alt;
+                    let (mark_in, mark_out, clear_marks) = ui.input(|i| {
+                        (
+                            plain(i) && i.key_pressed(egui::Key::I),
+                            plain(i) && i.key_pressed(egui::Key::O),
+                            i.modifiers.alt && i.key_pressed(egui::Key::X),
+                        )
+                    });
+                    if mark_in {
+                        self.edit_marks.timeline_in = Some(self.playhead);
+                    }
+                    if mark_out {
+                        self.edit_marks.timeline_out = Some(self.playhead + 1);
+                    }
+                    if clear_marks {
+                        self.edit_marks.timeline_in = None;
+                        self.edit_marks.timeline_out = None;
+                    }
+                    // , inserts the edit source and . overwrites with it; ; lifts and ' extracts
+                    let range_edit = ui.input(|i| {
+                        if !plain(i) {
+                            return None;
+                        }
+                        [
+                            egui::Key::Comma,
+                            egui::Key::Period,
+                            egui::Key::Semicolon,
+                            egui::Key::Quote,
+                        ]
+                        .into_iter()
+                        .find(|key| i.key_pressed(*key))
+                    });
+                    match range_edit {
+                        Some(egui::Key::Comma) => self.three_point_edit(false),
+                        Some(egui::Key::Period) => self.three_point_edit(true),
+                        Some(egui::Key::Semicolon) => self.remove_marked_range(false),
+                        Some(egui::Key::Quote) => self.remove_marked_range(true),
+                        _ => {}
+                    }
+                    // Alt+,/. slides the selected clip a frame; add Shift to slip its media instead
+                    let nudge = ui.input(|i| {
+                        if !i.modifiers.alt {
+                            return None;
+                        }
+                        let delta = if i.key_pressed(egui::Key::Comma) {
+                            -1
+                        } else if i.key_pressed(egui::Key::Period) {
+                            1
+                        } else {
+                            return None;
+                        };
+                        Some((i.modifiers.shift, delta))
+                    });
+                    if let Some((slip, delta)) = nudge {
+                        let selected_id = self.selected.and_then(|(t, iidx)| {
+                            self.seq
+                                .graph
+                                .tracks
+                                .get(t)
+                                .and_then(|b| b.node_ids.get(iidx))
+                                .copied()
+                        });
+                        if let Some(node_id) = selected_id {
+                            let command = if slip {
+                                TimelineCommand::SlipClip { node_id, delta }
+                            } else {
+                                TimelineCommand::SlideClip { node_id, delta }
+                            };
+                            if let Err(err) = self.apply_timeline_command(command) {
+                                eprintln!("timeline nudge failed: {err}");
+                            }
+                        }
                     }
                 }
 
@@ -1064,7 +1577,7 @@ impl App {
                     // Drop asset onto timeline if any
                     if let Some(asset) = self.dragging_asset.take() {
                         if let Some(pos) = ui.input(|i| i.pointer.hover_pos()) {
-                            if rect.contains(pos) {
+                            if rect.contains(pos) && ui.is_enabled() {
                                 let local_x = (pos.x - rect.left()).max(0.0) as f64;
                                 let frames =
                                     (local_x / self.zoom_px_per_frame as f64).round() as i64;
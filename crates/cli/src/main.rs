@@ -1,7 +1,10 @@
 use anyhow::Result;
-use clap::{Parser, Subcommand};
-use std::path::PathBuf;
-use timeline::{Fps, Sequence};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::path::{Path, PathBuf};
+use timeline::{
+    ClipNode, EditPoints, Fps, FrameRange, NodeId, Sequence, TimelineCommand, TimelineGraph,
+    TimelineNode, TimelineNodeKind, TrackBinding, TrackId, TrackKind, TrimEdge,
+};
 use tracing::{info, warn};
 
 #[derive(Parser)]
@@ -34,6 +37,10 @@ enum Commands {
         /// Generate thumbnails
         #[arg(long)]
         thumbnails: bool,
+
+        /// Project database (defaults to the CLI database)
+        #[arg(long)]
+        db: Option<PathBuf>,
     },
 
     /// Export a sequence to video
@@ -118,10 +125,205 @@ enum Commands {
         /// Frame rate (e.g., 30, 25, 24)
         #[arg(long, default_value = "30")]
         fps: u32,
+
+        /// Project database (defaults to the CLI database)
+        #[arg(long)]
+        db: Option<PathBuf>,
     },
 
     /// List available hardware encoders
     Encoders,
+
+    /// Three-point edits on a sequence in a project file
+    Edit {
+        /// Project file path
+        #[arg(short, long)]
+        project: PathBuf,
+
+        /// Sequence name to edit
+        #[arg(short, long, default_value = "Main")]
+        sequence: String,
+
+        /// Target track name, repeat for several (missing tracks are created)
+        #[arg(short, long = "track", required = true)]
+        tracks: Vec<String>,
+
+        #[command(subcommand)]
+        op: EditOp,
+    },
+
+    /// Collect a project's media and database into a portable archive
+    Archive {
+        /// Project file path
+        #[arg(short, long)]
+        project: PathBuf,
+
+        /// Output folder, or zip file with --zip
+        output: PathBuf,
+
+        /// Project database (defaults to the CLI database)
+        #[arg(long)]
+        db: Option<PathBuf>,
+
+        /// Cut media down to the ranges the sequences use
+        #[arg(long)]
+        trim: bool,
+
+        /// Seconds kept either side of the used ranges when trimming
+        #[arg(long, default_value = "1.0")]
+        handles: f64,
+
+        /// Include proxies
+        #[arg(long)]
+        proxies: bool,
+
+        /// Write a zip file instead of a folder
+        #[arg(long)]
+        zip: bool,
+    },
+
+    /// Show a project's settings, changing any that are given
+    Settings {
+        /// Project file path
+        #[arg(short, long)]
+        project: PathBuf,
+
+        /// Project database (defaults to the CLI database)
+        #[arg(long)]
+        db: Option<PathBuf>,
+
+        /// Playback source: original-optimized, proxy-preferred or proxy-only
+        #[arg(long)]
+        proxy_mode: Option<project::ProxyMode>,
+
+        /// Proxies queued on import: off, large-only or all
+        #[arg(long)]
+        auto_proxy: Option<project::AutoProxySetting>,
+
+        /// Width of the first sequence in a new project
+        #[arg(long)]
+        width: Option<u32>,
+
+        /// Height of the first sequence in a new project
+        #[arg(long)]
+        height: Option<u32>,
+
+        /// Frame rate of the first sequence in a new project
+        #[arg(long)]
+        fps: Option<u32>,
+    },
+
+    /// Add the project in an archive to a database and relink its media
+    Unarchive {
+        /// Archive folder or zip file
+        archive: PathBuf,
+
+        /// Folder to extract a zip into (next to the zip by default)
+        #[arg(short, long)]
+        dest: Option<PathBuf>,
+
+        /// Project database (defaults to the CLI database)
+        #[arg(long)]
+        db: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum EditOp {
+    /// Insert a source clip, pushing later clips on the target tracks along
+    Insert(SourceArgs),
+    /// Lay a source clip over whatever sits on the target tracks
+    Overwrite(SourceArgs),
+    /// Clear a frame range on the target tracks, leaving a gap
+    Lift(RangeArgs),
+    /// Clear a frame range on the target tracks and close the gap
+    Extract(RangeArgs),
+    /// Move one edge of the clip at a frame on the first target track
+    Trim(TrimArgs),
+    /// Trim the clip at a frame and move later clips on its track with the edge
+    RippleTrim(TrimArgs),
+    /// Move the edit point at a frame on the first target track
+    Roll(NudgeArgs),
+    /// Shift the media of the clip at a frame without moving the clip
+    Slip(NudgeArgs),
+    /// Move the clip at a frame, trimming the clips either side of it
+    Slide(NudgeArgs),
+}
+
+/// Source clip and edit points. Any three points are enough; all frames are
+/// at the sequence rate.
+#[derive(Args)]
+struct SourceArgs {
+    /// Source media file
+    media: PathBuf,
+
+    /// Source length in frames (probed from the file if omitted)
+    #[arg(long)]
+    length: Option<i64>,
+
+    #[arg(long)]
+    source_in: Option<i64>,
+
+    #[arg(long)]
+    source_out: Option<i64>,
+
+    #[arg(long)]
+    record_in: Option<i64>,
+
+    #[arg(long)]
+    record_out: Option<i64>,
+}
+
+#[derive(Args)]
+struct TrimArgs {
+    /// A frame inside the clip
+    #[arg(long)]
+    at: i64,
+
+    /// Edge to move
+    #[arg(long, value_enum)]
+    edge: EdgeArg,
+
+    /// Frames to move the edge by; negative moves it earlier
+    #[arg(long, allow_hyphen_values = true)]
+    delta: i64,
+}
+
+#[derive(Args)]
+struct NudgeArgs {
+    /// A frame inside the clip, or the edit point for a roll
+    #[arg(long)]
+    at: i64,
+
+    /// Frames to move by; negative moves earlier
+    #[arg(long, allow_hyphen_values = true)]
+    delta: i64,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum EdgeArg {
+    Start,
+    End,
+}
+
+impl From<EdgeArg> for TrimEdge {
+    fn from(edge: EdgeArg) -> Self {
+        match edge {
+            EdgeArg::Start => TrimEdge::Start,
+            EdgeArg::End => TrimEdge::End,
+        }
+    }
+}
+
+#[derive(Args)]
+struct RangeArgs {
+    /// First frame of the range
+    #[arg(long)]
+    from: i64,
+
+    /// Frame after the last one in the range
+    #[arg(long)]
+    to: i64,
 }
 
 #[tokio::main]
@@ -137,13 +339,18 @@ async fn main() -> Result<()> {
 
     tracing_subscriber::fmt().with_max_level(level).init();
 
-    match cli.command {
+    run(cli.command).await
+}
+
+async fn run(command: Commands) -> Result<()> {
+    match command {
         Commands::Import {
             project,
             files,
             proxies,
             thumbnails,
-        } => import_command(project, files, proxies, thumbnails).await,
+            db,
+        } => import_command(project, files, proxies, thumbnails, db).await,
         Commands::Export {
             project,
             sequence,
@@ -170,8 +377,51 @@ async fn main() -> Result<()> {
             width,
             height,
             fps,
-        } => new_command(name, directory, width, height, fps).await,
+            db,
+        } => new_command(name, directory, width, height, fps, db).await,
         Commands::Encoders => encoders_command().await,
+        Commands::Edit {
+            project,
+            sequence,
+            tracks,
+            op,
+        } => edit_command(project, sequence, tracks, op).await,
+        Commands::Archive {
+            project,
+            output,
+            db,
+            trim,
+            handles,
+            proxies,
+            zip,
+        } => {
+            let options = exporters::archive::ArchiveOptions {
+                trim,
+                handles_seconds: handles,
+                include_proxies: proxies,
+                zip,
+            };
+            archive_command(project, output, db, options).await
+        }
+        Commands::Settings {
+            project,
+            db,
+            proxy_mode,
+            auto_proxy,
+            width,
+            height,
+            fps,
+        } => {
+            let changes = SettingsChanges {
+                proxy_mode,
+                auto_proxy,
+                width,
+                height,
+                fps,
+            };
+            settings_command(project, db, changes).await
+        }
+        Commands::Unarchive { archive, dest, db } => unarchive_command(archive, dest, db).await,
     }
 }
 
@@ -180,6 +430,7 @@ async fn import_command(
     files: Vec<PathBuf>,
     generate_proxies: bool,
     generate_thumbnails: bool,
+    db: Option<PathBuf>,
 ) -> Result<()> {
     info!(
         "Importing {} files into project: {:?}",
@@ -188,15 +439,8 @@ async fn import_command(
     );
 
     // Create or open project
-    let data_dir = project::app_data_dir();
-    std::fs::create_dir_all(&data_dir)?;
-    let db_path = data_dir.join("cli.db");
-    let db = project::ProjectDb::open_or_create(&db_path)?;
-
-    let project_id = project_path
-        .file_stem()
-        .and_then(|s| s.to_str())
-        .unwrap_or("cli_project");
+    let db = open_project_db(db)?;
+    let project_id = project_id(&project_path);
 
     db.ensure_project(project_id, &format!("CLI Project: {}", project_id), None)?;
 
@@ -207,6 +451,14 @@ async fn import_command(
             continue;
         }
 
+        if let Some(existing) = db.find_duplicate_asset(project_id, file)? {
+            warn!(
+                "Skipping {:?}: same content as asset {} ({})",
+                file, existing.id, existing.src_abs
+            );
+            continue;
+        }
+
         info!("Importing: {:?}", file);
 
         match media_io::probe_media(file) {
@@ -248,16 +500,52 @@ async fn import_command(
                 )?;
 
                 info!("Imported {} as asset ID: {}", kind, asset_id);
+                if let Some(donor) = db.reuse_derived_media(&asset_id)? {
+                    info!("Reusing proxy and caches of asset {}", donor.id);
+                }
 
                 // Generate proxy if requested
                 if generate_proxies && matches!(info.kind, media_io::MediaKind::Video) {
                     let proxy_path = file.with_extension("proxy.mp4");
-                    info!("Generating proxy: {:?}", proxy_path);
-
-                    if let Err(e) = media_io::generate_proxy(file, &proxy_path, 960, 540, 2000) {
-                        warn!("Failed to generate proxy: {}", e);
+                    let (width, height, bitrate_kbps) = (960, 540, 2000);
+                    let settings_hash = project::variant_settings_hash(&serde_json::json!({
+                        "codec": "h264",
+                        "width": width,
+                        "height": height,
+                        "bitrate_kbps": bitrate_kbps,
+                    }));
+                    let have = db.proxy_variants(&asset_id)?.into_iter().any(|v| {
+                        v.valid
+                            && v.settings_hash == settings_hash
+                            && Path::new(&v.path_abs).is_file()
+                    });
+                    if have {
+                        info!("Proxy with these settings already exists");
                     } else {
-                        info!("Proxy generated successfully");
+                        info!("Generating proxy: {:?}", proxy_path);
+                        match media_io::generate_proxy(
+                            file,
+                            &proxy_path,
+                            width,
+                            height,
+                            bitrate_kbps,
+                        ) {
+                            Ok(()) => {
+                                db.register_proxy_variant(&project::ProxyVariantInsert {
+                                    asset_id: &asset_id,
+                                    kind: project::VariantKind::Proxy,
+                                    width: Some(width as i64),
+                                    height: Some(height as i64),
+                                    codec: Some("h264"),
+                                    pixel_fmt: None,
+                                    bitrate_kbps: Some(bitrate_kbps as i64),
+                                    path: &proxy_path,
+                                    settings_hash: &settings_hash,
+                                })?;
+                                info!("Proxy generated successfully");
+                            }
+                            Err(e) => warn!("Failed to generate proxy: {}", e),
+                        }
                     }
                 }
 
@@ -458,28 +746,38 @@ async fn new_command(
     width: u32,
     height: u32,
     fps: u32,
+    db: Option<PathBuf>,
 ) -> Result<()> {
     let project_dir = directory.unwrap_or_else(|| PathBuf::from(&name));
 
     info!("Creating new project '{}' in {:?}", name, project_dir);
     info!("Sequence settings: {}x{} @ {}fps", width, height, fps);
+    let format = project::SequenceDefaults {
+        width,
+        height,
+        fps_num: fps,
+        fps_den: 1,
+    };
+    format.validate()?;
 
     // Create project directory
     std::fs::create_dir_all(&project_dir)?;
 
     // Create basic project structure
-    let data_dir = project::app_data_dir();
-    std::fs::create_dir_all(&data_dir)?;
-    let db_path = data_dir.join(format!("{}.db", name));
+    let project_file = project_dir.join(format!("{}.gausian", name));
+    let project_id = project_id(&project_file);
+    let db_path = project_db_path(db)?;
     let db = project::ProjectDb::open_or_create(&db_path)?;
 
-    db.ensure_project(&name, &name, Some(&project_dir))?;
+    db.ensure_project(project_id, &name, Some(&project_dir))?;
+    let mut settings = db.get_project_settings(project_id)?;
+    settings.sequence = format;
+    db.update_project_settings(project_id, &settings)?;
 
     // Create a basic sequence
     let sequence = Sequence::new("Main", width, height, Fps::new(fps, 1), 0);
 
     // Save project file
-    let project_file = project_dir.join(format!("{}.gausian", name));
     let project_data = serde_json::json!({
         "name": name,
         "sequences": [sequence],
@@ -523,3 +821,406 @@ async fn encoders_command() -> Result<()> {
 
     Ok(())
 }
+
+async fn edit_command(
+    project_path: PathBuf,
+    sequence_name: String,
+    tracks: Vec<String>,
+    op: EditOp,
+) -> Result<()> {
+    let mut project: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&project_path)?)?;
+    let slot = project
+        .get_mut("sequences")
+        .and_then(|v| v.as_array_mut())
+        .and_then(|sequences| {
+            sequences
+                .iter_mut()
+                .find(|s| s.get("name").and_then(|n| n.as_str()) == Some(sequence_name.as_str()))
+        })
+        .ok_or_else(|| anyhow::anyhow!("Sequence not found: {}", sequence_name))?;
+    let mut sequence: Sequence = serde_json::from_value(slot.clone())?;
+    if sequence.graph.tracks.is_empty() {
+        sequence.graph = timeline::migrate_sequence_tracks(&sequence);
+    }
+
+    let mut commands = Vec::new();
+    let mut track_ids = Vec::new();
+    for name in &tracks {
+        if let Some(binding) = sequence.graph.tracks.iter().find(|t| &t.name == name) {
+            track_ids.push(binding.id);
+            continue;
+        }
+        let binding = TrackBinding {
+            id: TrackId::new(),
+            name: name.clone(),
+            kind: if name.to_ascii_uppercase().starts_with('A') {
+                TrackKind::Audio
+            } else {
+                TrackKind::Video
+            },
+            node_ids: Vec::new(),
+            properties: Default::default(),
+        };
+        info!("Creating track '{}'", name);
+        track_ids.push(binding.id);
+        commands.push(TimelineCommand::UpsertTrack { track: binding });
+    }
+
+    let edit = match op {
+        EditOp::Insert(source) => TimelineCommand::InsertEdit {
+            node: source_clip(&source, sequence.fps)?,
+            track_ids,
+        },
+        EditOp::Overwrite(source) => TimelineCommand::OverwriteEdit {
+            node: source_clip(&source, sequence.fps)?,
+            track_ids,
+        },
+        EditOp::Lift(range) => TimelineCommand::LiftRange {
+            range: FrameRange::new(range.from, range.to - range.from),
+            track_ids,
+        },
+        EditOp::Extract(range) => TimelineCommand::ExtractRange {
+            range: FrameRange::new(range.from, range.to - range.from),
+            track_ids,
+        },
+        EditOp::Trim(trim) => TimelineCommand::TrimNode {
+            node_id: clip_at(&sequence.graph, track_ids[0], trim.at)?,
+            edge: trim.edge.into(),
+            delta: trim.delta,
+        },
+        EditOp::RippleTrim(trim) => TimelineCommand::RippleTrim {
+            node_id: clip_at(&sequence.graph, track_ids[0], trim.at)?,
+            edge: trim.edge.into(),
+            delta: trim.delta,
+        },
+        EditOp::Roll(nudge) => TimelineCommand::RollEdit {
+            left: clip_where(&sequence.graph, track_ids[0], |r| r.end() == nudge.at)
+                .ok_or_else(|| anyhow::anyhow!("No edit point at frame {}", nudge.at))?,
+            right: clip_where(&sequence.graph, track_ids[0], |r| r.start == nudge.at)
+                .ok_or_else(|| anyhow::anyhow!("No edit point at frame {}", nudge.at))?,
+            delta: nudge.delta,
+        },
+        EditOp::Slip(nudge) => TimelineCommand::SlipClip {
+            node_id: clip_at(&sequence.graph, track_ids[0], nudge.at)?,
+            delta: nudge.delta,
+        },
+        EditOp::Slide(nudge) => TimelineCommand::SlideClip {
+            node_id: clip_at(&sequence.graph, track_ids[0], nudge.at)?,
+            delta: nudge.delta,
+        },
+    };
+    let label = edit.label();
+    commands.push(edit);
+    timeline::apply_command(&mut sequence.graph, TimelineCommand::Batch { commands })?;
+    // Older readers still use the legacy tracks, so rebuild them from the graph.
+    sequence.sync_tracks_from_graph();
+
+    *slot = serde_json::to_value(&sequence)?;
+    std::fs::write(&project_path, serde_json::to_string_pretty(&project)?)?;
+    info!("{} applied to sequence '{}'", label, sequence_name);
+    Ok(())
+}
+
+/// First node on `track_id` whose timeline range matches `pred`.
+fn clip_where(
+    graph: &TimelineGraph,
+    track_id: TrackId,
+    pred: impl Fn(&FrameRange) -> bool,
+) -> Option<NodeId> {
+    let track = graph.tracks.iter().find(|t| t.id == track_id)?;
+    track.node_ids.iter().copied().find(|id| {
+        graph
+            .nodes
+            .get(id)
+            .and_then(|n| n.timeline_range())
+            .is_some_and(&pred)
+    })
+}
+
+/// The clip on `track_id` that covers `frame`.
+fn clip_at(graph: &TimelineGraph, track_id: TrackId, frame: i64) -> Result<NodeId> {
+    clip_where(graph, track_id, |r| r.start <= frame && frame < r.end())
+        .ok_or_else(|| anyhow::anyhow!("No clip at frame {}", frame))
+}
+
+/// `db`, or the CLI database when none is given. Every subcommand keeps its
+/// projects here.
+fn project_db_path(db: Option<PathBuf>) -> Result<PathBuf> {
+    Ok(match db {
+        Some(path) => path,
+        None => {
+            let data_dir = project::app_data_dir();
+            std::fs::create_dir_all(&data_dir)?;
+            data_dir.join("cli.db")
+        }
+    })
+}
+
+fn open_project_db(db: Option<PathBuf>) -> Result<project::ProjectDb> {
+    project::ProjectDb::open_or_create(&project_db_path(db)?)
+}
+
+/// Database id of the project stored in `project_path`: the file name
+/// without its extension, as `new` names it.
+fn project_id(project_path: &Path) -> &str {
+    project_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("cli_project")
+}
+
+async fn archive_command(
+    project_path: PathBuf,
+    output: PathBuf,
+    db: Option<PathBuf>,
+    options: exporters::archive::ArchiveOptions,
+) -> Result<()> {
+    let db = open_project_db(db)?;
+    let project_id = project_id(&project_path);
+
+    info!("Archiving project '{}' to {:?}", project_id, output);
+    let report = exporters::archive::archive_project(&db, project_id, &output, &options)?;
+    for (path, reason) in &report.missing {
+        warn!("Left out {}: {}", path, reason);
+    }
+    info!(
+        "Archived {} media files ({} trimmed) and {} proxies to {:?}",
+        report.media, report.trimmed, report.proxies, report.output
+    );
+    Ok(())
+}
+
+/// Settings given on the command line for `settings`.
+struct SettingsChanges {
+    proxy_mode: Option<project::ProxyMode>,
+    auto_proxy: Option<project::AutoProxySetting>,
+    width: Option<u32>,
+    height: Option<u32>,
+    fps: Option<u32>,
+}
+
+async fn settings_command(
+    project_path: PathBuf,
+    db: Option<PathBuf>,
+    changes: SettingsChanges,
+) -> Result<()> {
+    let db = open_project_db(db)?;
+    let project_id = project_id(&project_path);
+
+    let mut settings = db.get_project_settings(project_id)?;
+    let before = settings.clone();
+    if let Some(mode) = changes.proxy_mode {
+        settings.proxy.mode = mode;
+    }
+    if let Some(auto_proxy) = changes.auto_proxy {
+        settings.proxy.auto_proxy = auto_proxy;
+    }
+    if let Some(width) = changes.width {
+        settings.sequence.width = width;
+    }
+    if let Some(height) = changes.height {
+        settings.sequence.height = height;
+    }
+    if let Some(fps) = changes.fps {
+        settings.sequence.fps_num = fps;
+        settings.sequence.fps_den = 1;
+    }
+    if settings != before {
+        db.update_project_settings(project_id, &settings)?;
+        info!("Updated settings for project '{}'", project_id);
+    }
+
+    println!("{}", serde_json::to_string_pretty(&settings)?);
+    Ok(())
+}
+
+async fn unarchive_command(
+    archive: PathBuf,
+    dest: Option<PathBuf>,
+    db: Option<PathBuf>,
+) -> Result<()> {
+    let db = open_project_db(db)?;
+    info!("Unpacking {:?}", archive);
+    let report = exporters::archive::unarchive_project(&db, &archive, dest.as_deref())?;
+    for (path, reason) in &report.failed {
+        warn!("Could not relink {}: {}", path, reason);
+    }
+    info!(
+        "Project '{}' unpacked to {:?}, {} media files relinked",
+        report.project_id, report.root, report.relinked
+    );
+    Ok(())
+}
+
+/// Clip node for a three-point edit, with media frames scaled to the
+/// source's own rate when it can be probed.
+fn source_clip(source: &SourceArgs, fps: Fps) -> Result<TimelineNode> {
+    let seq_rate = fps.num.max(1) as f64 / fps.den.max(1) as f64;
+    let info = media_io::probe_media(&source.media).ok();
+    let media_rate = info
+        .as_ref()
+        .and_then(|i| Some(i.fps_num? as f64 / i.fps_den.filter(|d| *d > 0)? as f64))
+        .unwrap_or(seq_rate);
+    let length = source
+        .length
+        .or_else(|| {
+            let seconds = info.as_ref()?.duration_seconds?;
+            Some((seconds * seq_rate).round() as i64)
+        })
+        .ok_or_else(|| anyhow::anyhow!("Cannot probe {:?}; pass --length", source.media))?;
+
+    let points = EditPoints {
+        source_in: source.source_in,
+        source_out: source.source_out,
+        record_in: source.record_in,
+        record_out: source.record_out,
+    };
+    let (range, record_in) = points
+        .resolve(length)
+        .ok_or_else(|| anyhow::anyhow!("Edit points do not describe a clip"))?;
+    let scale = media_rate / seq_rate;
+    let media_range = FrameRange::new(
+        (range.start as f64 * scale).round() as i64,
+        ((range.duration as f64 * scale).round() as i64).max(1),
+    );
+    let metadata = match info.as_ref().and_then(|i| Some((i.fps_num?, i.fps_den?))) {
+        Some((num, den)) => serde_json::json!({ "media_fps_num": num, "media_fps_den": den }),
+        None => serde_json::Value::Null,
+    };
+
+    Ok(TimelineNode {
+        id: NodeId::new(),
+        label: source
+            .media
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned()),
+        kind: TimelineNodeKind::Clip(ClipNode {
+            asset_id: Some(source.media.to_string_lossy().into_owned()),
+            media_range,
+            timeline_range: FrameRange::new(record_in, range.duration),
+            playback_rate: 1.0,
+            reverse: false,
+            time_remap: None,
+            markers: Vec::new(),
+            metadata,
+        }),
+        locked: false,
+        metadata: serde_json::Value::Null,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn run_args(args: &[&str]) -> Result<()> {
+        let cli = Cli::try_parse_from(std::iter::once("gausian-cli").chain(args.iter().copied()))?;
+        run(cli.command).await
+    }
+
+    #[tokio::test]
+    async fn settings_finds_a_project_made_by_new() {
+        let dir = std::env::temp_dir().join(format!("gausian-cli-test-{}", std::process::id()));
+        let db = dir.join("cli.db");
+        let db = db.to_str().unwrap();
+        let project_dir = dir.join("Trailer");
+        run_args(&[
+            "new",
+            "Trailer",
+            "--directory",
+            project_dir.to_str().unwrap(),
+            "--fps",
+            "25",
+            "--db",
+            db,
+        ])
+        .await
+        .unwrap();
+
+        let project_file = project_dir.join("Trailer.gausian");
+        run_args(&[
+            "settings",
+            "--project",
+            project_file.to_str().unwrap(),
+            "--db",
+            db,
+            "--width",
+            "1280",
+        ])
+        .await
+        .unwrap();
+
+        let settings = project::ProjectDb::open_or_create(Path::new(db))
+            .unwrap()
+            .get_project_settings("Trailer")
+            .unwrap();
+        assert_eq!(
+            (settings.sequence.width, settings.sequence.fps_num),
+            (1280, 25)
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn edit_trims_rolls_and_slides_clips() {
+        let dir = std::env::temp_dir().join(format!("gausian-cli-edit-{}", std::process::id()));
+        let db = dir.join("cli.db");
+        let project_dir = dir.join("Cut");
+        run_args(&[
+            "new",
+            "Cut",
+            "--directory",
+            project_dir.to_str().unwrap(),
+            "--db",
+            db.to_str().unwrap(),
+        ])
+        .await
+        .unwrap();
+        let project = project_dir.join("Cut.gausian");
+        let project = project.to_str().unwrap();
+        let media = dir.join("missing.mov");
+        let media = media.to_str().unwrap();
+        let edit = |op: &'static [&'static str]| {
+            let mut args = vec!["edit", "--project", project, "--track", "V1"];
+            args.extend_from_slice(op);
+            args
+        };
+        for record_in in ["0", "48"] {
+            let mut args = edit(&["insert", "--length", "48", "--source-in", "0"]);
+            args.extend_from_slice(&[media, "--record-in", record_in]);
+            run_args(&args).await.unwrap();
+        }
+        for op in [
+            &[
+                "ripple-trim",
+                "--at",
+                "10",
+                "--edge",
+                "end",
+                "--delta",
+                "-8",
+            ][..],
+            &["roll", "--at", "40", "--delta", "4"][..],
+            &["slide", "--at", "60", "--delta", "-2"][..],
+        ] {
+            run_args(&edit(op)).await.unwrap();
+        }
+        assert!(run_args(&edit(&["slip", "--at", "500", "--delta", "1"]))
+            .await
+            .is_err());
+
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(project).unwrap()).unwrap();
+        let sequence: Sequence = serde_json::from_value(json["sequences"][0].clone()).unwrap();
+        let mut ranges: Vec<_> = sequence
+            .graph
+            .nodes
+            .values()
+            .filter_map(|n| n.timeline_range().cloned())
+            .collect();
+        ranges.sort_by_key(|r| r.start);
+        assert_eq!(ranges, [FrameRange::new(0, 42), FrameRange::new(42, 44)]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,805 @@
+//! Project archives: a project's media, proxies and a project-only database
+//! gathered into one folder or zip that can be unpacked on another machine.
+//!
+//! An archive holds `project.db` (see [`ProjectDb::export_project_db`]),
+//! `manifest.json`, the media under `media/` and proxies under `proxies/`.
+//! Clips keep referring to media by the path it had when it was archived;
+//! unpacking relinks the assets and rewrites those references.
+
+use anyhow::{Context, Result};
+use project::{AssetMediaDetails, AssetRow, ProjectDb};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use timeline::{ClipNode, Fps, ItemKind, Sequence, TimelineNodeKind};
+use walkdir::WalkDir;
+
+use crate::relinking::{relink_assets, RelinkResult};
+use crate::{AssetInfo, AssetKind, RelinkStrategy};
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+pub const DATABASE_FILE: &str = "project.db";
+const MEDIA_DIR: &str = "media";
+const PROXY_DIR: &str = "proxies";
+const MANIFEST_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Default)]
+pub struct ArchiveOptions {
+    /// Cut video and audio down to the span the sequences use, plus
+    /// `handles_seconds` either side. Trimmed media is re-encoded to ProRes
+    /// 422 HQ, or 24-bit PCM for sound, so the cut lands on an exact frame.
+    /// Media no sequence uses is copied whole.
+    pub trim: bool,
+    pub handles_seconds: f64,
+    /// Copy ready proxies too. Trimmed media never brings its proxy, which
+    /// would no longer line up.
+    pub include_proxies: bool,
+    /// Write a zip file instead of a folder.
+    pub zip: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveManifest {
+    pub version: u32,
+    pub project_id: String,
+    pub project_name: String,
+    pub created_at: i64,
+    pub media: Vec<ArchivedMedia>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedMedia {
+    pub asset_id: String,
+    /// Where the media was when it was archived.
+    pub original_path: String,
+    /// Location inside the archive, with `/` separators.
+    pub path: String,
+    pub proxy: Option<String>,
+    /// Seconds cut from the start, for trimmed media.
+    pub trimmed_from: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ArchiveReport {
+    pub output: PathBuf,
+    pub media: usize,
+    pub trimmed: usize,
+    pub proxies: usize,
+    /// Assets whose media could not be read, with the reason. They stay in
+    /// the project pointing at their old location.
+    pub missing: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct UnarchiveReport {
+    pub project_id: String,
+    /// Folder the media now lives in.
+    pub root: PathBuf,
+    pub relinked: usize,
+    /// Assets left offline, with the reason.
+    pub failed: Vec<(String, String)>,
+}
+
+/// Archive a project from `db` into `output`, a new folder or, with
+/// [`ArchiveOptions::zip`], a zip file.
+pub fn archive_project(
+    db: &ProjectDb,
+    project_id: &str,
+    output: &Path,
+    options: &ArchiveOptions,
+) -> Result<ArchiveReport> {
+    if output.exists() {
+        anyhow::bail!("{} already exists", output.display());
+    }
+    let project_name = db
+        .list_projects()?
+        .into_iter()
+        .find(|p| p.id == project_id)
+        .map(|p| p.name)
+        .ok_or_else(|| anyhow::anyhow!("project not found: {project_id}"))?;
+    let root = if options.zip {
+        std::env::temp_dir().join(format!("gausian-archive-{}", uuid::Uuid::new_v4()))
+    } else {
+        output.to_path_buf()
+    };
+    fs::create_dir_all(root.join(MEDIA_DIR))?;
+
+    let result =
+        fill_archive(db, project_id, &project_name, &root, options).and_then(|mut report| {
+            if options.zip {
+                write_zip(&root, output)?;
+            }
+            report.output = output.to_path_buf();
+            Ok(report)
+        });
+    if options.zip || result.is_err() {
+        let _ = fs::remove_dir_all(&root);
+    }
+    result
+}
+
+fn fill_archive(
+    db: &ProjectDb,
+    project_id: &str,
+    project_name: &str,
+    root: &Path,
+    options: &ArchiveOptions,
+) -> Result<ArchiveReport> {
+    db.export_project_db(project_id, &root.join(DATABASE_FILE))?;
+    let archived = ProjectDb::open_or_create(&root.join(DATABASE_FILE))?;
+    let used = if options.trim {
+        used_spans(&archived, project_id)?
+    } else {
+        HashMap::new()
+    };
+
+    let mut report = ArchiveReport::default();
+    let mut taken = HashSet::new();
+    let mut taken_proxies = HashSet::new();
+    let mut media = Vec::new();
+    let mut shifts = HashMap::new();
+    for asset in archived.list_assets(project_id)? {
+        let src = PathBuf::from(&asset.src_abs);
+        if !src.is_file() {
+            report
+                .missing
+                .push((asset.src_abs.clone(), "file not found".to_string()));
+            continue;
+        }
+        let span = used
+            .get(&asset.src_abs)
+            .and_then(|span| trim_span(&asset, *span, options.handles_seconds));
+        let path = match span {
+            Some((start, end)) => {
+                let audio_only = asset.kind == "audio";
+                let ext = if audio_only { "wav" } else { "mov" };
+                let name = unique_name(&mut taken, &src, Some(ext));
+                let rel = format!("{MEDIA_DIR}/{name}");
+                trim_media(&src, &root.join(&rel), start, end - start, audio_only)?;
+                archived.refresh_asset_content(&asset.id, &root.join(&rel))?;
+                let fps = asset_fps(&asset);
+                archived.set_asset_duration(
+                    &asset.id,
+                    fps.map(|fps| ((end - start) * fps).round() as i64),
+                    Some(end - start),
+                )?;
+                archived.update_asset_media_details(
+                    &asset.id,
+                    &AssetMediaDetails {
+                        codec: Some(if audio_only { "pcm_s24le" } else { "prores" }),
+                        ..Default::default()
+                    },
+                )?;
+                shifts.insert(asset.src_abs.clone(), start);
+                report.trimmed += 1;
+                rel
+            }
+            None => {
+                let name = unique_name(&mut taken, &src, None);
+                let rel = format!("{MEDIA_DIR}/{name}");
+                fs::copy(&src, root.join(&rel))
+                    .with_context(|| format!("copying {}", src.display()))?;
+                rel
+            }
+        };
+        report.media += 1;
+
+        let proxy = match asset.proxy_path.as_deref().map(Path::new) {
+            Some(proxy) if options.include_proxies && span.is_none() && proxy.is_file() => {
+                fs::create_dir_all(root.join(PROXY_DIR))?;
+                let name = unique_name(&mut taken_proxies, proxy, None);
+                let rel = format!("{PROXY_DIR}/{name}");
+                fs::copy(proxy, root.join(&rel))
+                    .with_context(|| format!("copying {}", proxy.display()))?;
+                report.proxies += 1;
+                Some(rel)
+            }
+            _ => None,
+        };
+        archived.relocate_asset(&asset.id, &src, Some(Path::new(&path)))?;
+        archived.set_asset_proxy(&asset.id, None)?;
+        media.push(ArchivedMedia {
+            asset_id: asset.id.clone(),
+            original_path: asset.src_abs.clone(),
+            path,
+            proxy,
+            trimmed_from: span.map(|(start, _)| start),
+        });
+    }
+
+    if !shifts.is_empty() {
+        rewrite_sequences(&archived, project_id, |seq| {
+            let fps = seq.fps;
+            for clip in clips_mut(seq) {
+                let Some(start) = clip.asset_id.as_ref().and_then(|id| shifts.get(id)) else {
+                    continue;
+                };
+                shift_clip(clip, (start * media_rate(clip, fps)).round() as i64);
+            }
+        })?;
+    }
+    archived
+        .connection()
+        .pragma_update(None, "journal_mode", "DELETE")?;
+    drop(archived);
+
+    let manifest = ArchiveManifest {
+        version: MANIFEST_VERSION,
+        project_id: project_id.to_string(),
+        project_name: project_name.to_string(),
+        created_at: chrono::Utc::now().timestamp(),
+        media,
+    };
+    fs::write(
+        root.join(MANIFEST_FILE),
+        serde_json::to_string_pretty(&manifest)?,
+    )?;
+    Ok(report)
+}
+
+/// Add the project in an archive to `db`. A folder archive is used in
+/// place; a zip is extracted into `dest`, or next to it when `dest` is
+/// `None`. Assets are relinked to the archived media, falling back to a
+/// search by name inside the archive.
+pub fn unarchive_project(
+    db: &ProjectDb,
+    archive: &Path,
+    dest: Option<&Path>,
+) -> Result<UnarchiveReport> {
+    let root = if archive.is_dir() {
+        archive.to_path_buf()
+    } else {
+        let root = dest
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| archive.with_extension(""));
+        if root.exists() && fs::read_dir(&root)?.next().is_some() {
+            anyhow::bail!("{} is not empty", root.display());
+        }
+        extract_zip(archive, &root)?;
+        root
+    };
+    let root = root.canonicalize()?;
+    let manifest: ArchiveManifest = serde_json::from_str(
+        &fs::read_to_string(root.join(MANIFEST_FILE))
+            .with_context(|| format!("{} is not a project archive", archive.display()))?,
+    )?;
+    if manifest.version > MANIFEST_VERSION {
+        anyhow::bail!(
+            "archive version {} is newer than this build supports",
+            manifest.version
+        );
+    }
+
+    let project_id = db.import_project_db(&root.join(DATABASE_FILE))?;
+    let rows: HashMap<String, AssetRow> = db
+        .list_assets(&project_id)?
+        .into_iter()
+        .map(|a| (a.id.clone(), a))
+        .collect();
+    let entries: Vec<&ArchivedMedia> = manifest
+        .media
+        .iter()
+        .filter(|m| rows.contains_key(&m.asset_id))
+        .collect();
+    let mut infos: Vec<AssetInfo> = entries
+        .iter()
+        .map(|m| asset_info(&rows[&m.asset_id], m, &root))
+        .collect();
+    let mut results = relink_assets(&mut infos, &[], RelinkStrategy::Relative)?;
+    for ((entry, info), result) in entries.iter().zip(&mut infos).zip(&mut results) {
+        if matches!(result, RelinkResult::Relinked(path) if path.is_file()) {
+            continue;
+        }
+        // The bundled file moved; look for it by name anywhere in the archive.
+        info.path = root.join(&entry.path);
+        let found = relink_assets(
+            std::slice::from_mut(info),
+            std::slice::from_ref(&root),
+            RelinkStrategy::Heuristic,
+        )?;
+        *result = found.into_iter().next().unwrap_or(RelinkResult::Unchanged);
+    }
+
+    let mut report = UnarchiveReport {
+        project_id: project_id.clone(),
+        root: root.clone(),
+        ..Default::default()
+    };
+    let mut moved = HashMap::new();
+    for (entry, result) in entries.iter().zip(results) {
+        match result {
+            RelinkResult::Relinked(path) => {
+                let rel = path.strip_prefix(&root).ok().map(Path::to_path_buf);
+                db.relocate_asset(&entry.asset_id, &path, rel.as_deref())?;
+                let proxy = entry
+                    .proxy
+                    .as_ref()
+                    .map(|p| root.join(p))
+                    .filter(|p| p.is_file());
+                db.set_asset_proxy(&entry.asset_id, proxy.as_deref())?;
+                moved.insert(
+                    entry.original_path.clone(),
+                    path.to_string_lossy().into_owned(),
+                );
+                report.relinked += 1;
+            }
+            RelinkResult::Failed(reason) => {
+                report.failed.push((entry.original_path.clone(), reason));
+            }
+            RelinkResult::Unchanged => {
+                report
+                    .failed
+                    .push((entry.original_path.clone(), "not relinked".to_string()));
+            }
+        }
+    }
+
+    rewrite_sequences(db, &project_id, |seq| {
+        for clip in clips_mut(seq) {
+            if let Some(path) = clip.asset_id.as_ref().and_then(|id| moved.get(id)) {
+                clip.asset_id = Some(path.clone());
+            }
+        }
+        for item in seq.tracks.iter_mut().flat_map(|t| t.items.iter_mut()) {
+            if let ItemKind::Video { src, .. }
+            | ItemKind::Image { src }
+            | ItemKind::Audio { src, .. } = &mut item.kind
+            {
+                if let Some(path) = moved.get(src.as_str()) {
+                    *src = path.clone();
+                }
+            }
+        }
+    })?;
+    db.set_project_base_path(&project_id, &root)?;
+    Ok(report)
+}
+
+fn asset_info(asset: &AssetRow, entry: &ArchivedMedia, root: &Path) -> AssetInfo {
+    AssetInfo {
+        id: asset.id.clone(),
+        path: PathBuf::from(&asset.src_abs),
+        relative_path: Some(root.join(&entry.path)),
+        kind: match asset.kind.as_str() {
+            "audio" => AssetKind::Audio,
+            "image" => AssetKind::Image,
+            "sequence" => AssetKind::Sequence,
+            _ => AssetKind::Video,
+        },
+        width: asset.width.map(|w| w as u32),
+        height: asset.height.map(|h| h as u32),
+        duration_frames: asset.duration_frames,
+        fps: match (asset.fps_num, asset.fps_den) {
+            (Some(num), Some(den)) if num > 0 && den > 0 => Some(Fps::new(num as u32, den as u32)),
+            _ => None,
+        },
+        audio_channels: asset.audio_channels.map(|c| c as u32),
+        sample_rate: asset.sample_rate.map(|r| r as u32),
+        timecode: None,
+        color_space: None,
+        file_size: asset.file_size.map(|s| s as u64),
+        hash: asset.hash_sha256.clone(),
+    }
+}
+
+/// Seconds of each asset's media used by the project's sequences, keyed by
+/// the path clips refer to it by, from the project's `usages`. Media placed
+/// through the legacy track list of a sequence without a graph counts as
+/// fully used, since only graph clips are moved onto trimmed media. A
+/// sequence with a graph keeps its track list only as a copy rebuilt from
+/// the graph, so it is not read.
+fn used_spans(db: &ProjectDb, project_id: &str) -> Result<HashMap<String, (f64, f64)>> {
+    let mut spans: HashMap<String, (f64, f64)> = HashMap::new();
+    for asset in db.list_assets(project_id)? {
+        let ranges = db.asset_used_ranges(&asset.id)?;
+        if let (Some(first), Some(last)) = (ranges.first(), ranges.last()) {
+            spans.insert(asset.src_abs, (first.0, last.1));
+        }
+    }
+    for row in db.list_sequences(project_id)? {
+        let Some(json) = db.get_sequence_timeline_json(&row.id)? else {
+            continue;
+        };
+        let seq: Sequence = serde_json::from_str(&json)
+            .with_context(|| format!("reading sequence {}", row.name))?;
+        if !seq.graph.tracks.is_empty() {
+            continue;
+        }
+        for item in seq.tracks.iter().flat_map(|t| t.items.iter()) {
+            if let ItemKind::Video { src, .. }
+            | ItemKind::Image { src }
+            | ItemKind::Audio { src, .. } = &item.kind
+            {
+                spans.insert(src.clone(), (0.0, f64::INFINITY));
+            }
+        }
+    }
+    Ok(spans)
+}
+
+/// The part of an asset to keep when trimming, or `None` when it should be
+/// copied whole. The start is moved back onto a frame of the media.
+fn trim_span(asset: &AssetRow, (start, end): (f64, f64), handles: f64) -> Option<(f64, f64)> {
+    if !matches!(asset.kind.as_str(), "video" | "audio") {
+        return None;
+    }
+    let duration = asset.duration_seconds()?;
+    let mut start = (start - handles).max(0.0);
+    let end = (end + handles).min(duration);
+    if let Some(fps) = asset_fps(asset) {
+        start = (start * fps).floor() / fps;
+    }
+    (end > start && (start > 0.0 || end < duration)).then_some((start, end))
+}
+
+fn asset_fps(asset: &AssetRow) -> Option<f64> {
+    match (asset.fps_num, asset.fps_den) {
+        (Some(num), Some(den)) if num > 0 && den > 0 => Some(num as f64 / den as f64),
+        _ => None,
+    }
+}
+
+/// Frames per second a clip counts its media in: its own rate when the
+/// clip records one, otherwise the sequence rate.
+fn media_rate(clip: &ClipNode, fps: Fps) -> f64 {
+    let own = |key: &str| clip.metadata.get(key).and_then(|v| v.as_f64());
+    match (own("media_fps_num"), own("media_fps_den")) {
+        (Some(num), Some(den)) if num > 0.0 && den > 0.0 => num / den,
+        _ => fps.num.max(1) as f64 / fps.den.max(1) as f64,
+    }
+}
+
+/// Move a clip's media frames back by `frames`, for media whose start was
+/// cut off.
+fn shift_clip(clip: &mut ClipNode, frames: i64) {
+    clip.media_range.start -= frames;
+    for marker in &mut clip.markers {
+        marker.frame -= frames;
+    }
+    if let Some(mut remap) = clip.time_remap.take() {
+        for key in &mut remap.keyframes {
+            key.value -= frames as f64;
+        }
+        clip.set_time_remap(Some(remap));
+    }
+    if let Some(length) = clip
+        .metadata
+        .get("media_duration_frames")
+        .and_then(|v| v.as_i64())
+    {
+        clip.metadata["media_duration_frames"] = (length - frames).into();
+    }
+}
+
+/// Clips of a sequence's graph and the graphs nested in it.
+fn clips_mut(seq: &mut Sequence) -> Vec<&mut ClipNode> {
+    let graph = &mut seq.graph;
+    graph
+        .nodes
+        .values_mut()
+        .chain(
+            graph
+                .sequences
+                .values_mut()
+                .flat_map(|g| g.nodes.values_mut()),
+        )
+        .filter_map(|node| match &mut node.kind {
+            TimelineNodeKind::Clip(clip) => Some(clip),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Load, edit and store every sequence of a project.
+fn rewrite_sequences(
+    db: &ProjectDb,
+    project_id: &str,
+    mut edit: impl FnMut(&mut Sequence),
+) -> Result<()> {
+    for row in db.list_sequences(project_id)? {
+        let Some(json) = db.get_sequence_timeline_json(&row.id)? else {
+            continue;
+        };
+        let mut seq: Sequence = serde_json::from_str(&json)
+            .with_context(|| format!("reading sequence {}", row.name))?;
+        edit(&mut seq);
+        db.save_sequence_timeline(&row.id, &row.format(), &serde_json::to_string(&seq)?)?;
+    }
+    Ok(())
+}
+
+/// A file name for `src` not used yet in the archive, keeping its stem and
+/// taking `ext` as the extension when given.
+fn unique_name(taken: &mut HashSet<String>, src: &Path, ext: Option<&str>) -> String {
+    let stem = src
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "media".to_string());
+    let ext = ext
+        .map(str::to_string)
+        .or_else(|| src.extension().map(|e| e.to_string_lossy().into_owned()));
+    let name = |n: usize| {
+        let stem = if n == 0 {
+            stem.clone()
+        } else {
+            format!("{stem}_{n}")
+        };
+        match &ext {
+            Some(ext) => format!("{stem}.{ext}"),
+            None => stem,
+        }
+    };
+    let mut n = 0;
+    while taken.contains(&name(n).to_lowercase()) {
+        n += 1;
+    }
+    taken.insert(name(n).to_lowercase());
+    name(n)
+}
+
+fn trim_media(src: &Path, dest: &Path, start: f64, duration: f64, audio_only: bool) -> Result<()> {
+    let mut cmd = Command::new("ffmpeg");
+    cmd.arg("-y")
+        .arg("-loglevel")
+        .arg("error")
+        .arg("-ss")
+        .arg(format!("{:.6}", start))
+        .arg("-i")
+        .arg(src)
+        .arg("-t")
+        .arg(format!("{:.6}", duration));
+    if audio_only {
+        cmd.args(["-vn", "-c:a", "pcm_s24le"]);
+    } else {
+        cmd.args([
+            "-map",
+            "0:v:0",
+            "-map",
+            "0:a?",
+            "-c:v",
+            "prores_ks",
+            "-profile:v",
+            "3",
+            "-c:a",
+            "pcm_s24le",
+        ]);
+    }
+    let output = cmd
+        .arg(dest)
+        .stdin(std::process::Stdio::null())
+        .output()
+        .context("running ffmpeg")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "ffmpeg could not trim {}: {}",
+            src.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+fn write_zip(root: &Path, output: &Path) -> Result<()> {
+    if let Some(dir) = output.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut zip = zip::ZipWriter::new(fs::File::create(output)?);
+    for entry in WalkDir::new(root).sort_by_file_name() {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let rel = entry.path().strip_prefix(root)?;
+        let name = rel
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        // Media is compressed already; only the database and manifest shrink.
+        let method = if rel.starts_with(MEDIA_DIR) || rel.starts_with(PROXY_DIR) {
+            zip::CompressionMethod::Stored
+        } else {
+            zip::CompressionMethod::Deflated
+        };
+        let options = zip::write::FileOptions::default()
+            .compression_method(method)
+            .large_file(entry.metadata()?.len() >= u32::MAX as u64);
+        zip.start_file(name, options)?;
+        std::io::copy(&mut fs::File::open(entry.path())?, &mut zip)?;
+    }
+    zip.finish()?.flush()?;
+    Ok(())
+}
+
+fn extract_zip(archive: &Path, root: &Path) -> Result<()> {
+    let mut zip = zip::ZipArchive::new(fs::File::open(archive)?)?;
+    for i in 0..zip.len() {
+        let mut entry = zip.by_index(i)?;
+        let Some(rel) = entry.enclosed_name().map(Path::to_path_buf) else {
+            anyhow::bail!("unsafe path in archive: {}", entry.name());
+        };
+        let path = root.join(rel);
+        if entry.is_dir() {
+            fs::create_dir_all(&path)?;
+            continue;
+        }
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        std::io::copy(&mut entry, &mut fs::File::create(&path)?)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use timeline::{
+        FrameRange, Item, NodeId, TimelineNode, Track, TrackBinding, TrackId, TrackKind,
+    };
+
+    struct Fixture {
+        dir: PathBuf,
+        db: ProjectDb,
+        media: PathBuf,
+        asset_id: String,
+        sequence_id: String,
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    /// A project with one 10 second video, used from media frame
+    /// `media_start` for `duration` frames by a clip of a 24 fps sequence.
+    fn fixture(media_start: i64, duration: i64) -> Fixture {
+        let dir = std::env::temp_dir().join(format!("archive-test-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(dir.join("footage")).unwrap();
+        let media = dir.join("footage").join("clip.mov");
+        fs::write(&media, b"not really a movie").unwrap();
+
+        let db = ProjectDb::open_or_create(&dir.join("projects.db")).unwrap();
+        db.ensure_project("p1", "Demo", Some(&dir)).unwrap();
+        let asset_id = db.upsert_asset_fast("p1", "video", &media).unwrap();
+        db.set_asset_duration(&asset_id, Some(240), Some(10.0))
+            .unwrap();
+
+        let mut seq = Sequence::new("Main", 1920, 1080, Fps::new(24, 1), duration);
+        let node = TimelineNode {
+            id: NodeId::new(),
+            label: None,
+            kind: TimelineNodeKind::Clip(ClipNode {
+                asset_id: Some(media.to_string_lossy().into_owned()),
+                media_range: FrameRange::new(media_start, duration),
+                timeline_range: FrameRange::new(0, duration),
+                playback_rate: 1.0,
+                reverse: false,
+                time_remap: None,
+                markers: Vec::new(),
+                metadata: serde_json::Value::Null,
+            }),
+            locked: false,
+            metadata: serde_json::Value::Null,
+        };
+        seq.graph.tracks.push(TrackBinding {
+            id: TrackId::new(),
+            name: "V1".to_string(),
+            kind: TrackKind::Video,
+            node_ids: vec![node.id],
+            properties: Default::default(),
+        });
+        // The legacy track list mirrors the graph, as it does when saved.
+        seq.add_track(Track {
+            name: "V1".to_string(),
+            items: vec![Item {
+                id: node.id.0.to_string(),
+                from: 0,
+                duration_in_frames: duration,
+                kind: ItemKind::Video {
+                    src: media.to_string_lossy().into_owned(),
+                    frame_rate: Some(24.0),
+                    in_offset_sec: media_start as f64 / 24.0,
+                    rate: 1.0,
+                },
+            }],
+        });
+        seq.graph.nodes.insert(node.id, node);
+
+        let format = project::SequenceFormat {
+            fps_num: 24,
+            fps_den: 1,
+            width: 1920,
+            height: 1080,
+            duration_frames: duration,
+        };
+        let json = serde_json::to_string(&seq).unwrap();
+        let sequence_id = db.create_sequence("p1", "Main", &format, &json).unwrap();
+        db.save_sequence_timeline(&sequence_id, &format, &json)
+            .unwrap();
+        Fixture {
+            dir,
+            db,
+            media,
+            asset_id,
+            sequence_id,
+        }
+    }
+
+    #[test]
+    fn trim_spans_come_from_graph_clips_plus_handles() {
+        let f = fixture(48, 48);
+        let key = f.media.to_string_lossy().into_owned();
+        let used = used_spans(&f.db, "p1").unwrap();
+        assert_eq!(used.get(&key), Some(&(2.0, 4.0)));
+
+        let asset = f.db.get_asset(&f.asset_id).unwrap();
+        assert_eq!(trim_span(&asset, used[&key], 1.0), Some((1.0, 5.0)));
+        assert_eq!(trim_span(&asset, used[&key], 0.0), Some((2.0, 4.0)));
+        // Handles reaching both ends of the media leave nothing to cut.
+        assert_eq!(trim_span(&asset, used[&key], 8.0), None);
+    }
+
+    #[test]
+    fn unarchiving_relinks_assets_and_clips_to_the_bundled_media() {
+        let f = fixture(0, 24);
+        let zip = f.dir.join("demo.zip");
+        let options = ArchiveOptions {
+            zip: true,
+            ..Default::default()
+        };
+        let report = archive_project(&f.db, "p1", &zip, &options).unwrap();
+        assert_eq!((report.media, report.trimmed), (1, 0));
+        assert!(report.missing.is_empty());
+
+        let other = ProjectDb::open_or_create(&f.dir.join("other.db")).unwrap();
+        let dest = f.dir.join("unpacked");
+        let report = unarchive_project(&other, &zip, Some(&dest)).unwrap();
+        assert_eq!(report.relinked, 1);
+        assert!(report.failed.is_empty());
+
+        let bundled = dest.canonicalize().unwrap().join("media").join("clip.mov");
+        let asset = other.get_asset(&f.asset_id).unwrap();
+        assert_eq!(Path::new(&asset.src_abs), bundled);
+        let src_rel: Option<String> = other
+            .connection()
+            .query_row(
+                "SELECT src_rel FROM assets WHERE id = ?1",
+                [&f.asset_id],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(src_rel.as_deref(), Some("media/clip.mov"));
+        assert!(bundled.is_file());
+
+        let json = other
+            .get_sequence_timeline_json(&f.sequence_id)
+            .unwrap()
+            .unwrap();
+        let seq: Sequence = serde_json::from_str(&json).unwrap();
+        let bundled = bundled.to_string_lossy().into_owned();
+        let clip = seq
+            .graph
+            .nodes
+            .values()
+            .find_map(|n| match &n.kind {
+                TimelineNodeKind::Clip(clip) => Some(clip),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(clip.asset_id.as_deref(), Some(bundled.as_str()));
+        assert!(seq
+            .tracks
+            .iter()
+            .flat_map(|t| &t.items)
+            .all(|item| matches!(
+                &item.kind,
+                ItemKind::Video { src, .. } if *src == bundled
+            )));
+        assert_eq!(other.sequences_using_asset(&f.asset_id).unwrap().len(), 1);
+    }
+}
@@ -1,7 +1,8 @@
-use crate::{timecode, AssetInfo, ExportConfig, ExportError};
+use crate::{marker_label, timecode, AssetInfo, ExportConfig, ExportError};
 use anyhow::Result;
+use std::collections::HashSet;
 use std::path::Path;
-use timeline::{ItemKind, Sequence};
+use timeline::{ItemKind, Marker, MarkerColor, MarkerKind, Sequence};
 
 /// Export sequence to EDL format
 pub fn export_edl(sequence: &Sequence, assets: &[AssetInfo], config: &ExportConfig) -> Result<()> {
@@ -29,6 +30,8 @@ fn generate_edl(
 
     let mut edit_number = 1;
     let mut timeline_position = 0i64;
+    let clip_markers = sequence.graph.clip_markers_on_timeline();
+    let mut placed_markers = HashSet::new();
 
     // Process each track
     for (track_index, track) in sequence.tracks.iter().enumerate() {
@@ -69,13 +72,72 @@ fn generate_edl(
                 edit_number, source_name, track_type, source_in, source_out, record_in, record_out
             ));
 
+            // Locators: the clip's own markers, then timeline markers it covers
+            let item_end = item.from + item.duration_in_frames;
+            let markers = clip_markers
+                .iter()
+                .filter(|(node_id, _)| node_id.to_string() == item.id)
+                .map(|(_, marker)| marker)
+                .chain(
+                    sequence
+                        .graph
+                        .markers
+                        .iter()
+                        .filter(|m| m.frame >= item.from && m.frame < item_end),
+                );
+            for marker in markers {
+                if placed_markers.insert(marker.id) {
+                    edl.push_str(&locator_line(marker, sequence, config));
+                }
+            }
+
             edit_number += 1;
         }
     }
 
+    // Timeline markers outside every event still go out as trailing locators
+    for marker in &sequence.graph.markers {
+        if !placed_markers.contains(&marker.id) {
+            edl.push_str(&locator_line(marker, sequence, config));
+        }
+    }
+
     Ok(edl)
 }
 
+fn locator_line(marker: &Marker, sequence: &Sequence, config: &ExportConfig) -> String {
+    let tc = timecode::frames_to_timecode(marker.frame, sequence.fps, config.timecode_format);
+    let color = match marker.color {
+        MarkerColor::Red => "RED",
+        MarkerColor::Orange | MarkerColor::Yellow => "YELLOW",
+        MarkerColor::Green => "GREEN",
+        MarkerColor::Cyan => "CYAN",
+        MarkerColor::Blue => "BLUE",
+        MarkerColor::Purple | MarkerColor::Magenta => "MAGENTA",
+        MarkerColor::White => "WHITE",
+    };
+    format!("* LOC: {} {} {}\n", tc, color, marker_label(marker))
+}
+
+fn parse_locator(line: &str, sequence: &Sequence, config: &ExportConfig) -> Option<Marker> {
+    let mut parts = line.trim_start_matches("* LOC:").split_whitespace();
+    let frame =
+        timecode::timecode_to_frames(parts.next()?, sequence.fps, config.timecode_format).ok()?;
+    let color = match parts.next()?.to_ascii_uppercase().as_str() {
+        "RED" => MarkerColor::Red,
+        "YELLOW" => MarkerColor::Yellow,
+        "GREEN" => MarkerColor::Green,
+        "CYAN" => MarkerColor::Cyan,
+        "MAGENTA" => MarkerColor::Magenta,
+        "WHITE" | "BLACK" => MarkerColor::White,
+        _ => MarkerColor::Blue,
+    };
+    let mut marker = Marker::new(frame, MarkerKind::Standard);
+    marker.color = color;
+    marker.name = parts.collect::<Vec<_>>().join(" ");
+    Some(marker)
+}
+
 fn parse_edl(content: &str, config: &ExportConfig) -> Result<(Sequence, Vec<AssetInfo>)> {
     let mut sequence = Sequence::new("Imported EDL", 1920, 1080, timeline::Fps::new(30, 1), 0);
     let mut assets = Vec::new();
@@ -92,8 +154,20 @@ fn parse_edl(content: &str, config: &ExportConfig) -> Result<(Sequence, Vec<Asse
     for line in content.lines() {
         let line = line.trim();
 
+        // Locators become timeline markers
+        if line.starts_with("* LOC:") {
+            if let Some(marker) = parse_locator(line, &sequence, config) {
+                sequence.graph.markers.push(marker);
+            }
+            continue;
+        }
+
         // Skip comments and empty lines
-        if line.is_empty() || line.starts_with("TITLE:") || line.starts_with("FCM:") {
+        if line.is_empty()
+            || line.starts_with('*')
+            || line.starts_with("TITLE:")
+            || line.starts_with("FCM:")
+        {
             continue;
         }
 
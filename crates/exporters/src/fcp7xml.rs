@@ -1,4 +1,4 @@
-use crate::{AssetInfo, ExportConfig, ExportError};
+use crate::{marker_label, AssetInfo, ExportConfig, ExportError};
 use anyhow::Result;
 use std::path::Path;
 use timeline::Sequence;
@@ -37,6 +37,32 @@ fn generate_fcp7_xml(
     _assets: &[AssetInfo],
     config: &ExportConfig,
 ) -> Result<String> {
+    // Sequence markers; clip markers are placed on the timeline as well since
+    // clip items are not written yet.
+    let clip_markers = sequence.graph.clip_markers_on_timeline();
+    let mut markers_xml = String::new();
+    for marker in sequence
+        .graph
+        .markers
+        .iter()
+        .chain(clip_markers.iter().map(|(_, m)| m))
+    {
+        let out = marker.duration.map(|d| marker.frame + d).unwrap_or(-1);
+        markers_xml.push_str(&format!(
+            r#"
+                <marker>
+                    <name>{}</name>
+                    <comment>{}</comment>
+                    <in>{}</in>
+                    <out>{}</out>
+                </marker>"#,
+            quick_xml::escape::escape(marker_label(marker).as_str()),
+            quick_xml::escape::escape(marker.note.as_str()),
+            marker.frame,
+            out
+        ));
+    }
+
     let xml = format!(
         r#"<?xml version="1.0" encoding="UTF-8"?>
 <!DOCTYPE xmeml>
@@ -60,7 +86,7 @@ fn generate_fcp7_xml(
                     <frame>108000</frame>
                     <source>source</source>
                     <displayformat>NDF</displayformat>
-                </timecode>
+                </timecode>{}
                 <media>
                     <video>
                         <format>
@@ -102,6 +128,7 @@ fn generate_fcp7_xml(
         sequence.duration_in_frames,
         sequence.fps.num,
         sequence.fps.num,
+        markers_xml,
         sequence.fps.num,
         sequence.width,
         sequence.height,
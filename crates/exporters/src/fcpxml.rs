@@ -1,11 +1,11 @@
-use crate::{AssetInfo, ColorSpace, ExportConfig, ExportError, TimecodeFormat};
+use crate::{marker_label, AssetInfo, ColorSpace, ExportConfig, ExportError, TimecodeFormat};
 use anyhow::Result;
 use quick_xml::events::{BytesEnd, BytesStart, BytesText, Event};
 use quick_xml::{Reader, Writer};
 use serde::{Deserialize, Serialize};
 use std::io::Cursor;
 use std::path::Path;
-use timeline::{Fps, Item, ItemKind, Sequence, Track};
+use timeline::{Fps, Item, ItemKind, Marker, MarkerKind, Sequence, Track};
 use uuid::Uuid;
 
 /// Export sequence to FCPXML format
@@ -75,6 +75,16 @@ struct FcpClip {
     ref_id: String,
     format: Option<String>,
     audio_subitems: Vec<FcpAudioSubitem>,
+    markers: Vec<FcpMarker>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct FcpMarker {
+    start: String,
+    duration: String,
+    value: String,
+    note: Option<String>,
+    kind: MarkerKind,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -122,6 +132,18 @@ struct FcpMedia {
     projectRef: String,
 }
 
+impl FcpMarker {
+    fn from_marker(marker: &Marker, seconds: &dyn Fn(i64) -> String) -> Self {
+        Self {
+            start: seconds(marker.frame),
+            duration: seconds(marker.range().duration),
+            value: marker_label(marker),
+            note: (!marker.note.is_empty()).then(|| marker.note.clone()),
+            kind: marker.kind,
+        }
+    }
+}
+
 impl FcpXml {
     fn from_sequence(
         sequence: &Sequence,
@@ -158,6 +180,13 @@ impl FcpXml {
         // Convert timeline items to FCPXML clips
         let mut clips = Vec::new();
         let mut fcp_assets = Vec::new();
+        let clip_markers = sequence.graph.clip_markers_on_timeline();
+        let seconds = |frames: i64| {
+            format!(
+                "{}s",
+                (frames * sequence.fps.den as i64) as f64 / sequence.fps.num as f64
+            )
+        };
 
         for track in &sequence.tracks {
             for item in &track.items {
@@ -182,6 +211,24 @@ impl FcpXml {
                     (item.from * sequence.fps.den as i64) as f64 / sequence.fps.num as f64
                 );
 
+                // Clip markers plus any timeline markers the clip covers. The
+                // clip's start is its record position, so marker times line up
+                // with timeline frames.
+                let item_end = item.from + item.duration_in_frames;
+                let markers: Vec<FcpMarker> = clip_markers
+                    .iter()
+                    .filter(|(node_id, _)| node_id.to_string() == item.id)
+                    .map(|(_, marker)| marker)
+                    .chain(
+                        sequence
+                            .graph
+                            .markers
+                            .iter()
+                            .filter(|m| m.frame >= item.from && m.frame < item_end),
+                    )
+                    .map(|marker| FcpMarker::from_marker(marker, &seconds))
+                    .collect();
+
                 match &item.kind {
                     ItemKind::Video {
                         src, frame_rate: _, ..
@@ -216,6 +263,7 @@ impl FcpXml {
                             ref_id: asset_uid,
                             format: Some("r1".to_string()),
                             audio_subitems: Vec::new(),
+                            markers,
                         };
                         clips.push(clip);
                     }
@@ -250,6 +298,7 @@ impl FcpXml {
                             ref_id: asset_uid,
                             format: Some("r1".to_string()),
                             audio_subitems: Vec::new(),
+                            markers,
                         };
                         clips.push(clip);
                     }
@@ -284,6 +333,7 @@ impl FcpXml {
                             ref_id: asset_uid,
                             format: None,
                             audio_subitems: Vec::new(),
+                            markers,
                         };
                         clips.push(clip);
                     }
@@ -407,6 +457,24 @@ impl FcpXml {
         }
 
         writer.write_event(Event::Start(clip_elem))?;
+        for marker in &clip.markers {
+            let mut marker_elem = BytesStart::new(match marker.kind {
+                MarkerKind::Chapter => "chapter-marker",
+                _ => "marker",
+            });
+            marker_elem.push_attribute(("start", marker.start.as_str()));
+            marker_elem.push_attribute(("duration", marker.duration.as_str()));
+            marker_elem.push_attribute(("value", marker.value.as_str()));
+            match marker.kind {
+                MarkerKind::Todo => marker_elem.push_attribute(("completed", "0")),
+                MarkerKind::Completed => marker_elem.push_attribute(("completed", "1")),
+                _ => {}
+            }
+            if let Some(note) = &marker.note {
+                marker_elem.push_attribute(("note", note.as_str()));
+            }
+            writer.write_event(Event::Empty(marker_elem))?;
+        }
         writer.write_event(Event::End(BytesEnd::new("clip")))?;
 
         Ok(())
@@ -4,9 +4,10 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use thiserror::Error;
-use timeline::{Fps, Item, ItemKind, Sequence, Track};
+use timeline::{Fps, GraphDiagnostic, Item, ItemKind, Marker, Sequence, Track};
 use uuid::Uuid;
 
+pub mod archive;
 pub mod edl;
 pub mod fcp7xml;
 pub mod fcpxml;
@@ -110,14 +111,19 @@ impl Exporter {
         }
     }
 
-    /// Import sequence from file
-    pub fn import_sequence(&self, path: &Path) -> Result<(Sequence, Vec<AssetInfo>)> {
+    /// Import sequence from file. Also returns the problems found in its
+    /// graph: first those repaired on the way in, then those left for the
+    /// user, such as overlaps.
+    pub fn import_sequence(
+        &self,
+        path: &Path,
+    ) -> Result<(Sequence, Vec<AssetInfo>, Vec<GraphDiagnostic>)> {
         let extension = path
             .extension()
             .and_then(|e| e.to_str())
             .ok_or_else(|| ExportError::UnsupportedFormat("Unknown file extension".to_string()))?;
 
-        match extension.to_lowercase().as_str() {
+        let (mut sequence, assets) = match extension.to_lowercase().as_str() {
             "fcpxml" => fcpxml::import_fcpxml(path, &self.config),
             "xml" => {
                 // Try to detect if it's FCP7 XML or FCPXML
@@ -135,7 +141,11 @@ impl Exporter {
             _ => Err(
                 ExportError::UnsupportedFormat(format!("Unsupported format: {}", extension)).into(),
             ),
-        }
+        }?;
+        // Imported graphs may be hand-edited; drop references that cannot resolve.
+        let mut issues = sequence.graph.repair();
+        issues.extend(sequence.graph.validate());
+        Ok((sequence, assets, issues))
     }
 
     fn export_json(&self, sequence: &Sequence, assets: &[AssetInfo]) -> Result<()> {
@@ -161,6 +171,16 @@ impl Exporter {
     }
 }
 
+/// Single-line text for a marker, for formats that carry only one string.
+pub(crate) fn marker_label(marker: &Marker) -> String {
+    let text = if !marker.name.is_empty() {
+        &marker.name
+    } else {
+        &marker.note
+    };
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AssetInfo {
     pub id: String,
@@ -338,24 +358,9 @@ pub mod relinking {
         Ok(RelinkResult::Failed("File not found".to_string()))
     }
 
+    /// Same SHA-256 the project database stores in `assets.hash_sha256`.
     fn calculate_file_hash(path: &Path) -> Result<String> {
-        use std::io::Read;
-        let mut file = std::fs::File::open(path)?;
-        let mut hasher = std::collections::hash_map::DefaultHasher::new();
-        let mut buffer = [0; 8192];
-
-        loop {
-            match file.read(&mut buffer)? {
-                0 => break,
-                n => {
-                    use std::hash::{Hash, Hasher};
-                    buffer[..n].hash(&mut hasher);
-                }
-            }
-        }
-
-        use std::hash::Hasher;
-        Ok(format!("{:x}", hasher.finish()))
+        Ok(project::sha256_file(path)?)
     }
 
     #[derive(Debug, Clone)]
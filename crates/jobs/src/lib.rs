@@ -15,7 +15,9 @@ use thiserror::Error;
 use uuid::Uuid;
 
 use media_io::{generate_proxy, generate_thumbnail, generate_waveform};
-use project::{app_data_dir, JobRow, ProjectDb};
+use project::{
+    app_data_dir, variant_settings_hash, JobRow, ProjectDb, ProxyVariantInsert, VariantKind,
+};
 
 #[derive(Debug, Error)]
 pub enum JobError {
@@ -298,8 +300,26 @@ fn execute_job(db: &ProjectDb, spec: &JobSpec) -> Result<()> {
             fs::create_dir_all(&proxy_dir)?;
             let out = proxy_dir.join(format!("{}-proxy.mp4", asset.id));
             let (width, height) = choose_proxy_dimensions(&asset);
-            generate_proxy(source_path, &out, width, height, 6_000).context("generate proxy")?;
-            db.update_asset_analysis(&asset.id, None, None, Some(out.as_path()), None)?;
+            let bitrate_kbps = 6_000;
+            generate_proxy(source_path, &out, width, height, bitrate_kbps)
+                .context("generate proxy")?;
+            let settings = serde_json::json!({
+                "codec": "h264",
+                "width": width,
+                "height": height,
+                "bitrate_kbps": bitrate_kbps,
+            });
+            db.register_proxy_variant(&ProxyVariantInsert {
+                asset_id: &asset.id,
+                kind: VariantKind::Proxy,
+                width: Some(width as i64),
+                height: Some(height as i64),
+                codec: Some("h264"),
+                pixel_fmt: None,
+                bitrate_kbps: Some(bitrate_kbps as i64),
+                path: &out,
+                settings_hash: &variant_settings_hash(&settings),
+            })?;
         }
         JobKind::SeekIndex => {
             let idx_dir = cache_root.join("seek_index");
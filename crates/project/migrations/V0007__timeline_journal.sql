@@ -0,0 +1,8 @@
+-- Timeline edits applied since the last saved snapshot, oldest first
+CREATE TABLE IF NOT EXISTS timeline_journal (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  project_id TEXT NOT NULL,
+  entry_json TEXT NOT NULL,
+  created_at INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_timeline_journal_project ON timeline_journal(project_id, id);
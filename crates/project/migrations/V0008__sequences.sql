@@ -0,0 +1,2 @@
+-- Timelines live in `sequences`, one row per cut; the journal is kept per sequence
+CREATE INDEX IF NOT EXISTS idx_timeline_journal_sequence ON timeline_journal(sequence_id, id);
@@ -0,0 +1,29 @@
+-- Bins, tags and saved smart collections for organising assets (V0009)
+-- assets.bin_id, assets.rating and assets.color_label are added by ensure_column
+CREATE TABLE IF NOT EXISTS bins (
+  id TEXT PRIMARY KEY,
+  project_id TEXT NOT NULL,
+  parent_id TEXT,
+  name TEXT NOT NULL,
+  created_at INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_bins_project_id ON bins(project_id);
+
+CREATE TABLE IF NOT EXISTS asset_tags (
+  asset_id TEXT NOT NULL,
+  tag TEXT NOT NULL COLLATE NOCASE,
+  PRIMARY KEY(asset_id, tag)
+);
+CREATE INDEX IF NOT EXISTS idx_asset_tags_tag ON asset_tags(tag);
+
+CREATE TABLE IF NOT EXISTS smart_collections (
+  id TEXT PRIMARY KEY,
+  project_id TEXT NOT NULL,
+  name TEXT NOT NULL,
+  query_json TEXT NOT NULL,
+  created_at INTEGER NOT NULL,
+  updated_at INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_smart_collections_project_id ON smart_collections(project_id);
+
+CREATE INDEX IF NOT EXISTS idx_assets_bin_id ON assets(bin_id);
@@ -0,0 +1,11 @@
+-- Full-text index over asset names, notes, metadata text and transcript segments.
+-- Rows are rewritten from their source tables by ProjectDb whenever those change.
+CREATE VIRTUAL TABLE IF NOT EXISTS search_index USING fts5(
+  body,
+  project_id UNINDEXED,
+  asset_id UNINDEXED,
+  field UNINDEXED,
+  start_seconds UNINDEXED,
+  end_seconds UNINDEXED,
+  tokenize = 'unicode61 remove_diacritics 2'
+);
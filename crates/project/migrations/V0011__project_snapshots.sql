@@ -0,0 +1,10 @@
+-- Point-in-time copies of a project's settings, sequences and asset rows.
+-- Unnamed rows are rolling autosaves; named ones are kept until deleted.
+CREATE TABLE IF NOT EXISTS project_snapshots (
+  id TEXT PRIMARY KEY,
+  project_id TEXT NOT NULL,
+  name TEXT,
+  data_json TEXT NOT NULL,
+  created_at INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_project_snapshots_project ON project_snapshots(project_id, created_at);
@@ -0,0 +1,3 @@
+-- Quick content fingerprint for spotting duplicate media (V0012)
+-- assets.hash_partial is added by ensure_column; hash_sha256 dates from V0001
+CREATE INDEX IF NOT EXISTS idx_assets_hash_partial ON assets(hash_partial, file_size);
@@ -0,0 +1,4 @@
+-- Where-used lookups (V0013). usages rows are rewritten from a sequence's
+-- timeline whenever it is saved. usages.fps_num/fps_den, the rate
+-- start_frame/end_frame count in, are added by ensure_column.
+CREATE INDEX IF NOT EXISTS idx_usages_project_id ON usages(project_id);
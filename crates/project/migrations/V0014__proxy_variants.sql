@@ -0,0 +1,5 @@
+-- Proxy and transcode variants (V0014). Every generated file gets a proxies
+-- row, one per asset, kind and settings_hash; assets.proxy_path mirrors the
+-- proxy playback would pick. Ready proxies of older databases are added as
+-- variants of unknown size when this migration is first applied.
+CREATE INDEX IF NOT EXISTS idx_proxies_asset_valid ON proxies(asset_id, valid);
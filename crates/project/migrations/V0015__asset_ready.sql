@@ -0,0 +1,5 @@
+-- Asset readiness (V0015). mark_asset_ready used to write 'ready' or
+-- 'pending' into assets.notes, where it overwrote the user's notes and
+-- showed up in search; it now lives in assets.is_ready. Applied once.
+UPDATE assets SET is_ready = 1 WHERE notes = 'ready';
+UPDATE assets SET notes = NULL WHERE notes IN ('ready', 'pending');
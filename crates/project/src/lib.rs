@@ -1,9 +1,11 @@
 use anyhow::Result;
-use rusqlite::{params, Connection, Transaction};
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use std::fs;
 use std::path::{Path, PathBuf};
 use uuid::Uuid;
+use walkdir::WalkDir;
 
 pub fn app_data_dir() -> PathBuf {
     let base = dirs::data_local_dir().unwrap_or_else(|| std::env::temp_dir());
@@ -41,8 +43,8 @@ impl ProjectDb {
     pub fn mark_asset_ready(&self, asset_id: &str, ready: bool) -> Result<()> {
         let now = chrono::Utc::now().timestamp();
         self.conn.execute(
-            "UPDATE assets SET notes = ?2, updated_at = ?3 WHERE id = ?1",
-            params![asset_id, if ready { "ready" } else { "pending" }, now],
+            "UPDATE assets SET is_ready = ?2, updated_at = ?3 WHERE id = ?1",
+            params![asset_id, ready as i64, now],
         )?;
         Ok(())
     }
@@ -80,6 +82,7 @@ impl ProjectDb {
             "UPDATE assets SET metadata_json = ?2, updated_at = ?3 WHERE id = ?1",
             params![asset_id, metadata.to_string(), now],
         )?;
+        index_asset(&self.conn, asset_id)?;
         Ok(())
     }
 
@@ -105,33 +108,6 @@ impl ProjectDb {
         Ok(())
     }
 
-    pub fn get_project_settings_json(&self, project_id: &str) -> Result<serde_json::Value> {
-        let mut stmt = self
-            .conn
-            .prepare("SELECT settings_json FROM projects WHERE id = ?1 LIMIT 1")?;
-        let mut rows = stmt.query(params![project_id])?;
-        if let Some(row) = rows.next()? {
-            let raw: String = row.get(0)?;
-            let parsed = serde_json::from_str(&raw)
-                .unwrap_or_else(|_| serde_json::Value::Object(Default::default()));
-            Ok(parsed)
-        } else {
-            Ok(serde_json::Value::Object(Default::default()))
-        }
-    }
-
-    pub fn update_project_settings_json(
-        &self,
-        project_id: &str,
-        settings: &serde_json::Value,
-    ) -> Result<()> {
-        let now = chrono::Utc::now().timestamp();
-        self.conn.execute(
-            "UPDATE projects SET settings_json = ?2, updated_at = ?3 WHERE id = ?1",
-            params![project_id, settings.to_string(), now],
-        )?;
-        Ok(())
-    }
     pub fn open_or_create(path: &Path) -> Result<Self> {
         if let Some(dir) = path.parent() {
             fs::create_dir_all(dir)?;
@@ -254,6 +230,10 @@ impl ProjectDb {
                 "DELETE FROM proxy_jobs WHERE asset_id = ?1",
                 params![asset_id],
             )?;
+            tx.execute(
+                "DELETE FROM asset_tags WHERE asset_id = ?1",
+                params![asset_id],
+            )?;
         }
 
         tx.execute(
@@ -268,10 +248,30 @@ impl ProjectDb {
             "DELETE FROM project_timeline WHERE project_id = ?1",
             params![project_id],
         )?;
+        tx.execute(
+            "DELETE FROM timeline_journal WHERE project_id = ?1",
+            params![project_id],
+        )?;
         tx.execute(
             "DELETE FROM sequences WHERE project_id = ?1",
             params![project_id],
         )?;
+        tx.execute(
+            "DELETE FROM bins WHERE project_id = ?1",
+            params![project_id],
+        )?;
+        tx.execute(
+            "DELETE FROM smart_collections WHERE project_id = ?1",
+            params![project_id],
+        )?;
+        tx.execute(
+            "DELETE FROM search_index WHERE project_id = ?1",
+            params![project_id],
+        )?;
+        tx.execute(
+            "DELETE FROM project_snapshots WHERE project_id = ?1",
+            params![project_id],
+        )?;
         tx.execute(
             "DELETE FROM assets WHERE project_id = ?1",
             params![project_id],
@@ -315,8 +315,9 @@ impl ProjectDb {
             .and_then(|m| m.modified().ok())
             .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
             .map(|d| d.as_nanos() as i64);
+        let (hash_partial, hash_sha256) = content_hashes(src_abs);
         self.conn.execute(
-            "INSERT OR REPLACE INTO assets(id, project_id, kind, src_abs, src_rel, referenced, file_size, mtime_ns, width, height, duration_frames, fps_num, fps_den, audio_channels, sample_rate, duration_seconds, codec, bitrate_mbps, proxy_path, is_proxy_ready, metadata_json, bit_depth, is_hdr, is_variable_framerate, created_at, updated_at) VALUES(?1, ?2, ?3, ?4, ?5, 1, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25)",
+            "INSERT OR REPLACE INTO assets(id, project_id, kind, src_abs, src_rel, referenced, file_size, mtime_ns, width, height, duration_frames, fps_num, fps_den, audio_channels, sample_rate, duration_seconds, codec, bitrate_mbps, proxy_path, is_proxy_ready, metadata_json, bit_depth, is_hdr, is_variable_framerate, created_at, updated_at, hash_partial, hash_sha256) VALUES(?1, ?2, ?3, ?4, ?5, 1, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25, ?26, ?27)",
             params![
                 id,
                 project_id,
@@ -343,8 +344,11 @@ impl ProjectDb {
                 if is_variable_framerate { 1 } else { 0 },
                 now,
                 now,
+                hash_partial,
+                hash_sha256,
             ],
         )?;
+        index_asset(&self.conn, &id)?;
         Ok(id)
     }
 
@@ -375,34 +379,10 @@ impl ProjectDb {
     }
 
     pub fn list_assets(&self, project_id: &str) -> Result<Vec<AssetRow>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT id, project_id, kind, src_abs, width, height, duration_frames, fps_num, fps_den, audio_channels, sample_rate, metadata_json, proxy_path, duration_seconds, codec, bitrate_mbps, is_proxy_ready, bit_depth, is_hdr, is_variable_framerate \
-             FROM assets WHERE project_id = ?1 ORDER BY created_at DESC LIMIT 1000",
-        )?;
-        let rows = stmt.query_map(params![project_id], |row| {
-            Ok(AssetRow {
-                id: row.get(0)?,
-                project_id: row.get(1)?,
-                kind: row.get(2)?,
-                src_abs: row.get(3)?,
-                width: row.get(4)?,
-                height: row.get(5)?,
-                duration_frames: row.get(6)?,
-                fps_num: row.get(7)?,
-                fps_den: row.get(8)?,
-                audio_channels: row.get(9)?,
-                sample_rate: row.get(10)?,
-                metadata_json: row.get(11)?,
-                proxy_path: row.get(12)?,
-                duration_seconds: row.get(13)?,
-                codec: row.get(14)?,
-                bitrate_mbps: row.get(15)?,
-                is_proxy_ready: row.get::<_, Option<i64>>(16)?.unwrap_or(0) != 0,
-                bit_depth: row.get(17)?,
-                is_hdr: row.get::<_, Option<i64>>(18)?.unwrap_or(0) != 0,
-                is_variable_framerate: row.get::<_, Option<i64>>(19)?.unwrap_or(0) != 0,
-            })
-        })?;
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {ASSET_COLUMNS} FROM assets WHERE project_id = ?1 ORDER BY created_at DESC LIMIT 1000"
+        ))?;
+        let rows = stmt.query_map(params![project_id], asset_row)?;
         let mut out = Vec::new();
         for r in rows {
             out.push(r?);
@@ -429,37 +409,11 @@ impl ProjectDb {
     }
 
     pub fn find_asset_by_path(&self, project_id: &str, src_abs: &str) -> Result<Option<AssetRow>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT id, project_id, kind, src_abs, width, height, duration_frames, fps_num, fps_den, audio_channels, sample_rate, metadata_json, proxy_path, duration_seconds, codec, bitrate_mbps, is_proxy_ready, bit_depth, is_hdr, is_variable_framerate \
-             FROM assets WHERE project_id = ?1 AND src_abs = ?2 LIMIT 1",
-        )?;
-        let mut rows = stmt.query(params![project_id, src_abs])?;
-        if let Some(row) = rows.next()? {
-            Ok(Some(AssetRow {
-                id: row.get(0)?,
-                project_id: row.get(1)?,
-                kind: row.get(2)?,
-                src_abs: row.get(3)?,
-                width: row.get(4)?,
-                height: row.get(5)?,
-                duration_frames: row.get(6)?,
-                fps_num: row.get(7)?,
-                fps_den: row.get(8)?,
-                audio_channels: row.get(9)?,
-                sample_rate: row.get(10)?,
-                metadata_json: row.get(11)?,
-                proxy_path: row.get(12)?,
-                duration_seconds: row.get(13)?,
-                codec: row.get(14)?,
-                bitrate_mbps: row.get(15)?,
-                is_proxy_ready: row.get::<_, Option<i64>>(16)?.unwrap_or(0) != 0,
-                bit_depth: row.get(17)?,
-                is_hdr: row.get::<_, Option<i64>>(18)?.unwrap_or(0) != 0,
-                is_variable_framerate: row.get::<_, Option<i64>>(19)?.unwrap_or(0) != 0,
-            }))
-        } else {
-            Ok(None)
-        }
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {ASSET_COLUMNS} FROM assets WHERE project_id = ?1 AND src_abs = ?2 LIMIT 1"
+        ))?;
+        let mut rows = stmt.query_map(params![project_id, src_abs], asset_row)?;
+        Ok(rows.next().transpose()?)
     }
 }
 
@@ -485,6 +439,48 @@ pub struct AssetRow {
     pub bit_depth: Option<i64>,
     pub is_hdr: bool,
     pub is_variable_framerate: bool,
+    pub bin_id: Option<String>,
+    /// Star rating, 0 (unrated) to 5.
+    pub rating: i64,
+    pub color_label: Option<String>,
+    pub file_size: Option<i64>,
+    /// SHA-256 of the whole file, filled in when first needed.
+    pub hash_sha256: Option<String>,
+    /// See [`partial_hash`].
+    pub hash_partial: Option<String>,
+}
+
+const ASSET_COLUMNS: &str = "id, project_id, kind, src_abs, width, height, duration_frames, fps_num, fps_den, audio_channels, sample_rate, metadata_json, proxy_path, duration_seconds, codec, bitrate_mbps, is_proxy_ready, bit_depth, is_hdr, is_variable_framerate, bin_id, rating, color_label, file_size, hash_sha256, hash_partial";
+
+fn asset_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<AssetRow> {
+    Ok(AssetRow {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        kind: row.get(2)?,
+        src_abs: row.get(3)?,
+        width: row.get(4)?,
+        height: row.get(5)?,
+        duration_frames: row.get(6)?,
+        fps_num: row.get(7)?,
+        fps_den: row.get(8)?,
+        audio_channels: row.get(9)?,
+        sample_rate: row.get(10)?,
+        metadata_json: row.get(11)?,
+        proxy_path: row.get(12)?,
+        duration_seconds: row.get(13)?,
+        codec: row.get(14)?,
+        bitrate_mbps: row.get(15)?,
+        is_proxy_ready: row.get::<_, Option<i64>>(16)?.unwrap_or(0) != 0,
+        bit_depth: row.get(17)?,
+        is_hdr: row.get::<_, Option<i64>>(18)?.unwrap_or(0) != 0,
+        is_variable_framerate: row.get::<_, Option<i64>>(19)?.unwrap_or(0) != 0,
+        bin_id: row.get(20)?,
+        rating: row.get::<_, Option<i64>>(21)?.unwrap_or(0),
+        color_label: row.get(22)?,
+        file_size: row.get(23)?,
+        hash_sha256: row.get(24)?,
+        hash_partial: row.get(25)?,
+    })
 }
 
 impl AssetRow {
@@ -542,6 +538,52 @@ pub struct AssetTranscriptRow {
     pub updated_at: i64,
 }
 
+#[derive(Debug, Clone)]
+pub struct TimelineJournalRow {
+    pub id: i64,
+    pub project_id: String,
+    pub sequence_id: Option<String>,
+    pub entry_json: String,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct SequenceRow {
+    pub id: String,
+    pub project_id: String,
+    pub name: String,
+    pub fps_num: i64,
+    pub fps_den: i64,
+    pub width: i64,
+    pub height: i64,
+    pub duration_frames: i64,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+impl SequenceRow {
+    pub fn format(&self) -> SequenceFormat {
+        SequenceFormat {
+            fps_num: self.fps_num,
+            fps_den: self.fps_den,
+            width: self.width,
+            height: self.height,
+            duration_frames: self.duration_frames,
+        }
+    }
+}
+
+/// The columns of a sequence that mirror its timeline's frame rate, size and
+/// length, kept so sequences can be listed without parsing their timelines.
+#[derive(Debug, Clone, Copy)]
+pub struct SequenceFormat {
+    pub fps_num: i64,
+    pub fps_den: i64,
+    pub width: i64,
+    pub height: i64,
+    pub duration_frames: i64,
+}
+
 #[derive(Debug, Clone)]
 pub struct JobRow {
     pub id: String,
@@ -585,37 +627,13 @@ pub struct ProxyJobInsert<'a> {
 
 impl ProjectDb {
     pub fn get_asset(&self, asset_id: &str) -> Result<AssetRow> {
-        let mut stmt = self.conn.prepare(
-            "SELECT id, project_id, kind, src_abs, width, height, duration_frames, fps_num, fps_den, audio_channels, sample_rate, metadata_json, proxy_path, duration_seconds, codec, bitrate_mbps, is_proxy_ready, bit_depth, is_hdr, is_variable_framerate \
-             FROM assets WHERE id = ?1 LIMIT 1",
-        )?;
-        let mut rows = stmt.query(params![asset_id])?;
-        if let Some(row) = rows.next()? {
-            Ok(AssetRow {
-                id: row.get(0)?,
-                project_id: row.get(1)?,
-                kind: row.get(2)?,
-                src_abs: row.get(3)?,
-                width: row.get(4)?,
-                height: row.get(5)?,
-                duration_frames: row.get(6)?,
-                fps_num: row.get(7)?,
-                fps_den: row.get(8)?,
-                audio_channels: row.get(9)?,
-                sample_rate: row.get(10)?,
-                metadata_json: row.get(11)?,
-                proxy_path: row.get(12)?,
-                duration_seconds: row.get(13)?,
-                codec: row.get(14)?,
-                bitrate_mbps: row.get(15)?,
-                is_proxy_ready: row.get::<_, Option<i64>>(16)?.unwrap_or(0) != 0,
-                bit_depth: row.get(17)?,
-                is_hdr: row.get::<_, Option<i64>>(18)?.unwrap_or(0) != 0,
-                is_variable_framerate: row.get::<_, Option<i64>>(19)?.unwrap_or(0) != 0,
-            })
-        } else {
-            Err(anyhow::anyhow!("asset not found"))
-        }
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {ASSET_COLUMNS} FROM assets WHERE id = ?1 LIMIT 1"
+        ))?;
+        let mut rows = stmt.query_map(params![asset_id], asset_row)?;
+        rows.next()
+            .transpose()?
+            .ok_or_else(|| anyhow::anyhow!("asset not found"))
     }
 
     pub fn upsert_transcript(
@@ -634,6 +652,7 @@ impl ProjectDb {
              ON CONFLICT(asset_id) DO UPDATE SET project_id = excluded.project_id, checksum = excluded.checksum, json = excluded.json, source = excluded.source, version = excluded.version, updated_at = excluded.updated_at",
             params![asset_id, project_id, checksum, json, source, version, now],
         )?;
+        index_transcript(&self.conn, asset_id)?;
         Ok(())
     }
 
@@ -689,6 +708,7 @@ impl ProjectDb {
             "DELETE FROM asset_transcripts WHERE asset_id = ?1",
             params![asset_id],
         )?;
+        index_transcript(&self.conn, asset_id)?;
         Ok(())
     }
 
@@ -987,6 +1007,146 @@ fn apply_migrations(conn: &Connection) -> Result<()> {
         "INSERT OR IGNORE INTO migrations(name, applied_at) VALUES(?1, strftime('%s','now'))",
         params!["V0006__proxy_jobs"],
     )?;
+    conn.execute_batch(include_str!("../migrations/V0007__timeline_journal.sql"))?;
+    conn.execute(
+        "INSERT OR IGNORE INTO migrations(name, applied_at) VALUES(?1, strftime('%s','now'))",
+        params!["V0007__timeline_journal"],
+    )?;
+    ensure_column(
+        conn,
+        "timeline_journal",
+        "sequence_id",
+        "ALTER TABLE timeline_journal ADD COLUMN sequence_id TEXT",
+    )?;
+    conn.execute_batch(include_str!("../migrations/V0008__sequences.sql"))?;
+    conn.execute(
+        "INSERT OR IGNORE INTO migrations(name, applied_at) VALUES(?1, strftime('%s','now'))",
+        params!["V0008__sequences"],
+    )?;
+    ensure_column(
+        conn,
+        "assets",
+        "bin_id",
+        "ALTER TABLE assets ADD COLUMN bin_id TEXT",
+    )?;
+    ensure_column(
+        conn,
+        "assets",
+        "rating",
+        "ALTER TABLE assets ADD COLUMN rating INTEGER NOT NULL DEFAULT 0",
+    )?;
+    ensure_column(
+        conn,
+        "assets",
+        "color_label",
+        "ALTER TABLE assets ADD COLUMN color_label TEXT",
+    )?;
+    conn.execute_batch(include_str!("../migrations/V0009__asset_organization.sql"))?;
+    conn.execute(
+        "INSERT OR IGNORE INTO migrations(name, applied_at) VALUES(?1, strftime('%s','now'))",
+        params!["V0009__asset_organization"],
+    )?;
+    let search_indexed: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM migrations WHERE name = 'V0010__search')",
+        [],
+        |row| row.get(0),
+    )?;
+    conn.execute_batch(include_str!("../migrations/V0010__search.sql"))?;
+    if !search_indexed {
+        rebuild_search_index(conn)?;
+    }
+    conn.execute(
+        "INSERT OR IGNORE INTO migrations(name, applied_at) VALUES(?1, strftime('%s','now'))",
+        params!["V0010__search"],
+    )?;
+    conn.execute_batch(include_str!("../migrations/V0011__project_snapshots.sql"))?;
+    conn.execute(
+        "INSERT OR IGNORE INTO migrations(name, applied_at) VALUES(?1, strftime('%s','now'))",
+        params!["V0011__project_snapshots"],
+    )?;
+    ensure_column(
+        conn,
+        "assets",
+        "hash_partial",
+        "ALTER TABLE assets ADD COLUMN hash_partial TEXT",
+    )?;
+    conn.execute_batch(include_str!("../migrations/V0012__content_hash.sql"))?;
+    conn.execute(
+        "INSERT OR IGNORE INTO migrations(name, applied_at) VALUES(?1, strftime('%s','now'))",
+        params!["V0012__content_hash"],
+    )?;
+    ensure_column(
+        conn,
+        "usages",
+        "fps_num",
+        "ALTER TABLE usages ADD COLUMN fps_num INTEGER NOT NULL DEFAULT 1",
+    )?;
+    ensure_column(
+        conn,
+        "usages",
+        "fps_den",
+        "ALTER TABLE usages ADD COLUMN fps_den INTEGER NOT NULL DEFAULT 1",
+    )?;
+    let usages_synced: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM migrations WHERE name = 'V0013__usages')",
+        [],
+        |row| row.get(0),
+    )?;
+    conn.execute_batch(include_str!("../migrations/V0013__usages.sql"))?;
+    if !usages_synced {
+        let sequences: Vec<String> = {
+            let mut stmt = conn.prepare("SELECT id FROM sequences")?;
+            let rows = stmt.query_map([], |row| row.get(0))?;
+            rows.collect::<rusqlite::Result<_>>()?
+        };
+        for id in sequences {
+            sync_sequence_usages(conn, &id)?;
+        }
+    }
+    conn.execute(
+        "INSERT OR IGNORE INTO migrations(name, applied_at) VALUES(?1, strftime('%s','now'))",
+        params!["V0013__usages"],
+    )?;
+    let variants_added: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM migrations WHERE name = 'V0014__proxy_variants')",
+        [],
+        |row| row.get(0),
+    )?;
+    conn.execute_batch(include_str!("../migrations/V0014__proxy_variants.sql"))?;
+    if !variants_added {
+        conn.execute(
+            "INSERT OR IGNORE INTO proxies(id, asset_id, kind, path_abs, settings_hash, created_at)
+             SELECT 'px-' || id, id, 'proxy', proxy_path, 'legacy', updated_at FROM assets
+             WHERE proxy_path IS NOT NULL AND is_proxy_ready = 1",
+            [],
+        )?;
+    }
+    conn.execute(
+        "INSERT OR IGNORE INTO migrations(name, applied_at) VALUES(?1, strftime('%s','now'))",
+        params!["V0014__proxy_variants"],
+    )?;
+    ensure_column(
+        conn,
+        "assets",
+        "is_ready",
+        "ALTER TABLE assets ADD COLUMN is_ready INTEGER NOT NULL DEFAULT 0",
+    )?;
+    let ready_moved: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM migrations WHERE name = 'V0015__asset_ready')",
+        [],
+        |row| row.get(0),
+    )?;
+    if !ready_moved {
+        conn.execute_batch(include_str!("../migrations/V0015__asset_ready.sql"))?;
+        rebuild_search_index(conn)?;
+    }
+    conn.execute(
+        "INSERT OR IGNORE INTO migrations(name, applied_at) VALUES(?1, strftime('%s','now'))",
+        params!["V0015__asset_ready"],
+    )?;
+    // Timelines saved before sequences existed become sequences here, once,
+    // instead of on every read.
+    adopt_project_timelines(conn)?;
     Ok(())
 }
 
@@ -1004,13 +1164,3633 @@ impl ProjectDb {
         }
     }
 
-    pub fn upsert_project_timeline_json(&self, project_id: &str, json: &str) -> Result<()> {
+    /// Append one serialized timeline edit to a sequence's journal.
+    pub fn append_timeline_journal(
+        &self,
+        project_id: &str,
+        sequence_id: &str,
+        entry_json: &str,
+    ) -> Result<i64> {
+        let now = chrono::Utc::now().timestamp();
+        self.conn.execute(
+            "INSERT INTO timeline_journal(project_id, sequence_id, entry_json, created_at) VALUES(?1, ?2, ?3, ?4)",
+            params![project_id, sequence_id, entry_json, now],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Journal entries written since the sequence's last snapshot, oldest first.
+    pub fn list_timeline_journal(&self, sequence_id: &str) -> Result<Vec<TimelineJournalRow>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, project_id, sequence_id, entry_json, created_at FROM timeline_journal WHERE sequence_id = ?1 ORDER BY id ASC",
+        )?;
+        let rows = stmt.query_map(params![sequence_id], |row| {
+            Ok(TimelineJournalRow {
+                id: row.get(0)?,
+                project_id: row.get(1)?,
+                sequence_id: row.get(2)?,
+                entry_json: row.get(3)?,
+                created_at: row.get(4)?,
+            })
+        })?;
+        let mut out = Vec::new();
+        for r in rows {
+            out.push(r?);
+        }
+        Ok(out)
+    }
+
+    pub fn clear_timeline_journal(&self, sequence_id: &str) -> Result<()> {
+        self.conn.execute(
+            "DELETE FROM timeline_journal WHERE sequence_id = ?1",
+            params![sequence_id],
+        )?;
+        Ok(())
+    }
+}
+
+const SEQUENCE_COLUMNS: &str =
+    "id, project_id, name, fps_num, fps_den, width, height, duration_frames, created_at, updated_at";
+
+fn sequence_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<SequenceRow> {
+    Ok(SequenceRow {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        name: row.get(2)?,
+        fps_num: row.get(3)?,
+        fps_den: row.get(4)?,
+        width: row.get(5)?,
+        height: row.get(6)?,
+        duration_frames: row.get(7)?,
+        created_at: row.get(8)?,
+        updated_at: row.get(9)?,
+    })
+}
+
+impl ProjectDb {
+    /// Sequences of a project in the order they were created.
+    pub fn list_sequences(&self, project_id: &str) -> Result<Vec<SequenceRow>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {SEQUENCE_COLUMNS} FROM sequences WHERE project_id = ?1 ORDER BY created_at ASC, rowid ASC"
+        ))?;
+        let rows = stmt.query_map(params![project_id], sequence_row)?;
+        let mut out = Vec::new();
+        for r in rows {
+            out.push(r?);
+        }
+        Ok(out)
+    }
+
+    pub fn get_sequence(&self, sequence_id: &str) -> Result<Option<SequenceRow>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {SEQUENCE_COLUMNS} FROM sequences WHERE id = ?1 LIMIT 1"
+        ))?;
+        let mut rows = stmt.query_map(params![sequence_id], sequence_row)?;
+        Ok(rows.next().transpose()?)
+    }
+
+    /// Create a sequence and return its id.
+    pub fn create_sequence(
+        &self,
+        project_id: &str,
+        name: &str,
+        format: &SequenceFormat,
+        timeline_json: &str,
+    ) -> Result<String> {
+        let id = Uuid::new_v4().to_string();
+        insert_sequence(&self.conn, &id, project_id, name, format, timeline_json)?;
+        Ok(id)
+    }
+
+    pub fn rename_sequence(&self, sequence_id: &str, name: &str) -> Result<()> {
         let now = chrono::Utc::now().timestamp();
         self.conn.execute(
-            "INSERT INTO project_timeline(project_id, json, updated_at) VALUES(?1, ?2, ?3)
-             ON CONFLICT(project_id) DO UPDATE SET json = excluded.json, updated_at = excluded.updated_at",
-            params![project_id, json, now],
+            "UPDATE sequences SET name = ?2, updated_at = ?3 WHERE id = ?1",
+            params![sequence_id, name, now],
+        )?;
+        Ok(())
+    }
+
+    /// Copy a sequence's saved timeline into a new sequence and return its
+    /// id. Unsaved journal entries are not copied.
+    pub fn duplicate_sequence(&self, sequence_id: &str, name: &str) -> Result<String> {
+        let id = Uuid::new_v4().to_string();
+        let now = chrono::Utc::now().timestamp();
+        let copied = self.conn.execute(
+            "INSERT INTO sequences(id, project_id, name, fps_num, fps_den, width, height, duration_frames, timeline_json, created_at, updated_at)
+             SELECT ?2, project_id, ?3, fps_num, fps_den, width, height, duration_frames, timeline_json, ?4, ?4 FROM sequences WHERE id = ?1",
+            params![sequence_id, id, name, now],
+        )?;
+        if copied == 0 {
+            anyhow::bail!("sequence not found: {sequence_id}");
+        }
+        sync_sequence_usages(&self.conn, &id)?;
+        Ok(id)
+    }
+
+    pub fn delete_sequence(&self, sequence_id: &str) -> Result<()> {
+        let tx = self.begin_tx()?;
+        tx.execute(
+            "DELETE FROM timeline_journal WHERE sequence_id = ?1",
+            params![sequence_id],
+        )?;
+        tx.execute(
+            "DELETE FROM usages WHERE sequence_id = ?1",
+            params![sequence_id],
+        )?;
+        tx.execute("DELETE FROM sequences WHERE id = ?1", params![sequence_id])?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn get_sequence_timeline_json(&self, sequence_id: &str) -> Result<Option<String>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT timeline_json FROM sequences WHERE id = ?1 LIMIT 1")?;
+        let mut rows = stmt.query(params![sequence_id])?;
+        if let Some(row) = rows.next()? {
+            Ok(Some(row.get(0)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Store a full snapshot of a sequence's timeline along with its format.
+    /// The journal entries it supersedes are dropped and its `usages` rows
+    /// rewritten in the same transaction.
+    pub fn save_sequence_timeline(
+        &self,
+        sequence_id: &str,
+        format: &SequenceFormat,
+        timeline_json: &str,
+    ) -> Result<()> {
+        let now = chrono::Utc::now().timestamp();
+        let tx = self.begin_tx()?;
+        let updated = tx.execute(
+            "UPDATE sequences SET fps_num = ?2, fps_den = ?3, width = ?4, height = ?5, duration_frames = ?6, timeline_json = ?7, updated_at = ?8 WHERE id = ?1",
+            params![
+                sequence_id,
+                format.fps_num,
+                format.fps_den,
+                format.width,
+                format.height,
+                format.duration_frames,
+                timeline_json,
+                now
+            ],
+        )?;
+        if updated == 0 {
+            anyhow::bail!("sequence not found: {sequence_id}");
+        }
+        tx.execute(
+            "DELETE FROM timeline_journal WHERE sequence_id = ?1",
+            params![sequence_id],
         )?;
+        sync_sequence_usages(&tx, sequence_id)?;
+        tx.commit()?;
         Ok(())
     }
 }
+
+/// Move every pre-sequences `project_timeline` row into a new sequence of its
+/// project, together with its journal. Runs when a database is opened, so
+/// reads never have to. A project that already has sequences gets the old
+/// timeline as an extra one rather than losing it.
+fn adopt_project_timelines(conn: &Connection) -> Result<()> {
+    let legacy: Vec<(String, String)> = {
+        let mut stmt = conn.prepare("SELECT project_id, json FROM project_timeline")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect::<rusqlite::Result<_>>()?
+    };
+    for (project_id, json) in legacy {
+        let has_sequences: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM sequences WHERE project_id = ?1)",
+            params![project_id],
+            |row| row.get(0),
+        )?;
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap_or_default();
+        let int = |v: &serde_json::Value, default: i64| v.as_i64().unwrap_or(default);
+        let format = SequenceFormat {
+            fps_num: int(&value["fps"]["num"], 30),
+            fps_den: int(&value["fps"]["den"], 1),
+            width: int(&value["width"], 1920),
+            height: int(&value["height"], 1080),
+            duration_frames: int(&value["duration_in_frames"], 0),
+        };
+        let name = value["name"].as_str().unwrap_or("Main");
+        let name = if has_sequences {
+            format!("{name} (legacy)")
+        } else {
+            name.to_string()
+        };
+        let id = Uuid::new_v4().to_string();
+        let tx = conn.unchecked_transaction()?;
+        insert_sequence(&tx, &id, &project_id, &name, &format, &json)?;
+        tx.execute(
+            "UPDATE timeline_journal SET sequence_id = ?2 WHERE project_id = ?1 AND sequence_id IS NULL",
+            params![project_id, id],
+        )?;
+        tx.execute(
+            "DELETE FROM project_timeline WHERE project_id = ?1",
+            params![project_id],
+        )?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+fn insert_sequence(
+    conn: &Connection,
+    id: &str,
+    project_id: &str,
+    name: &str,
+    format: &SequenceFormat,
+    timeline_json: &str,
+) -> Result<()> {
+    let now = chrono::Utc::now().timestamp();
+    conn.execute(
+        "INSERT INTO sequences(id, project_id, name, fps_num, fps_den, width, height, duration_frames, timeline_json, created_at, updated_at)
+         VALUES(?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?10)",
+        params![
+            id,
+            project_id,
+            name,
+            format.fps_num,
+            format.fps_den,
+            format.width,
+            format.height,
+            format.duration_frames,
+            timeline_json,
+            now
+        ],
+    )?;
+    sync_sequence_usages(conn, id)
+}
+
+/// A place where a saved sequence uses an asset, from the `usages` table.
+#[derive(Debug, Clone)]
+pub struct UsageRow {
+    pub sequence_id: String,
+    /// Graph node id of the clip, or id of the legacy track item.
+    pub item_id: String,
+    pub asset_id: String,
+    /// Media frames read, end exclusive, counted at `fps_num / fps_den`.
+    pub start_frame: i64,
+    pub end_frame: i64,
+    pub fps_num: i64,
+    pub fps_den: i64,
+    pub speed_num: i64,
+    pub speed_den: i64,
+    pub reversed: bool,
+}
+
+impl UsageRow {
+    /// The media range read, in seconds.
+    pub fn seconds(&self) -> (f64, f64) {
+        let rate = self.fps_num.max(1) as f64 / self.fps_den.max(1) as f64;
+        (self.start_frame as f64 / rate, self.end_frame as f64 / rate)
+    }
+}
+
+const USAGE_COLUMNS: &str = "sequence_id, item_id, asset_id, start_frame, end_frame, fps_num, fps_den, speed_num, speed_den, reversed";
+
+fn usage_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<UsageRow> {
+    Ok(UsageRow {
+        sequence_id: row.get(0)?,
+        item_id: row.get(1)?,
+        asset_id: row.get(2)?,
+        start_frame: row.get(3)?,
+        end_frame: row.get(4)?,
+        fps_num: row.get(5)?,
+        fps_den: row.get(6)?,
+        speed_num: row.get(7)?,
+        speed_den: row.get(8)?,
+        reversed: row.get::<_, i64>(9)? != 0,
+    })
+}
+
+impl ProjectDb {
+    /// Every place a saved sequence uses an asset.
+    pub fn asset_usages(&self, asset_id: &str) -> Result<Vec<UsageRow>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {USAGE_COLUMNS} FROM usages WHERE asset_id = ?1 ORDER BY sequence_id, start_frame"
+        ))?;
+        let rows = stmt.query_map(params![asset_id], usage_row)?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Sequences whose saved timeline uses an asset, oldest first.
+    pub fn sequences_using_asset(&self, asset_id: &str) -> Result<Vec<SequenceRow>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {SEQUENCE_COLUMNS} FROM sequences WHERE id IN (SELECT sequence_id FROM usages WHERE asset_id = ?1)
+             ORDER BY created_at ASC, rowid ASC"
+        ))?;
+        let rows = stmt.query_map(params![asset_id], sequence_row)?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// The parts of an asset's media that saved sequences use, in seconds,
+    /// sorted, with overlapping and touching ranges merged.
+    pub fn asset_used_ranges(&self, asset_id: &str) -> Result<Vec<(f64, f64)>> {
+        let mut ranges: Vec<(f64, f64)> = self
+            .asset_usages(asset_id)?
+            .iter()
+            .map(UsageRow::seconds)
+            .collect();
+        ranges.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let mut merged: Vec<(f64, f64)> = Vec::with_capacity(ranges.len());
+        for (start, end) in ranges {
+            match merged.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+        Ok(merged)
+    }
+
+    /// Assets of a project that no saved sequence uses, newest first.
+    pub fn unused_assets(&self, project_id: &str) -> Result<Vec<AssetRow>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {ASSET_COLUMNS} FROM assets WHERE project_id = ?1
+             AND NOT EXISTS(SELECT 1 FROM usages WHERE usages.asset_id = assets.id)
+             ORDER BY created_at DESC"
+        ))?;
+        let rows = stmt.query_map(params![project_id], asset_row)?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Remove an asset and what was derived from it from the project.
+    /// Refuses while a saved sequence uses it. Media and proxy files stay
+    /// on disk, as other assets may share them.
+    pub fn delete_asset(&self, asset_id: &str) -> Result<()> {
+        let used_by: Vec<String> = self
+            .sequences_using_asset(asset_id)?
+            .into_iter()
+            .map(|row| row.name)
+            .collect();
+        if !used_by.is_empty() {
+            anyhow::bail!("asset is used by {}", used_by.join(", "));
+        }
+        let tx = self.begin_tx()?;
+        for table in [
+            "asset_files",
+            "proxies",
+            "cache",
+            "jobs",
+            "asset_transcripts",
+            "proxy_jobs",
+            "asset_tags",
+            "search_index",
+        ] {
+            tx.execute(
+                &format!("DELETE FROM {table} WHERE asset_id = ?1"),
+                params![asset_id],
+            )?;
+        }
+        tx.execute("DELETE FROM assets WHERE id = ?1", params![asset_id])?;
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+/// Media read by one clip of a serialized sequence, by path.
+struct TimelineUsage {
+    item_id: String,
+    path: String,
+    start: i64,
+    end: i64,
+    fps: (i64, i64),
+    speed: (i64, i64),
+    reversed: bool,
+}
+
+/// Rewrite the `usages` rows of a sequence from its saved timeline. Clips
+/// whose media is not an asset of the project are left out.
+fn sync_sequence_usages(conn: &Connection, sequence_id: &str) -> Result<()> {
+    conn.execute(
+        "DELETE FROM usages WHERE sequence_id = ?1",
+        params![sequence_id],
+    )?;
+    let Some((project_id, json)) = conn
+        .query_row(
+            "SELECT project_id, timeline_json FROM sequences WHERE id = ?1",
+            params![sequence_id],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+        )
+        .optional()?
+    else {
+        return Ok(());
+    };
+    let assets: std::collections::HashMap<String, String> = {
+        let mut stmt = conn.prepare("SELECT src_abs, id FROM assets WHERE project_id = ?1")?;
+        let rows = stmt.query_map(params![project_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect::<rusqlite::Result<_>>()?
+    };
+    for usage in timeline_usages(&json) {
+        let Some(asset_id) = assets.get(&usage.path) else {
+            continue;
+        };
+        conn.execute(
+            "INSERT INTO usages(id, project_id, sequence_id, item_id, asset_id, start_frame, end_frame, fps_num, fps_den, speed_num, speed_den, reversed)
+             VALUES(?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            params![
+                Uuid::new_v4().to_string(),
+                project_id,
+                sequence_id,
+                usage.item_id,
+                asset_id,
+                usage.start,
+                usage.end,
+                usage.fps.0,
+                usage.fps.1,
+                usage.speed.0,
+                usage.speed.1,
+                usage.reversed as i64
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+/// Clips of a serialized sequence and of the sequences nested in its graph.
+/// Legacy track items are read only while the sequence has no graph yet.
+/// Read from the JSON so the project crate stays independent of the
+/// timeline types.
+fn timeline_usages(json: &str) -> Vec<TimelineUsage> {
+    use serde_json::Value;
+    let Ok(value) = serde_json::from_str::<Value>(json) else {
+        return Vec::new();
+    };
+    let int = |v: &Value, default: i64| v.as_i64().unwrap_or(default);
+    let fps = (
+        int(&value["fps"]["num"], 30).max(1),
+        int(&value["fps"]["den"], 1).max(1),
+    );
+    let mut out = Vec::new();
+    let mut graphs = vec![&value["graph"]];
+    while let Some(graph) = graphs.pop() {
+        if let Some(nested) = graph["sequences"].as_object() {
+            graphs.extend(nested.values());
+        }
+        for (id, node) in graph["nodes"].as_object().into_iter().flatten() {
+            let clip = &node["kind"];
+            let (Some("clip"), Some(path)) = (clip["type"].as_str(), clip["asset_id"].as_str())
+            else {
+                continue;
+            };
+            let start = int(&clip["media_range"]["start"], 0);
+            let metadata = &clip["metadata"];
+            let media_fps = match (
+                metadata["media_fps_num"].as_i64(),
+                metadata["media_fps_den"].as_i64(),
+            ) {
+                (Some(num), Some(den)) if num > 0 && den > 0 => (num, den),
+                _ => fps,
+            };
+            // A speed ramp decides the media range; its overall speed is how
+            // far it moves through the media over the clip.
+            let keys = clip["time_remap"]["keyframes"].as_array();
+            let (speed, reversed) = match keys.filter(|k| k.len() >= 2) {
+                Some(keys) => {
+                    let value = |k: &Value| k["value"].as_f64().unwrap_or(0.0);
+                    let (first, last) = (value(&keys[0]), value(&keys[keys.len() - 1]));
+                    let frames = int(&clip["timeline_range"]["duration"], 1).max(1);
+                    ((last - first) / frames as f64, last < first)
+                }
+                None => (
+                    clip["playback_rate"].as_f64().unwrap_or(1.0),
+                    clip["reverse"].as_bool().unwrap_or(false),
+                ),
+            };
+            out.push(TimelineUsage {
+                item_id: id.clone(),
+                path: path.to_string(),
+                start,
+                end: start + int(&clip["media_range"]["duration"], 0).max(0),
+                fps: media_fps,
+                speed: speed_ratio(speed),
+                reversed,
+            });
+        }
+    }
+    if !out.is_empty()
+        || value["graph"]["nodes"]
+            .as_object()
+            .is_some_and(|n| !n.is_empty())
+    {
+        return out;
+    }
+    let items = value["tracks"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|track| track["items"].as_array())
+        .flatten();
+    for item in items {
+        let (Some(kind), Some(path)) = (item["type"].as_str(), item["src"].as_str()) else {
+            continue;
+        };
+        let duration = int(&item["duration_in_frames"], 0).max(0);
+        let rate = item["rate"].as_f64().unwrap_or(1.0);
+        let (start, end) = match kind {
+            "video" | "audio" => {
+                let offset = item["in_offset_sec"].as_f64().unwrap_or(0.0);
+                let start = (offset * fps.0 as f64 / fps.1 as f64).round() as i64;
+                (start, start + (duration as f64 * rate.abs()).ceil() as i64)
+            }
+            _ => (0, duration),
+        };
+        out.push(TimelineUsage {
+            item_id: item["id"].as_str().unwrap_or_default().to_string(),
+            path: path.to_string(),
+            start,
+            end,
+            fps,
+            speed: speed_ratio(rate),
+            reversed: false,
+        });
+    }
+    out
+}
+
+/// A playback speed as a reduced fraction, to a thousandth.
+fn speed_ratio(speed: f64) -> (i64, i64) {
+    let num = (speed.abs() * 1000.0).round() as i64;
+    let (mut a, mut b) = (num, 1000);
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    match a {
+        0 => (0, 1),
+        gcd => (num / gcd, 1000 / gcd),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BinRow {
+    pub id: String,
+    pub project_id: String,
+    pub parent_id: Option<String>,
+    pub name: String,
+    pub created_at: i64,
+}
+
+/// Saved filter behind a smart collection. Every field that is set must
+/// match; empty lists and `None` match anything. Kinds and codecs match any
+/// of the listed values, tags must all be present.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SmartQuery {
+    pub kinds: Vec<String>,
+    pub codecs: Vec<String>,
+    pub min_width: Option<i64>,
+    pub max_width: Option<i64>,
+    pub min_height: Option<i64>,
+    pub max_height: Option<i64>,
+    pub min_duration_seconds: Option<f64>,
+    pub max_duration_seconds: Option<f64>,
+    pub tags: Vec<String>,
+    pub proxy_ready: Option<bool>,
+    pub min_rating: Option<i64>,
+    pub color_label: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SmartCollectionRow {
+    pub id: String,
+    pub project_id: String,
+    pub name: String,
+    pub query: SmartQuery,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+impl ProjectDb {
+    /// Create a bin, at the top level when `parent_id` is `None`, and return
+    /// its id.
+    pub fn create_bin(
+        &self,
+        project_id: &str,
+        parent_id: Option<&str>,
+        name: &str,
+    ) -> Result<String> {
+        let id = Uuid::new_v4().to_string();
+        let now = chrono::Utc::now().timestamp();
+        self.conn.execute(
+            "INSERT INTO bins(id, project_id, parent_id, name, created_at) VALUES(?1, ?2, ?3, ?4, ?5)",
+            params![id, project_id, parent_id, name, now],
+        )?;
+        Ok(id)
+    }
+
+    /// Bins of a project sorted by name; nesting is given by `parent_id`.
+    pub fn list_bins(&self, project_id: &str) -> Result<Vec<BinRow>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, project_id, parent_id, name, created_at FROM bins WHERE project_id = ?1 ORDER BY name COLLATE NOCASE",
+        )?;
+        let rows = stmt.query_map(params![project_id], |row| {
+            Ok(BinRow {
+                id: row.get(0)?,
+                project_id: row.get(1)?,
+                parent_id: row.get(2)?,
+                name: row.get(3)?,
+                created_at: row.get(4)?,
+            })
+        })?;
+        let mut out = Vec::new();
+        for r in rows {
+            out.push(r?);
+        }
+        Ok(out)
+    }
+
+    pub fn rename_bin(&self, bin_id: &str, name: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE bins SET name = ?2 WHERE id = ?1",
+            params![bin_id, name],
+        )?;
+        Ok(())
+    }
+
+    /// Move a bin under another one, or to the top level. Refuses to move a
+    /// bin into itself or one of its own descendants.
+    pub fn move_bin(&self, bin_id: &str, parent_id: Option<&str>) -> Result<()> {
+        let mut ancestor = parent_id.map(str::to_string);
+        while let Some(id) = ancestor {
+            if id == bin_id {
+                anyhow::bail!("cannot move a bin into itself");
+            }
+            ancestor = self
+                .conn
+                .query_row(
+                    "SELECT parent_id FROM bins WHERE id = ?1",
+                    params![id],
+                    |row| row.get(0),
+                )
+                .map_err(|_| anyhow::anyhow!("bin not found: {id}"))?;
+        }
+        self.conn.execute(
+            "UPDATE bins SET parent_id = ?2 WHERE id = ?1",
+            params![bin_id, parent_id],
+        )?;
+        Ok(())
+    }
+
+    /// Delete a bin. Its sub-bins and assets move up to its parent.
+    pub fn delete_bin(&self, bin_id: &str) -> Result<()> {
+        let tx = self.begin_tx()?;
+        let parent_id: Option<String> = tx.query_row(
+            "SELECT parent_id FROM bins WHERE id = ?1",
+            params![bin_id],
+            |row| row.get(0),
+        )?;
+        tx.execute(
+            "UPDATE bins SET parent_id = ?2 WHERE parent_id = ?1",
+            params![bin_id, parent_id],
+        )?;
+        tx.execute(
+            "UPDATE assets SET bin_id = ?2 WHERE bin_id = ?1",
+            params![bin_id, parent_id],
+        )?;
+        tx.execute("DELETE FROM bins WHERE id = ?1", params![bin_id])?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn set_asset_bin(&self, asset_id: &str, bin_id: Option<&str>) -> Result<()> {
+        self.conn.execute(
+            "UPDATE assets SET bin_id = ?2 WHERE id = ?1",
+            params![asset_id, bin_id],
+        )?;
+        Ok(())
+    }
+
+    /// Set the star rating of an asset, clamped to 0 (unrated) to 5.
+    pub fn set_asset_rating(&self, asset_id: &str, rating: i64) -> Result<()> {
+        self.conn.execute(
+            "UPDATE assets SET rating = ?2 WHERE id = ?1",
+            params![asset_id, rating.clamp(0, 5)],
+        )?;
+        Ok(())
+    }
+
+    pub fn set_asset_color_label(&self, asset_id: &str, label: Option<&str>) -> Result<()> {
+        self.conn.execute(
+            "UPDATE assets SET color_label = ?2 WHERE id = ?1",
+            params![asset_id, label],
+        )?;
+        Ok(())
+    }
+
+    /// Tag an asset. Tags are trimmed and compared without regard to case;
+    /// adding one the asset already has does nothing.
+    pub fn add_asset_tag(&self, asset_id: &str, tag: &str) -> Result<()> {
+        let tag = tag.trim();
+        if tag.is_empty() {
+            anyhow::bail!("tag is empty");
+        }
+        self.conn.execute(
+            "INSERT OR IGNORE INTO asset_tags(asset_id, tag) VALUES(?1, ?2)",
+            params![asset_id, tag],
+        )?;
+        Ok(())
+    }
+
+    pub fn remove_asset_tag(&self, asset_id: &str, tag: &str) -> Result<()> {
+        self.conn.execute(
+            "DELETE FROM asset_tags WHERE asset_id = ?1 AND tag = ?2",
+            params![asset_id, tag.trim()],
+        )?;
+        Ok(())
+    }
+
+    pub fn list_asset_tags(&self, asset_id: &str) -> Result<Vec<String>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT tag FROM asset_tags WHERE asset_id = ?1 ORDER BY tag")?;
+        let rows = stmt.query_map(params![asset_id], |row| row.get(0))?;
+        let mut out = Vec::new();
+        for r in rows {
+            out.push(r?);
+        }
+        Ok(out)
+    }
+
+    /// Every tag used by an asset of the project, once each.
+    pub fn list_project_tags(&self, project_id: &str) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT DISTINCT t.tag FROM asset_tags t JOIN assets a ON a.id = t.asset_id
+             WHERE a.project_id = ?1 ORDER BY t.tag",
+        )?;
+        let rows = stmt.query_map(params![project_id], |row| row.get(0))?;
+        let mut out = Vec::new();
+        for r in rows {
+            out.push(r?);
+        }
+        Ok(out)
+    }
+
+    /// Assets of a project that match `query`, newest first.
+    pub fn query_assets(&self, project_id: &str, query: &SmartQuery) -> Result<Vec<AssetRow>> {
+        use rusqlite::types::Value;
+
+        let mut sql = format!("SELECT {ASSET_COLUMNS} FROM assets WHERE project_id = ?");
+        let mut args = vec![Value::from(project_id.to_string())];
+        let mut any_of = |sql: &mut String, column: &str, values: &[String]| {
+            if values.is_empty() {
+                return;
+            }
+            let marks = vec!["?"; values.len()].join(", ");
+            sql.push_str(&format!(" AND LOWER({column}) IN ({marks})"));
+            args.extend(values.iter().map(|v| Value::from(v.to_lowercase())));
+        };
+        any_of(&mut sql, "kind", &query.kinds);
+        any_of(&mut sql, "codec", &query.codecs);
+
+        let duration = "COALESCE(NULLIF(duration_seconds, 0), duration_frames * 1.0 * fps_den / NULLIF(fps_num, 0))";
+        let bounds = [
+            ("width >= ?", query.min_width.map(Value::from)),
+            ("width <= ?", query.max_width.map(Value::from)),
+            ("height >= ?", query.min_height.map(Value::from)),
+            ("height <= ?", query.max_height.map(Value::from)),
+            ("rating >= ?", query.min_rating.map(Value::from)),
+            ("is_proxy_ready = ?", query.proxy_ready.map(Value::from)),
+            (
+                "color_label = ?",
+                query.color_label.clone().map(Value::from),
+            ),
+        ];
+        for (clause, value) in bounds {
+            if let Some(value) = value {
+                sql.push_str(" AND ");
+                sql.push_str(clause);
+                args.push(value);
+            }
+        }
+        if let Some(min) = query.min_duration_seconds {
+            sql.push_str(&format!(" AND {duration} >= ?"));
+            args.push(Value::from(min));
+        }
+        if let Some(max) = query.max_duration_seconds {
+            sql.push_str(&format!(" AND {duration} <= ?"));
+            args.push(Value::from(max));
+        }
+        for tag in &query.tags {
+            sql.push_str(
+                " AND EXISTS(SELECT 1 FROM asset_tags t WHERE t.asset_id = assets.id AND t.tag = ?)",
+            );
+            args.push(Value::from(tag.trim().to_string()));
+        }
+        sql.push_str(" ORDER BY created_at DESC");
+
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(args), asset_row)?;
+        let mut out = Vec::new();
+        for r in rows {
+            out.push(r?);
+        }
+        Ok(out)
+    }
+
+    pub fn create_smart_collection(
+        &self,
+        project_id: &str,
+        name: &str,
+        query: &SmartQuery,
+    ) -> Result<String> {
+        let id = Uuid::new_v4().to_string();
+        let now = chrono::Utc::now().timestamp();
+        self.conn.execute(
+            "INSERT INTO smart_collections(id, project_id, name, query_json, created_at, updated_at)
+             VALUES(?1, ?2, ?3, ?4, ?5, ?5)",
+            params![id, project_id, name, serde_json::to_string(query)?, now],
+        )?;
+        Ok(id)
+    }
+
+    pub fn list_smart_collections(&self, project_id: &str) -> Result<Vec<SmartCollectionRow>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, project_id, name, query_json, created_at, updated_at FROM smart_collections
+             WHERE project_id = ?1 ORDER BY name COLLATE NOCASE",
+        )?;
+        let rows = stmt.query_map(params![project_id], |row| {
+            let query_json: String = row.get(3)?;
+            Ok(SmartCollectionRow {
+                id: row.get(0)?,
+                project_id: row.get(1)?,
+                name: row.get(2)?,
+                query: serde_json::from_str(&query_json).unwrap_or_default(),
+                created_at: row.get(4)?,
+                updated_at: row.get(5)?,
+            })
+        })?;
+        let mut out = Vec::new();
+        for r in rows {
+            out.push(r?);
+        }
+        Ok(out)
+    }
+
+    pub fn update_smart_collection(
+        &self,
+        collection_id: &str,
+        name: &str,
+        query: &SmartQuery,
+    ) -> Result<()> {
+        let now = chrono::Utc::now().timestamp();
+        self.conn.execute(
+            "UPDATE smart_collections SET name = ?2, query_json = ?3, updated_at = ?4 WHERE id = ?1",
+            params![collection_id, name, serde_json::to_string(query)?, now],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_smart_collection(&self, collection_id: &str) -> Result<()> {
+        self.conn.execute(
+            "DELETE FROM smart_collections WHERE id = ?1",
+            params![collection_id],
+        )?;
+        Ok(())
+    }
+}
+
+/// Which text of an asset a search hit was found in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchField {
+    Name,
+    Notes,
+    Metadata,
+    Transcript,
+}
+
+impl SearchField {
+    fn as_str(self) -> &'static str {
+        match self {
+            SearchField::Name => "name",
+            SearchField::Notes => "notes",
+            SearchField::Metadata => "metadata",
+            SearchField::Transcript => "transcript",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        [
+            SearchField::Name,
+            SearchField::Notes,
+            SearchField::Metadata,
+            SearchField::Transcript,
+        ]
+        .into_iter()
+        .find(|f| f.as_str() == s)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SearchMatch {
+    pub field: SearchField,
+    /// Text around the match, with matched words in `[` `]`.
+    pub snippet: String,
+    /// Where a transcript segment was spoken, in seconds of the source.
+    pub start_seconds: Option<f64>,
+    pub end_seconds: Option<f64>,
+}
+
+impl SearchMatch {
+    /// Source frame where the matched segment starts, for transcript hits on
+    /// assets with a known frame rate.
+    pub fn start_frame(&self, asset: &AssetRow) -> Option<i64> {
+        let seconds = self.start_seconds?;
+        let (num, den) = (asset.fps_num?, asset.fps_den?);
+        if num <= 0 || den <= 0 {
+            return None;
+        }
+        Some((seconds * num as f64 / den as f64).floor() as i64)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SearchResult {
+    pub asset: AssetRow,
+    pub matches: Vec<SearchMatch>,
+}
+
+impl ProjectDb {
+    /// Find assets whose name, notes, metadata text or transcript contain
+    /// every word of `query`, each taken as a word prefix. Assets come best
+    /// match first, each with its matching snippets.
+    pub fn search(&self, project_id: &str, query: &str) -> Result<Vec<SearchResult>> {
+        let Some(fts) = fts_query(query) else {
+            return Ok(Vec::new());
+        };
+        let mut stmt = self.conn.prepare(
+            "SELECT asset_id, field, snippet(search_index, 0, '[', ']', '…', 12), start_seconds, end_seconds
+             FROM search_index WHERE search_index MATCH ?2 AND project_id = ?1 ORDER BY rank LIMIT 500",
+        )?;
+        let rows = stmt.query_map(params![project_id, fts], |row| {
+            let asset_id: String = row.get(0)?;
+            let field: String = row.get(1)?;
+            Ok((
+                asset_id,
+                SearchField::parse(&field),
+                row.get::<_, String>(2)?,
+                row.get::<_, Option<f64>>(3)?,
+                row.get::<_, Option<f64>>(4)?,
+            ))
+        })?;
+        let mut out: Vec<SearchResult> = Vec::new();
+        for r in rows {
+            let (asset_id, field, snippet, start_seconds, end_seconds) = r?;
+            let Some(field) = field else {
+                continue;
+            };
+            let hit = SearchMatch {
+                field,
+                snippet,
+                start_seconds,
+                end_seconds,
+            };
+            if let Some(result) = out.iter_mut().find(|r| r.asset.id == asset_id) {
+                result.matches.push(hit);
+            } else if let Ok(asset) = self.get_asset(&asset_id) {
+                out.push(SearchResult {
+                    asset,
+                    matches: vec![hit],
+                });
+            }
+        }
+        Ok(out)
+    }
+
+    pub fn set_asset_notes(&self, asset_id: &str, notes: Option<&str>) -> Result<()> {
+        let now = chrono::Utc::now().timestamp();
+        self.conn.execute(
+            "UPDATE assets SET notes = ?2, updated_at = ?3 WHERE id = ?1",
+            params![asset_id, notes, now],
+        )?;
+        index_asset(&self.conn, asset_id)
+    }
+}
+
+/// Turn typed text into an FTS5 query matching every word as a prefix.
+/// Words are quoted so punctuation is never read as query syntax.
+fn fts_query(text: &str) -> Option<String> {
+    let terms: Vec<String> = text
+        .split_whitespace()
+        .filter(|w| w.chars().any(char::is_alphanumeric))
+        .map(|w| format!("\"{}\"*", w.replace('"', "\"\"")))
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+/// Start and end of a transcript segment in seconds, when known.
+type SegmentSpan = (Option<f64>, Option<f64>);
+
+fn insert_search_row(
+    conn: &Connection,
+    project_id: &str,
+    asset_id: &str,
+    field: SearchField,
+    span: SegmentSpan,
+    body: &str,
+) -> Result<()> {
+    conn.execute(
+        "INSERT INTO search_index(body, project_id, asset_id, field, start_seconds, end_seconds) VALUES(?1, ?2, ?3, ?4, ?5, ?6)",
+        params![body, project_id, asset_id, field.as_str(), span.0, span.1],
+    )?;
+    Ok(())
+}
+
+/// Rewrite the search rows for an asset's file name, notes and metadata.
+fn index_asset(conn: &Connection, asset_id: &str) -> Result<()> {
+    conn.execute(
+        "DELETE FROM search_index WHERE asset_id = ?1 AND field != 'transcript'",
+        params![asset_id],
+    )?;
+    let row = conn
+        .query_row(
+            "SELECT project_id, src_abs, notes, metadata_json FROM assets WHERE id = ?1",
+            params![asset_id],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                ))
+            },
+        )
+        .optional()?;
+    let Some((project_id, src_abs, notes, metadata_json)) = row else {
+        return Ok(());
+    };
+    let name = Path::new(&src_abs)
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned());
+    let metadata = metadata_json
+        .and_then(|json| serde_json::from_str::<serde_json::Value>(&json).ok())
+        .map(|value| {
+            let mut strings = Vec::new();
+            collect_strings(&value, &mut strings);
+            strings.join("\n")
+        });
+    for (field, body) in [
+        (SearchField::Name, name),
+        (SearchField::Notes, notes),
+        (SearchField::Metadata, metadata),
+    ] {
+        if let Some(body) = body.filter(|b| !b.trim().is_empty()) {
+            insert_search_row(conn, &project_id, asset_id, field, (None, None), &body)?;
+        }
+    }
+    Ok(())
+}
+
+fn collect_strings<'a>(value: &'a serde_json::Value, out: &mut Vec<&'a str>) {
+    match value {
+        serde_json::Value::String(s) => out.push(s),
+        serde_json::Value::Array(items) => items.iter().for_each(|v| collect_strings(v, out)),
+        serde_json::Value::Object(map) => map.values().for_each(|v| collect_strings(v, out)),
+        _ => {}
+    }
+}
+
+/// Rewrite the search rows for an asset's transcript, one per segment.
+fn index_transcript(conn: &Connection, asset_id: &str) -> Result<()> {
+    conn.execute(
+        "DELETE FROM search_index WHERE asset_id = ?1 AND field = 'transcript'",
+        params![asset_id],
+    )?;
+    let row = conn
+        .query_row(
+            "SELECT project_id, json FROM asset_transcripts WHERE asset_id = ?1",
+            params![asset_id],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+        )
+        .optional()?;
+    let Some((project_id, json)) = row else {
+        return Ok(());
+    };
+    for (span, text) in transcript_segments(&json) {
+        if !text.trim().is_empty() {
+            insert_search_row(
+                conn,
+                &project_id,
+                asset_id,
+                SearchField::Transcript,
+                span,
+                &text,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Timed text of a transcript. Accepts a list of segments, or an object with
+/// a `segments` list; segments carry `text` and `start`/`end` in seconds.
+/// Anything else is indexed as one untimed segment.
+fn transcript_segments(json: &str) -> Vec<(SegmentSpan, String)> {
+    let Ok(value) = serde_json::from_str::<serde_json::Value>(json) else {
+        return vec![((None, None), json.to_string())];
+    };
+    let segments = value
+        .as_array()
+        .or_else(|| value.get("segments").and_then(|s| s.as_array()));
+    match segments {
+        Some(segments) => segments
+            .iter()
+            .filter_map(|s| {
+                let text = s.get("text")?.as_str()?.to_string();
+                let start = s.get("start").and_then(|v| v.as_f64());
+                let end = s.get("end").and_then(|v| v.as_f64());
+                Some(((start, end), text))
+            })
+            .collect(),
+        None => {
+            let text = value
+                .get("text")
+                .and_then(|t| t.as_str())
+                .map(str::to_string)
+                .unwrap_or_else(|| {
+                    let mut strings = Vec::new();
+                    collect_strings(&value, &mut strings);
+                    strings.join("\n")
+                });
+            vec![((None, None), text)]
+        }
+    }
+}
+
+fn rebuild_search_index(conn: &Connection) -> Result<()> {
+    conn.execute("DELETE FROM search_index", [])?;
+    let mut ids = Vec::new();
+    {
+        let mut stmt = conn.prepare("SELECT id FROM assets")?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+        for r in rows {
+            ids.push(r?);
+        }
+    }
+    for id in &ids {
+        index_asset(conn, id)?;
+    }
+    let mut transcripts = Vec::new();
+    {
+        let mut stmt = conn.prepare("SELECT asset_id FROM asset_transcripts")?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+        for r in rows {
+            transcripts.push(r?);
+        }
+    }
+    for id in &transcripts {
+        index_transcript(conn, id)?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+pub struct ProjectSnapshotRow {
+    pub id: String,
+    pub project_id: String,
+    /// `None` for rolling autosave snapshots.
+    pub name: Option<String>,
+    pub created_at: i64,
+}
+
+/// What a project snapshot holds: the project settings, every sequence with
+/// its saved timeline, and the asset rows as column → value objects. Media
+/// files are only referenced, never copied.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProjectSnapshot {
+    pub settings: serde_json::Value,
+    pub sequences: Vec<SnapshotSequence>,
+    pub assets: Vec<serde_json::Map<String, serde_json::Value>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotSequence {
+    pub id: String,
+    pub name: String,
+    pub fps_num: i64,
+    pub fps_den: i64,
+    pub width: i64,
+    pub height: i64,
+    pub duration_frames: i64,
+    pub timeline_json: String,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotChange {
+    /// Exists now but not in the snapshot.
+    Added,
+    /// In the snapshot but gone now.
+    Removed,
+    Modified,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotItemChange {
+    pub id: String,
+    pub name: String,
+    pub change: SnapshotChange,
+}
+
+/// How the current project differs from a snapshot of it.
+#[derive(Debug, Clone, Default)]
+pub struct SnapshotDiff {
+    pub settings_changed: bool,
+    pub sequences: Vec<SnapshotItemChange>,
+    pub assets: Vec<SnapshotItemChange>,
+}
+
+impl SnapshotDiff {
+    pub fn is_empty(&self) -> bool {
+        !self.settings_changed && self.sequences.is_empty() && self.assets.is_empty()
+    }
+}
+
+impl ProjectDb {
+    /// Record the project as it is saved now and return the snapshot id.
+    /// Timeline edits still only in the journal are not included, so save
+    /// open sequences first.
+    pub fn create_project_snapshot(&self, project_id: &str, name: Option<&str>) -> Result<String> {
+        let data = capture_project(&self.conn, project_id)?;
+        let id = Uuid::new_v4().to_string();
+        let now = chrono::Utc::now().timestamp();
+        self.conn.execute(
+            "INSERT INTO project_snapshots(id, project_id, name, data_json, created_at) VALUES(?1, ?2, ?3, ?4, ?5)",
+            params![id, project_id, name, serde_json::to_string(&data)?, now],
+        )?;
+        Ok(id)
+    }
+
+    /// Take an unnamed snapshot and drop the oldest unnamed ones beyond
+    /// `keep`. Named snapshots are never pruned.
+    pub fn autosave_project_snapshot(&self, project_id: &str, keep: usize) -> Result<String> {
+        let id = self.create_project_snapshot(project_id, None)?;
+        self.conn.execute(
+            "DELETE FROM project_snapshots WHERE project_id = ?1 AND name IS NULL AND id NOT IN (
+               SELECT id FROM project_snapshots WHERE project_id = ?1 AND name IS NULL
+               ORDER BY created_at DESC, rowid DESC LIMIT ?2)",
+            params![project_id, keep as i64],
+        )?;
+        Ok(id)
+    }
+
+    /// Snapshots of a project, newest first.
+    pub fn list_project_snapshots(&self, project_id: &str) -> Result<Vec<ProjectSnapshotRow>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, project_id, name, created_at FROM project_snapshots WHERE project_id = ?1
+             ORDER BY created_at DESC, rowid DESC",
+        )?;
+        let rows = stmt.query_map(params![project_id], |row| {
+            Ok(ProjectSnapshotRow {
+                id: row.get(0)?,
+                project_id: row.get(1)?,
+                name: row.get(2)?,
+                created_at: row.get(3)?,
+            })
+        })?;
+        let mut out = Vec::new();
+        for r in rows {
+            out.push(r?);
+        }
+        Ok(out)
+    }
+
+    pub fn get_project_snapshot(&self, snapshot_id: &str) -> Result<Option<ProjectSnapshot>> {
+        Ok(self
+            .load_project_snapshot(snapshot_id)?
+            .map(|(_, data)| data))
+    }
+
+    pub fn rename_project_snapshot(&self, snapshot_id: &str, name: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE project_snapshots SET name = ?2 WHERE id = ?1",
+            params![snapshot_id, name],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_project_snapshot(&self, snapshot_id: &str) -> Result<()> {
+        self.conn.execute(
+            "DELETE FROM project_snapshots WHERE id = ?1",
+            params![snapshot_id],
+        )?;
+        Ok(())
+    }
+
+    /// Compare the current project with a snapshot of it.
+    pub fn diff_project_snapshot(&self, snapshot_id: &str) -> Result<SnapshotDiff> {
+        let (project_id, then) = self
+            .load_project_snapshot(snapshot_id)?
+            .ok_or_else(|| anyhow::anyhow!("snapshot not found: {snapshot_id}"))?;
+        let now = capture_project(&self.conn, &project_id)?;
+
+        let sequences = diff_keyed(
+            &then.sequences,
+            &now.sequences,
+            |s| s.id.clone(),
+            |s| s.name.clone(),
+            // Duration follows the timeline and creation times are not edits.
+            |a, b| {
+                a.name != b.name
+                    || a.timeline_json != b.timeline_json
+                    || (a.fps_num, a.fps_den, a.width, a.height)
+                        != (b.fps_num, b.fps_den, b.width, b.height)
+            },
+        );
+        let asset_id = |a: &serde_json::Map<String, serde_json::Value>| {
+            a.get("id")
+                .and_then(|v| v.as_str())
+                .unwrap_or_default()
+                .to_string()
+        };
+        let asset_name = |a: &serde_json::Map<String, serde_json::Value>| {
+            let src = a
+                .get("src_abs")
+                .and_then(|v| v.as_str())
+                .unwrap_or_default();
+            Path::new(src)
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| src.to_string())
+        };
+        let assets = diff_keyed(&then.assets, &now.assets, asset_id, asset_name, |a, b| {
+            // Timestamps move on every touch without the asset changing.
+            let strip = |m: &serde_json::Map<String, serde_json::Value>| {
+                let mut m = m.clone();
+                m.remove("updated_at");
+                m
+            };
+            strip(a) != strip(b)
+        });
+        Ok(SnapshotDiff {
+            settings_changed: then.settings != now.settings,
+            sequences,
+            assets,
+        })
+    }
+
+    /// Put the project back the way the snapshot recorded it: settings,
+    /// sequences and the asset list. Media, proxies and caches on disk are
+    /// left alone. The current state is snapshotted first so the restore can
+    /// itself be undone.
+    pub fn restore_project_snapshot(&self, snapshot_id: &str) -> Result<()> {
+        let (project_id, data) = self
+            .load_project_snapshot(snapshot_id)?
+            .ok_or_else(|| anyhow::anyhow!("snapshot not found: {snapshot_id}"))?;
+        self.create_project_snapshot(&project_id, Some("Before restore"))?;
+
+        let now = chrono::Utc::now().timestamp();
+        let tx = self.begin_tx()?;
+        tx.execute(
+            "UPDATE projects SET settings_json = ?2, updated_at = ?3 WHERE id = ?1",
+            params![project_id, data.settings.to_string(), now],
+        )?;
+        tx.execute(
+            "DELETE FROM timeline_journal WHERE project_id = ?1",
+            params![project_id],
+        )?;
+        tx.execute(
+            "DELETE FROM project_timeline WHERE project_id = ?1",
+            params![project_id],
+        )?;
+        tx.execute(
+            "DELETE FROM sequences WHERE project_id = ?1",
+            params![project_id],
+        )?;
+        for seq in &data.sequences {
+            tx.execute(
+                "INSERT INTO sequences(id, project_id, name, fps_num, fps_den, width, height, duration_frames, timeline_json, created_at, updated_at)
+                 VALUES(?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                params![
+                    seq.id,
+                    project_id,
+                    seq.name,
+                    seq.fps_num,
+                    seq.fps_den,
+                    seq.width,
+                    seq.height,
+                    seq.duration_frames,
+                    seq.timeline_json,
+                    seq.created_at,
+                    now
+                ],
+            )?;
+        }
+
+        let keep: HashSet<&str> = data
+            .assets
+            .iter()
+            .filter_map(|a| a.get("id").and_then(|v| v.as_str()))
+            .collect();
+        let current: Vec<String> = {
+            let mut stmt = tx.prepare("SELECT id FROM assets WHERE project_id = ?1")?;
+            let rows = stmt.query_map(params![project_id], |row| row.get(0))?;
+            rows.collect::<rusqlite::Result<_>>()?
+        };
+        for id in current.iter().filter(|id| !keep.contains(id.as_str())) {
+            tx.execute("DELETE FROM assets WHERE id = ?1", params![id])?;
+            tx.execute("DELETE FROM asset_tags WHERE asset_id = ?1", params![id])?;
+            tx.execute("DELETE FROM search_index WHERE asset_id = ?1", params![id])?;
+        }
+        let columns = table_columns(&tx, "assets")?;
+        for asset in &data.assets {
+            let (names, values): (Vec<&str>, Vec<rusqlite::types::Value>) = asset
+                .iter()
+                .filter(|(name, _)| columns.contains(name.as_str()))
+                .map(|(name, value)| (name.as_str(), json_to_sql(value)))
+                .unzip();
+            let marks = vec!["?"; names.len()].join(", ");
+            tx.execute(
+                &format!(
+                    "INSERT OR REPLACE INTO assets({}) VALUES({marks})",
+                    names.join(", ")
+                ),
+                rusqlite::params_from_iter(values),
+            )?;
+            if let Some(id) = asset.get("id").and_then(|v| v.as_str()) {
+                index_asset(&tx, id)?;
+            }
+        }
+        tx.execute(
+            "DELETE FROM usages WHERE project_id = ?1",
+            params![project_id],
+        )?;
+        for seq in &data.sequences {
+            sync_sequence_usages(&tx, &seq.id)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn load_project_snapshot(
+        &self,
+        snapshot_id: &str,
+    ) -> Result<Option<(String, ProjectSnapshot)>> {
+        let row = self
+            .conn
+            .query_row(
+                "SELECT project_id, data_json FROM project_snapshots WHERE id = ?1",
+                params![snapshot_id],
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+            )
+            .optional()?;
+        match row {
+            Some((project_id, json)) => Ok(Some((project_id, serde_json::from_str(&json)?))),
+            None => Ok(None),
+        }
+    }
+}
+
+fn capture_project(conn: &Connection, project_id: &str) -> Result<ProjectSnapshot> {
+    let settings = conn
+        .query_row(
+            "SELECT settings_json FROM projects WHERE id = ?1",
+            params![project_id],
+            |row| row.get::<_, String>(0),
+        )
+        .optional()?
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_else(|| serde_json::Value::Object(Default::default()));
+
+    let mut stmt = conn.prepare(
+        "SELECT id, name, fps_num, fps_den, width, height, duration_frames, timeline_json, created_at
+         FROM sequences WHERE project_id = ?1 ORDER BY created_at ASC, rowid ASC",
+    )?;
+    let sequences = stmt
+        .query_map(params![project_id], |row| {
+            Ok(SnapshotSequence {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                fps_num: row.get(2)?,
+                fps_den: row.get(3)?,
+                width: row.get(4)?,
+                height: row.get(5)?,
+                duration_frames: row.get(6)?,
+                timeline_json: row.get(7)?,
+                created_at: row.get(8)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut stmt =
+        conn.prepare("SELECT * FROM assets WHERE project_id = ?1 ORDER BY created_at ASC, id ASC")?;
+    let names: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+    let assets = stmt
+        .query_map(params![project_id], |row| {
+            let mut map = serde_json::Map::new();
+            for (i, name) in names.iter().enumerate() {
+                map.insert(name.clone(), sql_to_json(row.get_ref(i)?));
+            }
+            Ok(map)
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok(ProjectSnapshot {
+        settings,
+        sequences,
+        assets,
+    })
+}
+
+fn table_columns(conn: &Connection, table: &str) -> Result<HashSet<String>> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({table})"))?;
+    let rows = stmt.query_map([], |row| row.get::<_, String>(1))?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+fn sql_to_json(value: rusqlite::types::ValueRef<'_>) -> serde_json::Value {
+    use rusqlite::types::ValueRef;
+    match value {
+        ValueRef::Null | ValueRef::Blob(_) => serde_json::Value::Null,
+        ValueRef::Integer(i) => i.into(),
+        ValueRef::Real(f) => f.into(),
+        ValueRef::Text(t) => String::from_utf8_lossy(t).into_owned().into(),
+    }
+}
+
+fn json_to_sql(value: &serde_json::Value) -> rusqlite::types::Value {
+    use rusqlite::types::Value;
+    match value {
+        serde_json::Value::Null => Value::Null,
+        serde_json::Value::Bool(b) => Value::Integer(*b as i64),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => Value::Integer(i),
+            None => Value::Real(n.as_f64().unwrap_or_default()),
+        },
+        serde_json::Value::String(s) => Value::Text(s.clone()),
+        other => Value::Text(other.to_string()),
+    }
+}
+
+/// Changes from `then` to `now` in two lists of items keyed by id.
+fn diff_keyed<T>(
+    then: &[T],
+    now: &[T],
+    id: impl Fn(&T) -> String,
+    name: impl Fn(&T) -> String,
+    changed: impl Fn(&T, &T) -> bool,
+) -> Vec<SnapshotItemChange> {
+    let mut out = Vec::new();
+    for old in then {
+        let key = id(old);
+        let change = match now.iter().find(|n| id(n) == key) {
+            None => Some(SnapshotChange::Removed),
+            Some(new) if changed(old, new) => Some(SnapshotChange::Modified),
+            Some(_) => None,
+        };
+        if let Some(change) = change {
+            out.push(SnapshotItemChange {
+                id: key,
+                name: name(old),
+                change,
+            });
+        }
+    }
+    for new in now {
+        let key = id(new);
+        if !then.iter().any(|o| id(o) == key) {
+            out.push(SnapshotItemChange {
+                id: key,
+                name: name(new),
+                change: SnapshotChange::Added,
+            });
+        }
+    }
+    out
+}
+
+/// Tables that make up a project in a project-only export, with the
+/// condition selecting the project's rows. `{db}` is the schema the rows
+/// are read from. Job queues, caches, snapshots and unsaved journal entries
+/// belong to the machine the project was edited on and are left out, as
+/// are proxy rows, whose files an archive carries itself.
+const PROJECT_TABLES: &[(&str, &str)] = &[
+    ("projects", "id = ?1"),
+    ("sequences", "project_id = ?1"),
+    ("assets", "project_id = ?1"),
+    (
+        "asset_files",
+        "asset_id IN (SELECT id FROM {db}.assets WHERE project_id = ?1)",
+    ),
+    ("usages", "project_id = ?1"),
+    ("asset_transcripts", "project_id = ?1"),
+    ("bins", "project_id = ?1"),
+    (
+        "asset_tags",
+        "asset_id IN (SELECT id FROM {db}.assets WHERE project_id = ?1)",
+    ),
+    ("smart_collections", "project_id = ?1"),
+];
+
+impl ProjectDb {
+    /// Write a project into a new database at `dest` holding nothing else.
+    /// Save open sequences first; only saved timelines are exported.
+    pub fn export_project_db(&self, project_id: &str, dest: &Path) -> Result<()> {
+        if dest.exists() {
+            anyhow::bail!("{} already exists", dest.display());
+        }
+        let exists: bool = self.conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM projects WHERE id = ?1)",
+            params![project_id],
+            |row| row.get(0),
+        )?;
+        if !exists {
+            anyhow::bail!("project not found: {project_id}");
+        }
+        drop(ProjectDb::open_or_create(dest)?);
+
+        self.conn.execute(
+            "ATTACH DATABASE ?1 AS export",
+            params![dest.to_string_lossy()],
+        )?;
+        let copied = copy_project_rows(&self.conn, "main", "export", project_id);
+        self.conn.execute("DETACH DATABASE export", [])?;
+        copied?;
+
+        let export = ProjectDb::open_or_create(dest)?;
+        rebuild_search_index(&export.conn)?;
+        // One self-contained file, with no write-ahead log next to it.
+        export.conn.pragma_update(None, "journal_mode", "DELETE")?;
+        Ok(())
+    }
+
+    /// Add the project held by a database written by
+    /// [`ProjectDb::export_project_db`] and return its id. Refuses projects
+    /// that are already here.
+    pub fn import_project_db(&self, src: &Path) -> Result<String> {
+        let project_id = {
+            // Opening brings exports from older builds up to date.
+            let source = ProjectDb::open_or_create(src)?;
+            source
+                .conn
+                .query_row("SELECT id FROM projects LIMIT 1", [], |row| {
+                    row.get::<_, String>(0)
+                })
+                .optional()?
+                .ok_or_else(|| anyhow::anyhow!("{} holds no project", src.display()))?
+        };
+        let exists: bool = self.conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM projects WHERE id = ?1)",
+            params![project_id],
+            |row| row.get(0),
+        )?;
+        if exists {
+            anyhow::bail!("project {project_id} is already in this database");
+        }
+
+        self.conn.execute(
+            "ATTACH DATABASE ?1 AS import",
+            params![src.to_string_lossy()],
+        )?;
+        let copied = copy_project_rows(&self.conn, "import", "main", &project_id);
+        self.conn.execute("DETACH DATABASE import", [])?;
+        copied?;
+
+        for asset in self.list_assets(&project_id)? {
+            index_asset(&self.conn, &asset.id)?;
+            index_transcript(&self.conn, &asset.id)?;
+        }
+        Ok(project_id)
+    }
+
+    /// Point an asset at its media in a new place.
+    pub fn relocate_asset(
+        &self,
+        asset_id: &str,
+        src_abs: &Path,
+        src_rel: Option<&Path>,
+    ) -> Result<()> {
+        let now = chrono::Utc::now().timestamp();
+        self.conn.execute(
+            "UPDATE assets SET src_abs = ?2, src_rel = ?3, updated_at = ?4 WHERE id = ?1",
+            params![
+                asset_id,
+                src_abs.to_string_lossy(),
+                src_rel.map(|p| p.to_string_lossy()),
+                now
+            ],
+        )?;
+        index_asset(&self.conn, asset_id)
+    }
+
+    /// Set or clear an asset's proxy file, marking the proxy ready or not.
+    pub fn set_asset_proxy(&self, asset_id: &str, proxy_path: Option<&Path>) -> Result<()> {
+        let now = chrono::Utc::now().timestamp();
+        self.conn.execute(
+            "UPDATE assets SET proxy_path = ?2, is_proxy_ready = ?3, updated_at = ?4 WHERE id = ?1",
+            params![
+                asset_id,
+                proxy_path.map(|p| p.to_string_lossy()),
+                proxy_path.is_some() as i64,
+                now
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Record a new length for an asset whose media was cut down.
+    pub fn set_asset_duration(
+        &self,
+        asset_id: &str,
+        duration_frames: Option<i64>,
+        duration_seconds: Option<f64>,
+    ) -> Result<()> {
+        let now = chrono::Utc::now().timestamp();
+        self.conn.execute(
+            "UPDATE assets SET duration_frames = ?2, duration_seconds = ?3, updated_at = ?4 WHERE id = ?1",
+            params![asset_id, duration_frames, duration_seconds, now],
+        )?;
+        Ok(())
+    }
+}
+
+/// Copy a project's rows of every [`PROJECT_TABLES`] table between two
+/// schemas of `conn`, naming columns so their order does not matter.
+fn copy_project_rows(conn: &Connection, from: &str, to: &str, project_id: &str) -> Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    for (table, filter) in PROJECT_TABLES {
+        let columns = table_columns(&tx, table)?
+            .into_iter()
+            .map(|c| format!("\"{c}\""))
+            .collect::<Vec<_>>()
+            .join(", ");
+        tx.execute(
+            &format!(
+                "INSERT INTO {to}.{table}({columns}) SELECT {columns} FROM {from}.{table} WHERE {}",
+                filter.replace("{db}", from)
+            ),
+            params![project_id],
+        )?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// Bytes read from each end of a file for [`partial_hash`].
+pub const PARTIAL_HASH_CHUNK: u64 = 1 << 20;
+
+/// Streaming SHA-256 of a whole file, as lowercase hex.
+pub fn sha256_file(path: &Path) -> std::io::Result<String> {
+    use sha2::{Digest, Sha256};
+    use std::io::Read;
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        match file.read(&mut buffer)? {
+            0 => break,
+            n => hasher.update(&buffer[..n]),
+        }
+    }
+    Ok(hex(&hasher.finalize()))
+}
+
+/// Cheap fingerprint of a file: SHA-256 over its length and the first and
+/// last [`PARTIAL_HASH_CHUNK`] bytes. Files no longer than two chunks are
+/// read whole, so for them this equals [`sha256_file`].
+pub fn partial_hash(path: &Path) -> std::io::Result<String> {
+    use sha2::{Digest, Sha256};
+    use std::io::{Read, Seek, SeekFrom};
+    let len = fs::metadata(path)?.len();
+    if len <= 2 * PARTIAL_HASH_CHUNK {
+        return sha256_file(path);
+    }
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    hasher.update(len.to_le_bytes());
+    let mut buffer = vec![0; PARTIAL_HASH_CHUNK as usize];
+    file.read_exact(&mut buffer)?;
+    hasher.update(&buffer);
+    file.seek(SeekFrom::End(-(PARTIAL_HASH_CHUNK as i64)))?;
+    file.read_exact(&mut buffer)?;
+    hasher.update(&buffer);
+    Ok(hex(&hasher.finalize()))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Partial and, when it comes for free, full hash of a file about to be
+/// stored. Unreadable files get neither.
+fn content_hashes(path: &Path) -> (Option<String>, Option<String>) {
+    let Ok(len) = fs::metadata(path).map(|m| m.len()) else {
+        return (None, None);
+    };
+    let partial = partial_hash(path).ok();
+    let full = partial.clone().filter(|_| len <= 2 * PARTIAL_HASH_CHUNK);
+    (partial, full)
+}
+
+impl ProjectDb {
+    /// The asset's full SHA-256, hashing its file now if that was not done
+    /// yet. `None` when the file cannot be read.
+    pub fn ensure_asset_full_hash(&self, asset_id: &str) -> Result<Option<String>> {
+        let asset = self.get_asset(asset_id)?;
+        if asset.hash_sha256.is_some() {
+            return Ok(asset.hash_sha256);
+        }
+        let Ok(hash) = sha256_file(Path::new(&asset.src_abs)) else {
+            return Ok(None);
+        };
+        self.conn.execute(
+            "UPDATE assets SET hash_sha256 = ?2 WHERE id = ?1",
+            params![asset_id, hash],
+        )?;
+        Ok(Some(hash))
+    }
+
+    /// Re-read size, modification time and partial hash of an asset from
+    /// `path`, for when its media was replaced. The full hash is dropped
+    /// until it is needed again, and variants made from other content are
+    /// marked stale.
+    pub fn refresh_asset_content(&self, asset_id: &str, path: &Path) -> Result<()> {
+        let meta = fs::metadata(path)?;
+        let (partial, full) = content_hashes(path);
+        let old_partial: Option<String> = self.conn.query_row(
+            "SELECT hash_partial FROM assets WHERE id = ?1",
+            params![asset_id],
+            |row| row.get(0),
+        )?;
+        let now = chrono::Utc::now().timestamp();
+        self.conn.execute(
+            "UPDATE assets SET file_size = ?2, mtime_ns = ?3, hash_partial = ?4, hash_sha256 = ?5, updated_at = ?6 WHERE id = ?1",
+            params![asset_id, meta.len() as i64, mtime_ns(&meta), partial, full, now],
+        )?;
+        if partial.is_none() || partial != old_partial {
+            invalidate_variants(&self.conn, asset_id)?;
+        }
+        Ok(())
+    }
+
+    /// An asset of the project holding the same content as the file at
+    /// `path`, if it was imported before. Candidates are picked by size and
+    /// partial hash and confirmed with the full hash when the partial one
+    /// did not cover the whole file.
+    pub fn find_duplicate_asset(&self, project_id: &str, path: &Path) -> Result<Option<AssetRow>> {
+        let len = fs::metadata(path)?.len();
+        let partial = partial_hash(path)?;
+        let candidates = self.assets_with_content(Some(project_id), len, &partial)?;
+        self.confirm_same_content(candidates, len, || sha256_file(path).ok())
+    }
+
+    /// Other assets, in any project, with the same content as `asset_id`.
+    pub fn same_content_assets(&self, asset_id: &str) -> Result<Vec<AssetRow>> {
+        let asset = self.get_asset(asset_id)?;
+        let (Some(len), Some(partial)) = (asset.file_size, asset.hash_partial.as_deref()) else {
+            return Ok(Vec::new());
+        };
+        let mut out = Vec::new();
+        for other in self.assets_with_content(None, len as u64, partial)? {
+            if other.id == asset.id {
+                continue;
+            }
+            if let Some(same) = self.confirm_same_content(vec![other], len as u64, || {
+                self.ensure_asset_full_hash(asset_id).ok().flatten()
+            })? {
+                out.push(same);
+            }
+        }
+        Ok(out)
+    }
+
+    /// Give an asset the proxy, variants and cached thumbnails, waveforms
+    /// and analysis of another asset with the same content, so they are
+    /// not made twice.
+    /// Returns the asset they came from. Files that are gone are skipped.
+    pub fn reuse_derived_media(&self, asset_id: &str) -> Result<Option<AssetRow>> {
+        let donors = self.same_content_assets(asset_id)?;
+        let proxy_of = |a: &AssetRow| {
+            a.proxy_path
+                .as_deref()
+                .map(PathBuf::from)
+                .filter(|p| a.is_proxy_ready && p.is_file())
+        };
+        let Some(donor) = donors
+            .iter()
+            .find(|a| proxy_of(a).is_some())
+            .or_else(|| donors.first())
+            .cloned()
+        else {
+            return Ok(None);
+        };
+        if let Some(proxy) = proxy_of(&donor) {
+            self.set_asset_proxy(asset_id, Some(&proxy))?;
+        }
+        for variant in self.proxy_variants(&donor.id)? {
+            if !variant.valid || !Path::new(&variant.path_abs).is_file() {
+                continue;
+            }
+            self.register_proxy_variant(&ProxyVariantInsert {
+                asset_id,
+                kind: variant.kind,
+                width: variant.width,
+                height: variant.height,
+                codec: variant.codec.as_deref(),
+                pixel_fmt: variant.pixel_fmt.as_deref(),
+                bitrate_kbps: variant.bitrate_kbps,
+                path: Path::new(&variant.path_abs),
+                settings_hash: &variant.settings_hash,
+            })?;
+        }
+        let cached: Vec<(String, String, String, String)> = {
+            let mut stmt = self.conn.prepare(
+                "SELECT id, kind, path_abs, meta_json FROM cache WHERE asset_id = ?1 AND valid = 1",
+            )?;
+            let rows = stmt.query_map(params![donor.id], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })?;
+            rows.collect::<rusqlite::Result<_>>()?
+        };
+        for (id, kind, path, meta) in cached {
+            if !Path::new(&path).is_file() {
+                continue;
+            }
+            // Cache ids embed the asset id, e.g. `th-<asset>`.
+            let id = if id.contains(&donor.id) {
+                id.replace(&donor.id, asset_id)
+            } else {
+                Uuid::new_v4().to_string()
+            };
+            self.conn.execute(
+                "INSERT OR IGNORE INTO cache(id, asset_id, kind, path_abs, meta_json, created_at) VALUES(?1, ?2, ?3, ?4, ?5, strftime('%s','now'))",
+                params![id, asset_id, kind, path, meta],
+            )?;
+        }
+        Ok(Some(donor))
+    }
+
+    /// Assets whose size is `len` and whose partial hash is `partial`, in one
+    /// project or all. Rows imported before hashing existed are hashed on
+    /// the way when their size matches.
+    fn assets_with_content(
+        &self,
+        project_id: Option<&str>,
+        len: u64,
+        partial: &str,
+    ) -> Result<Vec<AssetRow>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {ASSET_COLUMNS} FROM assets WHERE file_size = ?1 AND (?2 IS NULL OR project_id = ?2)
+             AND (hash_partial = ?3 OR hash_partial IS NULL) ORDER BY created_at ASC, id ASC"
+        ))?;
+        let rows = stmt.query_map(params![len as i64, project_id, partial], asset_row)?;
+        let mut out = Vec::new();
+        for row in rows {
+            let row = row?;
+            if row.hash_partial.is_none() {
+                let Ok(hash) = partial_hash(Path::new(&row.src_abs)) else {
+                    continue;
+                };
+                self.conn.execute(
+                    "UPDATE assets SET hash_partial = ?2 WHERE id = ?1",
+                    params![row.id, hash],
+                )?;
+                if hash != partial {
+                    continue;
+                }
+            }
+            out.push(row);
+        }
+        Ok(out)
+    }
+
+    /// The first candidate whose full hash matches, hashing lazily. Files
+    /// short enough to be fully covered by the partial hash need no check.
+    fn confirm_same_content(
+        &self,
+        candidates: Vec<AssetRow>,
+        len: u64,
+        full: impl FnOnce() -> Option<String>,
+    ) -> Result<Option<AssetRow>> {
+        if len <= 2 * PARTIAL_HASH_CHUNK || candidates.is_empty() {
+            return Ok(candidates.into_iter().next());
+        }
+        let Some(full) = full() else {
+            return Ok(None);
+        };
+        for candidate in candidates {
+            if self.ensure_asset_full_hash(&candidate.id)?.as_deref() == Some(full.as_str()) {
+                return Ok(Some(candidate));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Whether an asset's media is where the project expects it, as found by
+/// [`ProjectDb::scan_media_status`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaStatus {
+    Online,
+    /// Nothing readable at `src_abs`.
+    Missing,
+    /// A file is there, but not the one that was imported.
+    Changed,
+}
+
+#[derive(Debug, Clone)]
+pub struct AssetMediaStatus {
+    pub asset: AssetRow,
+    pub status: MediaStatus,
+}
+
+/// Why [`ProjectDb::find_relink_candidates`] picked a file, weakest first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RelinkEvidence {
+    /// Same file name, with nothing stored to check it against.
+    Name,
+    /// Same file name and size, with no hash stored.
+    Size,
+    /// Same file name and duration.
+    Duration,
+    /// Same content, under any name.
+    Hash,
+}
+
+#[derive(Debug, Clone)]
+pub struct RelinkCandidate {
+    pub asset_id: String,
+    pub old_path: String,
+    pub new_path: PathBuf,
+    pub evidence: RelinkEvidence,
+}
+
+impl ProjectDb {
+    /// Check each asset of a project against its file: that it exists, its
+    /// size, and its partial hash when the modification time moved. With
+    /// `verify` the partial hash is always checked, and the full hash too
+    /// when one is stored. Files that were only touched have their new
+    /// modification time stored; the variants of changed ones are marked
+    /// stale.
+    pub fn scan_media_status(
+        &self,
+        project_id: &str,
+        verify: bool,
+    ) -> Result<Vec<AssetMediaStatus>> {
+        let rows: Vec<(AssetRow, Option<i64>)> = {
+            let mut stmt = self.conn.prepare(&format!(
+                "SELECT {ASSET_COLUMNS}, mtime_ns FROM assets WHERE project_id = ?1 ORDER BY created_at ASC, id ASC"
+            ))?;
+            let rows = stmt.query_map(params![project_id], |row| {
+                Ok((asset_row(row)?, row.get(26)?))
+            })?;
+            rows.collect::<rusqlite::Result<_>>()?
+        };
+        let mut out = Vec::with_capacity(rows.len());
+        for (asset, stored_mtime) in rows {
+            let status = self.media_status(&asset, stored_mtime, verify)?;
+            if status == MediaStatus::Changed {
+                invalidate_variants(&self.conn, &asset.id)?;
+            }
+            out.push(AssetMediaStatus { asset, status });
+        }
+        Ok(out)
+    }
+
+    fn media_status(
+        &self,
+        asset: &AssetRow,
+        stored_mtime: Option<i64>,
+        verify: bool,
+    ) -> Result<MediaStatus> {
+        let path = Path::new(&asset.src_abs);
+        let meta = match fs::metadata(path) {
+            Ok(meta) if meta.is_file() => meta,
+            _ => return Ok(MediaStatus::Missing),
+        };
+        let len = meta.len();
+        if asset.file_size.is_some_and(|size| size != len as i64) {
+            return Ok(MediaStatus::Changed);
+        }
+        let mtime = mtime_ns(&meta);
+        let touched = mtime != stored_mtime;
+        if !touched && !verify {
+            return Ok(MediaStatus::Online);
+        }
+        let Ok(partial) = partial_hash(path) else {
+            return Ok(MediaStatus::Missing);
+        };
+        if asset.hash_partial.as_deref().is_some_and(|h| h != partial) {
+            return Ok(MediaStatus::Changed);
+        }
+        if let Some(full) = asset.hash_sha256.as_deref().filter(|_| verify) {
+            if len > 2 * PARTIAL_HASH_CHUNK {
+                match sha256_file(path) {
+                    Ok(hash) if hash == full => {}
+                    Ok(_) => return Ok(MediaStatus::Changed),
+                    Err(_) => return Ok(MediaStatus::Missing),
+                }
+            }
+        }
+        if touched || asset.hash_partial.is_none() {
+            self.conn.execute(
+                "UPDATE assets SET file_size = ?2, mtime_ns = ?3, hash_partial = ?4 WHERE id = ?1",
+                params![asset.id, len as i64, mtime, partial],
+            )?;
+        }
+        Ok(MediaStatus::Online)
+    }
+
+    /// Look under `folders` for the media of a project's missing assets.
+    /// A file qualifies by content, matched on size and partial hash and
+    /// confirmed with the full hash when one is stored, whatever its name.
+    /// Failing that, a file with the same name qualifies when its duration
+    /// from `probe_duration` agrees with the asset, or, for assets imported
+    /// before hashing, its size does. The strongest match per asset is
+    /// returned; nothing is changed until [`ProjectDb::relink_assets`].
+    pub fn find_relink_candidates(
+        &self,
+        project_id: &str,
+        folders: &[PathBuf],
+        probe_duration: impl Fn(&Path) -> Option<f64>,
+    ) -> Result<Vec<RelinkCandidate>> {
+        let missing: Vec<AssetRow> = self
+            .scan_media_status(project_id, false)?
+            .into_iter()
+            .filter(|s| s.status == MediaStatus::Missing)
+            .map(|s| s.asset)
+            .collect();
+        if missing.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut files: Vec<(PathBuf, u64)> = folders
+            .iter()
+            .flat_map(|folder| WalkDir::new(folder).into_iter().filter_map(|e| e.ok()))
+            .filter(|entry| entry.file_type().is_file())
+            .filter_map(|entry| Some((entry.path().to_path_buf(), entry.metadata().ok()?.len())))
+            .collect();
+        files.sort();
+        files.dedup();
+
+        let file_name = |path: &Path| {
+            path.file_name()
+                .map(|name| name.to_string_lossy().to_lowercase())
+        };
+        let mut out = Vec::new();
+        for asset in missing {
+            let name = file_name(Path::new(&asset.src_abs));
+            let mut best: Option<(RelinkEvidence, &PathBuf)> = None;
+            for (path, len) in &files {
+                let same_name = name.is_some() && file_name(path) == name;
+                if !same_name && asset.file_size != Some(*len as i64) {
+                    continue;
+                }
+                let Some(evidence) =
+                    relink_evidence(&asset, path, *len, same_name, &probe_duration)
+                else {
+                    continue;
+                };
+                if best.is_none_or(|(b, _)| evidence > b) {
+                    best = Some((evidence, path));
+                }
+                if evidence == RelinkEvidence::Hash {
+                    break;
+                }
+            }
+            if let Some((evidence, path)) = best {
+                out.push(RelinkCandidate {
+                    asset_id: asset.id,
+                    old_path: asset.src_abs,
+                    new_path: path.clone(),
+                    evidence,
+                });
+            }
+        }
+        Ok(out)
+    }
+
+    /// Point assets of a project at media in new places, all in one
+    /// transaction. Each asset gets its new `src_abs`, a `src_rel` when the
+    /// file is under the project's base path, and the size, modification
+    /// time and hashes of the new file; its variants are marked stale unless
+    /// the content is the same. Saved sequences and their journals that
+    /// referenced an old path are rewritten to the new one.
+    pub fn relink_assets(&self, project_id: &str, relinks: &[(String, PathBuf)]) -> Result<()> {
+        let base = self.get_project_base_path(project_id)?;
+        let now = chrono::Utc::now().timestamp();
+        let tx = self.begin_tx()?;
+        let mut moved = Vec::new();
+        for (asset_id, path) in relinks {
+            let (old_path, old_partial, old_full): (String, Option<String>, Option<String>) = tx
+                .query_row(
+                    "SELECT src_abs, hash_partial, hash_sha256 FROM assets WHERE id = ?1 AND project_id = ?2",
+                    params![asset_id, project_id],
+                    |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+                )
+                .optional()?
+                .ok_or_else(|| anyhow::anyhow!("asset not found: {asset_id}"))?;
+            let meta =
+                fs::metadata(path).map_err(|err| anyhow::anyhow!("{}: {err}", path.display()))?;
+            let (partial, full) = content_hashes(path);
+            // The stored full hash still holds when the content is the same.
+            let full = full.or(old_full.filter(|_| partial.is_some() && partial == old_partial));
+            let src_rel = base.as_deref().and_then(|b| path.strip_prefix(b).ok());
+            tx.execute(
+                "UPDATE assets SET src_abs = ?2, src_rel = ?3, file_size = ?4, mtime_ns = ?5, hash_partial = ?6, hash_sha256 = ?7, updated_at = ?8 WHERE id = ?1",
+                params![
+                    asset_id,
+                    path.to_string_lossy(),
+                    src_rel.map(|p| p.to_string_lossy()),
+                    meta.len() as i64,
+                    mtime_ns(&meta),
+                    partial,
+                    full,
+                    now
+                ],
+            )?;
+            index_asset(&tx, asset_id)?;
+            if partial.is_none() || partial != old_partial {
+                invalidate_variants(&tx, asset_id)?;
+            }
+            moved.push((old_path, path.to_string_lossy().into_owned()));
+        }
+
+        let timelines: Vec<(String, String)> = {
+            let mut stmt =
+                tx.prepare("SELECT id, timeline_json FROM sequences WHERE project_id = ?1")?;
+            let rows = stmt.query_map(params![project_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<rusqlite::Result<_>>()?
+        };
+        for (id, json) in timelines {
+            if let Some(json) = replace_media_paths(&json, &moved) {
+                tx.execute(
+                    "UPDATE sequences SET timeline_json = ?2, updated_at = ?3 WHERE id = ?1",
+                    params![id, json, now],
+                )?;
+            }
+        }
+        let entries: Vec<(i64, String)> = {
+            let mut stmt =
+                tx.prepare("SELECT id, entry_json FROM timeline_journal WHERE project_id = ?1")?;
+            let rows = stmt.query_map(params![project_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<rusqlite::Result<_>>()?
+        };
+        for (id, json) in entries {
+            if let Some(json) = replace_media_paths(&json, &moved) {
+                tx.execute(
+                    "UPDATE timeline_journal SET entry_json = ?2 WHERE id = ?1",
+                    params![id, json],
+                )?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+fn mtime_ns(meta: &fs::Metadata) -> Option<i64> {
+    meta.modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_nanos() as i64)
+}
+
+/// How well the file at `path`, `len` bytes long, stands in for the missing
+/// media of `asset`, or `None` when it does not.
+fn relink_evidence(
+    asset: &AssetRow,
+    path: &Path,
+    len: u64,
+    same_name: bool,
+    probe_duration: &impl Fn(&Path) -> Option<f64>,
+) -> Option<RelinkEvidence> {
+    let same_size = asset.file_size == Some(len as i64);
+    if same_size && asset.hash_partial.is_some() {
+        let partial = partial_hash(path).ok();
+        let full_matches = || {
+            len <= 2 * PARTIAL_HASH_CHUNK
+                || asset
+                    .hash_sha256
+                    .as_ref()
+                    .is_none_or(|full| sha256_file(path).ok().as_ref() == Some(full))
+        };
+        if partial == asset.hash_partial && full_matches() {
+            return Some(RelinkEvidence::Hash);
+        }
+    }
+    if !same_name {
+        return None;
+    }
+    // A re-encoded or re-wrapped file keeps its duration but not its bytes.
+    if let (Some(expected), Some(found)) = (asset.duration_seconds(), probe_duration(path)) {
+        let half_frame = match (asset.fps_num, asset.fps_den) {
+            (Some(num), Some(den)) if num > 0 && den > 0 => 0.5 * den as f64 / num as f64,
+            _ => 0.0,
+        };
+        return ((found - expected).abs() <= half_frame.max(0.05))
+            .then_some(RelinkEvidence::Duration);
+    }
+    // Stored size or content that does not match rules the file out.
+    if asset.hash_partial.is_some() || (asset.file_size.is_some() && !same_size) {
+        return None;
+    }
+    Some(if same_size {
+        RelinkEvidence::Size
+    } else {
+        RelinkEvidence::Name
+    })
+}
+
+/// Swap moved media paths in a serialized timeline or journal entry: the
+/// `asset_id` of graph clips and the `src` of legacy track items. Returns
+/// the new JSON, or `None` when nothing referenced a moved path.
+fn replace_media_paths(json: &str, moved: &[(String, String)]) -> Option<String> {
+    fn walk(value: &mut serde_json::Value, moved: &[(String, String)]) -> bool {
+        match value {
+            serde_json::Value::Object(map) => {
+                let mut changed = false;
+                for (key, v) in map.iter_mut() {
+                    let new_path = (key == "asset_id" || key == "src")
+                        .then(|| v.as_str())
+                        .flatten()
+                        .and_then(|path| moved.iter().find(|(old, _)| old == path))
+                        .map(|(_, new)| new.clone());
+                    match new_path {
+                        Some(new) => {
+                            *v = serde_json::Value::String(new);
+                            changed = true;
+                        }
+                        None => changed |= walk(v, moved),
+                    }
+                }
+                changed
+            }
+            serde_json::Value::Array(items) => items
+                .iter_mut()
+                .fold(false, |changed, v| walk(v, moved) | changed),
+            _ => false,
+        }
+    }
+    let mut value: serde_json::Value = serde_json::from_str(json).ok()?;
+    walk(&mut value, moved).then(|| value.to_string())
+}
+
+/// Version of [`ProjectSettings`] written by this build. Older settings are
+/// upgraded by [`migrate_settings`] when they are read.
+pub const PROJECT_SETTINGS_VERSION: u32 = 1;
+
+/// Per-project settings, stored as JSON in `projects.settings_json`.
+///
+/// The CLI and the desktop app both go through
+/// [`ProjectDb::get_project_settings`] and
+/// [`ProjectDb::update_project_settings`], so neither writes keys the other
+/// cannot read.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProjectSettings {
+    pub version: u32,
+    /// Format of the first sequence in a new project.
+    pub sequence: SequenceDefaults,
+    pub proxy: ProxySettings,
+    pub comfy: ComfySettings,
+    /// Storyboard cards and ComfyUI jobs. Their layout belongs to the
+    /// desktop app, so they are kept as raw JSON here.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub storyboard: Option<serde_json::Value>,
+    /// Keys this build does not know, kept so a save does not drop them.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Default for ProjectSettings {
+    fn default() -> Self {
+        Self {
+            version: PROJECT_SETTINGS_VERSION,
+            sequence: SequenceDefaults::default(),
+            proxy: ProxySettings::default(),
+            comfy: ComfySettings::default(),
+            storyboard: None,
+            extra: serde_json::Map::new(),
+        }
+    }
+}
+
+impl ProjectSettings {
+    pub fn validate(&self) -> Result<()> {
+        if self.version != PROJECT_SETTINGS_VERSION {
+            anyhow::bail!(
+                "settings version {} is not {}",
+                self.version,
+                PROJECT_SETTINGS_VERSION
+            );
+        }
+        self.sequence.validate()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SequenceDefaults {
+    pub width: u32,
+    pub height: u32,
+    pub fps_num: u32,
+    pub fps_den: u32,
+}
+
+impl Default for SequenceDefaults {
+    fn default() -> Self {
+        Self {
+            width: 1920,
+            height: 1080,
+            fps_num: 30,
+            fps_den: 1,
+        }
+    }
+}
+
+impl SequenceDefaults {
+    pub fn validate(&self) -> Result<()> {
+        if self.width == 0
+            || self.height == 0
+            || !self.width.is_multiple_of(2)
+            || !self.height.is_multiple_of(2)
+        {
+            anyhow::bail!(
+                "sequence size must be even and not zero, got {}x{}",
+                self.width,
+                self.height
+            );
+        }
+        if self.fps_num == 0 || self.fps_den == 0 {
+            anyhow::bail!(
+                "sequence frame rate must not be zero, got {}/{}",
+                self.fps_num,
+                self.fps_den
+            );
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProxySettings {
+    /// What playback reads when both the original and a proxy exist.
+    pub mode: ProxyMode,
+    /// Which imports get a proxy queued automatically.
+    pub auto_proxy: AutoProxySetting,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ProxyMode {
+    #[default]
+    OriginalOptimized,
+    ProxyPreferred,
+    ProxyOnly,
+}
+
+impl ProxyMode {
+    pub fn display_name(self) -> &'static str {
+        match self {
+            ProxyMode::OriginalOptimized => "Original (Optimized)",
+            ProxyMode::ProxyPreferred => "Proxy Preferred",
+            ProxyMode::ProxyOnly => "Proxy Only",
+        }
+    }
+}
+
+impl std::str::FromStr for ProxyMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        serde_json::from_value(serde_json::Value::String(s.to_string())).map_err(|_| {
+            format!(
+                "unknown proxy mode '{s}', expected original-optimized, proxy-preferred \
+                 or proxy-only"
+            )
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AutoProxySetting {
+    Off,
+    #[default]
+    LargeOnly,
+    All,
+}
+
+impl AutoProxySetting {
+    pub fn display_name(self) -> &'static str {
+        match self {
+            AutoProxySetting::Off => "Off",
+            AutoProxySetting::LargeOnly => "Large Only",
+            AutoProxySetting::All => "All Clips",
+        }
+    }
+}
+
+impl std::str::FromStr for AutoProxySetting {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        serde_json::from_value(serde_json::Value::String(s.to_string())).map_err(|_| {
+            format!("unknown auto proxy setting '{s}', expected off, large-only or all")
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ComfySettings {
+    pub api_key: String,
+}
+
+impl ProjectDb {
+    /// Read a project's settings, upgrading them from older versions. A
+    /// project without settings, or with settings that are not JSON, gets
+    /// the defaults.
+    pub fn get_project_settings(&self, project_id: &str) -> Result<ProjectSettings> {
+        let raw: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT settings_json FROM projects WHERE id = ?1",
+                params![project_id],
+                |r| r.get(0),
+            )
+            .optional()?;
+        let mut value = raw
+            .and_then(|raw| serde_json::from_str::<serde_json::Value>(&raw).ok())
+            .unwrap_or_else(|| serde_json::json!({}));
+        migrate_settings(&mut value)?;
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// Validate `settings` and store them as the current version.
+    pub fn update_project_settings(
+        &self,
+        project_id: &str,
+        settings: &ProjectSettings,
+    ) -> Result<()> {
+        settings.validate()?;
+        let now = chrono::Utc::now().timestamp();
+        let updated = self.conn.execute(
+            "UPDATE projects SET settings_json = ?2, updated_at = ?3 WHERE id = ?1",
+            params![project_id, serde_json::to_string(settings)?, now],
+        )?;
+        if updated == 0 {
+            anyhow::bail!("project not found: {project_id}");
+        }
+        Ok(())
+    }
+}
+
+/// Upgrade stored settings in place to [`PROJECT_SETTINGS_VERSION`].
+/// Settings without a version predate it: they kept the proxy mode under
+/// `proxy.proxy_mode`, spelled like the Rust variant. Refuses settings
+/// written by a newer build.
+pub fn migrate_settings(value: &mut serde_json::Value) -> Result<()> {
+    if !value.is_object() {
+        *value = serde_json::json!({});
+    }
+    let settings = value.as_object_mut().expect("settings are an object");
+    let version = match settings.get("version") {
+        None => 0,
+        Some(v) => v
+            .as_u64()
+            .ok_or_else(|| anyhow::anyhow!("settings version is not a number: {v}"))?,
+    };
+    if version > PROJECT_SETTINGS_VERSION as u64 {
+        anyhow::bail!(
+            "settings version {} is newer than this build supports ({})",
+            version,
+            PROJECT_SETTINGS_VERSION
+        );
+    }
+    if version < 1 {
+        if let Some(proxy) = settings.get_mut("proxy") {
+            match proxy.as_object_mut() {
+                Some(proxy) => {
+                    let mode = proxy.remove("proxy_mode").and_then(|mode| {
+                        Some(match mode.as_str()? {
+                            "OriginalOptimized" => "original-optimized",
+                            "ProxyPreferred" => "proxy-preferred",
+                            "ProxyOnly" => "proxy-only",
+                            _ => return None,
+                        })
+                    });
+                    if let Some(mode) = mode {
+                        proxy.insert("mode".into(), mode.into());
+                    }
+                    // Earlier builds ignored proxy settings they could not
+                    // read, so fall back to the default the same way.
+                    if proxy.get("auto_proxy").is_some_and(|v| {
+                        serde_json::from_value::<AutoProxySetting>(v.clone()).is_err()
+                    }) {
+                        proxy.remove("auto_proxy");
+                    }
+                }
+                None => *proxy = serde_json::json!({}),
+            }
+        }
+        if settings
+            .get("comfy")
+            .is_some_and(|c| !c["api_key"].is_string())
+        {
+            settings.remove("comfy");
+        }
+    }
+    settings.insert("version".into(), PROJECT_SETTINGS_VERSION.into());
+    Ok(())
+}
+
+/// What a row of the `proxies` table holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VariantKind {
+    /// Smaller, lighter media for editing.
+    Proxy,
+    /// Full-size media in an easier codec, good enough to export from.
+    Transcode,
+}
+
+impl VariantKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            VariantKind::Proxy => "proxy",
+            VariantKind::Transcode => "transcode",
+        }
+    }
+
+    fn id_prefix(self) -> &'static str {
+        match self {
+            VariantKind::Proxy => "px",
+            VariantKind::Transcode => "tc",
+        }
+    }
+}
+
+/// A proxy or transcoded file made from an asset's media.
+#[derive(Debug, Clone)]
+pub struct ProxyVariant {
+    pub id: String,
+    pub asset_id: String,
+    pub kind: VariantKind,
+    pub width: Option<i64>,
+    pub height: Option<i64>,
+    pub codec: Option<String>,
+    pub pixel_fmt: Option<String>,
+    pub bitrate_kbps: Option<i64>,
+    pub path_abs: String,
+    /// See [`variant_settings_hash`].
+    pub settings_hash: String,
+    /// Cleared when the source media changes.
+    pub valid: bool,
+    pub created_at: i64,
+}
+
+pub struct ProxyVariantInsert<'a> {
+    pub asset_id: &'a str,
+    pub kind: VariantKind,
+    pub width: Option<i64>,
+    pub height: Option<i64>,
+    pub codec: Option<&'a str>,
+    pub pixel_fmt: Option<&'a str>,
+    pub bitrate_kbps: Option<i64>,
+    pub path: &'a Path,
+    pub settings_hash: &'a str,
+}
+
+/// What a variant is picked for by [`choose_variant`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaybackContext {
+    /// Seeking and stepping, where decode speed matters most.
+    Scrub,
+    /// Playing through, where smooth full-quality frames matter most.
+    Play,
+    /// Rendering output, where only full-quality media will do.
+    Export,
+}
+
+const VARIANT_COLUMNS: &str = "id, asset_id, kind, width, height, codec, pixel_fmt, bitrate_kbps, path_abs, settings_hash, valid, created_at";
+
+fn variant_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<ProxyVariant> {
+    let kind: String = row.get(2)?;
+    Ok(ProxyVariant {
+        id: row.get(0)?,
+        asset_id: row.get(1)?,
+        kind: if kind == "transcode" {
+            VariantKind::Transcode
+        } else {
+            VariantKind::Proxy
+        },
+        width: row.get(3)?,
+        height: row.get(4)?,
+        codec: row.get(5)?,
+        pixel_fmt: row.get(6)?,
+        bitrate_kbps: row.get(7)?,
+        path_abs: row.get(8)?,
+        settings_hash: row.get(9)?,
+        valid: row.get::<_, i64>(10)? != 0,
+        created_at: row.get(11)?,
+    })
+}
+
+/// Short stable hash of the settings a variant was made with, so the same
+/// settings map to the same `proxies` row and different ones sit side by
+/// side.
+pub fn variant_settings_hash(settings: &serde_json::Value) -> String {
+    use sha2::{Digest, Sha256};
+    let digest = Sha256::digest(settings.to_string().as_bytes());
+    hex(&digest[..8])
+}
+
+/// The variant of `asset` that suits `context` best, among valid ones.
+/// Scrubbing takes the smallest proxy, playing the largest variant and
+/// prefers transcodes, and export only takes transcodes at the source's
+/// frame size.
+pub fn choose_variant<'a>(
+    asset: &AssetRow,
+    variants: &'a [ProxyVariant],
+    context: PlaybackContext,
+) -> Option<&'a ProxyVariant> {
+    let area = |v: &ProxyVariant| Some(v.width? * v.height?);
+    let full_size = |v: &ProxyVariant| match (asset.width, asset.height, v.width, v.height) {
+        (Some(aw), Some(ah), Some(w), Some(h)) => w >= aw && h >= ah,
+        // Transcodes keep the frame size unless they say otherwise.
+        _ => v.kind == VariantKind::Transcode,
+    };
+    let valid = variants
+        .iter()
+        .filter(|v| v.valid && v.asset_id == asset.id);
+    match context {
+        PlaybackContext::Scrub => valid.min_by_key(|v| {
+            (
+                v.kind == VariantKind::Transcode,
+                area(v).unwrap_or(i64::MAX),
+                std::cmp::Reverse(v.created_at),
+            )
+        }),
+        PlaybackContext::Play => {
+            valid.max_by_key(|v| (v.kind == VariantKind::Transcode, area(v), v.created_at))
+        }
+        PlaybackContext::Export => valid
+            .filter(|v| v.kind == VariantKind::Transcode && full_size(v))
+            .max_by_key(|v| v.created_at),
+    }
+}
+
+impl ProjectDb {
+    /// Record a generated proxy or transcode. A variant with the same kind
+    /// and settings hash replaces the earlier one. For proxies the asset's
+    /// `proxy_path` is pointed at the one playback would pick.
+    pub fn register_proxy_variant(&self, variant: &ProxyVariantInsert<'_>) -> Result<String> {
+        let id = format!(
+            "{}-{}-{}",
+            variant.kind.id_prefix(),
+            variant.asset_id,
+            variant.settings_hash
+        );
+        self.conn.execute(
+            "INSERT OR REPLACE INTO proxies(id, asset_id, kind, width, height, codec, pixel_fmt, bitrate_kbps, path_abs, settings_hash, valid, created_at) VALUES(?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, 1, strftime('%s','now'))",
+            params![
+                id,
+                variant.asset_id,
+                variant.kind.as_str(),
+                variant.width,
+                variant.height,
+                variant.codec,
+                variant.pixel_fmt,
+                variant.bitrate_kbps,
+                variant.path.to_string_lossy(),
+                variant.settings_hash
+            ],
+        )?;
+        if variant.kind == VariantKind::Proxy {
+            sync_asset_proxy(&self.conn, variant.asset_id)?;
+        }
+        Ok(id)
+    }
+
+    /// Every variant of an asset, valid or not, newest first.
+    pub fn proxy_variants(&self, asset_id: &str) -> Result<Vec<ProxyVariant>> {
+        asset_variants(&self.conn, asset_id)
+    }
+
+    /// The variant to read for `context`, skipping those whose file is
+    /// gone. `None` means the original media.
+    pub fn best_proxy_variant(
+        &self,
+        asset_id: &str,
+        context: PlaybackContext,
+    ) -> Result<Option<ProxyVariant>> {
+        let asset = self.get_asset(asset_id)?;
+        let mut variants = self.proxy_variants(asset_id)?;
+        variants.retain(|v| v.valid && Path::new(&v.path_abs).is_file());
+        Ok(choose_variant(&asset, &variants, context).cloned())
+    }
+
+    /// Mark every variant of an asset stale, for when its media changed,
+    /// and clear its proxy. Returns how many were valid.
+    pub fn invalidate_proxy_variants(&self, asset_id: &str) -> Result<usize> {
+        invalidate_variants(&self.conn, asset_id)
+    }
+
+    /// Drop the stale variants of a project's assets. Returns the files
+    /// that no valid variant uses any more, for the caller to delete.
+    pub fn take_invalid_proxy_variants(&self, project_id: &str) -> Result<Vec<PathBuf>> {
+        let tx = self.begin_tx()?;
+        let paths: Vec<String> = {
+            let mut stmt = tx.prepare(
+                "SELECT DISTINCT path_abs FROM proxies WHERE valid = 0
+                 AND asset_id IN (SELECT id FROM assets WHERE project_id = ?1)",
+            )?;
+            let rows = stmt.query_map(params![project_id], |row| row.get(0))?;
+            rows.collect::<rusqlite::Result<_>>()?
+        };
+        tx.execute(
+            "DELETE FROM proxies WHERE valid = 0
+             AND asset_id IN (SELECT id FROM assets WHERE project_id = ?1)",
+            params![project_id],
+        )?;
+        let mut out = Vec::new();
+        for path in paths {
+            let in_use: bool = tx.query_row(
+                "SELECT EXISTS(SELECT 1 FROM proxies WHERE path_abs = ?1)",
+                params![path],
+                |row| row.get(0),
+            )?;
+            if !in_use {
+                out.push(PathBuf::from(path));
+            }
+        }
+        tx.commit()?;
+        Ok(out)
+    }
+}
+
+fn asset_variants(conn: &Connection, asset_id: &str) -> Result<Vec<ProxyVariant>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {VARIANT_COLUMNS} FROM proxies WHERE asset_id = ?1 ORDER BY created_at DESC, id ASC"
+    ))?;
+    let rows = stmt.query_map(params![asset_id], variant_row)?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+fn invalidate_variants(conn: &Connection, asset_id: &str) -> Result<usize> {
+    let stale = conn.execute(
+        "UPDATE proxies SET valid = 0 WHERE asset_id = ?1 AND valid = 1",
+        params![asset_id],
+    )?;
+    sync_asset_proxy(conn, asset_id)?;
+    Ok(stale)
+}
+
+/// Point `assets.proxy_path` at the valid proxy playback would pick, or
+/// clear it when there is none. A proxy still being made is left alone.
+fn sync_asset_proxy(conn: &Connection, asset_id: &str) -> Result<()> {
+    let asset = conn.query_row(
+        &format!("SELECT {ASSET_COLUMNS} FROM assets WHERE id = ?1"),
+        params![asset_id],
+        asset_row,
+    )?;
+    let proxies: Vec<ProxyVariant> = asset_variants(conn, asset_id)?
+        .into_iter()
+        .filter(|v| v.kind == VariantKind::Proxy)
+        .collect();
+    let best = choose_variant(&asset, &proxies, PlaybackContext::Play);
+    if best.is_none() && !asset.is_proxy_ready {
+        return Ok(());
+    }
+    let now = chrono::Utc::now().timestamp();
+    conn.execute(
+        "UPDATE assets SET proxy_path = ?2, is_proxy_ready = ?3, updated_at = ?4 WHERE id = ?1",
+        params![
+            asset_id,
+            best.map(|v| v.path_abs.as_str()),
+            best.is_some() as i64,
+            now
+        ],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory_db() -> ProjectDb {
+        let db = ProjectDb::open_or_create(Path::new(":memory:")).unwrap();
+        db.ensure_project("p1", "Project", None).unwrap();
+        db
+    }
+
+    fn temp_db_path() -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("project-test-{}", Uuid::new_v4()))
+            .join("project.db")
+    }
+
+    fn add_video(db: &ProjectDb, path: &str, width: i64, codec: &str) -> String {
+        db.insert_asset_row(
+            "p1",
+            "video",
+            Path::new(path),
+            None,
+            Some(width),
+            Some(width * 9 / 16),
+            Some(240),
+            Some(24),
+            Some(1),
+            None,
+            None,
+            Some(10.0),
+            Some(codec),
+            None,
+            None,
+            false,
+            false,
+            None,
+        )
+        .unwrap()
+    }
+
+    fn format_24p() -> SequenceFormat {
+        SequenceFormat {
+            fps_num: 24,
+            fps_den: 1,
+            width: 1920,
+            height: 1080,
+            duration_frames: 0,
+        }
+    }
+
+    #[test]
+    fn sequences_can_be_created_renamed_duplicated_and_deleted() {
+        let db = memory_db();
+        let a = db.create_sequence("p1", "A", &format_24p(), "{}").unwrap();
+        db.rename_sequence(&a, "Edit").unwrap();
+        let b = db.duplicate_sequence(&a, "Edit copy").unwrap();
+        db.append_timeline_journal("p1", &b, "{}").unwrap();
+
+        let names: Vec<String> = db
+            .list_sequences("p1")
+            .unwrap()
+            .into_iter()
+            .map(|s| s.name)
+            .collect();
+        assert_eq!(names, ["Edit", "Edit copy"]);
+        assert_eq!(db.get_sequence(&b).unwrap().unwrap().fps_num, 24);
+
+        db.delete_sequence(&b).unwrap();
+        assert!(db.get_sequence(&b).unwrap().is_none());
+        assert!(db.list_timeline_journal(&b).unwrap().is_empty());
+        assert_eq!(db.list_sequences("p1").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn legacy_timelines_are_adopted_when_opened_not_when_listed() {
+        let path = temp_db_path();
+        {
+            let db = ProjectDb::open_or_create(&path).unwrap();
+            db.ensure_project("p1", "Project", None).unwrap();
+            db.ensure_project("p2", "Other", None).unwrap();
+            db.create_sequence("p2", "Edit", &format_24p(), "{}")
+                .unwrap();
+            db.connection()
+                .execute_batch(
+                    r#"INSERT INTO project_timeline(project_id, json, updated_at) VALUES
+                         ('p1', '{"name":"Old","fps":{"num":25,"den":1},"width":1280,"height":720,"duration_in_frames":50}', 0),
+                         ('p2', '{"name":"Old"}', 0);
+                       INSERT INTO timeline_journal(project_id, sequence_id, entry_json, created_at)
+                       VALUES('p1', NULL, '{}', 0);"#,
+                )
+                .unwrap();
+            // Listing is a plain read and leaves the legacy row alone.
+            assert!(db.list_sequences("p1").unwrap().is_empty());
+            assert!(db.get_project_timeline_json("p1").unwrap().is_some());
+        }
+
+        let db = ProjectDb::open_or_create(&path).unwrap();
+        let sequences = db.list_sequences("p1").unwrap();
+        assert_eq!(sequences.len(), 1);
+        let seq = &sequences[0];
+        assert_eq!(seq.name, "Old");
+        assert_eq!(
+            (seq.fps_num, seq.width, seq.duration_frames),
+            (25, 1280, 50)
+        );
+        assert_eq!(db.list_timeline_journal(&seq.id).unwrap().len(), 1);
+        assert!(db.get_project_timeline_json("p1").unwrap().is_none());
+        // A project that already has sequences keeps the old timeline too.
+        let names: Vec<String> = db
+            .list_sequences("p2")
+            .unwrap()
+            .into_iter()
+            .map(|s| s.name)
+            .collect();
+        assert_eq!(names, ["Edit", "Old (legacy)"]);
+        assert!(db.get_project_timeline_json("p2").unwrap().is_none());
+        drop(db);
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn unversioned_settings_are_migrated_on_read() {
+        let db = memory_db();
+        db.connection()
+            .execute(
+                "UPDATE projects SET settings_json = ?1 WHERE id = 'p1'",
+                params![r#"{"proxy":{"proxy_mode":"ProxyOnly","auto_proxy":"sometimes"},"comfy":{"api_key":3},"custom":true}"#],
+            )
+            .unwrap();
+
+        let settings = db.get_project_settings("p1").unwrap();
+        assert_eq!(settings.version, PROJECT_SETTINGS_VERSION);
+        assert_eq!(settings.proxy.mode, ProxyMode::ProxyOnly);
+        assert_eq!(settings.proxy.auto_proxy, AutoProxySetting::default());
+        assert_eq!(settings.comfy, ComfySettings::default());
+        assert_eq!(settings.extra["custom"], true);
+
+        let mut newer = serde_json::json!({ "version": PROJECT_SETTINGS_VERSION + 1 });
+        assert!(migrate_settings(&mut newer).is_err());
+    }
+
+    #[test]
+    fn settings_round_trip_and_are_validated() {
+        let db = memory_db();
+        assert_eq!(
+            db.get_project_settings("p1").unwrap(),
+            ProjectSettings::default()
+        );
+
+        let mut settings = ProjectSettings::default();
+        settings.proxy.mode = ProxyMode::ProxyPreferred;
+        settings.sequence.fps_num = 24;
+        db.update_project_settings("p1", &settings).unwrap();
+        assert_eq!(db.get_project_settings("p1").unwrap(), settings);
+
+        settings.sequence.width = 1921;
+        assert!(db.update_project_settings("p1", &settings).is_err());
+        assert!(db
+            .update_project_settings("missing", &ProjectSettings::default())
+            .is_err());
+    }
+
+    #[test]
+    fn deleting_a_bin_moves_its_contents_up() {
+        let db = memory_db();
+        let footage = db.create_bin("p1", None, "Footage").unwrap();
+        let day1 = db.create_bin("p1", Some(&footage), "Day 1").unwrap();
+        let takes = db.create_bin("p1", Some(&day1), "Takes").unwrap();
+        let clip = add_video(&db, "/media/a.mov", 1920, "prores");
+        db.set_asset_bin(&clip, Some(&day1)).unwrap();
+
+        assert!(db.move_bin(&footage, Some(&takes)).is_err());
+        db.delete_bin(&day1).unwrap();
+
+        let bins = db.list_bins("p1").unwrap();
+        assert_eq!(bins.len(), 2);
+        let moved = bins.iter().find(|b| b.id == takes).unwrap();
+        assert_eq!(moved.parent_id.as_deref(), Some(footage.as_str()));
+        let bin_id: Option<String> = db
+            .connection()
+            .query_row(
+                "SELECT bin_id FROM assets WHERE id = ?1",
+                params![clip],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(bin_id, Some(footage));
+    }
+
+    #[test]
+    fn smart_queries_combine_tags_ratings_and_media_fields() {
+        let db = memory_db();
+        let uhd = add_video(&db, "/media/uhd.mov", 3840, "ProRes");
+        let hd = add_video(&db, "/media/hd.mp4", 1920, "h264");
+        db.add_asset_tag(&uhd, " Interview ").unwrap();
+        db.add_asset_tag(&uhd, "interview").unwrap();
+        db.add_asset_tag(&hd, "broll").unwrap();
+        db.set_asset_rating(&uhd, 9).unwrap();
+        db.set_asset_rating(&hd, 2).unwrap();
+        assert_eq!(db.list_asset_tags(&uhd).unwrap(), ["Interview"]);
+        assert_eq!(db.list_project_tags("p1").unwrap(), ["broll", "Interview"]);
+
+        let ids = |query: &SmartQuery| -> Vec<String> {
+            db.query_assets("p1", query)
+                .unwrap()
+                .into_iter()
+                .map(|a| a.id)
+                .collect()
+        };
+        let query = SmartQuery {
+            codecs: vec!["prores".into()],
+            min_rating: Some(5),
+            tags: vec!["interview".into()],
+            ..Default::default()
+        };
+        assert_eq!(ids(&query), vec![uhd]);
+        let query = SmartQuery {
+            max_width: Some(1920),
+            max_duration_seconds: Some(10.0),
+            ..Default::default()
+        };
+        assert_eq!(ids(&query), vec![hd]);
+
+        let collection = db.create_smart_collection("p1", "Good", &query).unwrap();
+        let saved = db.list_smart_collections("p1").unwrap();
+        assert_eq!(saved.len(), 1);
+        assert_eq!(saved[0].id, collection);
+        assert_eq!(saved[0].query, query);
+    }
+
+    #[test]
+    fn search_follows_notes_updates_and_finds_transcript_segments() {
+        let db = memory_db();
+        let clip = add_video(&db, "/media/harbour_wide.mov", 1920, "h264");
+        db.set_asset_notes(&clip, Some("golden hour over the pier"))
+            .unwrap();
+        let hits = db.search("p1", "gold").unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].asset.id, clip);
+        assert_eq!(hits[0].matches[0].field, SearchField::Notes);
+
+        db.set_asset_notes(&clip, Some("overcast")).unwrap();
+        assert!(db.search("p1", "gold").unwrap().is_empty());
+        db.mark_asset_ready(&clip, true).unwrap();
+        assert!(db.search("p1", "ready").unwrap().is_empty());
+        assert_eq!(db.search("p1", "overcast").unwrap().len(), 1);
+        assert_eq!(db.search("p1", "harbour").unwrap().len(), 1);
+        assert!(db.search("p1", "\"harbour OR\" (").unwrap().is_empty());
+
+        db.upsert_transcript(
+            &clip,
+            "p1",
+            r#"[{"start": 2.5, "end": 4.0, "text": "the boats come in"}]"#,
+            None,
+            None,
+            1,
+        )
+        .unwrap();
+        let hits = db.search("p1", "boats").unwrap();
+        let hit = &hits[0].matches[0];
+        assert_eq!(hit.field, SearchField::Transcript);
+        assert_eq!(hit.start_frame(&hits[0].asset), Some(60));
+        assert!(db.search("other", "boats").unwrap().is_empty());
+    }
+
+    #[test]
+    fn a_baseline_database_is_migrated_and_indexed_on_open() {
+        let path = temp_db_path();
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        {
+            let conn = Connection::open(&path).unwrap();
+            for sql in [
+                include_str!("../migrations/V0001__init.sql"),
+                include_str!("../migrations/V0002__jobs.sql"),
+                include_str!("../migrations/V0003__timeline.sql"),
+                include_str!("../migrations/V0004__transcripts.sql"),
+            ] {
+                conn.execute_batch(sql).unwrap();
+            }
+            conn.execute_batch(
+                "INSERT INTO projects(id, name, created_at, updated_at) VALUES('p1', 'Old', 0, 0);
+                 INSERT INTO assets(id, project_id, kind, src_abs, notes, created_at, updated_at)
+                 VALUES('a1', 'p1', 'video', '/media/dock.mov', 'sunset over the water', 0, 0);
+                 INSERT INTO assets(id, project_id, kind, src_abs, notes, created_at, updated_at)
+                 VALUES('a2', 'p1', 'video', '/media/pier.mov', 'ready', 0, 0);",
+            )
+            .unwrap();
+        }
+
+        let db = ProjectDb::open_or_create(&path).unwrap();
+        let applied: i64 = db
+            .connection()
+            .query_row(
+                "SELECT COUNT(*) FROM migrations WHERE name LIKE 'V00%'",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(applied, 15);
+        let hits = db.search("p1", "sunset").unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].asset.id, "a1");
+        assert!(!db.get_asset("a1").unwrap().is_proxy_ready);
+        // The readiness old versions kept in notes is not searchable text.
+        assert!(db.search("p1", "ready").unwrap().is_empty());
+        let (notes, ready): (Option<String>, bool) = db
+            .connection()
+            .query_row(
+                "SELECT notes, is_ready FROM assets WHERE id = 'a2'",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!((notes, ready), (None, true));
+        drop(db);
+
+        // Opening again leaves the data alone.
+        let db = ProjectDb::open_or_create(&path).unwrap();
+        assert_eq!(db.search("p1", "sunset").unwrap().len(), 1);
+        drop(db);
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn snapshots_diff_and_restore_sequences_assets_and_settings() {
+        let db = memory_db();
+        let main = db
+            .create_sequence("p1", "Main", &format_24p(), "{}")
+            .unwrap();
+        let clip = add_video(&db, "/media/a.mov", 1920, "h264");
+        let settings = || -> String {
+            db.connection()
+                .query_row(
+                    "SELECT settings_json FROM projects WHERE id = 'p1'",
+                    [],
+                    |r| r.get(0),
+                )
+                .unwrap()
+        };
+        let saved_settings = settings();
+        let snapshot = db.create_project_snapshot("p1", Some("v1")).unwrap();
+        assert!(db.diff_project_snapshot(&snapshot).unwrap().is_empty());
+
+        db.rename_sequence(&main, "Main cut").unwrap();
+        let extra = db
+            .create_sequence("p1", "Extra", &format_24p(), "{}")
+            .unwrap();
+        db.connection()
+            .execute("DELETE FROM assets WHERE id = ?1", params![clip])
+            .unwrap();
+        db.connection()
+            .execute(
+                "UPDATE projects SET settings_json = '{\"theme\":\"dark\"}' WHERE id = 'p1'",
+                [],
+            )
+            .unwrap();
+
+        let diff = db.diff_project_snapshot(&snapshot).unwrap();
+        assert!(diff.settings_changed);
+        let change = |changes: &[SnapshotItemChange], id: &str| {
+            changes.iter().find(|c| c.id == id).map(|c| c.change)
+        };
+        assert_eq!(
+            change(&diff.sequences, &main),
+            Some(SnapshotChange::Modified)
+        );
+        assert_eq!(change(&diff.sequences, &extra), Some(SnapshotChange::Added));
+        assert_eq!(change(&diff.assets, &clip), Some(SnapshotChange::Removed));
+
+        db.restore_project_snapshot(&snapshot).unwrap();
+        let sequences = db.list_sequences("p1").unwrap();
+        assert_eq!(sequences.len(), 1);
+        assert_eq!(
+            (sequences[0].id.as_str(), sequences[0].name.as_str()),
+            (main.as_str(), "Main")
+        );
+        assert!(db.get_asset(&clip).is_ok());
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&settings()).unwrap(),
+            serde_json::from_str::<serde_json::Value>(&saved_settings).unwrap()
+        );
+        assert!(db.diff_project_snapshot(&snapshot).unwrap().is_empty());
+
+        let names: Vec<Option<String>> = db
+            .list_project_snapshots("p1")
+            .unwrap()
+            .into_iter()
+            .map(|s| s.name)
+            .collect();
+        assert_eq!(names, [Some("Before restore".into()), Some("v1".into())]);
+    }
+
+    #[test]
+    fn autosaves_keep_only_the_newest_unnamed_snapshots() {
+        let db = memory_db();
+        db.create_project_snapshot("p1", Some("named")).unwrap();
+        let mut last = String::new();
+        for _ in 0..4 {
+            last = db.autosave_project_snapshot("p1", 2).unwrap();
+        }
+        let snapshots = db.list_project_snapshots("p1").unwrap();
+        assert_eq!(snapshots.len(), 3);
+        assert_eq!(snapshots.iter().filter(|s| s.name.is_none()).count(), 2);
+        assert!(snapshots.iter().any(|s| s.id == last));
+    }
+
+    #[test]
+    fn duplicates_are_confirmed_with_the_full_hash() {
+        let dir = temp_db_path().parent().unwrap().to_path_buf();
+        fs::create_dir_all(&dir).unwrap();
+        let big = |middle: u8| {
+            let mut bytes = vec![1u8; 3 * PARTIAL_HASH_CHUNK as usize];
+            bytes[PARTIAL_HASH_CHUNK as usize + 10] = middle;
+            bytes
+        };
+        let original = dir.join("a.mov");
+        let copy = dir.join("copy.mov");
+        let edited = dir.join("edited.mov");
+        let small = dir.join("small.wav");
+        fs::write(&original, big(1)).unwrap();
+        fs::write(&copy, big(1)).unwrap();
+        fs::write(&edited, big(2)).unwrap();
+        fs::write(&small, b"tone").unwrap();
+
+        // Only the ends of a large file go into its partial hash.
+        assert_eq!(
+            partial_hash(&original).unwrap(),
+            partial_hash(&edited).unwrap()
+        );
+        assert_ne!(
+            sha256_file(&original).unwrap(),
+            sha256_file(&edited).unwrap()
+        );
+        assert_eq!(partial_hash(&small).unwrap(), sha256_file(&small).unwrap());
+
+        let db = memory_db();
+        db.ensure_project("p2", "Other", None).unwrap();
+        let first = db.upsert_asset_fast("p1", "video", &original).unwrap();
+        assert!(db.get_asset(&first).unwrap().hash_sha256.is_none());
+        let found = db.find_duplicate_asset("p1", &copy).unwrap();
+        assert_eq!(found.map(|a| a.id), Some(first.clone()));
+        assert!(db.get_asset(&first).unwrap().hash_sha256.is_some());
+        assert!(db.find_duplicate_asset("p1", &edited).unwrap().is_none());
+        assert!(db.find_duplicate_asset("p2", &copy).unwrap().is_none());
+
+        let elsewhere = db.upsert_asset_fast("p2", "video", &copy).unwrap();
+        db.upsert_asset_fast("p2", "video", &edited).unwrap();
+        let same: Vec<String> = db
+            .same_content_assets(&first)
+            .unwrap()
+            .into_iter()
+            .map(|a| a.id)
+            .collect();
+        assert_eq!(same, vec![elsewhere]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    /// A saved timeline using the media at `path` from a graph clip and a
+    /// legacy track item.
+    fn timeline_using(path: &str) -> String {
+        serde_json::json!({
+            "fps": { "num": 24, "den": 1 },
+            "graph": { "nodes": { "n1": { "kind": {
+                "type": "clip",
+                "asset_id": path,
+                "media_range": { "start": 24, "duration": 48 },
+                "timeline_range": { "start": 0, "duration": 48 },
+            } } } },
+            "tracks": [{ "items": [{ "id": "i1", "type": "video", "src": path }] }],
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn relinking_moved_media_rewrites_assets_sequences_and_journal() {
+        let dir = temp_db_path().parent().unwrap().to_path_buf();
+        fs::create_dir_all(dir.join("old")).unwrap();
+        fs::create_dir_all(dir.join("new")).unwrap();
+        let old = dir.join("old").join("a.mov");
+        fs::write(&old, b"original take").unwrap();
+        let old_path = old.to_string_lossy().into_owned();
+
+        let db = memory_db();
+        db.set_project_base_path("p1", &dir).unwrap();
+        let asset = db.upsert_asset_fast("p1", "video", &old).unwrap();
+        let seq = db
+            .create_sequence("p1", "Main", &format_24p(), &timeline_using(&old_path))
+            .unwrap();
+        db.append_timeline_journal("p1", &seq, &timeline_using(&old_path))
+            .unwrap();
+
+        let new = dir.join("new").join("renamed.mov");
+        fs::rename(&old, &new).unwrap();
+        // Same name, other content: never a candidate once a hash is stored.
+        fs::write(dir.join("new").join("a.mov"), b"another take").unwrap();
+        let status = db.scan_media_status("p1", false).unwrap();
+        assert_eq!(status[0].status, MediaStatus::Missing);
+
+        let candidates = db
+            .find_relink_candidates("p1", std::slice::from_ref(&dir), |_| None)
+            .unwrap();
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].new_path, new);
+        assert_eq!(candidates[0].evidence, RelinkEvidence::Hash);
+
+        db.relink_assets("p1", &[(asset.clone(), new.clone())])
+            .unwrap();
+        let new_path = new.to_string_lossy().into_owned();
+        assert_eq!(db.get_asset(&asset).unwrap().src_abs, new_path);
+        let src_rel: Option<String> = db
+            .connection()
+            .query_row(
+                "SELECT src_rel FROM assets WHERE id = ?1",
+                params![asset],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(
+            src_rel.map(PathBuf::from),
+            Some(Path::new("new").join("renamed.mov"))
+        );
+        let saved = db.get_sequence_timeline_json(&seq).unwrap().unwrap();
+        let journal = &db.list_timeline_journal(&seq).unwrap()[0].entry_json;
+        for json in [&saved, journal] {
+            assert!(!json.contains(&old_path));
+            assert_eq!(json.matches(&new_path).count(), 2);
+        }
+        let status = db.scan_media_status("p1", true).unwrap();
+        assert_eq!(status[0].status, MediaStatus::Online);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn usages_follow_each_save_of_a_sequence() {
+        let db = memory_db();
+        let a = db
+            .upsert_asset_fast("p1", "video", Path::new("/media/a.mov"))
+            .unwrap();
+        let b = db
+            .upsert_asset_fast("p1", "video", Path::new("/media/b.mov"))
+            .unwrap();
+        let seq = db
+            .create_sequence("p1", "Main", &format_24p(), &timeline_using("/media/a.mov"))
+            .unwrap();
+        let usages = db.asset_usages(&a).unwrap();
+        assert_eq!(usages.len(), 1);
+        assert_eq!(
+            (
+                usages[0].item_id.as_str(),
+                usages[0].start_frame,
+                usages[0].end_frame
+            ),
+            ("n1", 24, 72)
+        );
+        assert_eq!(db.asset_used_ranges(&a).unwrap(), [(1.0, 3.0)]);
+        assert_eq!(db.unused_assets("p1").unwrap().len(), 1);
+        assert!(db.delete_asset(&a).is_err());
+
+        // A reversed double-speed clip of b replaces the clip of a.
+        let json = serde_json::json!({
+            "fps": { "num": 24, "den": 1 },
+            "graph": { "nodes": { "n2": { "kind": {
+                "type": "clip",
+                "asset_id": "/media/b.mov",
+                "media_range": { "start": 0, "duration": 96 },
+                "timeline_range": { "start": 0, "duration": 48 },
+                "playback_rate": 2.0,
+                "reverse": true,
+            } } } },
+            "tracks": [],
+        })
+        .to_string();
+        db.save_sequence_timeline(&seq, &format_24p(), &json)
+            .unwrap();
+        assert!(db.asset_usages(&a).unwrap().is_empty());
+        assert!(db.sequences_using_asset(&a).unwrap().is_empty());
+        let usages = db.asset_usages(&b).unwrap();
+        assert_eq!(usages.len(), 1);
+        assert_eq!(
+            (usages[0].speed_num, usages[0].speed_den, usages[0].reversed),
+            (2, 1, true)
+        );
+        assert_eq!(db.sequences_using_asset(&b).unwrap()[0].id, seq);
+        db.delete_asset(&a).unwrap();
+
+        db.delete_sequence(&seq).unwrap();
+        assert!(db.asset_usages(&b).unwrap().is_empty());
+    }
+
+    #[test]
+    fn legacy_track_items_count_only_without_a_graph() {
+        let db = memory_db();
+        let a = db
+            .upsert_asset_fast("p1", "video", Path::new("/media/a.mov"))
+            .unwrap();
+        let json = serde_json::json!({
+            "fps": { "num": 24, "den": 1 },
+            "tracks": [{ "items": [
+                { "id": "i1", "type": "video", "src": "/media/a.mov", "duration_in_frames": 24, "in_offset_sec": 1.0 },
+                { "id": "i2", "type": "video", "src": "/media/a.mov", "duration_in_frames": 24, "in_offset_sec": 2.0 },
+            ] }],
+        })
+        .to_string();
+        db.create_sequence("p1", "Old", &format_24p(), &json)
+            .unwrap();
+        assert_eq!(db.asset_usages(&a).unwrap().len(), 2);
+        assert_eq!(db.asset_used_ranges(&a).unwrap(), [(1.0, 3.0)]);
+
+        // Once a graph exists, the rebuilt track list is not counted again.
+        db.create_sequence("p1", "New", &format_24p(), &timeline_using("/media/a.mov"))
+            .unwrap();
+        assert_eq!(db.asset_usages(&a).unwrap().len(), 3);
+    }
+
+    fn variant(asset: &AssetRow, id: &str, kind: VariantKind, width: i64) -> ProxyVariant {
+        ProxyVariant {
+            id: id.to_string(),
+            asset_id: asset.id.clone(),
+            kind,
+            width: Some(width),
+            height: Some(width * 9 / 16),
+            codec: None,
+            pixel_fmt: None,
+            bitrate_kbps: None,
+            path_abs: format!("/proxies/{id}.mov"),
+            settings_hash: id.to_string(),
+            valid: true,
+            created_at: 0,
+        }
+    }
+
+    #[test]
+    fn each_playback_context_picks_its_own_variant() {
+        let db = memory_db();
+        let id = add_video(&db, "/media/uhd.mov", 3840, "hevc");
+        let asset = db.get_asset(&id).unwrap();
+        let mut stale = variant(&asset, "stale", VariantKind::Transcode, 7680);
+        stale.valid = false;
+        let variants = [
+            variant(&asset, "small", VariantKind::Proxy, 960),
+            variant(&asset, "large", VariantKind::Proxy, 1920),
+            variant(&asset, "half", VariantKind::Transcode, 1920),
+            variant(&asset, "full", VariantKind::Transcode, 3840),
+            stale,
+        ];
+        let pick = |variants: &[ProxyVariant], context| {
+            choose_variant(&asset, variants, context).map(|v| v.id.clone())
+        };
+        assert_eq!(
+            pick(&variants, PlaybackContext::Scrub).as_deref(),
+            Some("small")
+        );
+        assert_eq!(
+            pick(&variants, PlaybackContext::Play).as_deref(),
+            Some("full")
+        );
+        assert_eq!(
+            pick(&variants, PlaybackContext::Export).as_deref(),
+            Some("full")
+        );
+        assert_eq!(pick(&variants[..3], PlaybackContext::Export), None);
+        assert_eq!(
+            pick(&variants[..2], PlaybackContext::Play).as_deref(),
+            Some("large")
+        );
+    }
+
+    #[test]
+    fn changed_media_invalidates_its_variants() {
+        let dir = temp_db_path().parent().unwrap().to_path_buf();
+        fs::create_dir_all(&dir).unwrap();
+        let media = dir.join("a.mov");
+        fs::write(&media, b"take one").unwrap();
+        let db = memory_db();
+        let asset = db.upsert_asset_fast("p1", "video", &media).unwrap();
+        let proxy = |width: i64, name: &str| {
+            let path = dir.join(name);
+            fs::write(&path, name).unwrap();
+            let settings = serde_json::json!({ "width": width });
+            db.register_proxy_variant(&ProxyVariantInsert {
+                asset_id: &asset,
+                kind: VariantKind::Proxy,
+                width: Some(width),
+                height: Some(width * 9 / 16),
+                codec: Some("h264"),
+                pixel_fmt: None,
+                bitrate_kbps: None,
+                path: &path,
+                settings_hash: &variant_settings_hash(&settings),
+            })
+            .unwrap();
+            path
+        };
+        proxy(640, "small.mp4");
+        let large = proxy(1280, "large.mp4");
+        // The same settings again replace the earlier row.
+        proxy(1280, "large.mp4");
+        assert_eq!(db.proxy_variants(&asset).unwrap().len(), 2);
+        let row = db.get_asset(&asset).unwrap();
+        assert!(row.is_proxy_ready);
+        assert_eq!(row.proxy_path.map(PathBuf::from), Some(large.clone()));
+        let scrub = db
+            .best_proxy_variant(&asset, PlaybackContext::Scrub)
+            .unwrap()
+            .unwrap();
+        assert_eq!(scrub.width, Some(640));
+
+        fs::write(&media, b"take two, longer").unwrap();
+        db.refresh_asset_content(&asset, &media).unwrap();
+        assert!(db.proxy_variants(&asset).unwrap().iter().all(|v| !v.valid));
+        let row = db.get_asset(&asset).unwrap();
+        assert!(!row.is_proxy_ready && row.proxy_path.is_none());
+        assert!(db
+            .best_proxy_variant(&asset, PlaybackContext::Play)
+            .unwrap()
+            .is_none());
+
+        let mut stale = db.take_invalid_proxy_variants("p1").unwrap();
+        stale.sort();
+        assert_eq!(stale, [large, dir.join("small.mp4")]);
+        assert!(db.proxy_variants(&asset).unwrap().is_empty());
+        assert_eq!(db.invalidate_proxy_variants(&asset).unwrap(), 0);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,282 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+use crate::{
+    AutomationInterpolation, AutomationKeyframe, AutomationLane, Frame, KeyframeEasing, NodeId,
+    TimelineGraph,
+};
+
+// Easing tangents are normalised slopes: 1.0 is the straight line between two
+// keyframes, 0.0 is flat, and larger values overshoot. They are converted into
+// value-per-frame derivatives per segment so the same keyframe can sit between
+// segments of different lengths.
+
+impl KeyframeEasing {
+    /// Normalised slope arriving at a keyframe with this easing.
+    pub fn in_slope(&self) -> f64 {
+        match self {
+            KeyframeEasing::Linear | KeyframeEasing::EaseOut => 1.0,
+            KeyframeEasing::EaseIn | KeyframeEasing::EaseInOut => 0.0,
+            KeyframeEasing::Custom { in_tangent, .. } => *in_tangent as f64,
+        }
+    }
+
+    /// Normalised slope leaving a keyframe with this easing.
+    pub fn out_slope(&self) -> f64 {
+        match self {
+            KeyframeEasing::Linear | KeyframeEasing::EaseIn => 1.0,
+            KeyframeEasing::EaseOut | KeyframeEasing::EaseInOut => 0.0,
+            KeyframeEasing::Custom { out_tangent, .. } => *out_tangent as f64,
+        }
+    }
+}
+
+impl AutomationLane {
+    /// Value of the lane at `frame`, or `None` if it has no keyframes.
+    ///
+    /// Frames before the first keyframe or after the last hold the nearest
+    /// keyframe's value. Between keyframes the lane's interpolation decides the
+    /// curve: `Step` holds the left value, `Linear` blends along the easing of
+    /// both keyframes, and `Bezier` does the same but derives tangents for
+    /// `Linear`-eased keyframes from their neighbours so the curve passes
+    /// smoothly through them.
+    pub fn value_at(&self, frame: Frame) -> Option<f64> {
+        sample_curve(&self.interpolation, &self.keyframes, frame)
+    }
+}
+
+impl TimelineGraph {
+    /// Automation lanes that target `node_id`, in graph order.
+    pub fn automation_lanes_for(&self, node_id: NodeId) -> impl Iterator<Item = &AutomationLane> {
+        self.automation
+            .iter()
+            .filter(move |lane| lane.target.node == node_id)
+    }
+
+    /// Every automated parameter of `node_id` evaluated at `frame`.
+    ///
+    /// Lanes without keyframes are skipped. If several lanes drive the same
+    /// parameter the last one in `automation` wins.
+    pub fn automation_values_at(&self, node_id: NodeId, frame: Frame) -> BTreeMap<String, f64> {
+        let mut values = BTreeMap::new();
+        for lane in self.automation_lanes_for(node_id) {
+            if let Some(value) = lane.value_at(frame) {
+                values.insert(lane.target.parameter.clone(), value);
+            }
+        }
+        values
+    }
+}
+
+/// Shared by automation lanes and time-remap curves.
+pub(crate) fn sample_curve(
+    interpolation: &AutomationInterpolation,
+    keyframes: &[AutomationKeyframe],
+    frame: Frame,
+) -> Option<f64> {
+    let keys = sorted_keyframes(keyframes);
+    let first = keys.first()?;
+    let last = keys.last()?;
+    if frame <= first.frame {
+        return Some(first.value);
+    }
+    if frame >= last.frame {
+        return Some(last.value);
+    }
+
+    // First keyframe strictly after `frame`; never 0 or len here.
+    let right = keys.partition_point(|k| k.frame <= frame);
+    let left = right - 1;
+    let (a, b) = (&keys[left], &keys[right]);
+    let span = (b.frame - a.frame) as f64;
+    let t = (frame - a.frame) as f64 / span;
+
+    let value = match interpolation {
+        AutomationInterpolation::Step => a.value,
+        AutomationInterpolation::Linear => {
+            let rise = b.value - a.value;
+            hermite(
+                t,
+                a.value,
+                b.value,
+                a.easing.out_slope() * rise,
+                b.easing.in_slope() * rise,
+            )
+        }
+        AutomationInterpolation::Bezier => {
+            let rise = b.value - a.value;
+            let m0 = match a.easing {
+                KeyframeEasing::Linear => auto_derivative(&keys, left) * span,
+                ref easing => easing.out_slope() * rise,
+            };
+            let m1 = match b.easing {
+                KeyframeEasing::Linear => auto_derivative(&keys, right) * span,
+                ref easing => easing.in_slope() * rise,
+            };
+            hermite(t, a.value, b.value, m0, m1)
+        }
+    };
+    Some(value)
+}
+
+fn sorted_keyframes(keyframes: &[AutomationKeyframe]) -> Cow<'_, [AutomationKeyframe]> {
+    if keyframes.windows(2).all(|w| w[0].frame <= w[1].frame) {
+        Cow::Borrowed(keyframes)
+    } else {
+        let mut sorted = keyframes.to_vec();
+        sorted.sort_by_key(|k| k.frame);
+        Cow::Owned(sorted)
+    }
+}
+
+/// Catmull-Rom style derivative (value per frame) at keyframe `idx`.
+fn auto_derivative(keys: &[AutomationKeyframe], idx: usize) -> f64 {
+    let prev = &keys[idx.saturating_sub(1)];
+    let next = &keys[(idx + 1).min(keys.len() - 1)];
+    let frames = (next.frame - prev.frame) as f64;
+    if frames <= 0.0 {
+        0.0
+    } else {
+        (next.value - prev.value) / frames
+    }
+}
+
+/// Cubic Hermite between `p0` and `p1` with end tangents scaled to the segment.
+fn hermite(t: f64, p0: f64, p1: f64, m0: f64, m1: f64) -> f64 {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    (2.0 * t3 - 3.0 * t2 + 1.0) * p0
+        + (t3 - 2.0 * t2 + t) * m0
+        + (-2.0 * t3 + 3.0 * t2) * p1
+        + (t3 - t2) * m1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AutomationTarget, LaneId};
+
+    fn lane(
+        interpolation: AutomationInterpolation,
+        keys: &[(Frame, f64, KeyframeEasing)],
+    ) -> AutomationLane {
+        AutomationLane {
+            id: LaneId::new(),
+            target: AutomationTarget {
+                node: NodeId::new(),
+                parameter: "opacity".into(),
+            },
+            interpolation,
+            keyframes: keys
+                .iter()
+                .map(|(frame, value, easing)| AutomationKeyframe {
+                    frame: *frame,
+                    value: *value,
+                    easing: easing.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    fn approx(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn step_and_linear_sample_between_keyframes() {
+        let keys = [
+            (10, 0.0, KeyframeEasing::Linear),
+            (20, 1.0, KeyframeEasing::Linear),
+        ];
+        let step = lane(AutomationInterpolation::Step, &keys);
+        assert_eq!(step.value_at(0), Some(0.0));
+        assert_eq!(step.value_at(19), Some(0.0));
+        assert_eq!(step.value_at(20), Some(1.0));
+
+        let linear = lane(AutomationInterpolation::Linear, &keys);
+        assert!(approx(linear.value_at(15).unwrap(), 0.5));
+        assert!(approx(linear.value_at(12).unwrap(), 0.2));
+        assert_eq!(linear.value_at(99), Some(1.0));
+        assert_eq!(lane(AutomationInterpolation::Linear, &[]).value_at(5), None);
+    }
+
+    #[test]
+    fn easing_shapes_the_segment() {
+        let ease = lane(
+            AutomationInterpolation::Linear,
+            &[
+                (0, 0.0, KeyframeEasing::EaseInOut),
+                (10, 1.0, KeyframeEasing::EaseInOut),
+            ],
+        );
+        assert!(approx(ease.value_at(5).unwrap(), 0.5));
+        assert!(ease.value_at(2).unwrap() < 0.2);
+        assert!(ease.value_at(8).unwrap() > 0.8);
+
+        let custom = lane(
+            AutomationInterpolation::Linear,
+            &[
+                (
+                    0,
+                    0.0,
+                    KeyframeEasing::Custom {
+                        in_tangent: 1.0,
+                        out_tangent: 1.0,
+                    },
+                ),
+                (10, 1.0, KeyframeEasing::Linear),
+            ],
+        );
+        assert!(approx(custom.value_at(3).unwrap(), 0.3));
+    }
+
+    #[test]
+    fn bezier_is_smooth_through_linear_keyframes() {
+        let bezier = lane(
+            AutomationInterpolation::Bezier,
+            &[
+                (0, 0.0, KeyframeEasing::Linear),
+                (10, 1.0, KeyframeEasing::Linear),
+                (20, 0.0, KeyframeEasing::Linear),
+            ],
+        );
+        // Peak keyframe gets a flat tangent, so its neighbours sit just below it.
+        let before = bezier.value_at(9).unwrap();
+        let after = bezier.value_at(11).unwrap();
+        assert!(approx(before, after));
+        assert!(before < 1.0 && before > 0.9);
+    }
+
+    #[test]
+    fn resolves_all_parameters_for_a_node() {
+        let mut opacity = lane(
+            AutomationInterpolation::Linear,
+            &[
+                (0, 0.0, KeyframeEasing::Linear),
+                (10, 1.0, KeyframeEasing::Linear),
+            ],
+        );
+        let node = opacity.target.node;
+        let mut volume = lane(
+            AutomationInterpolation::Step,
+            &[(0, -6.0, KeyframeEasing::Linear)],
+        );
+        volume.target = AutomationTarget {
+            node,
+            parameter: "volume".into(),
+        };
+        let mut other = opacity.clone();
+        other.id = LaneId::new();
+        other.target.node = NodeId::new();
+        opacity.keyframes.reverse();
+
+        let graph = TimelineGraph {
+            automation: vec![opacity, volume, other],
+            ..TimelineGraph::default()
+        };
+        let values = graph.automation_values_at(node, 5);
+        assert_eq!(values.len(), 2);
+        assert!(approx(values["opacity"], 0.5));
+        assert_eq!(values["volume"], -6.0);
+    }
+}
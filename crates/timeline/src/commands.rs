@@ -1,9 +1,12 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+use std::collections::HashMap;
+
 use crate::{
-    AutomationKeyframe, AutomationLane, Frame, FrameRange, LaneId, NodeId, TimelineEdge,
-    TimelineError, TimelineGraph, TimelineNode, TimelineNodeKind, TrackBinding, TrackId, TrackKind,
+    AutomationKeyframe, AutomationLane, CompoundNode, EdgeKind, Frame, FrameRange, LaneId, Marker,
+    MarkerId, NodeId, TimeRemap, TimelineEdge, TimelineError, TimelineGraph, TimelineNode,
+    TimelineNodeKind, TrackBinding, TrackId, TrackKind, TrackProperties,
 };
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
@@ -12,6 +15,13 @@ pub struct TrackPlacement {
     pub position: Option<usize>,
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum TrimEdge {
+    Start,
+    End,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(tag = "command", rename_all = "snake_case")]
 pub enum TimelineCommand {
@@ -44,6 +54,11 @@ pub enum TimelineCommand {
         track_id: TrackId,
         index: usize,
     },
+    /// Replace a track's mix and composite settings, keeping its nodes.
+    SetTrackProperties {
+        track_id: TrackId,
+        properties: TrackProperties,
+    },
     AddAutomationLane {
         lane: AutomationLane,
     },
@@ -61,6 +76,191 @@ pub enum TimelineCommand {
         lane_id: LaneId,
         frame: Frame,
     },
+    AddMarker {
+        marker: Marker,
+    },
+    UpdateMarker {
+        marker: Marker,
+    },
+    RemoveMarker {
+        marker_id: MarkerId,
+    },
+    /// Clip markers are stored on the clip in media frames.
+    AddClipMarker {
+        node_id: NodeId,
+        marker: Marker,
+    },
+    UpdateClipMarker {
+        node_id: NodeId,
+        marker: Marker,
+    },
+    RemoveClipMarker {
+        node_id: NodeId,
+        marker_id: MarkerId,
+    },
+    /// Store or replace the nested sequence graph used by compound clips.
+    UpsertSequence {
+        sequence_id: String,
+        graph: Box<TimelineGraph>,
+    },
+    RemoveSequence {
+        sequence_id: String,
+    },
+    /// Move `node_ids` into a new nested sequence and put a single compound
+    /// clip covering their span in their place.
+    CollapseToCompound {
+        node_ids: Vec<NodeId>,
+        compound_id: NodeId,
+        sequence_id: String,
+    },
+    /// Replace a compound clip with copies of the nested nodes it shows.
+    ExpandCompound {
+        node_id: NodeId,
+    },
+    /// Trim one edge of a node, leaving the rest of its track where it is.
+    /// Positive `delta` moves the edge later; it may not run into a neighbour.
+    /// Trims, rolls, slips and slides repeat on the node's linked partners.
+    TrimNode {
+        node_id: NodeId,
+        edge: TrimEdge,
+        delta: Frame,
+    },
+    /// Trim one edge of a node and shift everything after it on the same track
+    /// so no gap opens or closes. Positive `delta` moves the edge later.
+    RippleTrim {
+        node_id: NodeId,
+        edge: TrimEdge,
+        delta: Frame,
+    },
+    /// Move the shared edit point between two adjacent nodes on one track.
+    /// Partners of `left` roll too where they meet a partner of `right`.
+    RollEdit {
+        left: NodeId,
+        right: NodeId,
+        delta: Frame,
+    },
+    /// Shift the media window of a clip without moving it on the timeline.
+    SlipClip {
+        node_id: NodeId,
+        delta: Frame,
+    },
+    /// Move a node along its track, trimming the adjacent neighbours to compensate.
+    SlideClip {
+        node_id: NodeId,
+        delta: Frame,
+    },
+    /// Join the nodes, and any groups they already belong to, into one link
+    /// group.
+    LinkNodes {
+        node_ids: Vec<NodeId>,
+    },
+    /// Take the nodes out of their link groups.
+    UnlinkNodes {
+        node_ids: Vec<NodeId>,
+    },
+    /// Move a node and everything linked to it by `delta` frames.
+    MoveLinked {
+        node_id: NodeId,
+        delta: Frame,
+    },
+    /// Remove a node together with everything linked to it.
+    RemoveLinked {
+        node_id: NodeId,
+    },
+    /// Cut a node and its linked partners at `frame`. The right-hand pieces
+    /// get fresh ids and form a link group of their own.
+    SplitNode {
+        node_id: NodeId,
+        frame: Frame,
+    },
+    /// Install or clear a clip's time-remap curve. The inverse restores the
+    /// whole node, since the curve also changes its ranges.
+    SetTimeRemap {
+        node_id: NodeId,
+        remap: Option<TimeRemap>,
+    },
+    /// Three-point insert: put a copy of `node` on each target track at its
+    /// timeline start. Whatever spans that frame is split and everything from
+    /// it onwards on those tracks moves later by the node's duration. The
+    /// first copy keeps `node.id`; the copies are linked.
+    InsertEdit {
+        node: TimelineNode,
+        track_ids: Vec<TrackId>,
+    },
+    /// Three-point overwrite: like `InsertEdit`, but the copies replace
+    /// whatever sits underneath them, which is trimmed, split or removed.
+    /// Nothing else moves.
+    OverwriteEdit {
+        node: TimelineNode,
+        track_ids: Vec<TrackId>,
+    },
+    /// Clear `range` on the target tracks and leave the gap.
+    LiftRange {
+        range: FrameRange,
+        track_ids: Vec<TrackId>,
+    },
+    /// Clear `range` on the target tracks and pull later nodes on those
+    /// tracks back to close the gap.
+    ExtractRange {
+        range: FrameRange,
+        track_ids: Vec<TrackId>,
+    },
+    /// Apply several commands as one unit. If any command fails, the ones
+    /// already applied are rolled back before the error is returned.
+    Batch {
+        commands: Vec<TimelineCommand>,
+    },
+}
+
+impl TimelineCommand {
+    /// Human-readable name used for undo history entries.
+    pub fn label(&self) -> &'static str {
+        match self {
+            TimelineCommand::InsertNode { .. } => "Insert clip",
+            TimelineCommand::RemoveNode { .. } => "Remove clip",
+            TimelineCommand::UpdateNode { .. } => "Edit clip",
+            TimelineCommand::AddEdge { .. } => "Connect nodes",
+            TimelineCommand::RemoveEdge { .. } => "Disconnect nodes",
+            TimelineCommand::UpsertTrack { .. } => "Edit track",
+            TimelineCommand::RemoveTrack { .. } => "Remove track",
+            TimelineCommand::MoveTrack { .. } => "Move track",
+            TimelineCommand::SetTrackProperties { .. } => "Edit track",
+            TimelineCommand::AddAutomationLane { .. } => "Add automation lane",
+            TimelineCommand::UpdateAutomationLane { .. } => "Edit automation lane",
+            TimelineCommand::RemoveAutomationLane { .. } => "Remove automation lane",
+            TimelineCommand::InsertAutomationKeyframe { .. } => "Set keyframe",
+            TimelineCommand::RemoveAutomationKeyframe { .. } => "Remove keyframe",
+            TimelineCommand::AddMarker { .. } | TimelineCommand::AddClipMarker { .. } => {
+                "Add marker"
+            }
+            TimelineCommand::UpdateMarker { .. } | TimelineCommand::UpdateClipMarker { .. } => {
+                "Edit marker"
+            }
+            TimelineCommand::RemoveMarker { .. } | TimelineCommand::RemoveClipMarker { .. } => {
+                "Remove marker"
+            }
+            TimelineCommand::UpsertSequence { .. } => "Edit sequence",
+            TimelineCommand::RemoveSequence { .. } => "Remove sequence",
+            TimelineCommand::CollapseToCompound { .. } => "Collapse to compound clip",
+            TimelineCommand::ExpandCompound { .. } => "Expand compound clip",
+            TimelineCommand::TrimNode { .. } => "Trim clip",
+            TimelineCommand::RippleTrim { .. } => "Ripple trim",
+            TimelineCommand::RollEdit { .. } => "Roll edit",
+            TimelineCommand::SlipClip { .. } => "Slip clip",
+            TimelineCommand::SlideClip { .. } => "Slide clip",
+            TimelineCommand::LinkNodes { .. } => "Link clips",
+            TimelineCommand::UnlinkNodes { .. } => "Unlink clips",
+            TimelineCommand::MoveLinked { .. } => "Move clip",
+            TimelineCommand::RemoveLinked { .. } => "Remove clip",
+            TimelineCommand::SplitNode { .. } => "Split clip",
+            TimelineCommand::SetTimeRemap { .. } => "Time remap",
+            TimelineCommand::InsertEdit { .. } => "Insert edit",
+            TimelineCommand::OverwriteEdit { .. } => "Overwrite edit",
+            TimelineCommand::LiftRange { .. } => "Lift",
+            TimelineCommand::ExtractRange { .. } => "Extract",
+            TimelineCommand::Batch { .. } => "Batch edit",
+        }
+    }
 }
 
 pub fn apply_command(
@@ -80,6 +280,10 @@ pub fn apply_command(
         TimelineCommand::UpsertTrack { track } => upsert_track(graph, track),
         TimelineCommand::RemoveTrack { track_id } => remove_track(graph, track_id),
         TimelineCommand::MoveTrack { track_id, index } => move_track(graph, track_id, index),
+        TimelineCommand::SetTrackProperties {
+            track_id,
+            properties,
+        } => set_track_properties(graph, track_id, properties),
         TimelineCommand::AddAutomationLane { lane } => add_lane(graph, lane),
         TimelineCommand::UpdateAutomationLane { lane } => update_lane(graph, lane),
         TimelineCommand::RemoveAutomationLane { lane_id } => remove_lane(graph, lane_id),
@@ -89,7 +293,163 @@ pub fn apply_command(
         TimelineCommand::RemoveAutomationKeyframe { lane_id, frame } => {
             remove_keyframe(graph, lane_id, frame)
         }
+        TimelineCommand::AddMarker { marker } => {
+            let marker_id = marker.id;
+            add_marker(&mut graph.markers, marker)?;
+            Ok(TimelineCommand::RemoveMarker { marker_id })
+        }
+        TimelineCommand::UpdateMarker { marker } => Ok(TimelineCommand::UpdateMarker {
+            marker: update_marker(&mut graph.markers, marker)?,
+        }),
+        TimelineCommand::RemoveMarker { marker_id } => Ok(TimelineCommand::AddMarker {
+            marker: remove_marker(&mut graph.markers, marker_id)?,
+        }),
+        TimelineCommand::AddClipMarker { node_id, marker } => {
+            let marker_id = marker.id;
+            add_marker(clip_markers_mut(graph, node_id)?, marker)?;
+            Ok(TimelineCommand::RemoveClipMarker { node_id, marker_id })
+        }
+        TimelineCommand::UpdateClipMarker { node_id, marker } => {
+            Ok(TimelineCommand::UpdateClipMarker {
+                node_id,
+                marker: update_marker(clip_markers_mut(graph, node_id)?, marker)?,
+            })
+        }
+        TimelineCommand::RemoveClipMarker { node_id, marker_id } => {
+            Ok(TimelineCommand::AddClipMarker {
+                node_id,
+                marker: remove_marker(clip_markers_mut(graph, node_id)?, marker_id)?,
+            })
+        }
+        TimelineCommand::UpsertSequence {
+            sequence_id,
+            graph: inner,
+        } => Ok(upsert_sequence(graph, sequence_id, *inner)),
+        TimelineCommand::RemoveSequence { sequence_id } => remove_sequence(graph, sequence_id),
+        TimelineCommand::CollapseToCompound {
+            node_ids,
+            compound_id,
+            sequence_id,
+        } => collapse_to_compound(graph, node_ids, compound_id, sequence_id),
+        TimelineCommand::ExpandCompound { node_id } => {
+            let commands = expand_compound(graph, node_id)?;
+            apply_batch(graph, commands)
+        }
+        TimelineCommand::TrimNode {
+            node_id,
+            edge,
+            delta,
+        } => trim_node(graph, node_id, edge, delta),
+        TimelineCommand::RippleTrim {
+            node_id,
+            edge,
+            delta,
+        } => ripple_trim(graph, node_id, edge, delta),
+        TimelineCommand::RollEdit { left, right, delta } => roll_edit(graph, left, right, delta),
+        TimelineCommand::SlipClip { node_id, delta } => slip_clip(graph, node_id, delta),
+        TimelineCommand::SlideClip { node_id, delta } => slide_clip(graph, node_id, delta),
+        TimelineCommand::LinkNodes { node_ids } => link_nodes(graph, node_ids),
+        TimelineCommand::UnlinkNodes { node_ids } => unlink_nodes(graph, node_ids),
+        TimelineCommand::MoveLinked { node_id, delta } => move_linked(graph, node_id, delta),
+        TimelineCommand::RemoveLinked { node_id } => remove_linked(graph, node_id),
+        TimelineCommand::SplitNode { node_id, frame } => {
+            let commands = split_node(graph, node_id, frame)?;
+            apply_batch(graph, commands)
+        }
+        TimelineCommand::SetTimeRemap { node_id, remap } => set_time_remap(graph, node_id, remap),
+        TimelineCommand::InsertEdit { node, track_ids } => {
+            let commands = three_point_edit(graph, node, track_ids, true)?;
+            apply_batch(graph, commands)
+        }
+        TimelineCommand::OverwriteEdit { node, track_ids } => {
+            let commands = three_point_edit(graph, node, track_ids, false)?;
+            apply_batch(graph, commands)
+        }
+        TimelineCommand::LiftRange { range, track_ids } => {
+            let commands = clear_range(graph, range, track_ids, false)?;
+            apply_batch(graph, commands)
+        }
+        TimelineCommand::ExtractRange { range, track_ids } => {
+            let commands = clear_range(graph, range, track_ids, true)?;
+            apply_batch(graph, commands)
+        }
+        TimelineCommand::Batch { commands } => apply_batch(graph, commands),
+    }
+}
+
+/// Replace commands that mint fresh ids while applying (split, expand and the
+/// three-point edits) with the batch of primitive commands they expand to
+/// against `graph`. Applying the result always creates the same ids, so it
+/// is what a journal should record: later entries that target the new
+/// pieces still find them on replay. Other commands are returned unchanged.
+pub fn resolve_command(
+    graph: &TimelineGraph,
+    command: TimelineCommand,
+) -> Result<TimelineCommand, TimelineError> {
+    let commands = match command {
+        TimelineCommand::ExpandCompound { node_id } => expand_compound(graph, node_id)?,
+        TimelineCommand::SplitNode { node_id, frame } => split_node(graph, node_id, frame)?,
+        TimelineCommand::InsertEdit { node, track_ids } => {
+            three_point_edit(graph, node, track_ids, true)?
+        }
+        TimelineCommand::OverwriteEdit { node, track_ids } => {
+            three_point_edit(graph, node, track_ids, false)?
+        }
+        TimelineCommand::LiftRange { range, track_ids } => {
+            clear_range(graph, range, track_ids, false)?
+        }
+        TimelineCommand::ExtractRange { range, track_ids } => {
+            clear_range(graph, range, track_ids, true)?
+        }
+        TimelineCommand::Batch { commands } if commands.iter().any(mints_ids) => {
+            // Later commands in the batch see the state earlier ones leave.
+            let mut scratch = graph.clone();
+            let mut resolved = Vec::with_capacity(commands.len());
+            for command in commands {
+                let command = resolve_command(&scratch, command)?;
+                apply_command(&mut scratch, command.clone())?;
+                resolved.push(command);
+            }
+            resolved
+        }
+        other => return Ok(other),
+    };
+    Ok(TimelineCommand::Batch { commands })
+}
+
+fn mints_ids(command: &TimelineCommand) -> bool {
+    match command {
+        TimelineCommand::ExpandCompound { .. }
+        | TimelineCommand::SplitNode { .. }
+        | TimelineCommand::InsertEdit { .. }
+        | TimelineCommand::OverwriteEdit { .. }
+        | TimelineCommand::LiftRange { .. }
+        | TimelineCommand::ExtractRange { .. } => true,
+        TimelineCommand::Batch { commands } => commands.iter().any(mints_ids),
+        _ => false,
+    }
+}
+
+fn apply_batch(
+    graph: &mut TimelineGraph,
+    commands: Vec<TimelineCommand>,
+) -> Result<TimelineCommand, TimelineError> {
+    let mut inverses = Vec::with_capacity(commands.len());
+    for command in commands {
+        match apply_command(graph, command) {
+            Ok(inverse) => inverses.push(inverse),
+            Err(err) => {
+                // Each inverse undoes a command that just succeeded, so the
+                // rollback only fails if the graph invariants are already broken.
+                while let Some(inverse) = inverses.pop() {
+                    let _ = apply_command(graph, inverse);
+                }
+                return Err(err);
+            }
+        }
     }
+    inverses.reverse();
+    Ok(TimelineCommand::Batch { commands: inverses })
 }
 
 fn insert_node(
@@ -107,12 +467,16 @@ fn insert_node(
 
     graph.nodes.insert(node.id, node.clone());
 
+    let mut indexed = Vec::new();
     for placement in &placements {
-        if let Some(track) = graph.tracks.iter_mut().find(|t| t.id == placement.track_id) {
+        if let Some(pos) = graph.tracks.iter().position(|t| t.id == placement.track_id) {
+            let track = &mut graph.tracks[pos];
             let idx = placement.position.unwrap_or(track.node_ids.len());
             track.node_ids.insert(idx, node.id);
+            indexed.push(pos);
         }
     }
+    graph.reindex_node_on(node.id, &indexed);
 
     for edge in edges.iter() {
         graph.edges.push(edge.clone());
@@ -145,6 +509,7 @@ fn remove_node(
             }
         }
     }
+    graph.reindex_node_on(node_id, &[]);
 
     let mut edges = Vec::new();
     let mut idx = 0;
@@ -172,6 +537,7 @@ fn update_node(
     if let Some(entry) = graph.nodes.get_mut(&node_id) {
         let previous = entry.clone();
         *entry = node;
+        graph.respan_node(node_id);
         Ok(TimelineCommand::UpdateNode { node: previous })
     } else {
         Err(TimelineError::NodeNotFound(node_id))
@@ -221,9 +587,11 @@ fn upsert_track(
 ) -> Result<TimelineCommand, TimelineError> {
     if let Some(idx) = graph.tracks.iter().position(|t| t.id == track.id) {
         let previous = std::mem::replace(&mut graph.tracks[idx], track);
+        graph.invalidate_index();
         Ok(TimelineCommand::UpsertTrack { track: previous })
     } else {
         graph.tracks.push(track.clone());
+        graph.invalidate_index();
         Ok(TimelineCommand::RemoveTrack { track_id: track.id })
     }
 }
@@ -234,6 +602,7 @@ fn remove_track(
 ) -> Result<TimelineCommand, TimelineError> {
     if let Some(idx) = graph.tracks.iter().position(|t| t.id == track_id) {
         let track = graph.tracks.remove(idx);
+        graph.invalidate_index();
         Ok(TimelineCommand::UpsertTrack { track })
     } else {
         Err(TimelineError::TrackNotFound(track_id))
@@ -253,12 +622,30 @@ fn move_track(
     let track = graph.tracks.remove(current);
     let target = std::cmp::min(index, graph.tracks.len());
     graph.tracks.insert(target, track);
+    graph.invalidate_index();
     Ok(TimelineCommand::MoveTrack {
         track_id,
         index: current,
     })
 }
 
+fn set_track_properties(
+    graph: &mut TimelineGraph,
+    track_id: TrackId,
+    properties: TrackProperties,
+) -> Result<TimelineCommand, TimelineError> {
+    let track = graph
+        .tracks
+        .iter_mut()
+        .find(|t| t.id == track_id)
+        .ok_or(TimelineError::TrackNotFound(track_id))?;
+    let previous = std::mem::replace(&mut track.properties, properties);
+    Ok(TimelineCommand::SetTrackProperties {
+        track_id,
+        properties: previous,
+    })
+}
+
 fn add_lane(
     graph: &mut TimelineGraph,
     lane: AutomationLane,
@@ -361,107 +748,1411 @@ fn remove_keyframe(
     }
 }
 
-fn validate_placements(
-    graph: &TimelineGraph,
-    placements: &[TrackPlacement],
-) -> Result<(), TimelineError> {
-    for placement in placements {
-        let track = graph
-            .tracks
+fn clip_markers_mut(
+    graph: &mut TimelineGraph,
+    node_id: NodeId,
+) -> Result<&mut Vec<Marker>, TimelineError> {
+    let node = graph
+        .nodes
+        .get_mut(&node_id)
+        .ok_or(TimelineError::NodeNotFound(node_id))?;
+    match &mut node.kind {
+        TimelineNodeKind::Clip(clip) => Ok(&mut clip.markers),
+        _ => Err(TimelineError::InvalidOp(format!(
+            "markers can only be placed on clips: {}",
+            node_id
+        ))),
+    }
+}
+
+fn add_marker(markers: &mut Vec<Marker>, marker: Marker) -> Result<(), TimelineError> {
+    if markers.iter().any(|m| m.id == marker.id) {
+        return Err(TimelineError::InvalidOp(format!(
+            "marker exists: {}",
+            marker.id
+        )));
+    }
+    if marker.duration.is_some_and(|d| d < 1) {
+        return Err(TimelineError::InvalidOp(
+            "marker duration must be at least one frame".to_string(),
+        ));
+    }
+    markers.push(marker);
+    markers.sort_by_key(|m| m.frame);
+    Ok(())
+}
+
+fn update_marker(markers: &mut [Marker], marker: Marker) -> Result<Marker, TimelineError> {
+    let idx = markers
+        .iter()
+        .position(|m| m.id == marker.id)
+        .ok_or(TimelineError::MarkerNotFound(marker.id))?;
+    let previous = std::mem::replace(&mut markers[idx], marker);
+    markers.sort_by_key(|m| m.frame);
+    Ok(previous)
+}
+
+fn remove_marker(markers: &mut Vec<Marker>, marker_id: MarkerId) -> Result<Marker, TimelineError> {
+    let idx = markers
+        .iter()
+        .position(|m| m.id == marker_id)
+        .ok_or(TimelineError::MarkerNotFound(marker_id))?;
+    Ok(markers.remove(idx))
+}
+
+fn upsert_sequence(
+    graph: &mut TimelineGraph,
+    sequence_id: String,
+    inner: TimelineGraph,
+) -> TimelineCommand {
+    match graph.sequences.insert(sequence_id.clone(), inner) {
+        Some(previous) => TimelineCommand::UpsertSequence {
+            sequence_id,
+            graph: Box::new(previous),
+        },
+        None => TimelineCommand::RemoveSequence { sequence_id },
+    }
+}
+
+fn remove_sequence(
+    graph: &mut TimelineGraph,
+    sequence_id: String,
+) -> Result<TimelineCommand, TimelineError> {
+    let in_use = std::iter::once(&*graph)
+        .chain(graph.sequences.values())
+        .flat_map(|g| g.nodes.values())
+        .any(|n| matches!(&n.kind, TimelineNodeKind::Compound(c) if c.sequence_id == sequence_id));
+    if in_use {
+        return Err(TimelineError::InvalidOp(format!(
+            "sequence {} is still used by a compound clip",
+            sequence_id
+        )));
+    }
+    let inner = graph
+        .sequences
+        .remove(&sequence_id)
+        .ok_or_else(|| TimelineError::SequenceNotFound(sequence_id.clone()))?;
+    Ok(TimelineCommand::UpsertSequence {
+        sequence_id,
+        graph: Box::new(inner),
+    })
+}
+
+/// Collapse is expressed as a batch of primitive commands so its inverse is
+/// exact: store the nested graph, lift the nodes and their automation out of
+/// the host graph, then insert the compound clip on the topmost track.
+fn collapse_to_compound(
+    graph: &mut TimelineGraph,
+    node_ids: Vec<NodeId>,
+    compound_id: NodeId,
+    sequence_id: String,
+) -> Result<TimelineCommand, TimelineError> {
+    if node_ids.is_empty() {
+        return Err(TimelineError::InvalidOp(
+            "nothing selected to collapse".to_string(),
+        ));
+    }
+    if graph.sequences.contains_key(&sequence_id) {
+        return Err(TimelineError::InvalidOp(format!(
+            "sequence exists: {}",
+            sequence_id
+        )));
+    }
+    let mut span_start = Frame::MAX;
+    let mut span_end = Frame::MIN;
+    for &node_id in &node_ids {
+        let range = timeline_range_of(&editable_node(graph, node_id)?)?;
+        track_of(graph, node_id)?;
+        span_start = span_start.min(range.start);
+        span_end = span_end.max(range.end());
+    }
+
+    let mut inner = TimelineGraph {
+        version: graph.version,
+        ..TimelineGraph::default()
+    };
+    let mut host_track = None;
+    for binding in &graph.tracks {
+        let ids: Vec<NodeId> = binding
+            .node_ids
             .iter()
-            .find(|t| t.id == placement.track_id)
-            .ok_or(TimelineError::TrackNotFound(placement.track_id))?;
-        if let Some(position) = placement.position {
-            if position > track.node_ids.len() {
-                return Err(TimelineError::InvalidOp(format!(
-                    "placement index {} out of bounds for track {}",
-                    position, track.id
-                )));
+            .filter(|id| node_ids.contains(id))
+            .copied()
+            .collect();
+        if ids.is_empty() {
+            continue;
+        }
+        host_track.get_or_insert(binding.id);
+        for id in &ids {
+            let mut node = graph.nodes[id].clone();
+            shift_node(&mut node, -span_start);
+            inner.nodes.insert(*id, node);
+        }
+        inner.tracks.push(TrackBinding {
+            id: binding.id,
+            name: binding.name.clone(),
+            kind: binding.kind.clone(),
+            node_ids: ids,
+            properties: binding.properties.clone(),
+        });
+    }
+    inner.edges = graph
+        .edges
+        .iter()
+        .filter(|e| inner.nodes.contains_key(&e.from) && inner.nodes.contains_key(&e.to))
+        .cloned()
+        .collect();
+
+    let mut commands = Vec::new();
+    for lane in &graph.automation {
+        if inner.nodes.contains_key(&lane.target.node) {
+            let mut moved = lane.clone();
+            for keyframe in &mut moved.keyframes {
+                keyframe.frame -= span_start;
             }
+            inner.automation.push(moved);
+            commands.push(TimelineCommand::RemoveAutomationLane { lane_id: lane.id });
         }
     }
-    Ok(())
+    commands.insert(
+        0,
+        TimelineCommand::UpsertSequence {
+            sequence_id: sequence_id.clone(),
+            graph: Box::new(inner),
+        },
+    );
+    commands.extend(
+        node_ids
+            .iter()
+            .map(|&node_id| TimelineCommand::RemoveNode { node_id }),
+    );
+
+    let span = FrameRange::new(span_start, span_end - span_start);
+    if let Some(track_id) = host_track {
+        ensure_free(graph, track_id, &span, &node_ids)?;
+    }
+    let compound = TimelineNode {
+        id: compound_id,
+        label: Some(sequence_id.clone()),
+        kind: TimelineNodeKind::Compound(CompoundNode {
+            sequence_id,
+            media_range: FrameRange::new(0, span.duration),
+            timeline_range: span,
+            metadata: Value::Null,
+        }),
+        locked: false,
+        metadata: Value::Null,
+    };
+    commands.push(TimelineCommand::InsertNode {
+        node: compound,
+        placements: host_track
+            .map(|track_id| TrackPlacement {
+                track_id,
+                position: None,
+            })
+            .into_iter()
+            .collect(),
+        edges: Vec::new(),
+    });
+    apply_batch(graph, commands)
 }
 
-fn validate_edges_for_insert(
+/// Expanded nodes get fresh ids so a nested sequence can be expanded more than
+/// once. Nodes are trimmed to the compound's media window; inner tracks that no
+/// longer exist in the host graph are recreated with their properties.
+fn expand_compound(
     graph: &TimelineGraph,
-    edges: &[TimelineEdge],
-    new_node: NodeId,
-) -> Result<(), TimelineError> {
-    for edge in edges {
-        if edge.from != new_node && !graph.nodes.contains_key(&edge.from) {
-            return Err(TimelineError::NodeNotFound(edge.from));
+    node_id: NodeId,
+) -> Result<Vec<TimelineCommand>, TimelineError> {
+    let node = editable_node(graph, node_id)?;
+    let TimelineNodeKind::Compound(compound) = &node.kind else {
+        return Err(TimelineError::InvalidOp(format!(
+            "node {} is not a compound clip",
+            node_id
+        )));
+    };
+    track_of(graph, node_id)?;
+    let inner = graph
+        .sequences
+        .get(&compound.sequence_id)
+        .ok_or_else(|| TimelineError::SequenceNotFound(compound.sequence_id.clone()))?;
+    let window = &compound.media_range;
+    let offset = compound.timeline_range.start - window.start;
+
+    let mut commands = vec![TimelineCommand::RemoveNode { node_id }];
+    let mut new_ids: HashMap<NodeId, NodeId> = HashMap::new();
+    for binding in &inner.tracks {
+        let track_id = if graph.tracks.iter().any(|t| t.id == binding.id) {
+            binding.id
+        } else {
+            let track = TrackBinding {
+                id: TrackId::new(),
+                name: binding.name.clone(),
+                kind: binding.kind.clone(),
+                node_ids: Vec::new(),
+                properties: binding.properties.clone(),
+            };
+            let track_id = track.id;
+            commands.push(TimelineCommand::UpsertTrack { track });
+            track_id
+        };
+        for id in &binding.node_ids {
+            let Some(inner_node) = inner.nodes.get(id) else {
+                continue;
+            };
+            if new_ids.contains_key(id) {
+                continue;
+            }
+            let mut copy = inner_node.clone();
+            if let Some(range) = copy.timeline_range().cloned() {
+                let start = range.start.max(window.start);
+                let end = range.end().min(window.end());
+                if end <= start {
+                    continue;
+                }
+                adjust_edges(&mut copy, start - range.start, end - range.end())?;
+                shift_node(&mut copy, offset);
+                if let Some(range) = copy.timeline_range() {
+                    ensure_free(graph, track_id, range, &[node_id])?;
+                }
+            }
+            copy.id = NodeId::new();
+            new_ids.insert(*id, copy.id);
+            commands.push(TimelineCommand::InsertNode {
+                node: copy,
+                placements: vec![TrackPlacement {
+                    track_id,
+                    position: None,
+                }],
+                edges: Vec::new(),
+            });
         }
-        if edge.to != new_node && !graph.nodes.contains_key(&edge.to) {
-            return Err(TimelineError::NodeNotFound(edge.to));
+    }
+    for edge in &inner.edges {
+        if let (Some(&from), Some(&to)) = (new_ids.get(&edge.from), new_ids.get(&edge.to)) {
+            commands.push(TimelineCommand::AddEdge {
+                edge: TimelineEdge {
+                    from,
+                    to,
+                    kind: edge.kind.clone(),
+                },
+            });
         }
-        if graph
-            .edges
-            .iter()
-            .any(|e| e.from == edge.from && e.to == edge.to && e.kind == edge.kind)
-        {
-            return Err(TimelineError::EdgeExists(edge.from, edge.to));
+    }
+    for lane in &inner.automation {
+        if let Some(&target) = new_ids.get(&lane.target.node) {
+            let mut copy = lane.clone();
+            copy.id = LaneId::new();
+            copy.target.node = target;
+            for keyframe in &mut copy.keyframes {
+                keyframe.frame += offset;
+            }
+            commands.push(TimelineCommand::AddAutomationLane { lane: copy });
         }
     }
-    Ok(())
+    Ok(commands)
 }
 
-#[derive(Debug, Default, Clone)]
-pub struct CommandHistory {
-    undo_stack: Vec<TimelineCommand>,
-    redo_stack: Vec<TimelineCommand>,
+fn editable_node(graph: &TimelineGraph, node_id: NodeId) -> Result<TimelineNode, TimelineError> {
+    let node = graph
+        .nodes
+        .get(&node_id)
+        .ok_or(TimelineError::NodeNotFound(node_id))?;
+    ensure_unlocked(graph, node)?;
+    Ok(node.clone())
 }
 
-impl CommandHistory {
-    pub fn apply(
-        &mut self,
-        graph: &mut TimelineGraph,
-        command: TimelineCommand,
-    ) -> Result<(), TimelineError> {
-        let inverse = apply_command(graph, command)?;
-        self.undo_stack.push(inverse);
-        self.redo_stack.clear();
-        Ok(())
+/// Fails if the node or any track holding it is locked.
+fn ensure_unlocked(graph: &TimelineGraph, node: &TimelineNode) -> Result<(), TimelineError> {
+    if node.locked {
+        return Err(TimelineError::NodeLocked(node.id));
     }
-
-    pub fn undo(&mut self, graph: &mut TimelineGraph) -> Result<(), TimelineError> {
-        let command = self
-            .undo_stack
-            .pop()
-            .ok_or(TimelineError::HistoryEmpty("undo stack"))?;
-        let inverse = apply_command(graph, command)?;
-        self.redo_stack.push(inverse);
-        Ok(())
+    match graph
+        .tracks
+        .iter()
+        .find(|t| t.properties.locked && t.node_ids.contains(&node.id))
+    {
+        Some(track) => Err(TimelineError::TrackLocked(track.id)),
+        None => Ok(()),
     }
+}
 
-    pub fn redo(&mut self, graph: &mut TimelineGraph) -> Result<(), TimelineError> {
-        let command = self
-            .redo_stack
-            .pop()
-            .ok_or(TimelineError::HistoryEmpty("redo stack"))?;
-        let inverse = apply_command(graph, command)?;
-        self.undo_stack.push(inverse);
-        Ok(())
+/// Fails if a node on `track_id`, other than those in `ignore`, overlaps
+/// `range`. Tracks that do not exist yet are free.
+fn ensure_free(
+    graph: &TimelineGraph,
+    track_id: TrackId,
+    range: &FrameRange,
+    ignore: &[NodeId],
+) -> Result<(), TimelineError> {
+    let Some(track) = graph.tracks.iter().find(|t| t.id == track_id) else {
+        return Ok(());
+    };
+    for id in track.node_ids.iter().filter(|id| !ignore.contains(id)) {
+        let Some(other) = graph.nodes.get(id).and_then(|n| n.timeline_range()) else {
+            continue;
+        };
+        if other.start < range.end() && range.start < other.end() {
+            return Err(TimelineError::InvalidOp(format!(
+                "node {} is in the way on track {}",
+                id, track.name
+            )));
+        }
     }
+    Ok(())
+}
 
-    pub fn clear(&mut self) {
-        self.undo_stack.clear();
-        self.redo_stack.clear();
-    }
+fn timeline_range_of(node: &TimelineNode) -> Result<FrameRange, TimelineError> {
+    node.timeline_range()
+        .cloned()
+        .ok_or_else(|| TimelineError::InvalidOp(format!("node {} has no timeline range", node.id)))
 }
 
-pub fn migrate_sequence_tracks(sequence: &crate::Sequence) -> TimelineGraph {
-    let mut result = TimelineGraph::default();
-    result.version = sequence.graph.version;
-    if sequence.graph.metadata != Value::Null {
-        result.metadata = sequence.graph.metadata.clone();
-    }
+fn track_of(graph: &TimelineGraph, node_id: NodeId) -> Result<&TrackBinding, TimelineError> {
+    graph
+        .track_for_node(node_id)
+        .ok_or_else(|| TimelineError::InvalidOp(format!("node {} is not on a track", node_id)))
+}
 
-    for (track_index, legacy_track) in sequence.tracks.iter().enumerate() {
-        let track_id = TrackId::new();
-        let mut binding = TrackBinding {
-            id: track_id,
-            name: legacy_track.name.clone(),
+/// Move the head and/or tail of a node. Media ranges follow the timeline at
+/// the clip's playback rate, from the tail of the media when it plays in
+/// reverse, so every remaining frame keeps showing the same media.
+fn adjust_edges(
+    node: &mut TimelineNode,
+    start_delta: Frame,
+    end_delta: Frame,
+) -> Result<(), TimelineError> {
+    let node_id = node.id;
+    let range = node.timeline_range_mut().ok_or_else(|| {
+        TimelineError::InvalidOp(format!("node {} has no timeline range", node_id))
+    })?;
+    let duration = range.duration - start_delta + end_delta;
+    if duration < 1 {
+        return Err(TimelineError::InvalidOp(format!(
+            "edit would leave node {} without duration",
+            node_id
+        )));
+    }
+    range.start += start_delta;
+    range.duration = duration;
+    let (mut media_start_delta, mut media_end_delta) = (start_delta, end_delta);
+    if let TimelineNodeKind::Clip(clip) = &mut node.kind {
+        if let Some(remap) = &mut clip.time_remap {
+            // Keep every remaining frame on the media it showed before.
+            remap.shift_frames(-start_delta);
+            clip.media_range = remap.media_range(duration);
+            if clip.media_range.start < 0 {
+                return Err(TimelineError::InvalidOp(format!(
+                    "edit would move node {} before the start of its media",
+                    node_id
+                )));
+            }
+            return Ok(());
+        }
+        let rate = clip.playback_rate as f64;
+        let scale = |delta: Frame| (delta as f64 * rate).round() as Frame;
+        (media_start_delta, media_end_delta) = if clip.reverse {
+            // Reversed clips read their head from the tail of the media.
+            (-scale(end_delta), -scale(start_delta))
+        } else {
+            (scale(start_delta), scale(end_delta))
+        };
+    }
+    if let Some(media) = node.media_range_mut() {
+        media.start += media_start_delta;
+        media.duration += media_end_delta - media_start_delta;
+        if media.start < 0 {
+            return Err(TimelineError::InvalidOp(format!(
+                "edit would move node {} before the start of its media",
+                node_id
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn shift_node(node: &mut TimelineNode, delta: Frame) {
+    if let Some(range) = node.timeline_range_mut() {
+        range.start += delta;
+    }
+}
+
+fn commit_nodes(graph: &mut TimelineGraph, nodes: Vec<TimelineNode>) {
+    for node in nodes {
+        let node_id = node.id;
+        graph.nodes.insert(node_id, node);
+        graph.respan_node(node_id);
+    }
+}
+
+/// `node_id` followed by one node of its link group from every other track
+/// the group sits on. Trims, rolls and slides repeat on these so linked
+/// clips stay in sync.
+fn track_partners(graph: &TimelineGraph, node_id: NodeId) -> Result<Vec<NodeId>, TimelineError> {
+    let mut tracks = vec![track_of(graph, node_id)?.id];
+    let mut ids = vec![node_id];
+    for id in graph.linked_nodes(node_id) {
+        let Some(track) = graph.track_for_node(id) else {
+            continue;
+        };
+        if !tracks.contains(&track.id) {
+            tracks.push(track.id);
+            ids.push(id);
+        }
+    }
+    Ok(ids)
+}
+
+fn trim_node(
+    graph: &mut TimelineGraph,
+    node_id: NodeId,
+    edge: TrimEdge,
+    delta: Frame,
+) -> Result<TimelineCommand, TimelineError> {
+    let mut updates = Vec::new();
+    for id in track_partners(graph, node_id)? {
+        let mut node = editable_node(graph, id)?;
+        match edge {
+            TrimEdge::Start => adjust_edges(&mut node, delta, 0)?,
+            TrimEdge::End => adjust_edges(&mut node, 0, delta)?,
+        }
+        let range = timeline_range_of(&node)?;
+        if range.start < 0 {
+            return Err(TimelineError::InvalidOp(format!(
+                "trim would move node {} before frame 0",
+                id
+            )));
+        }
+        ensure_free(graph, track_of(graph, id)?.id, &range, &[id])?;
+        updates.push(node);
+    }
+
+    commit_nodes(graph, updates);
+    Ok(TimelineCommand::TrimNode {
+        node_id,
+        edge,
+        delta: -delta,
+    })
+}
+
+fn ripple_trim(
+    graph: &mut TimelineGraph,
+    node_id: NodeId,
+    edge: TrimEdge,
+    delta: Frame,
+) -> Result<TimelineCommand, TimelineError> {
+    let mut updates = Vec::new();
+    for id in track_partners(graph, node_id)? {
+        updates.extend(ripple_trim_nodes(graph, id, edge, delta)?);
+    }
+    commit_nodes(graph, updates);
+    Ok(TimelineCommand::RippleTrim {
+        node_id,
+        edge,
+        delta: -delta,
+    })
+}
+
+/// The node trimmed and the rest of its track shifted, for one track of a
+/// ripple trim.
+fn ripple_trim_nodes(
+    graph: &TimelineGraph,
+    node_id: NodeId,
+    edge: TrimEdge,
+    delta: Frame,
+) -> Result<Vec<TimelineNode>, TimelineError> {
+    let mut node = editable_node(graph, node_id)?;
+    let range = timeline_range_of(&node)?;
+    let track = track_of(graph, node_id)?;
+
+    // A ripple keeps the node's start fixed; only its length changes.
+    let shift = match edge {
+        TrimEdge::Start => {
+            adjust_edges(&mut node, delta, 0)?;
+            shift_node(&mut node, -delta);
+            -delta
+        }
+        TrimEdge::End => {
+            adjust_edges(&mut node, 0, delta)?;
+            delta
+        }
+    };
+
+    let mut updates = vec![node];
+    for other_id in &track.node_ids {
+        if *other_id == node_id {
+            continue;
+        }
+        let Some(other) = graph.nodes.get(other_id) else {
+            continue;
+        };
+        match other.timeline_range() {
+            Some(r) if r.start >= range.end() => {}
+            _ => continue,
+        }
+        ensure_unlocked(graph, other)?;
+        let mut moved = other.clone();
+        shift_node(&mut moved, shift);
+        updates.push(moved);
+    }
+    Ok(updates)
+}
+
+/// Linked partners of `left` that meet a partner of `right` at an edit point
+/// of their own roll with it.
+fn roll_edit(
+    graph: &mut TimelineGraph,
+    left_id: NodeId,
+    right_id: NodeId,
+    delta: Frame,
+) -> Result<TimelineCommand, TimelineError> {
+    let mut updates = roll_nodes(graph, left_id, right_id, delta)?;
+    let right_group = link_group(graph, right_id)?;
+    for left in track_partners(graph, left_id)?.into_iter().skip(1) {
+        let Some(edit) = graph.nodes.get(&left).and_then(|n| n.timeline_range()) else {
+            continue;
+        };
+        let right = track_of(graph, left)?.node_ids.iter().copied().find(|id| {
+            right_group.contains(id)
+                && graph
+                    .nodes
+                    .get(id)
+                    .and_then(|n| n.timeline_range())
+                    .is_some_and(|r| r.start == edit.end())
+        });
+        if let Some(right) = right {
+            updates.extend(roll_nodes(graph, left, right, delta)?);
+        }
+    }
+    commit_nodes(graph, updates);
+    Ok(TimelineCommand::RollEdit {
+        left: left_id,
+        right: right_id,
+        delta: -delta,
+    })
+}
+
+fn roll_nodes(
+    graph: &TimelineGraph,
+    left_id: NodeId,
+    right_id: NodeId,
+    delta: Frame,
+) -> Result<Vec<TimelineNode>, TimelineError> {
+    let mut left = editable_node(graph, left_id)?;
+    let mut right = editable_node(graph, right_id)?;
+    let left_range = timeline_range_of(&left)?;
+    let right_range = timeline_range_of(&right)?;
+    if track_of(graph, left_id)?.id != track_of(graph, right_id)?.id
+        || left_range.end() != right_range.start
+    {
+        return Err(TimelineError::InvalidOp(format!(
+            "nodes {} and {} do not share an edit point",
+            left_id, right_id
+        )));
+    }
+
+    adjust_edges(&mut left, 0, delta)?;
+    adjust_edges(&mut right, delta, 0)?;
+    Ok(vec![left, right])
+}
+
+fn slip_clip(
+    graph: &mut TimelineGraph,
+    node_id: NodeId,
+    delta: Frame,
+) -> Result<TimelineCommand, TimelineError> {
+    let mut updates = Vec::new();
+    for id in link_group(graph, node_id)? {
+        let mut node = editable_node(graph, id)?;
+        if let TimelineNodeKind::Clip(clip) = &mut node.kind {
+            if let Some(remap) = &mut clip.time_remap {
+                remap.shift_media(delta as f64);
+            }
+        }
+        let Some(media) = node.media_range_mut() else {
+            // Partners without media, such as generators, have nothing to slip.
+            if id != node_id {
+                continue;
+            }
+            return Err(TimelineError::InvalidOp(format!(
+                "node {} has no media to slip",
+                node_id
+            )));
+        };
+        media.start += delta;
+        if media.start < 0 {
+            return Err(TimelineError::InvalidOp(format!(
+                "slip would move node {} before the start of its media",
+                id
+            )));
+        }
+        updates.push(node);
+    }
+    commit_nodes(graph, updates);
+    Ok(TimelineCommand::SlipClip {
+        node_id,
+        delta: -delta,
+    })
+}
+
+fn slide_clip(
+    graph: &mut TimelineGraph,
+    node_id: NodeId,
+    delta: Frame,
+) -> Result<TimelineCommand, TimelineError> {
+    let mut updates = Vec::new();
+    for id in track_partners(graph, node_id)? {
+        updates.extend(slide_nodes(graph, id, delta)?);
+    }
+    commit_nodes(graph, updates);
+    Ok(TimelineCommand::SlideClip {
+        node_id,
+        delta: -delta,
+    })
+}
+
+/// The node moved and its neighbours trimmed, for one track of a slide.
+fn slide_nodes(
+    graph: &TimelineGraph,
+    node_id: NodeId,
+    delta: Frame,
+) -> Result<Vec<TimelineNode>, TimelineError> {
+    let mut node = editable_node(graph, node_id)?;
+    let range = timeline_range_of(&node)?;
+    let track = track_of(graph, node_id)?;
+    if range.start + delta < 0 {
+        return Err(TimelineError::InvalidOp(format!(
+            "slide would move node {} before frame 0",
+            node_id
+        )));
+    }
+    let slid = FrameRange::new(range.start + delta, range.duration);
+
+    let mut previous: Option<TimelineNode> = None;
+    let mut next: Option<TimelineNode> = None;
+    for other_id in &track.node_ids {
+        if *other_id == node_id {
+            continue;
+        }
+        let Some(other) = graph.nodes.get(other_id) else {
+            continue;
+        };
+        let Some(r) = other.timeline_range() else {
+            continue;
+        };
+        if r.end() == range.start && previous.is_none() {
+            previous = Some(other.clone());
+        } else if r.start == range.end() && next.is_none() {
+            next = Some(other.clone());
+        } else if (r.start < slid.end() && r.end() > slid.start)
+            || r.end() == slid.start
+            || r.start == slid.end()
+        {
+            // Touching a new neighbour would make the inverse slide trim it.
+            return Err(TimelineError::InvalidOp(format!(
+                "slide would collide node {} with {}",
+                node_id, other_id
+            )));
+        }
+    }
+
+    shift_node(&mut node, delta);
+    let mut updates = vec![node];
+    if let Some(mut prev) = previous {
+        ensure_unlocked(graph, &prev)?;
+        adjust_edges(&mut prev, 0, delta)?;
+        updates.push(prev);
+    }
+    if let Some(mut following) = next {
+        ensure_unlocked(graph, &following)?;
+        adjust_edges(&mut following, delta, 0)?;
+        updates.push(following);
+    }
+    Ok(updates)
+}
+
+/// The node followed by every node linked to it.
+fn link_group(graph: &TimelineGraph, node_id: NodeId) -> Result<Vec<NodeId>, TimelineError> {
+    if !graph.nodes.contains_key(&node_id) {
+        return Err(TimelineError::NodeNotFound(node_id));
+    }
+    let mut group = vec![node_id];
+    group.extend(graph.linked_nodes(node_id));
+    Ok(group)
+}
+
+fn link_nodes(
+    graph: &mut TimelineGraph,
+    node_ids: Vec<NodeId>,
+) -> Result<TimelineCommand, TimelineError> {
+    let Some(&anchor) = node_ids.first() else {
+        return Err(TimelineError::InvalidOp("nothing to link".to_string()));
+    };
+    // Each new edge hangs off the anchor, so unlinking any one node later
+    // leaves the rest of the group connected.
+    let mut group = link_group(graph, anchor)?;
+    let mut commands = Vec::new();
+    for &node_id in &node_ids[1..] {
+        if group.contains(&node_id) {
+            continue;
+        }
+        group.extend(link_group(graph, node_id)?);
+        commands.push(TimelineCommand::AddEdge {
+            edge: TimelineEdge {
+                from: anchor,
+                to: node_id,
+                kind: EdgeKind::Link,
+            },
+        });
+    }
+    apply_batch(graph, commands)
+}
+
+fn unlink_nodes(
+    graph: &mut TimelineGraph,
+    node_ids: Vec<NodeId>,
+) -> Result<TimelineCommand, TimelineError> {
+    // The rest of each group stays linked, so note who was together before
+    // the edges of the detached nodes go.
+    let mut groups: Vec<Vec<NodeId>> = Vec::new();
+    for node_id in &node_ids {
+        if groups.iter().any(|g| g.contains(node_id)) {
+            continue;
+        }
+        groups.push(link_group(graph, *node_id)?);
+    }
+    let removals = graph
+        .edges
+        .iter()
+        .filter(|e| {
+            e.kind == EdgeKind::Link && (node_ids.contains(&e.from) || node_ids.contains(&e.to))
+        })
+        .map(|edge| TimelineCommand::RemoveEdge { edge: edge.clone() })
+        .collect();
+    let removed = apply_batch(graph, removals)?;
+
+    let mut relinks = Vec::new();
+    for group in groups {
+        let rest: Vec<NodeId> = group
+            .into_iter()
+            .filter(|id| !node_ids.contains(id))
+            .collect();
+        let Some(&anchor) = rest.first() else {
+            continue;
+        };
+        let mut joined = link_group(graph, anchor)?;
+        for &node_id in &rest[1..] {
+            if joined.contains(&node_id) {
+                continue;
+            }
+            joined.extend(link_group(graph, node_id)?);
+            relinks.push(TimelineCommand::AddEdge {
+                edge: TimelineEdge {
+                    from: anchor,
+                    to: node_id,
+                    kind: EdgeKind::Link,
+                },
+            });
+        }
+    }
+    match apply_batch(graph, relinks) {
+        Ok(relinked) => Ok(TimelineCommand::Batch {
+            commands: vec![relinked, removed],
+        }),
+        Err(err) => {
+            let _ = apply_command(graph, removed);
+            Err(err)
+        }
+    }
+}
+
+fn move_linked(
+    graph: &mut TimelineGraph,
+    node_id: NodeId,
+    delta: Frame,
+) -> Result<TimelineCommand, TimelineError> {
+    timeline_range_of(&editable_node(graph, node_id)?)?;
+    let mut commands = Vec::new();
+    for id in link_group(graph, node_id)? {
+        let mut node = editable_node(graph, id)?;
+        let Some(range) = node.timeline_range() else {
+            continue;
+        };
+        if range.start + delta < 0 {
+            return Err(TimelineError::InvalidOp(format!(
+                "move would put node {} before frame 0",
+                id
+            )));
+        }
+        shift_node(&mut node, delta);
+        commands.push(TimelineCommand::UpdateNode { node });
+    }
+    apply_batch(graph, commands)
+}
+
+fn remove_linked(
+    graph: &mut TimelineGraph,
+    node_id: NodeId,
+) -> Result<TimelineCommand, TimelineError> {
+    let mut commands = Vec::new();
+    for id in link_group(graph, node_id)? {
+        editable_node(graph, id)?;
+        commands.push(TimelineCommand::RemoveNode { node_id: id });
+    }
+    apply_batch(graph, commands)
+}
+
+/// Partners that do not span `frame` are left whole. Each right-hand piece is
+/// placed directly after its left half on every track the original sits on.
+fn split_node(
+    graph: &TimelineGraph,
+    node_id: NodeId,
+    frame: Frame,
+) -> Result<Vec<TimelineCommand>, TimelineError> {
+    let range = timeline_range_of(&editable_node(graph, node_id)?)?;
+    if frame <= range.start || frame >= range.end() {
+        return Err(TimelineError::InvalidOp(format!(
+            "frame {} is not inside node {}",
+            frame, node_id
+        )));
+    }
+
+    let mut commands = Vec::new();
+    let mut anchor: Option<NodeId> = None;
+    for id in link_group(graph, node_id)? {
+        let Some(range) = graph
+            .nodes
+            .get(&id)
+            .and_then(|n| n.timeline_range())
+            .cloned()
+        else {
+            continue;
+        };
+        if frame <= range.start || frame >= range.end() {
+            continue;
+        }
+        let node = editable_node(graph, id)?;
+        let mut left = node.clone();
+        adjust_edges(&mut left, 0, frame - range.end())?;
+        let mut right = node;
+        adjust_edges(&mut right, frame - range.start, 0)?;
+        right.id = NodeId::new();
+
+        let placements = graph
+            .tracks
+            .iter()
+            .filter_map(|track| {
+                let position = track.node_ids.iter().position(|n| *n == id)?;
+                Some(TrackPlacement {
+                    track_id: track.id,
+                    position: Some(position + 1),
+                })
+            })
+            .collect();
+        let edges = anchor
+            .map(|from| TimelineEdge {
+                from,
+                to: right.id,
+                kind: EdgeKind::Link,
+            })
+            .into_iter()
+            .collect();
+        anchor.get_or_insert(right.id);
+        commands.push(TimelineCommand::UpdateNode { node: left });
+        commands.push(TimelineCommand::InsertNode {
+            node: right,
+            placements,
+            edges,
+        });
+    }
+    Ok(commands)
+}
+
+fn set_time_remap(
+    graph: &mut TimelineGraph,
+    node_id: NodeId,
+    remap: Option<TimeRemap>,
+) -> Result<TimelineCommand, TimelineError> {
+    let mut node = editable_node(graph, node_id)?;
+    let TimelineNodeKind::Clip(clip) = &mut node.kind else {
+        return Err(TimelineError::InvalidOp(format!(
+            "node {} is not a clip",
+            node_id
+        )));
+    };
+    if remap.as_ref().is_some_and(|r| r.keyframes.len() < 2) {
+        return Err(TimelineError::InvalidOp(
+            "time remap needs at least two keyframes".to_string(),
+        ));
+    }
+    clip.set_time_remap(remap);
+    if clip.media_range.start < 0 {
+        return Err(TimelineError::InvalidOp(format!(
+            "time remap would read node {} before the start of its media",
+            node_id
+        )));
+    }
+    update_node(graph, node)
+}
+
+fn target_tracks(
+    graph: &TimelineGraph,
+    track_ids: &[TrackId],
+) -> Result<Vec<TrackBinding>, TimelineError> {
+    if track_ids.is_empty() {
+        return Err(TimelineError::InvalidOp("no target tracks".to_string()));
+    }
+    let mut seen = Vec::new();
+    track_ids
+        .iter()
+        .filter(|id| {
+            let first = !seen.contains(*id);
+            seen.push(**id);
+            first
+        })
+        .map(|id| {
+            let track = graph
+                .tracks
+                .iter()
+                .find(|t| t.id == *id)
+                .ok_or(TimelineError::TrackNotFound(*id))?;
+            if track.properties.locked {
+                return Err(TimelineError::TrackLocked(*id));
+            }
+            Ok(track.clone())
+        })
+        .collect()
+}
+
+/// Target tracks of a rippling edit from `from` onwards, plus every other
+/// track holding a node linked to one the ripple cuts or moves, so link
+/// groups stay in sync. Target tracks ripple as a whole; on the added tracks
+/// only the linked partners do, and they come paired with their ids.
+fn ripple_tracks(
+    graph: &TimelineGraph,
+    track_ids: &[TrackId],
+    from: Frame,
+) -> Result<Vec<CutTrack>, TimelineError> {
+    let targets = target_tracks(graph, track_ids)?;
+    let mut partners: Vec<(TrackBinding, Vec<NodeId>)> = Vec::new();
+    for track in &targets {
+        let moved = track.node_ids.iter().filter(|id| {
+            graph
+                .nodes
+                .get(id)
+                .and_then(|n| n.timeline_range())
+                .is_some_and(|r| r.end() > from)
+        });
+        for id in moved {
+            for partner in graph.linked_nodes(*id) {
+                for other in &graph.tracks {
+                    if targets.iter().any(|t| t.id == other.id)
+                        || !other.node_ids.contains(&partner)
+                    {
+                        continue;
+                    }
+                    if other.properties.locked {
+                        return Err(TimelineError::TrackLocked(other.id));
+                    }
+                    match partners.iter_mut().find(|(t, _)| t.id == other.id) {
+                        Some((_, ids)) if ids.contains(&partner) => {}
+                        Some((_, ids)) => ids.push(partner),
+                        None => partners.push((other.clone(), vec![partner])),
+                    }
+                }
+            }
+        }
+    }
+    let mut tracks = whole_tracks(targets);
+    tracks.extend(partners.into_iter().map(|(t, ids)| (t, Some(ids))));
+    Ok(tracks)
+}
+
+/// A track an edit cuts, and the only nodes on it the cut may touch when it
+/// must leave the others alone.
+type CutTrack = (TrackBinding, Option<Vec<NodeId>>);
+
+/// `tracks` cut as a whole.
+fn whole_tracks(tracks: Vec<TrackBinding>) -> Vec<CutTrack> {
+    tracks.into_iter().map(|t| (t, None)).collect()
+}
+
+/// Commands that cut `range` out of `track`: nodes inside it are removed and
+/// nodes crossing its edges are trimmed back to it, or split in two when
+/// they cross both. Nodes from the end of the range onwards, including the
+/// right-hand halves of split nodes, then move by `shift`.
+///
+/// An empty range cuts nothing and only splits what spans its start, which
+/// is how an insert opens its gap. Right-hand halves get fresh ids and are
+/// linked to each other when their originals were; `pieces` collects them
+/// as `(original, piece)` across tracks.
+///
+/// With `only`, the cut touches just those nodes and the rest of the track
+/// stays put; moving one of them onto another node is an error.
+fn cut_track(
+    graph: &TimelineGraph,
+    track: &TrackBinding,
+    only: Option<&[NodeId]>,
+    range: &FrameRange,
+    shift: Frame,
+    pieces: &mut Vec<(NodeId, NodeId)>,
+) -> Result<Vec<TimelineCommand>, TimelineError> {
+    let (start, end) = (range.start, range.end());
+    let mut commands = Vec::new();
+    // Removals and new pieces earlier in the batch move later positions.
+    let mut offset: isize = 0;
+    for (position, id) in track.node_ids.iter().enumerate() {
+        if only.is_some_and(|ids| !ids.contains(id)) {
+            continue;
+        }
+        let Some(r) = graph
+            .nodes
+            .get(id)
+            .and_then(|n| n.timeline_range())
+            .cloned()
+        else {
+            continue;
+        };
+        if r.start >= end {
+            if shift != 0 {
+                let mut node = editable_node(graph, *id)?;
+                shift_node(&mut node, shift);
+                commands.push(TimelineCommand::UpdateNode { node });
+            }
+            continue;
+        }
+        if r.end() <= start {
+            continue;
+        }
+        let node = editable_node(graph, *id)?;
+        if r.start >= start && r.end() <= end {
+            commands.push(TimelineCommand::RemoveNode { node_id: *id });
+            offset -= 1;
+            continue;
+        }
+        if r.start < start {
+            let mut left = node.clone();
+            adjust_edges(&mut left, 0, start - r.end())?;
+            commands.push(TimelineCommand::UpdateNode { node: left });
+        }
+        if r.end() > end {
+            let mut right = node;
+            adjust_edges(&mut right, end - r.start, 0)?;
+            shift_node(&mut right, shift);
+            if r.start >= start {
+                commands.push(TimelineCommand::UpdateNode { node: right });
+                continue;
+            }
+            right.id = NodeId::new();
+            let linked = graph.linked_nodes(*id);
+            let edges = pieces
+                .iter()
+                .find(|(original, _)| linked.contains(original))
+                .map(|(_, from)| TimelineEdge {
+                    from: *from,
+                    to: right.id,
+                    kind: EdgeKind::Link,
+                })
+                .into_iter()
+                .collect();
+            pieces.push((*id, right.id));
+            commands.push(TimelineCommand::InsertNode {
+                placements: vec![TrackPlacement {
+                    track_id: track.id,
+                    position: Some((position as isize + offset) as usize + 1),
+                }],
+                node: right,
+                edges,
+            });
+            offset += 1;
+        }
+    }
+    if let Some(ids) = only {
+        for command in &commands {
+            if let TimelineCommand::UpdateNode { node } | TimelineCommand::InsertNode { node, .. } =
+                command
+            {
+                ensure_free(graph, track.id, &timeline_range_of(node)?, ids)?;
+            }
+        }
+    }
+    Ok(commands)
+}
+
+fn clear_range(
+    graph: &TimelineGraph,
+    range: FrameRange,
+    track_ids: Vec<TrackId>,
+    close_gap: bool,
+) -> Result<Vec<TimelineCommand>, TimelineError> {
+    if range.duration < 1 {
+        return Err(TimelineError::InvalidOp("range is empty".to_string()));
+    }
+    let (shift, tracks) = if close_gap {
+        (
+            -range.duration,
+            ripple_tracks(graph, &track_ids, range.start)?,
+        )
+    } else {
+        (0, whole_tracks(target_tracks(graph, &track_ids)?))
+    };
+    let mut pieces = Vec::new();
+    let mut commands = Vec::new();
+    for (track, only) in &tracks {
+        commands.extend(cut_track(
+            graph,
+            track,
+            only.as_deref(),
+            &range,
+            shift,
+            &mut pieces,
+        )?);
+    }
+    Ok(commands)
+}
+
+fn three_point_edit(
+    graph: &TimelineGraph,
+    node: TimelineNode,
+    track_ids: Vec<TrackId>,
+    ripple: bool,
+) -> Result<Vec<TimelineCommand>, TimelineError> {
+    let range = timeline_range_of(&node)?;
+    if range.duration < 1 || range.start < 0 {
+        return Err(TimelineError::InvalidOp(format!(
+            "node {} has no room on the timeline",
+            node.id
+        )));
+    }
+    let (cut, shift, cut_tracks) = if ripple {
+        let tracks = ripple_tracks(graph, &track_ids, range.start)?;
+        (FrameRange::new(range.start, 0), range.duration, tracks)
+    } else {
+        (range, 0, whole_tracks(target_tracks(graph, &track_ids)?))
+    };
+    let mut pieces = Vec::new();
+    let mut commands = Vec::new();
+    for (track, only) in &cut_tracks {
+        commands.extend(cut_track(
+            graph,
+            track,
+            only.as_deref(),
+            &cut,
+            shift,
+            &mut pieces,
+        )?);
+    }
+    let tracks = target_tracks(graph, &track_ids)?;
+    for (i, track) in tracks.iter().enumerate() {
+        let mut copy = node.clone();
+        if i > 0 {
+            copy.id = NodeId::new();
+        }
+        let edges = (i > 0)
+            .then_some(TimelineEdge {
+                from: node.id,
+                to: copy.id,
+                kind: EdgeKind::Link,
+            })
+            .into_iter()
+            .collect();
+        commands.push(TimelineCommand::InsertNode {
+            node: copy,
+            placements: vec![TrackPlacement {
+                track_id: track.id,
+                position: None,
+            }],
+            edges,
+        });
+    }
+    Ok(commands)
+}
+
+fn validate_placements(
+    graph: &TimelineGraph,
+    placements: &[TrackPlacement],
+) -> Result<(), TimelineError> {
+    for placement in placements {
+        let track = graph
+            .tracks
+            .iter()
+            .find(|t| t.id == placement.track_id)
+            .ok_or(TimelineError::TrackNotFound(placement.track_id))?;
+        if let Some(position) = placement.position {
+            if position > track.node_ids.len() {
+                return Err(TimelineError::InvalidOp(format!(
+                    "placement index {} out of bounds for track {}",
+                    position, track.id
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn validate_edges_for_insert(
+    graph: &TimelineGraph,
+    edges: &[TimelineEdge],
+    new_node: NodeId,
+) -> Result<(), TimelineError> {
+    for edge in edges {
+        if edge.from != new_node && !graph.nodes.contains_key(&edge.from) {
+            return Err(TimelineError::NodeNotFound(edge.from));
+        }
+        if edge.to != new_node && !graph.nodes.contains_key(&edge.to) {
+            return Err(TimelineError::NodeNotFound(edge.to));
+        }
+        if graph
+            .edges
+            .iter()
+            .any(|e| e.from == edge.from && e.to == edge.to && e.kind == edge.kind)
+        {
+            return Err(TimelineError::EdgeExists(edge.from, edge.to));
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+    pub label: String,
+    command: TimelineCommand,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct CommandHistory {
+    undo_stack: Vec<HistoryEntry>,
+    redo_stack: Vec<HistoryEntry>,
+}
+
+impl CommandHistory {
+    pub fn apply(
+        &mut self,
+        graph: &mut TimelineGraph,
+        command: TimelineCommand,
+    ) -> Result<(), TimelineError> {
+        let label = command.label().to_string();
+        self.apply_labeled(graph, label, command)
+    }
+
+    pub fn apply_labeled(
+        &mut self,
+        graph: &mut TimelineGraph,
+        label: impl Into<String>,
+        command: TimelineCommand,
+    ) -> Result<(), TimelineError> {
+        let inverse = apply_command(graph, command)?;
+        self.undo_stack.push(HistoryEntry {
+            label: label.into(),
+            command: inverse,
+        });
+        self.redo_stack.clear();
+        Ok(())
+    }
+
+    /// Apply `commands` atomically and record them as a single undo step.
+    pub fn apply_batch(
+        &mut self,
+        graph: &mut TimelineGraph,
+        label: impl Into<String>,
+        commands: Vec<TimelineCommand>,
+    ) -> Result<(), TimelineError> {
+        self.apply_labeled(graph, label, TimelineCommand::Batch { commands })
+    }
+
+    pub fn undo(&mut self, graph: &mut TimelineGraph) -> Result<(), TimelineError> {
+        let entry = self
+            .undo_stack
+            .pop()
+            .ok_or(TimelineError::HistoryEmpty("undo stack"))?;
+        match apply_command(graph, entry.command.clone()) {
+            Ok(inverse) => {
+                self.redo_stack.push(HistoryEntry {
+                    label: entry.label,
+                    command: inverse,
+                });
+                Ok(())
+            }
+            Err(err) => {
+                self.undo_stack.push(entry);
+                Err(err)
+            }
+        }
+    }
+
+    pub fn redo(&mut self, graph: &mut TimelineGraph) -> Result<(), TimelineError> {
+        let entry = self
+            .redo_stack
+            .pop()
+            .ok_or(TimelineError::HistoryEmpty("redo stack"))?;
+        match apply_command(graph, entry.command.clone()) {
+            Ok(inverse) => {
+                self.undo_stack.push(HistoryEntry {
+                    label: entry.label,
+                    command: inverse,
+                });
+                Ok(())
+            }
+            Err(err) => {
+                self.redo_stack.push(entry);
+                Err(err)
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+    }
+
+    /// Label of the step the next `undo` would revert.
+    pub fn undo_label(&self) -> Option<&str> {
+        self.undo_stack.last().map(|e| e.label.as_str())
+    }
+
+    /// Label of the step the next `redo` would reapply.
+    pub fn redo_label(&self) -> Option<&str> {
+        self.redo_stack.last().map(|e| e.label.as_str())
+    }
+
+    /// Undo entries, oldest first.
+    pub fn undo_entries(&self) -> &[HistoryEntry] {
+        &self.undo_stack
+    }
+
+    /// Redo entries, most recently undone last.
+    pub fn redo_entries(&self) -> &[HistoryEntry] {
+        &self.redo_stack
+    }
+}
+
+/// Build a graph from a sequence that only has legacy tracks. Callers run it
+/// once, when `sequence.graph` has no tracks yet.
+///
+/// Track and node ids are derived from where each track and item sits, so
+/// migrating the same sequence again gives the same graph. A journal written
+/// against the migrated graph therefore still replays after a crash, even if
+/// the graph itself was never saved.
+///
+/// Legacy tracks carry no link information, so this is the one place clips
+/// are still linked by position: a video and an audio clip of the same asset
+/// over the same frames become a link group. From then on links only change
+/// through explicit commands.
+pub fn migrate_sequence_tracks(sequence: &crate::Sequence) -> TimelineGraph {
+    let mut result = TimelineGraph::default();
+    result.version = sequence.graph.version;
+    if sequence.graph.metadata != Value::Null {
+        result.metadata = sequence.graph.metadata.clone();
+    }
+    result.markers = sequence.graph.markers.clone();
+    result.sequences = sequence.graph.sequences.clone();
+
+    for (track_index, legacy_track) in sequence.tracks.iter().enumerate() {
+        let track_index = track_index.to_string();
+        let track_id = TrackId(migrated_id(&[&track_index, &legacy_track.name]));
+        let mut binding = TrackBinding {
+            id: track_id,
+            name: legacy_track.name.clone(),
             kind: {
                 // Heuristics:
                 // - Empty tracks: infer from name prefix ("A" -> Audio), else Video.
@@ -483,10 +2174,16 @@ pub fn migrate_sequence_tracks(sequence: &crate::Sequence) -> TimelineGraph {
                 }
             },
             node_ids: Vec::new(),
+            properties: Default::default(),
         };
 
-        for item in &legacy_track.items {
-            let node_id = NodeId::new();
+        for (item_index, item) in legacy_track.items.iter().enumerate() {
+            let node_id = NodeId(migrated_id(&[
+                &track_index,
+                &legacy_track.name,
+                &item_index.to_string(),
+                &item.id,
+            ]));
             let clip = crate::ClipNode {
                 asset_id: match &item.kind {
                     crate::ItemKind::Video { src, .. } => Some(src.clone()),
@@ -502,6 +2199,8 @@ pub fn migrate_sequence_tracks(sequence: &crate::Sequence) -> TimelineGraph {
                     _ => 1.0,
                 },
                 reverse: false,
+                time_remap: None,
+                markers: previous_clip_markers(sequence, &item.id),
                 metadata: Value::Null,
             };
             let node = TimelineNode {
@@ -531,6 +2230,1486 @@ pub fn migrate_sequence_tracks(sequence: &crate::Sequence) -> TimelineGraph {
 
         result.tracks.push(binding);
     }
+    link_legacy_av_pairs(&mut result);
 
     result
 }
+
+/// Stable id for something [`migrate_sequence_tracks`] creates: two FNV-1a
+/// passes with different offset bases over `parts`, each part terminated so
+/// `["ab", "c"]` and `["a", "bc"]` differ.
+fn migrated_id(parts: &[&str]) -> uuid::Uuid {
+    let hash = |offset: u64| {
+        parts
+            .iter()
+            .flat_map(|part| part.bytes().chain([0xff]))
+            .fold(offset, |h, byte| {
+                (h ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+            })
+    };
+    uuid::Uuid::from_u64_pair(hash(0xcbf2_9ce4_8422_2325), hash(0x8422_2325_cbf2_9ce4))
+}
+
+/// Compound clips nested deeper than this are left out, which also stops
+/// sequences that contain themselves.
+pub const MAX_COMPOUND_DEPTH: usize = 8;
+
+/// Legacy tracks for a graph, one per graph track: the reverse of
+/// [`migrate_sequence_tracks`]. Items take their ids from the nodes they were
+/// made from. Compound clips are flattened into the clips they show, trimmed
+/// to the compound's window, so readers of the legacy tracks (export, EDL,
+/// FCPXML) see the same media as the preview.
+pub fn tracks_from_graph(graph: &TimelineGraph, fps: crate::Fps) -> Vec<crate::Track> {
+    graph
+        .tracks
+        .iter()
+        .map(|binding| {
+            let mut items = Vec::with_capacity(binding.node_ids.len());
+            for node_id in &binding.node_ids {
+                let Some(node) = graph.nodes.get(node_id) else {
+                    continue;
+                };
+                for flat in graph.flatten_node(node, &binding.kind) {
+                    if let Some(mut item) = item_from_node(&flat.node, &flat.track_kind, fps) {
+                        item.id = flat.id;
+                        items.push(item);
+                    }
+                }
+            }
+            crate::Track {
+                name: binding.name.clone(),
+                items,
+            }
+        })
+        .collect()
+}
+
+/// A node as it plays on the root timeline; see [`TimelineGraph::flatten_node`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlatNode {
+    /// The node's id, after those of the compound clips it sits in,
+    /// outermost first, joined by `/`.
+    pub id: String,
+    pub node: TimelineNode,
+    /// Kind of the track the node sits on in its own graph.
+    pub track_kind: TrackKind,
+}
+
+impl TimelineGraph {
+    /// What `node`, sitting on a track of `track_kind`, plays. That is the
+    /// node itself, or for a compound clip the nodes of its nested sequence
+    /// trimmed to the compound's window and moved onto this timeline,
+    /// flattened through further compounds up to [`MAX_COMPOUND_DEPTH`].
+    pub fn flatten_node(&self, node: &TimelineNode, track_kind: &TrackKind) -> Vec<FlatNode> {
+        let mut out = Vec::new();
+        flatten_into(self, node.clone(), track_kind, "", 0, &mut out);
+        out
+    }
+}
+
+fn flatten_into(
+    root: &TimelineGraph,
+    node: TimelineNode,
+    track_kind: &TrackKind,
+    id_prefix: &str,
+    depth: usize,
+    out: &mut Vec<FlatNode>,
+) {
+    let TimelineNodeKind::Compound(compound) = &node.kind else {
+        out.push(FlatNode {
+            id: format!("{}{}", id_prefix, node.id),
+            node,
+            track_kind: track_kind.clone(),
+        });
+        return;
+    };
+    let Some(inner) = root.sequence(&compound.sequence_id) else {
+        return;
+    };
+    if depth >= MAX_COMPOUND_DEPTH {
+        return;
+    }
+    let window = &compound.media_range;
+    let offset = compound.timeline_range.start - window.start;
+    let prefix = format!("{}{}/", id_prefix, node.id);
+    let mut shown = Vec::new();
+    for binding in &inner.tracks {
+        for id in &binding.node_ids {
+            let Some(mut copy) = inner.nodes.get(id).cloned() else {
+                continue;
+            };
+            let Some(range) = copy.timeline_range().cloned() else {
+                continue;
+            };
+            let start = range.start.max(window.start);
+            let end = range.end().min(window.end());
+            if end <= start
+                || adjust_edges(&mut copy, start - range.start, end - range.end()).is_err()
+            {
+                continue;
+            }
+            shift_node(&mut copy, offset);
+            shown.push((copy, &binding.kind));
+        }
+    }
+    shown.sort_by_key(|(node, _)| node.timeline_range().map(|r| r.start));
+    for (copy, kind) in shown {
+        flatten_into(root, copy, kind, &prefix, depth + 1, out);
+    }
+}
+
+/// Legacy item for a single clip or generator node; other nodes have none.
+pub fn item_from_node(
+    node: &TimelineNode,
+    track_kind: &TrackKind,
+    fps: crate::Fps,
+) -> Option<crate::Item> {
+    use crate::{Item, ItemKind};
+    let id = node.id.to_string();
+    let seconds = |frames: Frame| match fps.num {
+        0 => 0.0,
+        num => frames as f64 * fps.den.max(1) as f64 / num as f64,
+    };
+    match (&node.kind, track_kind) {
+        (TimelineNodeKind::Clip(clip), TrackKind::Audio) => Some(Item {
+            id,
+            from: clip.timeline_range.start,
+            duration_in_frames: clip.timeline_range.duration,
+            kind: ItemKind::Audio {
+                src: clip.asset_id.clone().unwrap_or_default(),
+                in_offset_sec: seconds(clip.media_range.start),
+                rate: clip.playback_rate,
+            },
+        }),
+        (TimelineNodeKind::Clip(clip), _) => {
+            let src = clip.asset_id.clone().unwrap_or_default();
+            let is_image = std::path::Path::new(&src)
+                .extension()
+                .and_then(|e| e.to_str())
+                .map(|ext| {
+                    [
+                        "png", "jpg", "jpeg", "gif", "webp", "bmp", "tif", "tiff", "exr",
+                    ]
+                    .iter()
+                    .any(|known| ext.eq_ignore_ascii_case(known))
+                })
+                .unwrap_or(false);
+            let kind = if is_image {
+                ItemKind::Image { src }
+            } else {
+                ItemKind::Video {
+                    src,
+                    frame_rate: Some(fps.num as f32 / fps.den.max(1) as f32),
+                    in_offset_sec: seconds(clip.media_range.start),
+                    rate: clip.playback_rate,
+                }
+            };
+            Some(Item {
+                id,
+                from: clip.timeline_range.start,
+                duration_in_frames: clip.timeline_range.duration,
+                kind,
+            })
+        }
+        (
+            TimelineNodeKind::Generator {
+                generator_id,
+                timeline_range,
+                metadata,
+            },
+            _,
+        ) => {
+            let text = |key: &str, default: &str| {
+                metadata
+                    .get(key)
+                    .and_then(|v| v.as_str())
+                    .unwrap_or(default)
+                    .to_string()
+            };
+            let kind = match generator_id.as_str() {
+                "solid" => ItemKind::Solid {
+                    color: text("color", "#4c4c4c"),
+                },
+                "text" => ItemKind::Text {
+                    text: text("text", ""),
+                    color: text("color", "#ffffff"),
+                },
+                _ => return None,
+            };
+            Some(Item {
+                id,
+                from: timeline_range.start,
+                duration_in_frames: timeline_range.duration,
+                kind,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Legacy tracks carry no link information. Pair each audio clip with an
+/// unlinked picture clip of the same source and placement so migrated
+/// projects keep sound and picture together. Only [`migrate_sequence_tracks`]
+/// may call this; nothing else infers links.
+fn link_legacy_av_pairs(graph: &mut TimelineGraph) {
+    let mut pictures = Vec::new();
+    let mut sounds = Vec::new();
+    for track in &graph.tracks {
+        for id in &track.node_ids {
+            let Some(TimelineNodeKind::Clip(clip)) = graph.nodes.get(id).map(|n| &n.kind) else {
+                continue;
+            };
+            let Some(asset_id) = clip.asset_id.clone() else {
+                continue;
+            };
+            let entry = (*id, asset_id, clip.timeline_range.clone());
+            if matches!(track.kind, TrackKind::Audio) {
+                sounds.push(entry);
+            } else {
+                pictures.push(entry);
+            }
+        }
+    }
+    for (audio_id, asset_id, range) in sounds {
+        if let Some(pos) = pictures
+            .iter()
+            .position(|(_, a, r)| *a == asset_id && *r == range)
+        {
+            let (video_id, _, _) = pictures.remove(pos);
+            graph.edges.push(TimelineEdge {
+                from: video_id,
+                to: audio_id,
+                kind: EdgeKind::Link,
+            });
+        }
+    }
+}
+
+/// Clip markers from the graph node a legacy item was synced from, if any.
+fn previous_clip_markers(sequence: &crate::Sequence, item_id: &str) -> Vec<Marker> {
+    let Ok(uuid) = uuid::Uuid::parse_str(item_id) else {
+        return Vec::new();
+    };
+    match sequence.graph.nodes.get(&NodeId(uuid)).map(|n| &n.kind) {
+        Some(TimelineNodeKind::Clip(clip)) => clip.markers.clone(),
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BlendMode, ClipNode};
+
+    fn clip(start: Frame, duration: Frame, media_start: Frame) -> TimelineNode {
+        TimelineNode {
+            id: NodeId::new(),
+            label: None,
+            kind: TimelineNodeKind::Clip(ClipNode {
+                asset_id: Some("a.mov".to_string()),
+                media_range: FrameRange::new(media_start, duration),
+                timeline_range: FrameRange::new(start, duration),
+                playback_rate: 1.0,
+                reverse: false,
+                time_remap: None,
+                markers: Vec::new(),
+                metadata: Value::Null,
+            }),
+            locked: false,
+            metadata: Value::Null,
+        }
+    }
+
+    fn graph_with(nodes: &[TimelineNode]) -> TimelineGraph {
+        let mut graph = TimelineGraph::default();
+        graph.tracks.push(TrackBinding {
+            id: TrackId::new(),
+            name: "V1".to_string(),
+            kind: TrackKind::Video,
+            node_ids: nodes.iter().map(|n| n.id).collect(),
+            properties: Default::default(),
+        });
+        for node in nodes {
+            graph.nodes.insert(node.id, node.clone());
+        }
+        graph
+    }
+
+    fn range(graph: &TimelineGraph, id: NodeId) -> (FrameRange, Option<FrameRange>) {
+        let node = &graph.nodes[&id];
+        let media = match &node.kind {
+            TimelineNodeKind::Clip(c) => Some(c.media_range.clone()),
+            _ => None,
+        };
+        (node.timeline_range().cloned().unwrap(), media)
+    }
+
+    #[test]
+    fn ripple_trim_shifts_downstream_and_undoes() {
+        let a = clip(0, 50, 0);
+        let b = clip(50, 30, 10);
+        let mut graph = graph_with(&[a.clone(), b.clone()]);
+        let before = graph.clone();
+        let mut history = CommandHistory::default();
+
+        history
+            .apply(
+                &mut graph,
+                TimelineCommand::RippleTrim {
+                    node_id: a.id,
+                    edge: TrimEdge::End,
+                    delta: -20,
+                },
+            )
+            .unwrap();
+        assert_eq!(range(&graph, a.id).0, FrameRange::new(0, 30));
+        assert_eq!(range(&graph, b.id).0, FrameRange::new(30, 30));
+
+        history.undo(&mut graph).unwrap();
+        assert_eq!(graph, before);
+
+        history
+            .apply(
+                &mut graph,
+                TimelineCommand::RippleTrim {
+                    node_id: a.id,
+                    edge: TrimEdge::Start,
+                    delta: 10,
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            range(&graph, a.id),
+            (FrameRange::new(0, 40), Some(FrameRange::new(10, 40)))
+        );
+        assert_eq!(range(&graph, b.id).0, FrameRange::new(40, 30));
+        history.undo(&mut graph).unwrap();
+        assert_eq!(graph, before);
+    }
+
+    #[test]
+    fn plain_trims_leave_the_track_alone() {
+        let a = clip(0, 50, 0);
+        let b = clip(60, 30, 10);
+        let mut graph = graph_with(&[a.clone(), b.clone()]);
+        let before = graph.clone();
+        let mut history = CommandHistory::default();
+
+        history
+            .apply(
+                &mut graph,
+                TimelineCommand::TrimNode {
+                    node_id: a.id,
+                    edge: TrimEdge::End,
+                    delta: 5,
+                },
+            )
+            .unwrap();
+        history
+            .apply(
+                &mut graph,
+                TimelineCommand::TrimNode {
+                    node_id: b.id,
+                    edge: TrimEdge::Start,
+                    delta: -5,
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            range(&graph, a.id),
+            (FrameRange::new(0, 55), Some(FrameRange::new(0, 55)))
+        );
+        assert_eq!(
+            range(&graph, b.id),
+            (FrameRange::new(55, 35), Some(FrameRange::new(5, 35)))
+        );
+        assert!(matches!(
+            apply_command(
+                &mut graph,
+                TimelineCommand::TrimNode {
+                    node_id: a.id,
+                    edge: TrimEdge::End,
+                    delta: 1,
+                },
+            ),
+            Err(TimelineError::InvalidOp(_))
+        ));
+
+        history.undo(&mut graph).unwrap();
+        history.undo(&mut graph).unwrap();
+        assert_eq!(graph, before);
+    }
+
+    #[test]
+    fn trims_rolls_slips_and_slides_keep_linked_clips_in_sync() {
+        let v = clip(0, 50, 0);
+        let w = clip(50, 30, 10);
+        let a = clip(0, 50, 0);
+        let b = clip(50, 30, 10);
+        let mut graph = graph_with(&[v.clone(), w.clone()]);
+        graph.tracks.push(TrackBinding {
+            id: TrackId::new(),
+            name: "A1".to_string(),
+            kind: TrackKind::Audio,
+            node_ids: vec![a.id, b.id],
+            properties: Default::default(),
+        });
+        for (video, audio) in [(&v, &a), (&w, &b)] {
+            graph.nodes.insert(audio.id, audio.clone());
+            graph.edges.push(TimelineEdge {
+                from: video.id,
+                to: audio.id,
+                kind: EdgeKind::Link,
+            });
+        }
+        let before = graph.clone();
+        let mut history = CommandHistory::default();
+        let mut check = |command, expected: [(NodeId, FrameRange, Frame); 4]| {
+            history.apply(&mut graph, command).unwrap();
+            for (id, timeline, media_start) in expected {
+                let (r, media) = range(&graph, id);
+                assert_eq!((r, media.unwrap().start), (timeline, media_start));
+            }
+            history.undo(&mut graph).unwrap();
+            assert_eq!(graph, before);
+        };
+
+        check(
+            TimelineCommand::TrimNode {
+                node_id: v.id,
+                edge: TrimEdge::End,
+                delta: -10,
+            },
+            [
+                (v.id, FrameRange::new(0, 40), 0),
+                (a.id, FrameRange::new(0, 40), 0),
+                (w.id, FrameRange::new(50, 30), 10),
+                (b.id, FrameRange::new(50, 30), 10),
+            ],
+        );
+        check(
+            TimelineCommand::RippleTrim {
+                node_id: a.id,
+                edge: TrimEdge::End,
+                delta: -10,
+            },
+            [
+                (v.id, FrameRange::new(0, 40), 0),
+                (a.id, FrameRange::new(0, 40), 0),
+                (w.id, FrameRange::new(40, 30), 10),
+                (b.id, FrameRange::new(40, 30), 10),
+            ],
+        );
+        check(
+            TimelineCommand::RollEdit {
+                left: v.id,
+                right: w.id,
+                delta: 5,
+            },
+            [
+                (v.id, FrameRange::new(0, 55), 0),
+                (a.id, FrameRange::new(0, 55), 0),
+                (w.id, FrameRange::new(55, 25), 15),
+                (b.id, FrameRange::new(55, 25), 15),
+            ],
+        );
+        check(
+            TimelineCommand::SlipClip {
+                node_id: w.id,
+                delta: 3,
+            },
+            [
+                (v.id, FrameRange::new(0, 50), 0),
+                (a.id, FrameRange::new(0, 50), 0),
+                (w.id, FrameRange::new(50, 30), 13),
+                (b.id, FrameRange::new(50, 30), 13),
+            ],
+        );
+        check(
+            TimelineCommand::SlideClip {
+                node_id: b.id,
+                delta: -5,
+            },
+            [
+                (v.id, FrameRange::new(0, 45), 0),
+                (a.id, FrameRange::new(0, 45), 0),
+                (w.id, FrameRange::new(45, 30), 10),
+                (b.id, FrameRange::new(45, 30), 10),
+            ],
+        );
+    }
+
+    #[test]
+    fn trims_follow_playback_rate_and_reverse() {
+        let mut fast = clip(0, 10, 0);
+        if let TimelineNodeKind::Clip(c) = &mut fast.kind {
+            c.playback_rate = 2.0;
+            c.media_range = FrameRange::new(0, 20);
+        }
+        let mut graph = graph_with(std::slice::from_ref(&fast));
+        apply_command(
+            &mut graph,
+            TimelineCommand::RippleTrim {
+                node_id: fast.id,
+                edge: TrimEdge::Start,
+                delta: 3,
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            range(&graph, fast.id),
+            (FrameRange::new(0, 7), Some(FrameRange::new(6, 14)))
+        );
+
+        let mut reversed = clip(0, 10, 20);
+        if let TimelineNodeKind::Clip(c) = &mut reversed.kind {
+            c.reverse = true;
+        }
+        let mut graph = graph_with(std::slice::from_ref(&reversed));
+        let before = graph.clone();
+        let mut history = CommandHistory::default();
+        history
+            .apply(
+                &mut graph,
+                TimelineCommand::RippleTrim {
+                    node_id: reversed.id,
+                    edge: TrimEdge::Start,
+                    delta: 3,
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            range(&graph, reversed.id),
+            (FrameRange::new(0, 7), Some(FrameRange::new(20, 7)))
+        );
+        history
+            .apply(
+                &mut graph,
+                TimelineCommand::RippleTrim {
+                    node_id: reversed.id,
+                    edge: TrimEdge::End,
+                    delta: -2,
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            range(&graph, reversed.id),
+            (FrameRange::new(0, 5), Some(FrameRange::new(22, 5)))
+        );
+        history.undo(&mut graph).unwrap();
+        history.undo(&mut graph).unwrap();
+        assert_eq!(graph, before);
+    }
+
+    #[test]
+    fn roll_slip_and_slide_round_trip() {
+        let a = clip(0, 50, 0);
+        let b = clip(50, 30, 10);
+        let c = clip(80, 40, 20);
+        let mut graph = graph_with(&[a.clone(), b.clone(), c.clone()]);
+        let before = graph.clone();
+        let mut history = CommandHistory::default();
+
+        history
+            .apply(
+                &mut graph,
+                TimelineCommand::RollEdit {
+                    left: a.id,
+                    right: b.id,
+                    delta: 5,
+                },
+            )
+            .unwrap();
+        assert_eq!(range(&graph, a.id).0, FrameRange::new(0, 55));
+        assert_eq!(
+            range(&graph, b.id),
+            (FrameRange::new(55, 25), Some(FrameRange::new(15, 25)))
+        );
+        history.undo(&mut graph).unwrap();
+
+        history
+            .apply(
+                &mut graph,
+                TimelineCommand::SlipClip {
+                    node_id: b.id,
+                    delta: 7,
+                },
+            )
+            .unwrap();
+        assert_eq!(range(&graph, b.id).1, Some(FrameRange::new(17, 30)));
+        history.undo(&mut graph).unwrap();
+
+        history
+            .apply(
+                &mut graph,
+                TimelineCommand::SlideClip {
+                    node_id: b.id,
+                    delta: -10,
+                },
+            )
+            .unwrap();
+        assert_eq!(range(&graph, a.id).0, FrameRange::new(0, 40));
+        assert_eq!(range(&graph, b.id).0, FrameRange::new(40, 30));
+        assert_eq!(
+            range(&graph, c.id),
+            (FrameRange::new(70, 50), Some(FrameRange::new(10, 50)))
+        );
+        history.undo(&mut graph).unwrap();
+        assert_eq!(graph, before);
+    }
+
+    #[test]
+    fn batch_rolls_back_on_error_and_undoes_as_one_step() {
+        let a = clip(0, 50, 0);
+        let b = clip(50, 30, 10);
+        let mut graph = graph_with(&[a.clone(), b.clone()]);
+        let before = graph.clone();
+        let mut history = CommandHistory::default();
+
+        let err = history
+            .apply_batch(
+                &mut graph,
+                "Broken",
+                vec![
+                    TimelineCommand::SlipClip {
+                        node_id: a.id,
+                        delta: 5,
+                    },
+                    TimelineCommand::RemoveNode {
+                        node_id: NodeId::new(),
+                    },
+                ],
+            )
+            .unwrap_err();
+        assert!(matches!(err, TimelineError::NodeNotFound(_)));
+        assert_eq!(graph, before);
+        assert!(history.undo_label().is_none());
+
+        history
+            .apply_batch(
+                &mut graph,
+                "Split clip",
+                vec![
+                    TimelineCommand::SlipClip {
+                        node_id: a.id,
+                        delta: 5,
+                    },
+                    TimelineCommand::RemoveNode { node_id: b.id },
+                ],
+            )
+            .unwrap();
+        assert_eq!(history.undo_label(), Some("Split clip"));
+        history.undo(&mut graph).unwrap();
+        assert_eq!(graph, before);
+        assert_eq!(history.redo_label(), Some("Split clip"));
+        history.redo(&mut graph).unwrap();
+        assert!(!graph.nodes.contains_key(&b.id));
+    }
+
+    #[test]
+    fn edits_respect_locked_nodes() {
+        let a = clip(0, 50, 0);
+        let mut b = clip(50, 30, 10);
+        b.locked = true;
+        let mut graph = graph_with(&[a.clone(), b.clone()]);
+        let before = graph.clone();
+
+        let err = apply_command(
+            &mut graph,
+            TimelineCommand::RippleTrim {
+                node_id: a.id,
+                edge: TrimEdge::End,
+                delta: 10,
+            },
+        )
+        .unwrap_err();
+        assert!(matches!(err, TimelineError::NodeLocked(id) if id == b.id));
+        let err = apply_command(
+            &mut graph,
+            TimelineCommand::SlipClip {
+                node_id: b.id,
+                delta: 1,
+            },
+        )
+        .unwrap_err();
+        assert!(matches!(err, TimelineError::NodeLocked(_)));
+        assert_eq!(graph, before);
+    }
+
+    #[test]
+    fn track_properties_undo_and_locked_tracks_refuse_edits() {
+        let a = clip(0, 50, 0);
+        let mut graph = graph_with(std::slice::from_ref(&a));
+        let track_id = graph.tracks[0].id;
+        let before = graph.clone();
+        let mut history = CommandHistory::default();
+
+        let properties = TrackProperties {
+            locked: true,
+            muted: true,
+            pan: -0.5,
+            ..TrackProperties::default()
+        };
+        history
+            .apply(
+                &mut graph,
+                TimelineCommand::SetTrackProperties {
+                    track_id,
+                    properties,
+                },
+            )
+            .unwrap();
+        assert!(!graph.track_audible(0));
+        assert_eq!(graph.tracks[0].properties.stereo_gain(), [1.0, 0.5]);
+
+        let locked = graph.clone();
+        let err = apply_command(
+            &mut graph,
+            TimelineCommand::SlipClip {
+                node_id: a.id,
+                delta: 1,
+            },
+        )
+        .unwrap_err();
+        assert!(matches!(err, TimelineError::TrackLocked(id) if id == track_id));
+        let err = apply_command(
+            &mut graph,
+            TimelineCommand::LiftRange {
+                range: FrameRange::new(0, 10),
+                track_ids: vec![track_id],
+            },
+        )
+        .unwrap_err();
+        assert!(matches!(err, TimelineError::TrackLocked(_)));
+        assert_eq!(graph, locked);
+
+        history.undo(&mut graph).unwrap();
+        assert_eq!(graph, before);
+    }
+
+    #[test]
+    fn unlinking_the_anchor_keeps_the_rest_of_the_group_linked() {
+        let video = clip(0, 10, 0);
+        let left = clip(10, 10, 0);
+        let right = clip(20, 10, 0);
+        let loose = clip(30, 10, 0);
+        let mut graph = graph_with(&[video.clone(), left.clone(), right.clone(), loose.clone()]);
+        let mut history = CommandHistory::default();
+        history
+            .apply(
+                &mut graph,
+                TimelineCommand::LinkNodes {
+                    node_ids: vec![video.id, left.id, right.id],
+                },
+            )
+            .unwrap();
+        history
+            .apply(
+                &mut graph,
+                TimelineCommand::LinkNodes {
+                    node_ids: vec![loose.id, right.id],
+                },
+            )
+            .unwrap();
+        let linked = graph.edges.clone();
+
+        history
+            .apply(
+                &mut graph,
+                TimelineCommand::UnlinkNodes {
+                    node_ids: vec![video.id],
+                },
+            )
+            .unwrap();
+        assert!(graph.linked_nodes(video.id).is_empty());
+        let mut rest = graph.linked_nodes(left.id);
+        rest.sort_by_key(|id| id.0);
+        let mut expected = vec![right.id, loose.id];
+        expected.sort_by_key(|id| id.0);
+        assert_eq!(rest, expected);
+
+        history.undo(&mut graph).unwrap();
+        assert_eq!(graph.edges.len(), linked.len());
+        assert!(linked.iter().all(|e| graph.edges.contains(e)));
+    }
+
+    #[test]
+    fn link_groups_move_split_and_remove_together() {
+        let video = clip(0, 40, 10);
+        let audio = clip(0, 40, 10);
+        let other = clip(60, 20, 0);
+        let mut graph = graph_with(&[video.clone(), other.clone()]);
+        let audio_track = TrackId::new();
+        graph.tracks.push(TrackBinding {
+            id: audio_track,
+            name: "A1".to_string(),
+            kind: TrackKind::Audio,
+            node_ids: vec![audio.id],
+            properties: Default::default(),
+        });
+        graph.nodes.insert(audio.id, audio.clone());
+        let mut history = CommandHistory::default();
+
+        history
+            .apply(
+                &mut graph,
+                TimelineCommand::LinkNodes {
+                    node_ids: vec![video.id, audio.id, video.id],
+                },
+            )
+            .unwrap();
+        assert_eq!(graph.linked_nodes(audio.id), vec![video.id]);
+        assert!(graph.linked_nodes(other.id).is_empty());
+
+        history
+            .apply(
+                &mut graph,
+                TimelineCommand::MoveLinked {
+                    node_id: audio.id,
+                    delta: 5,
+                },
+            )
+            .unwrap();
+        assert_eq!(range(&graph, video.id).0, FrameRange::new(5, 40));
+        assert_eq!(range(&graph, audio.id).0, FrameRange::new(5, 40));
+        assert_eq!(range(&graph, other.id).0, FrameRange::new(60, 20));
+
+        history
+            .apply(
+                &mut graph,
+                TimelineCommand::SplitNode {
+                    node_id: video.id,
+                    frame: 15,
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            range(&graph, audio.id),
+            (FrameRange::new(5, 10), Some(FrameRange::new(10, 10)))
+        );
+        let right_video = graph.tracks[0].node_ids[1];
+        let right_audio = graph.tracks[1].node_ids[1];
+        assert_eq!(
+            range(&graph, right_audio),
+            (FrameRange::new(15, 30), Some(FrameRange::new(20, 30)))
+        );
+        assert_eq!(graph.linked_nodes(right_video), vec![right_audio]);
+        assert_eq!(graph.linked_nodes(video.id), vec![audio.id]);
+
+        history
+            .apply(
+                &mut graph,
+                TimelineCommand::RemoveLinked {
+                    node_id: right_audio,
+                },
+            )
+            .unwrap();
+        assert_eq!(graph.nodes.len(), 3);
+        history
+            .apply(
+                &mut graph,
+                TimelineCommand::UnlinkNodes {
+                    node_ids: vec![video.id],
+                },
+            )
+            .unwrap();
+        assert!(graph.linked_nodes(audio.id).is_empty());
+
+        for _ in 0..5 {
+            history.undo(&mut graph).unwrap();
+        }
+        assert_eq!(graph.nodes.len(), 3);
+        assert!(graph.edges.is_empty());
+        assert_eq!(graph.nodes[&audio.id], audio);
+    }
+
+    #[test]
+    fn three_point_edits_insert_overwrite_lift_and_extract() {
+        let a = clip(0, 40, 0);
+        let b = clip(40, 20, 0);
+        let mut graph = graph_with(&[a.clone(), b.clone()]);
+        let track = graph.tracks[0].id;
+        let before = graph.clone();
+        let mut history = CommandHistory::default();
+
+        // Insert splits `a` at 10 and pushes the rest along by 5.
+        let source = clip(10, 5, 100);
+        history
+            .apply(
+                &mut graph,
+                TimelineCommand::InsertEdit {
+                    node: source.clone(),
+                    track_ids: vec![track],
+                },
+            )
+            .unwrap();
+        assert_eq!(graph.tracks[0].node_ids.len(), 4);
+        let tail = graph.tracks[0].node_ids[1];
+        assert_eq!(
+            range(&graph, a.id),
+            (FrameRange::new(0, 10), Some(FrameRange::new(0, 10)))
+        );
+        assert_eq!(
+            range(&graph, tail),
+            (FrameRange::new(15, 30), Some(FrameRange::new(10, 30)))
+        );
+        assert_eq!(range(&graph, b.id).0, FrameRange::new(45, 20));
+        assert_eq!(range(&graph, source.id).0, FrameRange::new(10, 5));
+        history.undo(&mut graph).unwrap();
+        assert_eq!(graph, before);
+
+        // Overwrite trims the tail of `a` and the head of `b`; nothing moves.
+        history
+            .apply(
+                &mut graph,
+                TimelineCommand::OverwriteEdit {
+                    node: clip(30, 20, 100),
+                    track_ids: vec![track],
+                },
+            )
+            .unwrap();
+        assert_eq!(range(&graph, a.id).0, FrameRange::new(0, 30));
+        assert_eq!(
+            range(&graph, b.id),
+            (FrameRange::new(50, 10), Some(FrameRange::new(10, 10)))
+        );
+        history.undo(&mut graph).unwrap();
+
+        history
+            .apply(
+                &mut graph,
+                TimelineCommand::LiftRange {
+                    range: FrameRange::new(35, 10),
+                    track_ids: vec![track],
+                },
+            )
+            .unwrap();
+        assert_eq!(range(&graph, a.id).0, FrameRange::new(0, 35));
+        assert_eq!(range(&graph, b.id).0, FrameRange::new(45, 15));
+        history.undo(&mut graph).unwrap();
+
+        // Extract removes `b` entirely and closes up behind the cut.
+        let c = clip(70, 10, 0);
+        graph.nodes.insert(c.id, c.clone());
+        graph.tracks[0].node_ids.push(c.id);
+        graph.invalidate_index();
+        history
+            .apply(
+                &mut graph,
+                TimelineCommand::ExtractRange {
+                    range: FrameRange::new(30, 35),
+                    track_ids: vec![track],
+                },
+            )
+            .unwrap();
+        assert!(!graph.nodes.contains_key(&b.id));
+        assert_eq!(range(&graph, a.id).0, FrameRange::new(0, 30));
+        assert_eq!(range(&graph, c.id).0, FrameRange::new(35, 10));
+        history.undo(&mut graph).unwrap();
+        assert_eq!(graph.nodes[&b.id], b);
+        assert_eq!(graph.nodes[&c.id], c);
+
+        assert!(matches!(
+            apply_command(
+                &mut graph,
+                TimelineCommand::LiftRange {
+                    range: FrameRange::new(0, 10),
+                    track_ids: Vec::new(),
+                },
+            ),
+            Err(TimelineError::InvalidOp(_))
+        ));
+    }
+
+    #[test]
+    fn rippling_edits_carry_linked_nodes_on_other_tracks() {
+        let video = clip(0, 40, 0);
+        let audio = clip(0, 40, 0);
+        let music = clip(20, 40, 0);
+        // Not linked to anything, so it stays where it is.
+        let sfx = clip(60, 10, 0);
+        let mut graph = graph_with(std::slice::from_ref(&video));
+        let v1 = graph.tracks[0].id;
+        for (name, nodes) in [("A1", vec![&audio, &sfx]), ("A2", vec![&music])] {
+            graph.tracks.push(TrackBinding {
+                id: TrackId::new(),
+                name: name.to_string(),
+                kind: TrackKind::Audio,
+                node_ids: nodes.iter().map(|n| n.id).collect(),
+                properties: Default::default(),
+            });
+            for node in nodes {
+                graph.nodes.insert(node.id, node.clone());
+            }
+        }
+        graph.edges.push(TimelineEdge {
+            from: video.id,
+            to: audio.id,
+            kind: EdgeKind::Link,
+        });
+        let before = graph.clone();
+        let mut history = CommandHistory::default();
+
+        // Only V1 is targeted, but the linked sound on A1 opens the same gap.
+        history
+            .apply(
+                &mut graph,
+                TimelineCommand::InsertEdit {
+                    node: clip(10, 5, 100),
+                    track_ids: vec![v1],
+                },
+            )
+            .unwrap();
+        assert_eq!(graph.tracks[1].node_ids.len(), 3);
+        let video_tail = graph.tracks[0].node_ids[1];
+        let audio_tail = graph.tracks[1].node_ids[1];
+        assert_eq!(range(&graph, audio.id).0, FrameRange::new(0, 10));
+        assert_eq!(range(&graph, audio_tail).0, FrameRange::new(15, 30));
+        assert_eq!(graph.linked_nodes(video_tail), vec![audio_tail]);
+        assert_eq!(range(&graph, music.id).0, FrameRange::new(20, 40));
+        assert_eq!(range(&graph, sfx.id).0, FrameRange::new(60, 10));
+        history.undo(&mut graph).unwrap();
+        assert_eq!(graph, before);
+
+        // Pushing the linked sound onto the unrelated clip is refused.
+        assert!(matches!(
+            apply_command(
+                &mut graph,
+                TimelineCommand::InsertEdit {
+                    node: clip(10, 25, 100),
+                    track_ids: vec![v1],
+                },
+            ),
+            Err(TimelineError::InvalidOp(_))
+        ));
+        assert_eq!(graph, before);
+
+        history
+            .apply(
+                &mut graph,
+                TimelineCommand::ExtractRange {
+                    range: FrameRange::new(30, 10),
+                    track_ids: vec![v1],
+                },
+            )
+            .unwrap();
+        assert_eq!(range(&graph, video.id).0, FrameRange::new(0, 30));
+        assert_eq!(range(&graph, audio.id).0, FrameRange::new(0, 30));
+        assert_eq!(range(&graph, music.id).0, FrameRange::new(20, 40));
+        assert_eq!(range(&graph, sfx.id).0, FrameRange::new(60, 10));
+        history.undo(&mut graph).unwrap();
+        assert_eq!(graph, before);
+    }
+
+    #[test]
+    fn markers_are_undoable_and_survive_migration() {
+        let a = clip(100, 50, 20);
+        let mut graph = graph_with(std::slice::from_ref(&a));
+        let mut history = CommandHistory::default();
+
+        let mut chapter = Marker::new(10, crate::MarkerKind::Chapter);
+        chapter.duration = Some(40);
+        history
+            .apply(
+                &mut graph,
+                TimelineCommand::AddMarker {
+                    marker: chapter.clone(),
+                },
+            )
+            .unwrap();
+        let note = Marker::new(30, crate::MarkerKind::Comment);
+        history
+            .apply(
+                &mut graph,
+                TimelineCommand::AddClipMarker {
+                    node_id: a.id,
+                    marker: note.clone(),
+                },
+            )
+            .unwrap();
+        assert_eq!(graph.markers_in(45, 60).count(), 1);
+        let on_timeline = graph.clip_markers_on_timeline();
+        assert_eq!(on_timeline.len(), 1);
+        assert_eq!(on_timeline[0].1.frame, 110);
+
+        let mut edited = chapter.clone();
+        edited.note = "Act one".to_string();
+        history
+            .apply(&mut graph, TimelineCommand::UpdateMarker { marker: edited })
+            .unwrap();
+        history.undo(&mut graph).unwrap();
+        assert_eq!(graph.markers, vec![chapter.clone()]);
+
+        let mut sequence = crate::Sequence::new("seq", 1920, 1080, crate::Fps::new(30, 1), 150);
+        sequence.tracks.push(crate::Track {
+            name: "V1".to_string(),
+            items: vec![crate::Item {
+                id: a.id.to_string(),
+                from: 100,
+                duration_in_frames: 50,
+                kind: crate::ItemKind::Video {
+                    src: "a.mov".to_string(),
+                    frame_rate: None,
+                    in_offset_sec: 0.0,
+                    rate: 1.0,
+                },
+            }],
+        });
+        sequence.graph = graph.clone();
+        let migrated = migrate_sequence_tracks(&sequence);
+        assert_eq!(migrated.markers, vec![chapter]);
+        let clip_markers: Vec<_> = migrated
+            .nodes
+            .values()
+            .filter_map(|n| match &n.kind {
+                TimelineNodeKind::Clip(clip) => Some(clip.markers.clone()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(clip_markers, vec![vec![note]]);
+
+        history.undo(&mut graph).unwrap();
+        history.undo(&mut graph).unwrap();
+        assert!(graph.markers.is_empty());
+        assert!(graph.clip_markers_on_timeline().is_empty());
+    }
+
+    #[test]
+    fn journal_replays_on_a_fresh_migration() {
+        let item = |id: &str, from: Frame, kind: crate::ItemKind| crate::Item {
+            id: id.to_string(),
+            from,
+            duration_in_frames: 30,
+            kind,
+        };
+        let video = |src: &str| crate::ItemKind::Video {
+            src: src.to_string(),
+            frame_rate: None,
+            in_offset_sec: 0.0,
+            rate: 1.0,
+        };
+        let mut sequence = crate::Sequence::new("seq", 1920, 1080, crate::Fps::new(30, 1), 90);
+        sequence.tracks.push(crate::Track {
+            name: "V1".to_string(),
+            items: vec![item("a", 0, video("a.mov")), item("b", 30, video("b.mov"))],
+        });
+        sequence.tracks.push(crate::Track {
+            name: "A1".to_string(),
+            items: vec![item(
+                "a-audio",
+                0,
+                crate::ItemKind::Audio {
+                    src: "a.mov".to_string(),
+                    in_offset_sec: 0.0,
+                    rate: 1.0,
+                },
+            )],
+        });
+
+        let mut graph = migrate_sequence_tracks(&sequence);
+        assert_eq!(graph, migrate_sequence_tracks(&sequence));
+
+        let first = graph.tracks[0].node_ids[0];
+        let mut history = CommandHistory::default();
+        let mut journal = Vec::new();
+        for command in [
+            TimelineCommand::RippleTrim {
+                node_id: first,
+                edge: TrimEdge::End,
+                delta: -10,
+            },
+            TimelineCommand::SplitNode {
+                node_id: graph.tracks[0].node_ids[1],
+                frame: 30,
+            },
+        ] {
+            let command = resolve_command(&graph, command).unwrap();
+            history
+                .apply_labeled(&mut graph, command.label(), command.clone())
+                .unwrap();
+            journal.push(crate::JournalEntry::apply(command.label(), command));
+        }
+
+        let mut reopened = migrate_sequence_tracks(&sequence);
+        let mut replayed = CommandHistory::default();
+        let applied = crate::replay_journal(&mut reopened, &mut replayed, journal).unwrap();
+        assert_eq!(applied, 2);
+        assert_eq!(reopened, graph);
+    }
+
+    #[test]
+    fn collapsed_clips_keep_their_legacy_items() {
+        let a = clip(10, 20, 0);
+        let b = clip(30, 20, 5);
+        let sound = clip(10, 20, 0);
+        let mut graph = graph_with(&[a.clone(), b.clone()]);
+        graph.tracks.push(TrackBinding {
+            id: TrackId::new(),
+            name: "A1".to_string(),
+            kind: TrackKind::Audio,
+            node_ids: vec![sound.id],
+            properties: Default::default(),
+        });
+        graph.nodes.insert(sound.id, sound.clone());
+        let compound_id = NodeId::new();
+        apply_command(
+            &mut graph,
+            TimelineCommand::CollapseToCompound {
+                node_ids: vec![a.id, b.id, sound.id],
+                compound_id,
+                sequence_id: "sub".to_string(),
+            },
+        )
+        .unwrap();
+        apply_command(
+            &mut graph,
+            TimelineCommand::RippleTrim {
+                node_id: compound_id,
+                edge: TrimEdge::Start,
+                delta: 5,
+            },
+        )
+        .unwrap();
+
+        let mut sequence = crate::Sequence::new("seq", 1920, 1080, crate::Fps::new(30, 1), 0);
+        sequence.graph = graph;
+        sequence.sync_tracks_from_graph();
+        let items: Vec<_> = sequence.tracks[0]
+            .items
+            .iter()
+            .map(|item| {
+                let audio = matches!(item.kind, crate::ItemKind::Audio { .. });
+                (item.id.clone(), item.from, item.duration_in_frames, audio)
+            })
+            .collect();
+        let nested = |id: NodeId| format!("{}/{}", compound_id, id);
+        assert_eq!(
+            items,
+            vec![
+                (nested(a.id), 10, 15, false),
+                (nested(sound.id), 10, 15, true),
+                (nested(b.id), 25, 20, false),
+            ]
+        );
+        assert!(sequence.tracks[1].items.is_empty());
+        assert_eq!(sequence.duration_in_frames, 45);
+    }
+
+    #[test]
+    fn compounds_refuse_to_land_on_other_clips() {
+        let with_v2 = |v1: &[TimelineNode], v2: &TimelineNode| {
+            let mut graph = graph_with(v1);
+            graph.tracks.push(TrackBinding {
+                id: TrackId::new(),
+                name: "V2".to_string(),
+                kind: TrackKind::Video,
+                node_ids: vec![v2.id],
+                properties: Default::default(),
+            });
+            graph.nodes.insert(v2.id, v2.clone());
+            graph
+        };
+        let collapse = |node_ids| TimelineCommand::CollapseToCompound {
+            node_ids,
+            compound_id: NodeId::new(),
+            sequence_id: "sub".to_string(),
+        };
+
+        // The compound would cover `c`, which stays on V1.
+        let (a, c, d) = (clip(10, 20, 0), clip(40, 10, 0), clip(10, 40, 0));
+        let mut graph = with_v2(&[a.clone(), c], &d);
+        let before = graph.clone();
+        assert!(matches!(
+            apply_command(&mut graph, collapse(vec![a.id, d.id])),
+            Err(TimelineError::InvalidOp(_))
+        ));
+        assert_eq!(graph, before);
+
+        // Expanding would put `d` back on V2 over a clip added since.
+        let d = clip(10, 20, 0);
+        let mut graph = with_v2(std::slice::from_ref(&a), &d);
+        apply_command(&mut graph, collapse(vec![a.id, d.id])).unwrap();
+        let compound_id = graph.tracks[0].node_ids[0];
+        let v2 = graph.tracks[1].id;
+        apply_command(
+            &mut graph,
+            TimelineCommand::InsertNode {
+                node: clip(15, 5, 0),
+                placements: vec![TrackPlacement {
+                    track_id: v2,
+                    position: None,
+                }],
+                edges: Vec::new(),
+            },
+        )
+        .unwrap();
+        let collapsed = graph.clone();
+        assert!(matches!(
+            apply_command(
+                &mut graph,
+                TimelineCommand::ExpandCompound {
+                    node_id: compound_id
+                }
+            ),
+            Err(TimelineError::InvalidOp(_))
+        ));
+        assert_eq!(graph, collapsed);
+    }
+
+    #[test]
+    fn collapse_and_expand_keep_track_properties() {
+        let a = clip(10, 20, 0);
+        let b = clip(30, 20, 5);
+        let mut graph = graph_with(&[a.clone(), b.clone()]);
+        let v1 = graph.tracks[0].id;
+        graph.tracks[0].properties = TrackProperties {
+            muted: true,
+            opacity: 0.5,
+            blend_mode: BlendMode::Screen,
+            ..TrackProperties::default()
+        };
+        let before = graph.clone();
+        let mut history = CommandHistory::default();
+        let compound_id = NodeId::new();
+
+        history
+            .apply(
+                &mut graph,
+                TimelineCommand::CollapseToCompound {
+                    node_ids: vec![a.id, b.id],
+                    compound_id,
+                    sequence_id: "sub".to_string(),
+                },
+            )
+            .unwrap();
+        let mut inner = graph.sequence("sub").unwrap().clone();
+        assert_eq!(inner.tracks[0].properties, before.tracks[0].properties);
+
+        // Lock the nested track, then move the compound to a new track and
+        // drop V1 so expansion has to recreate it.
+        inner.tracks[0].properties.locked = true;
+        let v2 = TrackBinding {
+            id: TrackId::new(),
+            name: "V2".to_string(),
+            kind: TrackKind::Video,
+            node_ids: vec![compound_id],
+            properties: Default::default(),
+        };
+        history
+            .apply(
+                &mut graph,
+                TimelineCommand::Batch {
+                    commands: vec![
+                        TimelineCommand::UpsertSequence {
+                            sequence_id: "sub".to_string(),
+                            graph: Box::new(inner),
+                        },
+                        TimelineCommand::RemoveTrack { track_id: v1 },
+                        TimelineCommand::UpsertTrack { track: v2 },
+                    ],
+                },
+            )
+            .unwrap();
+        history
+            .apply(
+                &mut graph,
+                TimelineCommand::ExpandCompound {
+                    node_id: compound_id,
+                },
+            )
+            .unwrap();
+        let recreated = graph.tracks.iter().find(|t| t.name == "V1").unwrap();
+        assert_eq!(recreated.node_ids.len(), 2);
+        assert_eq!(
+            recreated.properties,
+            TrackProperties {
+                locked: true,
+                ..before.tracks[0].properties.clone()
+            }
+        );
+
+        while history.undo_label().is_some() {
+            history.undo(&mut graph).unwrap();
+        }
+        assert_eq!(graph, before);
+    }
+
+    #[test]
+    fn collapse_and_expand_compound_round_trip() {
+        let a = clip(10, 20, 0);
+        let b = clip(30, 20, 5);
+        let c = clip(60, 10, 0);
+        let mut graph = graph_with(&[a.clone(), b.clone(), c.clone()]);
+        let before = graph.clone();
+        let mut history = CommandHistory::default();
+        let compound_id = NodeId::new();
+
+        history
+            .apply(
+                &mut graph,
+                TimelineCommand::CollapseToCompound {
+                    node_ids: vec![a.id, b.id],
+                    compound_id,
+                    sequence_id: "sub".to_string(),
+                },
+            )
+            .unwrap();
+        assert_eq!(graph.nodes.len(), 2);
+        let inner = graph.sequence("sub").unwrap();
+        assert_eq!(range(inner, a.id).0, FrameRange::new(0, 20));
+        assert_eq!(range(inner, b.id).0, FrameRange::new(20, 20));
+        let TimelineNodeKind::Compound(compound) = &graph.nodes[&compound_id].kind else {
+            panic!("expected compound clip");
+        };
+        assert_eq!(compound.timeline_range, FrameRange::new(10, 40));
+        assert_eq!(compound.inner_frame(25), 15);
+
+        // Trim the compound's head so expansion has to cut into `a`.
+        history
+            .apply(
+                &mut graph,
+                TimelineCommand::RippleTrim {
+                    node_id: compound_id,
+                    edge: TrimEdge::Start,
+                    delta: 5,
+                },
+            )
+            .unwrap();
+        history
+            .apply(
+                &mut graph,
+                TimelineCommand::ExpandCompound {
+                    node_id: compound_id,
+                },
+            )
+            .unwrap();
+        let mut ranges: Vec<_> = graph
+            .nodes
+            .values()
+            .filter(|n| n.id != c.id)
+            .map(|n| range(&graph, n.id))
+            .collect();
+        ranges.sort_by_key(|(r, _)| r.start);
+        assert_eq!(
+            ranges,
+            vec![
+                (FrameRange::new(10, 15), Some(FrameRange::new(5, 15))),
+                (FrameRange::new(25, 20), Some(FrameRange::new(5, 20))),
+            ]
+        );
+        assert!(matches!(
+            apply_command(
+                &mut graph,
+                TimelineCommand::RemoveSequence {
+                    sequence_id: "missing".to_string()
+                }
+            ),
+            Err(TimelineError::SequenceNotFound(_))
+        ));
+
+        while history.undo_label().is_some() {
+            history.undo(&mut graph).unwrap();
+        }
+        assert_eq!(graph, before);
+    }
+}
@@ -0,0 +1,412 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    AutomationKeyframe, ClipNode, Fps, Frame, FrameRange, Marker, Sequence, TimelineError,
+    TimelineGraph, TimelineNodeKind, TrackKind,
+};
+
+/// How a frame that lands between two frames of the new rate is snapped.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FrameRounding {
+    #[default]
+    Nearest,
+    Down,
+    Up,
+}
+
+/// How pictures framed for the old aspect ratio are placed in the new one.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReframeMode {
+    /// Show the whole old frame, leaving bars where the aspects differ.
+    #[default]
+    Fit,
+    /// Cover the whole new frame, cropping what falls outside.
+    Fill,
+}
+
+/// Placement of a clip's picture after the sequence it was framed for was
+/// resized: scaled by `scale` and centred, then moved by the offsets, all
+/// in pixels of the current sequence size. Stored in the clip's metadata
+/// under `"reframe"`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct Reframe {
+    pub scale: f64,
+    #[serde(default)]
+    pub offset_x: f64,
+    #[serde(default)]
+    pub offset_y: f64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ConformOptions {
+    pub fps: Fps,
+    pub width: u32,
+    pub height: u32,
+    pub rounding: FrameRounding,
+    pub reframe: ReframeMode,
+}
+
+impl ClipNode {
+    /// Reframing recorded by [`Sequence::conform`], if any.
+    pub fn reframe(&self) -> Option<Reframe> {
+        serde_json::from_value(self.metadata.get("reframe")?.clone()).ok()
+    }
+}
+
+impl Sequence {
+    /// Move the sequence to a new frame rate and size.
+    ///
+    /// Timeline positions, automation keyframes, markers and transition
+    /// lengths are converted to the new rate, in nested sequences and the
+    /// legacy track list too. Start and end of each range are converted
+    /// separately so neighbouring clips stay butted together. Media frames
+    /// are only converted for clips that count them at the sequence rate,
+    /// which are those without `media_fps_num`/`media_fps_den` metadata.
+    ///
+    /// When the aspect ratio changes, every picture clip gets a [`Reframe`],
+    /// and clips that already had one are rescaled to the new size.
+    pub fn conform(&mut self, options: &ConformOptions) -> Result<(), TimelineError> {
+        let ConformOptions {
+            fps,
+            width,
+            height,
+            rounding,
+            reframe,
+        } = *options;
+        if fps.num == 0 || fps.den == 0 || width == 0 || height == 0 {
+            return Err(TimelineError::InvalidOp(
+                "conform needs a non-zero frame rate and size".to_string(),
+            ));
+        }
+        let time = Rescale {
+            from: self.fps,
+            to: fps,
+            rounding,
+        };
+        let sx = width as f64 / self.width.max(1) as f64;
+        let sy = height as f64 / self.height.max(1) as f64;
+        let aspect_changed = self.width as u64 * height as u64 != width as u64 * self.height as u64;
+        let scale = match reframe {
+            ReframeMode::Fit => sx.min(sy),
+            ReframeMode::Fill => sx.max(sy),
+        };
+        let frame = Reframing {
+            scale,
+            add_missing: aspect_changed,
+        };
+
+        conform_graph(&mut self.graph, &time, &frame);
+        for inner in self.graph.sequences.values_mut() {
+            conform_graph(inner, &time, &frame);
+        }
+        for item in self.tracks.iter_mut().flat_map(|t| t.items.iter_mut()) {
+            let range = time.range(&FrameRange::new(item.from, item.duration_in_frames));
+            item.from = range.start;
+            item.duration_in_frames = range.duration;
+        }
+        self.duration_in_frames = time.frame(self.duration_in_frames);
+        self.fps = fps;
+        self.width = width;
+        self.height = height;
+        Ok(())
+    }
+}
+
+struct Rescale {
+    from: Fps,
+    to: Fps,
+    rounding: FrameRounding,
+}
+
+impl Rescale {
+    fn frame(&self, frame: Frame) -> Frame {
+        let num = frame as i128 * self.from.den as i128 * self.to.num as i128;
+        let den = self.from.num as i128 * self.to.den as i128;
+        let scaled = match self.rounding {
+            FrameRounding::Down => num.div_euclid(den),
+            FrameRounding::Up => -(-num).div_euclid(den),
+            FrameRounding::Nearest => (2 * num + den).div_euclid(2 * den),
+        };
+        scaled as Frame
+    }
+
+    fn exact(&self, frame: f64) -> f64 {
+        frame * self.from.den as f64 * self.to.num as f64
+            / (self.from.num as f64 * self.to.den as f64)
+    }
+
+    /// A length that has no position of its own, kept at least one frame.
+    fn length(&self, frames: Frame) -> Frame {
+        self.frame(frames).max(1)
+    }
+
+    fn range(&self, range: &FrameRange) -> FrameRange {
+        let start = self.frame(range.start);
+        let end = self.frame(range.end()).max(start + range.duration.min(1));
+        FrameRange::new(start, end - start)
+    }
+
+    fn marker(&self, marker: &mut Marker) {
+        let range = self.range(&FrameRange::new(marker.frame, marker.duration.unwrap_or(0)));
+        marker.frame = range.start;
+        marker.duration = marker.duration.map(|_| range.duration);
+    }
+
+    fn keyframes(&self, keyframes: &mut Vec<AutomationKeyframe>) {
+        for key in keyframes.iter_mut() {
+            key.frame = self.frame(key.frame);
+        }
+        // Keys that now share a frame would make the curve jump; keep the first.
+        keyframes.dedup_by_key(|k| k.frame);
+    }
+}
+
+struct Reframing {
+    scale: f64,
+    add_missing: bool,
+}
+
+impl Reframing {
+    fn apply(&self, clip: &mut ClipNode) {
+        let reframe = match clip.reframe() {
+            Some(r) => Reframe {
+                scale: r.scale * self.scale,
+                offset_x: r.offset_x * self.scale,
+                offset_y: r.offset_y * self.scale,
+            },
+            None if self.add_missing => Reframe {
+                scale: self.scale,
+                offset_x: 0.0,
+                offset_y: 0.0,
+            },
+            None => return,
+        };
+        if clip.metadata.is_null() {
+            clip.metadata = Value::Object(Default::default());
+        }
+        if let (Value::Object(map), Ok(value)) = (&mut clip.metadata, serde_json::to_value(reframe))
+        {
+            map.insert("reframe".to_string(), value);
+        }
+    }
+}
+
+fn conform_graph(graph: &mut TimelineGraph, time: &Rescale, frame: &Reframing) {
+    let sound_only: Vec<_> = graph
+        .nodes
+        .keys()
+        .filter(|id| {
+            let mut kinds = graph
+                .tracks
+                .iter()
+                .filter(|t| t.node_ids.contains(id))
+                .map(|t| &t.kind)
+                .peekable();
+            kinds.peek().is_some() && kinds.all(|k| *k == TrackKind::Audio)
+        })
+        .copied()
+        .collect();
+
+    for node in graph.nodes.values_mut() {
+        match &mut node.kind {
+            TimelineNodeKind::Clip(clip) => {
+                conform_clip(clip, time);
+                if !sound_only.contains(&node.id) {
+                    frame.apply(clip);
+                }
+            }
+            TimelineNodeKind::Compound(compound) => {
+                compound.timeline_range = time.range(&compound.timeline_range);
+                compound.media_range = time.range(&compound.media_range);
+            }
+            TimelineNodeKind::Transition(transition) => {
+                transition.duration = time.length(transition.duration);
+            }
+            TimelineNodeKind::Generator { timeline_range, .. } => {
+                *timeline_range = time.range(timeline_range);
+            }
+            TimelineNodeKind::Effect { .. } => {}
+        }
+    }
+    for lane in &mut graph.automation {
+        time.keyframes(&mut lane.keyframes);
+    }
+    for marker in &mut graph.markers {
+        time.marker(marker);
+    }
+    graph.invalidate_index();
+}
+
+fn conform_clip(clip: &mut ClipNode, time: &Rescale) {
+    let media_at_sequence_rate = !matches!(
+        &clip.metadata,
+        Value::Object(map) if map.contains_key("media_fps_num") && map.contains_key("media_fps_den")
+    );
+    let old_duration = clip.timeline_range.duration;
+    clip.timeline_range = time.range(&clip.timeline_range);
+    if media_at_sequence_rate {
+        clip.media_range = time.range(&clip.media_range);
+        for marker in &mut clip.markers {
+            time.marker(marker);
+        }
+    }
+    if let Some(mut remap) = clip.time_remap.take() {
+        let ends_clip = remap.duration() == old_duration;
+        time.keyframes(&mut remap.keyframes);
+        if media_at_sequence_rate {
+            for key in &mut remap.keyframes {
+                key.value = time.exact(key.value);
+            }
+        }
+        // Pin the last key to the clip end, which may have rounded the
+        // other way.
+        if let Some(last) = remap.keyframes.iter_mut().max_by_key(|k| k.frame) {
+            if ends_clip {
+                last.frame = clip.timeline_range.duration;
+            }
+        }
+        clip.set_time_remap(Some(remap));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        AutomationLane, AutomationTarget, LaneId, NodeId, TimeRemap, TimelineNode, TrackBinding,
+        TrackId,
+    };
+    use serde_json::json;
+
+    fn clip(start: Frame, duration: Frame, metadata: Value) -> TimelineNode {
+        TimelineNode {
+            id: NodeId::new(),
+            label: None,
+            kind: TimelineNodeKind::Clip(ClipNode {
+                asset_id: Some("a.mov".to_string()),
+                media_range: FrameRange::new(0, duration),
+                timeline_range: FrameRange::new(start, duration),
+                playback_rate: 1.0,
+                reverse: false,
+                time_remap: None,
+                markers: Vec::new(),
+                metadata,
+            }),
+            locked: false,
+            metadata: Value::Null,
+        }
+    }
+
+    fn clip_of(sequence: &Sequence, node: &TimelineNode) -> ClipNode {
+        match &sequence.graph.nodes[&node.id].kind {
+            TimelineNodeKind::Clip(clip) => clip.clone(),
+            _ => unreachable!(),
+        }
+    }
+
+    fn options(fps: Fps, width: u32, height: u32, rounding: FrameRounding) -> ConformOptions {
+        ConformOptions {
+            fps,
+            width,
+            height,
+            rounding,
+            reframe: ReframeMode::Fill,
+        }
+    }
+
+    #[test]
+    fn conform_rescales_time_and_reframes_on_aspect_change() {
+        let own_rate = json!({ "media_fps_num": 24, "media_fps_den": 1 });
+        let a = clip(0, 47, own_rate.clone());
+        let b = clip(47, 49, Value::Null);
+        let mut ramped = clip(96, 48, own_rate);
+        if let TimelineNodeKind::Clip(c) = &mut ramped.kind {
+            c.set_time_remap(Some(TimeRemap::constant(0.0, 48, 0.5)));
+        }
+        let sound = clip(0, 47, Value::Null);
+        let mut seq = Sequence::new("Edit", 1920, 1080, Fps::new(24, 1), 144);
+        seq.graph.nodes = [&a, &b, &ramped, &sound]
+            .into_iter()
+            .map(|n| (n.id, n.clone()))
+            .collect();
+        seq.graph.tracks = vec![
+            TrackBinding {
+                id: TrackId::new(),
+                name: "V1".to_string(),
+                kind: TrackKind::Video,
+                node_ids: vec![a.id, b.id, ramped.id],
+                properties: Default::default(),
+            },
+            TrackBinding {
+                id: TrackId::new(),
+                name: "A1".to_string(),
+                kind: TrackKind::Audio,
+                node_ids: vec![sound.id],
+                properties: Default::default(),
+            },
+        ];
+        seq.graph.automation.push(AutomationLane {
+            id: LaneId::new(),
+            target: AutomationTarget {
+                node: a.id,
+                parameter: "opacity".to_string(),
+            },
+            interpolation: Default::default(),
+            keyframes: vec![
+                AutomationKeyframe {
+                    frame: 0,
+                    value: 0.0,
+                    easing: Default::default(),
+                },
+                AutomationKeyframe {
+                    frame: 24,
+                    value: 1.0,
+                    easing: Default::default(),
+                },
+            ],
+        });
+        seq.graph.markers.push(Marker {
+            duration: Some(24),
+            ..Marker::new(48, Default::default())
+        });
+
+        let mut up = seq.clone();
+        up.conform(&options(
+            Fps::new(25, 1),
+            1080,
+            1920,
+            FrameRounding::Nearest,
+        ))
+        .unwrap();
+        let (ca, cb, cr) = (clip_of(&up, &a), clip_of(&up, &b), clip_of(&up, &ramped));
+        // 47 and 96 frames at 24 fps land on 48.96 and 100 at 25 fps.
+        assert_eq!(ca.timeline_range, FrameRange::new(0, 49));
+        assert_eq!(cb.timeline_range, FrameRange::new(49, 51));
+        assert_eq!(cr.timeline_range, FrameRange::new(100, 50));
+        // Media counted at its own rate stays put; media at the sequence
+        // rate moves with the timeline.
+        assert_eq!(ca.media_range, FrameRange::new(0, 47));
+        assert_eq!(cb.media_range, FrameRange::new(0, 51));
+        assert_eq!(cr.media_range, FrameRange::new(0, 24));
+        assert_eq!(up.graph.automation[0].keyframes[1].frame, 25);
+        assert_eq!(up.graph.markers[0].frame, 50);
+        assert_eq!(up.graph.markers[0].duration, Some(25));
+        assert_eq!(up.duration_in_frames, 150);
+        assert_eq!((up.fps.num, up.width, up.height), (25, 1080, 1920));
+
+        // 16:9 into 9:16, filled: scaled by the height ratio.
+        let fill = ca.reframe().unwrap();
+        assert!((fill.scale - 1920.0 / 1080.0).abs() < 1e-9);
+        assert_eq!(clip_of(&up, &sound).reframe(), None);
+
+        // The rounding policy decides frames that fall between two.
+        let mut down = seq.clone();
+        down.conform(&options(Fps::new(25, 1), 1920, 1080, FrameRounding::Down))
+            .unwrap();
+        assert_eq!(clip_of(&down, &a).timeline_range, FrameRange::new(0, 48));
+        assert_eq!(clip_of(&down, &a).reframe(), None);
+    }
+}
@@ -52,6 +52,28 @@ impl fmt::Display for LaneId {
     }
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(transparent)]
+pub struct MarkerId(pub Uuid);
+
+impl MarkerId {
+    pub fn new() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl Default for MarkerId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for MarkerId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct FrameRange {
     pub start: Frame,
@@ -76,10 +98,77 @@ pub struct ClipNode {
     pub playback_rate: f32,
     #[serde(default)]
     pub reverse: bool,
+    /// Speed ramp from clip frames to media frames. When set it decides
+    /// `media_range` and the timeline duration.
+    #[serde(default)]
+    pub time_remap: Option<crate::TimeRemap>,
+    /// Markers in media frames, so they stay with the footage when the clip
+    /// is trimmed, slipped or moved.
+    #[serde(default)]
+    pub markers: Vec<Marker>,
     #[serde(default)]
     pub metadata: serde_json::Value,
 }
 
+impl ClipNode {
+    /// Timeline frame a clip marker lands on, or `None` if the clip never
+    /// shows its media frame. Follows speed, reverse and remap curves: the
+    /// first timeline frame showing the marker's frame wins, and a frame
+    /// skipped by fast playback lands where playback passes over it.
+    pub fn marker_timeline_frame(&self, marker: &Marker) -> Option<Frame> {
+        let target = marker.frame as f64;
+        let range = &self.timeline_range;
+        let rate = self.playback_rate as f64;
+        if self.time_remap.is_none() && rate > 0.0 {
+            // Constant speed is monotonic, so invert it and check the frames
+            // around the estimate to absorb rounding.
+            let offset = if self.reverse {
+                ((self.media_range.end() as f64 - target - 1.0) / rate - 1.0).floor() + 1.0
+            } else {
+                ((target - self.media_range.start as f64) / rate).ceil()
+            };
+            let estimate = range.start + (offset as Frame).max(0);
+            return (estimate - 1..=estimate + 1)
+                .filter(|frame| *frame >= range.start && *frame < range.end())
+                .find(|frame| self.shows_media_frame(*frame, target));
+        }
+        (range.start..range.end()).find(|frame| self.shows_media_frame(*frame, target))
+    }
+
+    /// Whether timeline `frame` shows media frame `target`, or is where
+    /// playback passes over it.
+    fn shows_media_frame(&self, frame: Frame, target: f64) -> bool {
+        let Some(media) = self.media_time_at(frame) else {
+            return false;
+        };
+        if media.floor() == target {
+            return true;
+        }
+        match self.media_time_at(frame - 1) {
+            Some(prev) => (prev < target && target < media) || (media < target && target < prev),
+            None => false,
+        }
+    }
+}
+
+/// A clip whose media is another sequence. `media_range` is a window onto the
+/// inner sequence's timeline.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CompoundNode {
+    pub sequence_id: String,
+    pub media_range: FrameRange,
+    pub timeline_range: FrameRange,
+    #[serde(default)]
+    pub metadata: serde_json::Value,
+}
+
+impl CompoundNode {
+    /// Frame of the inner sequence shown at timeline `frame`.
+    pub fn inner_frame(&self, frame: Frame) -> Frame {
+        self.media_range.start + (frame - self.timeline_range.start)
+    }
+}
+
 fn default_playback_rate() -> f32 {
     1.0
 }
@@ -161,6 +250,68 @@ impl Default for KeyframeEasing {
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Marker {
+    pub id: MarkerId,
+    pub frame: Frame,
+    /// Length of a range marker; `None` for a point marker.
+    #[serde(default)]
+    pub duration: Option<Frame>,
+    #[serde(default)]
+    pub kind: MarkerKind,
+    #[serde(default)]
+    pub color: MarkerColor,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub note: String,
+}
+
+impl Marker {
+    pub fn new(frame: Frame, kind: MarkerKind) -> Self {
+        Self {
+            id: MarkerId::new(),
+            frame,
+            duration: None,
+            kind,
+            color: MarkerColor::default(),
+            name: String::new(),
+            note: String::new(),
+        }
+    }
+
+    /// Span covered by the marker; point markers are one frame long.
+    pub fn range(&self) -> FrameRange {
+        FrameRange::new(self.frame, self.duration.unwrap_or(1).max(1))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum MarkerKind {
+    #[default]
+    Standard,
+    Chapter,
+    Todo,
+    Completed,
+    Comment,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum MarkerColor {
+    Red,
+    Orange,
+    Yellow,
+    Green,
+    Cyan,
+    #[default]
+    Blue,
+    Purple,
+    Magenta,
+    White,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct TimelineNode {
     pub id: NodeId,
@@ -172,10 +323,41 @@ pub struct TimelineNode {
     pub metadata: serde_json::Value,
 }
 
+impl TimelineNode {
+    /// Span the node occupies on the timeline, if it has one.
+    pub fn timeline_range(&self) -> Option<&FrameRange> {
+        match &self.kind {
+            TimelineNodeKind::Clip(clip) => Some(&clip.timeline_range),
+            TimelineNodeKind::Compound(compound) => Some(&compound.timeline_range),
+            TimelineNodeKind::Generator { timeline_range, .. } => Some(timeline_range),
+            _ => None,
+        }
+    }
+
+    pub fn timeline_range_mut(&mut self) -> Option<&mut FrameRange> {
+        match &mut self.kind {
+            TimelineNodeKind::Clip(clip) => Some(&mut clip.timeline_range),
+            TimelineNodeKind::Compound(compound) => Some(&mut compound.timeline_range),
+            TimelineNodeKind::Generator { timeline_range, .. } => Some(timeline_range),
+            _ => None,
+        }
+    }
+
+    /// Source window of nodes that play media: clips and compound clips.
+    pub fn media_range_mut(&mut self) -> Option<&mut FrameRange> {
+        match &mut self.kind {
+            TimelineNodeKind::Clip(clip) => Some(&mut clip.media_range),
+            TimelineNodeKind::Compound(compound) => Some(&mut compound.media_range),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum TimelineNodeKind {
     Clip(ClipNode),
+    Compound(CompoundNode),
     Transition(TransitionNode),
     Generator {
         generator_id: String,
@@ -203,6 +385,10 @@ pub enum EdgeKind {
     Layer,
     TransitionInput,
     Automation,
+    /// Nodes that edit together, such as the picture and sound of one shot.
+    /// Direction carries no meaning; a link group is every node reachable
+    /// through `Link` edges.
+    Link,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -212,6 +398,65 @@ pub struct TrackBinding {
     pub kind: TrackKind,
     #[serde(default)]
     pub node_ids: Vec<NodeId>,
+    #[serde(flatten)]
+    pub properties: TrackProperties,
+}
+
+/// Mix and composite settings of a track, serialized alongside its binding.
+///
+/// `volume` is a linear gain (1.0 is unity), `pan` runs from -1.0 (left) to
+/// 1.0 (right) and `opacity` from 0.0 to 1.0. A locked track refuses edits to
+/// the nodes on it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct TrackProperties {
+    pub muted: bool,
+    pub solo: bool,
+    pub locked: bool,
+    pub hidden: bool,
+    pub volume: f32,
+    pub pan: f32,
+    pub opacity: f32,
+    pub blend_mode: BlendMode,
+}
+
+impl Default for TrackProperties {
+    fn default() -> Self {
+        Self {
+            muted: false,
+            solo: false,
+            locked: false,
+            hidden: false,
+            volume: 1.0,
+            pan: 0.0,
+            opacity: 1.0,
+            blend_mode: BlendMode::Normal,
+        }
+    }
+}
+
+impl TrackProperties {
+    /// Left and right gains from `volume` and `pan`. Panning attenuates the
+    /// opposite side and leaves the near side at full volume.
+    pub fn stereo_gain(&self) -> [f32; 2] {
+        let pan = self.pan.clamp(-1.0, 1.0);
+        [
+            self.volume * (1.0 - pan).min(1.0),
+            self.volume * (1.0 + pan).min(1.0),
+        ]
+    }
+}
+
+/// How a track's picture combines with the tracks beneath it.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BlendMode {
+    #[default]
+    Normal,
+    Multiply,
+    Screen,
+    Overlay,
+    Add,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -229,7 +474,9 @@ impl Default for TrackKind {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+/// Deserializing runs the graph through [`crate::MIGRATIONS`] first; see
+/// [`crate::migrate_graph_value`].
+#[derive(Debug, Clone, Serialize, PartialEq)]
 pub struct TimelineGraph {
     pub version: u16,
     pub nodes: HashMap<NodeId, TimelineNode>,
@@ -239,18 +486,115 @@ pub struct TimelineGraph {
     #[serde(default)]
     pub automation: Vec<AutomationLane>,
     #[serde(default)]
+    pub markers: Vec<Marker>,
+    /// Graphs of nested sequences referenced by compound clips, keyed by
+    /// sequence id. Only the root graph's map is consulted.
+    #[serde(default)]
+    pub sequences: HashMap<String, TimelineGraph>,
+    #[serde(default)]
     pub metadata: serde_json::Value,
+    #[serde(skip)]
+    pub(crate) index: crate::index::IndexCache,
 }
 
 impl Default for TimelineGraph {
     fn default() -> Self {
         Self {
-            version: 1,
+            version: crate::GRAPH_VERSION,
             nodes: HashMap::new(),
             edges: Vec::new(),
             tracks: Vec::new(),
             automation: Vec::new(),
+            markers: Vec::new(),
+            sequences: HashMap::new(),
             metadata: serde_json::Value::Null,
+            index: Default::default(),
+        }
+    }
+}
+
+impl TimelineGraph {
+    /// First track binding that references `node_id`.
+    pub fn track_for_node(&self, node_id: NodeId) -> Option<&TrackBinding> {
+        self.tracks.iter().find(|t| t.node_ids.contains(&node_id))
+    }
+
+    /// Whether track `index` is heard: it is not muted and, while any track
+    /// is soloed, it is soloed too. Indices past the last track count as
+    /// audible so callers holding legacy tracks need no special case.
+    pub fn track_audible(&self, index: usize) -> bool {
+        let Some(track) = self.tracks.get(index) else {
+            return true;
+        };
+        let any_solo = self.tracks.iter().any(|t| t.properties.solo);
+        !track.properties.muted && (!any_solo || track.properties.solo)
+    }
+
+    /// Whether track `index` shows picture: it is neither hidden nor fully
+    /// transparent. Indices past the last track count as visible.
+    pub fn track_visible(&self, index: usize) -> bool {
+        let Some(track) = self.tracks.get(index) else {
+            return true;
+        };
+        !track.properties.hidden && track.properties.opacity > 0.0
+    }
+
+    /// Every other node in `node_id`'s link group, nearest links first.
+    pub fn linked_nodes(&self, node_id: NodeId) -> Vec<NodeId> {
+        let mut group = vec![node_id];
+        let mut next = 0;
+        while let Some(&current) = group.get(next) {
+            next += 1;
+            for edge in self.edges.iter().filter(|e| e.kind == EdgeKind::Link) {
+                let other = if edge.from == current {
+                    edge.to
+                } else if edge.to == current {
+                    edge.from
+                } else {
+                    continue;
+                };
+                if !group.contains(&other) && self.nodes.contains_key(&other) {
+                    group.push(other);
+                }
+            }
+        }
+        group.remove(0);
+        group
+    }
+
+    /// Nested sequence graph a compound clip refers to.
+    pub fn sequence(&self, sequence_id: &str) -> Option<&TimelineGraph> {
+        self.sequences.get(sequence_id)
+    }
+
+    /// Markers whose span overlaps `start..end`, timeline-level only.
+    pub fn markers_in(&self, start: Frame, end: Frame) -> impl Iterator<Item = &Marker> {
+        self.markers.iter().filter(move |m| {
+            let range = m.range();
+            range.start < end && range.end() > start
+        })
+    }
+
+    /// Markers of every clip mapped onto timeline frames, paired with the
+    /// clip that owns them. Markers in trimmed-away media are left out.
+    pub fn clip_markers_on_timeline(&self) -> Vec<(NodeId, Marker)> {
+        let mut out = Vec::new();
+        for node in self.nodes.values() {
+            if let TimelineNodeKind::Clip(clip) = &node.kind {
+                for marker in &clip.markers {
+                    if let Some(frame) = clip.marker_timeline_frame(marker) {
+                        out.push((
+                            node.id,
+                            Marker {
+                                frame,
+                                ..marker.clone()
+                            },
+                        ));
+                    }
+                }
+            }
         }
+        out.sort_by_key(|(_, m)| m.frame);
+        out
     }
 }
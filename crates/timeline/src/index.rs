@@ -0,0 +1,469 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::OnceLock;
+
+use crate::{Frame, NodeId, TimelineGraph};
+
+/// A node's timeline span as stored in [`TimelineIndex`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexedSpan {
+    pub node_id: NodeId,
+    pub start: Frame,
+    pub end: Frame,
+}
+
+/// Sorted spans of one track. `reach[i]` is the furthest end among
+/// `spans[..=i]`, which lets a query stop walking left as soon as nothing
+/// earlier can still cover the frame, even when spans overlap.
+#[derive(Debug, Clone, Default)]
+struct TrackSpans {
+    spans: Vec<IndexedSpan>,
+    reach: Vec<Frame>,
+}
+
+impl TrackSpans {
+    fn overlapping(&self, start: Frame, end: Frame) -> Vec<IndexedSpan> {
+        let mut out = Vec::new();
+        let mut i = self.spans.partition_point(|s| s.start < end);
+        while i > 0 {
+            i -= 1;
+            if self.reach[i] <= start {
+                break;
+            }
+            if self.spans[i].end > start {
+                out.push(self.spans[i]);
+            }
+        }
+        out.reverse();
+        out
+    }
+
+    fn insert(&mut self, span: IndexedSpan) {
+        let at = self
+            .spans
+            .partition_point(|s| (s.start, s.end) <= (span.start, span.end));
+        self.spans.insert(at, span);
+        self.refresh_reach(at);
+    }
+
+    fn remove(&mut self, span: &IndexedSpan) {
+        let from = self
+            .spans
+            .partition_point(|s| (s.start, s.end) < (span.start, span.end));
+        if let Some(offset) = self.spans[from..]
+            .iter()
+            .position(|s| s.node_id == span.node_id)
+        {
+            self.spans.remove(from + offset);
+            self.refresh_reach(from + offset);
+        }
+    }
+
+    /// Recompute `reach` from position `from` on; the prefix is unchanged.
+    fn refresh_reach(&mut self, from: usize) {
+        self.reach.truncate(from);
+        let mut furthest = self.reach.last().copied().unwrap_or(Frame::MIN);
+        for span in &self.spans[from..] {
+            furthest = furthest.max(span.end);
+            self.reach.push(furthest);
+        }
+    }
+}
+
+/// Interval index over the timeline ranges of every node on every track.
+///
+/// Point and range lookups are a binary search per track plus the matches;
+/// edit-point lookups are a single ordered-map search. Tracks are addressed
+/// by their position in [`TimelineGraph::tracks`]. Single nodes can be
+/// re-indexed without rebuilding the rest.
+#[derive(Debug, Clone, Default)]
+pub struct TimelineIndex {
+    tracks: Vec<TrackSpans>,
+    /// Edit points with the number of spans starting or ending on each.
+    edits: BTreeMap<Frame, usize>,
+    /// Track position and span of every indexed node, for re-indexing.
+    placed: HashMap<NodeId, Vec<(usize, IndexedSpan)>>,
+}
+
+impl TimelineIndex {
+    pub fn build(graph: &TimelineGraph) -> Self {
+        let mut index = Self {
+            tracks: vec![TrackSpans::default(); graph.tracks.len()],
+            ..Self::default()
+        };
+        for (track, binding) in graph.tracks.iter().enumerate() {
+            for id in &binding.node_ids {
+                if let Some(span) = span_of(graph, *id) {
+                    index.tracks[track].spans.push(span);
+                    index.note_span(track, span);
+                }
+            }
+            let spans = &mut index.tracks[track];
+            spans.spans.sort_by_key(|s| (s.start, s.end));
+            spans.refresh_reach(0);
+        }
+        index
+    }
+
+    pub fn track_count(&self) -> usize {
+        self.tracks.len()
+    }
+
+    /// Nodes on `track` whose range contains `frame`, ordered by start.
+    pub fn at(&self, track: usize, frame: Frame) -> Vec<IndexedSpan> {
+        self.in_range(track, frame, frame + 1)
+    }
+
+    /// Nodes on `track` that overlap `[start, end)`, ordered by start.
+    pub fn in_range(&self, track: usize, start: Frame, end: Frame) -> Vec<IndexedSpan> {
+        match self.tracks.get(track) {
+            Some(spans) if start < end => spans.overlapping(start, end),
+            _ => Vec::new(),
+        }
+    }
+
+    /// First clip start or end on any track strictly after `frame`.
+    pub fn next_edit_after(&self, frame: Frame) -> Option<Frame> {
+        self.edits.range(frame + 1..).next().map(|(f, _)| *f)
+    }
+
+    /// Last clip start or end on any track strictly before `frame`.
+    pub fn prev_edit_before(&self, frame: Frame) -> Option<Frame> {
+        self.edits.range(..frame).next_back().map(|(f, _)| *f)
+    }
+
+    /// Every clip start and end across all tracks, ascending.
+    pub fn edit_points(&self) -> impl Iterator<Item = Frame> + '_ {
+        self.edits.keys().copied()
+    }
+
+    fn note_span(&mut self, track: usize, span: IndexedSpan) {
+        *self.edits.entry(span.start).or_default() += 1;
+        *self.edits.entry(span.end).or_default() += 1;
+        self.placed
+            .entry(span.node_id)
+            .or_default()
+            .push((track, span));
+    }
+
+    fn forget_edit(&mut self, frame: Frame) {
+        if let Some(count) = self.edits.get_mut(&frame) {
+            *count -= 1;
+            if *count == 0 {
+                self.edits.remove(&frame);
+            }
+        }
+    }
+
+    /// Replace the spans of `node_id` with its current range on `tracks`.
+    fn reindex(&mut self, graph: &TimelineGraph, node_id: NodeId, tracks: &[usize]) {
+        for (track, span) in self.placed.remove(&node_id).unwrap_or_default() {
+            self.tracks[track].remove(&span);
+            self.forget_edit(span.start);
+            self.forget_edit(span.end);
+        }
+        let Some(span) = span_of(graph, node_id) else {
+            return;
+        };
+        for &track in tracks {
+            self.tracks[track].insert(span);
+            self.note_span(track, span);
+        }
+    }
+}
+
+fn span_of(graph: &TimelineGraph, node_id: NodeId) -> Option<IndexedSpan> {
+    let range = graph.nodes.get(&node_id)?.timeline_range()?;
+    Some(IndexedSpan {
+        node_id,
+        start: range.start,
+        end: range.end(),
+    })
+    .filter(|span| span.end > span.start)
+}
+
+/// Lazily built [`TimelineIndex`] cached on a graph. It is not serialized and
+/// never affects graph equality.
+#[derive(Clone, Default)]
+pub(crate) struct IndexCache(OnceLock<TimelineIndex>);
+
+impl PartialEq for IndexCache {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl fmt::Debug for IndexCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = if self.0.get().is_some() {
+            "built"
+        } else {
+            "empty"
+        };
+        write!(f, "IndexCache({})", state)
+    }
+}
+
+impl TimelineGraph {
+    /// Interval index over the track layout, built on first use and kept up
+    /// to date by [`crate::apply_command`].
+    pub fn index(&self) -> &TimelineIndex {
+        self.index.0.get_or_init(|| TimelineIndex::build(self))
+    }
+
+    /// Drop the cached index so the next query rebuilds it. Code that
+    /// rearranges `tracks` directly must call this; for a few nodes whose
+    /// range or track changed, [`Self::reindex_node`] is cheaper.
+    pub fn invalidate_index(&mut self) {
+        self.index = IndexCache::default();
+    }
+
+    /// Update the cached index for one node after its range or its track
+    /// changed in place, as a drag preview does every frame.
+    pub fn reindex_node(&mut self, node_id: NodeId) {
+        let tracks: Vec<usize> = self
+            .tracks
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| t.node_ids.contains(&node_id))
+            .map(|(i, _)| i)
+            .collect();
+        self.reindex_node_on(node_id, &tracks);
+    }
+
+    /// Like [`Self::reindex_node`], for a node whose tracks are known.
+    pub(crate) fn reindex_node_on(&mut self, node_id: NodeId, tracks: &[usize]) {
+        let mut cache = std::mem::take(&mut self.index);
+        match cache.0.get_mut() {
+            // The track layout changed under the index: leave it dropped.
+            Some(index) if index.track_count() != self.tracks.len() => return,
+            Some(index) => index.reindex(self, node_id, tracks),
+            None => return,
+        }
+        self.index = cache;
+    }
+
+    /// Refresh the span of a node that kept its tracks, such as after a trim.
+    pub(crate) fn respan_node(&mut self, node_id: NodeId) {
+        let Some(index) = self.index.0.get() else {
+            return;
+        };
+        let tracks: Vec<usize> = index
+            .placed
+            .get(&node_id)
+            .map(|spans| spans.iter().map(|(track, _)| *track).collect())
+            .unwrap_or_default();
+        if tracks.is_empty() {
+            // Not indexed before, e.g. it had no duration: look it up.
+            self.reindex_node(node_id);
+        } else {
+            self.reindex_node_on(node_id, &tracks);
+        }
+    }
+
+    /// Nodes on track `track` whose range contains `frame`.
+    pub fn nodes_at(&self, track: usize, frame: Frame) -> Vec<NodeId> {
+        self.index()
+            .at(track, frame)
+            .into_iter()
+            .map(|span| span.node_id)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        apply_command, ClipNode, FrameRange, TimelineCommand, TimelineNode, TimelineNodeKind,
+        TrackBinding, TrackId, TrackKind,
+    };
+    use serde_json::Value;
+
+    fn clip(start: Frame, duration: Frame) -> TimelineNode {
+        TimelineNode {
+            id: NodeId::new(),
+            label: None,
+            kind: TimelineNodeKind::Clip(ClipNode {
+                asset_id: None,
+                media_range: FrameRange::new(0, duration),
+                timeline_range: FrameRange::new(start, duration),
+                playback_rate: 1.0,
+                reverse: false,
+                time_remap: None,
+                markers: Vec::new(),
+                metadata: Value::Null,
+            }),
+            locked: false,
+            metadata: Value::Null,
+        }
+    }
+
+    #[test]
+    fn answers_point_range_and_edit_queries_and_follows_edits() {
+        let long = clip(0, 100);
+        let short = clip(10, 5);
+        let late = clip(120, 30);
+        let track_id = TrackId::new();
+        let mut graph = TimelineGraph {
+            nodes: [&long, &short, &late]
+                .into_iter()
+                .map(|n| (n.id, n.clone()))
+                .collect(),
+            tracks: vec![TrackBinding {
+                id: track_id,
+                name: "V1".to_string(),
+                kind: TrackKind::Video,
+                node_ids: vec![late.id, short.id, long.id],
+                properties: Default::default(),
+            }],
+            ..TimelineGraph::default()
+        };
+
+        // The long clip is still found past the short one that starts later.
+        assert_eq!(graph.nodes_at(0, 50), vec![long.id]);
+        assert_eq!(graph.nodes_at(0, 12), vec![long.id, short.id]);
+        assert!(graph.nodes_at(0, 100).is_empty());
+        assert!(graph.nodes_at(1, 0).is_empty());
+        let hits: Vec<NodeId> = graph
+            .index()
+            .in_range(0, 90, 125)
+            .iter()
+            .map(|s| s.node_id)
+            .collect();
+        assert_eq!(hits, vec![long.id, late.id]);
+        assert_eq!(graph.index().next_edit_after(15), Some(100));
+        assert_eq!(graph.index().prev_edit_before(120), Some(100));
+        assert_eq!(graph.index().next_edit_after(150), None);
+
+        let mut moved = late.clone();
+        if let TimelineNodeKind::Clip(clip) = &mut moved.kind {
+            clip.timeline_range.start = 200;
+        }
+        apply_command(&mut graph, TimelineCommand::UpdateNode { node: moved }).unwrap();
+        assert!(graph.nodes_at(0, 125).is_empty());
+        assert_eq!(graph.nodes_at(0, 210), vec![late.id]);
+        assert_eq!(graph.index().next_edit_after(100), Some(200));
+    }
+
+    /// Per-track spans and edit points, comparable between two indexes.
+    fn snapshot(index: &TimelineIndex) -> (Vec<Vec<IndexedSpan>>, Vec<Frame>) {
+        let tracks = (0..index.track_count())
+            .map(|t| {
+                let mut spans = index.in_range(t, Frame::MIN, Frame::MAX);
+                spans.sort_by_key(|s| (s.start, s.end, s.node_id.0));
+                spans
+            })
+            .collect();
+        (tracks, index.edit_points().collect())
+    }
+
+    #[test]
+    fn commands_keep_the_cached_index_in_step_with_a_rebuild() {
+        let a = clip(0, 10);
+        let b = clip(10, 20);
+        let c = clip(30, 5);
+        let under = clip(5, 40);
+        let v1 = TrackId::new();
+        let v2 = TrackId::new();
+        let binding = |id, name: &str, node_ids| TrackBinding {
+            id,
+            name: name.to_string(),
+            kind: TrackKind::Video,
+            node_ids,
+            properties: Default::default(),
+        };
+        let mut graph = TimelineGraph {
+            nodes: [&a, &b, &c, &under]
+                .into_iter()
+                .map(|n| (n.id, n.clone()))
+                .collect(),
+            tracks: vec![
+                binding(v1, "V1", vec![a.id, b.id, c.id]),
+                binding(v2, "V2", vec![under.id]),
+            ],
+            ..TimelineGraph::default()
+        };
+        let check = |graph: &TimelineGraph| {
+            assert_eq!(
+                snapshot(graph.index()),
+                snapshot(&TimelineIndex::build(graph))
+            );
+        };
+        check(&graph);
+
+        let late = clip(50, 10);
+        let commands = vec![
+            TimelineCommand::RippleTrim {
+                node_id: a.id,
+                edge: crate::TrimEdge::End,
+                delta: 4,
+            },
+            TimelineCommand::InsertNode {
+                node: late.clone(),
+                placements: vec![crate::TrackPlacement {
+                    track_id: v2,
+                    position: None,
+                }],
+                edges: Vec::new(),
+            },
+            TimelineCommand::RemoveNode { node_id: b.id },
+            TimelineCommand::MoveTrack {
+                track_id: v2,
+                index: 0,
+            },
+            TimelineCommand::SlideClip {
+                node_id: late.id,
+                delta: 3,
+            },
+        ];
+        let mut undo = Vec::new();
+        for command in commands {
+            undo.push(apply_command(&mut graph, command).unwrap());
+            check(&graph);
+        }
+
+        // A batch that fails halfway is rolled back through the same paths.
+        let failing = TimelineCommand::Batch {
+            commands: vec![
+                TimelineCommand::RemoveNode { node_id: c.id },
+                TimelineCommand::RemoveNode { node_id: b.id },
+            ],
+        };
+        assert!(apply_command(&mut graph, failing).is_err());
+        check(&graph);
+
+        while let Some(inverse) = undo.pop() {
+            apply_command(&mut graph, inverse).unwrap();
+            check(&graph);
+        }
+        assert_eq!(graph.nodes_at(0, 15), vec![b.id]);
+    }
+
+    #[test]
+    fn reindex_node_follows_direct_edits() {
+        let a = clip(0, 10);
+        let b = clip(20, 10);
+        let v1 = TrackId::new();
+        let mut graph = TimelineGraph {
+            nodes: [&a, &b].into_iter().map(|n| (n.id, n.clone())).collect(),
+            tracks: vec![TrackBinding {
+                id: v1,
+                name: "V1".to_string(),
+                kind: TrackKind::Video,
+                node_ids: vec![a.id, b.id],
+                properties: Default::default(),
+            }],
+            ..TimelineGraph::default()
+        };
+        assert_eq!(graph.nodes_at(0, 25), vec![b.id]);
+
+        if let Some(range) = graph.nodes.get_mut(&b.id).unwrap().timeline_range_mut() {
+            range.start = 5;
+        }
+        graph.reindex_node(b.id);
+        assert_eq!(graph.nodes_at(0, 7), vec![a.id, b.id]);
+        assert!(graph.nodes_at(0, 25).is_empty());
+        assert_eq!(graph.index().next_edit_after(10), Some(15));
+    }
+}
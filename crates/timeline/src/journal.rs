@@ -0,0 +1,195 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{CommandHistory, TimelineCommand, TimelineError, TimelineGraph};
+
+/// One step of a timeline editing session as written to a crash journal.
+///
+/// Entries are recorded after they succeed and are replayed through a
+/// [`CommandHistory`], so recovery rebuilds the undo stack along with the
+/// graph. Undo and redo carry no payload: replaying the earlier entries
+/// recreates the stacks they act on. Commands should be recorded after
+/// [`resolve_command`](crate::resolve_command), so pieces an edit creates get
+/// the same ids on replay and later entries can still find them.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum JournalEntry {
+    Apply {
+        label: String,
+        command: Box<TimelineCommand>,
+    },
+    Undo,
+    Redo,
+}
+
+impl JournalEntry {
+    pub fn apply(label: impl Into<String>, command: TimelineCommand) -> Self {
+        JournalEntry::Apply {
+            label: label.into(),
+            command: Box::new(command),
+        }
+    }
+
+    pub fn replay(
+        self,
+        history: &mut CommandHistory,
+        graph: &mut TimelineGraph,
+    ) -> Result<(), TimelineError> {
+        match self {
+            JournalEntry::Apply { label, command } => history.apply_labeled(graph, label, *command),
+            JournalEntry::Undo => history.undo(graph),
+            JournalEntry::Redo => history.redo(graph),
+        }
+    }
+}
+
+/// Replay `entries` in order on top of a snapshot. Returns how many were
+/// applied; on failure the error comes with the index of the entry that
+/// failed, and the entries before it stay applied.
+pub fn replay_journal(
+    graph: &mut TimelineGraph,
+    history: &mut CommandHistory,
+    entries: impl IntoIterator<Item = JournalEntry>,
+) -> Result<usize, (usize, TimelineError)> {
+    let mut applied = 0;
+    for entry in entries {
+        entry.replay(history, graph).map_err(|err| (applied, err))?;
+        applied += 1;
+    }
+    Ok(applied)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        resolve_command, ClipNode, FrameRange, NodeId, TimelineNode, TimelineNodeKind,
+        TrackBinding, TrackId, TrackKind, TrimEdge,
+    };
+    use serde_json::Value;
+
+    fn track(name: &str) -> TrackBinding {
+        TrackBinding {
+            id: TrackId::new(),
+            name: name.to_string(),
+            kind: TrackKind::Video,
+            node_ids: Vec::new(),
+            properties: Default::default(),
+        }
+    }
+
+    #[test]
+    fn replay_rebuilds_graph_and_history_from_a_snapshot() {
+        let snapshot = TimelineGraph::default();
+        let (v1, v2) = (track("V1"), track("V2"));
+        let entries = vec![
+            JournalEntry::apply("Add track", TimelineCommand::UpsertTrack { track: v1 }),
+            JournalEntry::apply("Add track", TimelineCommand::UpsertTrack { track: v2 }),
+            JournalEntry::Undo,
+            JournalEntry::Redo,
+            JournalEntry::Undo,
+        ];
+        let json: Vec<String> = entries
+            .iter()
+            .map(|e| serde_json::to_string(e).unwrap())
+            .collect();
+
+        // What the live session ended with.
+        let mut live = snapshot.clone();
+        let mut live_history = CommandHistory::default();
+        replay_journal(&mut live, &mut live_history, entries).unwrap();
+
+        let mut recovered = snapshot.clone();
+        let mut history = CommandHistory::default();
+        let parsed = json.iter().map(|j| serde_json::from_str(j).unwrap());
+        assert_eq!(
+            replay_journal(&mut recovered, &mut history, parsed).unwrap(),
+            5
+        );
+        assert_eq!(recovered, live);
+        assert_eq!(recovered.tracks.len(), 1);
+        assert_eq!(history.redo_label(), Some("Add track"));
+
+        // A journal that no longer fits the snapshot stops where it breaks.
+        let mut graph = TimelineGraph::default();
+        let mut history = CommandHistory::default();
+        let broken = vec![
+            JournalEntry::apply(
+                "Add track",
+                TimelineCommand::UpsertTrack { track: track("V1") },
+            ),
+            JournalEntry::apply(
+                "Remove track",
+                TimelineCommand::RemoveTrack {
+                    track_id: TrackId::new(),
+                },
+            ),
+        ];
+        let err = replay_journal(&mut graph, &mut history, broken).unwrap_err();
+        assert_eq!(err.0, 1);
+        assert_eq!(graph.tracks.len(), 1);
+    }
+
+    #[test]
+    fn replay_finds_pieces_created_by_earlier_entries() {
+        let clip = TimelineNode {
+            id: NodeId::new(),
+            label: None,
+            kind: TimelineNodeKind::Clip(ClipNode {
+                asset_id: Some("a.mov".to_string()),
+                media_range: FrameRange::new(0, 40),
+                timeline_range: FrameRange::new(0, 40),
+                playback_rate: 1.0,
+                reverse: false,
+                time_remap: None,
+                markers: Vec::new(),
+                metadata: Value::Null,
+            }),
+            locked: false,
+            metadata: Value::Null,
+        };
+        let mut snapshot = TimelineGraph::default();
+        let mut v1 = track("V1");
+        v1.node_ids.push(clip.id);
+        snapshot.tracks.push(v1);
+        snapshot.nodes.insert(clip.id, clip.clone());
+
+        // The live session splits the clip, then trims the new right piece.
+        let mut live = snapshot.clone();
+        let mut live_history = CommandHistory::default();
+        let mut entries = Vec::new();
+        let split = TimelineCommand::SplitNode {
+            node_id: clip.id,
+            frame: 10,
+        };
+        let split = resolve_command(&live, split).unwrap();
+        entries.push(JournalEntry::apply("Split clip", split.clone()));
+        live_history
+            .apply_labeled(&mut live, "Split clip", split)
+            .unwrap();
+        let right = *live.nodes.keys().find(|id| **id != clip.id).unwrap();
+        let trim = TimelineCommand::RippleTrim {
+            node_id: right,
+            edge: TrimEdge::End,
+            delta: -5,
+        };
+        entries.push(JournalEntry::apply("Ripple trim", trim.clone()));
+        live_history.apply(&mut live, trim).unwrap();
+
+        let json: Vec<String> = entries
+            .iter()
+            .map(|e| serde_json::to_string(e).unwrap())
+            .collect();
+        let mut recovered = snapshot.clone();
+        let mut history = CommandHistory::default();
+        let parsed = json.iter().map(|j| serde_json::from_str(j).unwrap());
+        assert_eq!(
+            replay_journal(&mut recovered, &mut history, parsed).unwrap(),
+            2
+        );
+        assert_eq!(recovered, live);
+        assert_eq!(
+            recovered.nodes[&right].timeline_range(),
+            Some(&FrameRange::new(10, 25))
+        );
+    }
+}
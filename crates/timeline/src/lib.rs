@@ -5,6 +5,23 @@ mod graph;
 pub use graph::*;
 mod commands;
 pub use commands::*;
+mod automation;
+mod conform;
+mod index;
+mod journal;
+mod merge;
+mod migrate;
+mod remap;
+mod three_point;
+pub use conform::{ConformOptions, FrameRounding, Reframe, ReframeMode};
+pub use index::{IndexedSpan, TimelineIndex};
+pub use journal::{replay_journal, JournalEntry};
+pub use merge::{diff_graphs, merge_graphs, MergeConflict, MergeResult};
+pub use migrate::{migrate_graph_value, Migration, GRAPH_VERSION, MIGRATIONS};
+pub use remap::TimeRemap;
+pub use three_point::EditPoints;
+mod validate;
+pub use validate::*;
 
 #[derive(Debug, Error)]
 pub enum TimelineError {
@@ -22,6 +39,16 @@ pub enum TimelineError {
     EdgeExists(NodeId, NodeId),
     #[error("edge not found between {0} -> {1}")]
     EdgeNotFound(NodeId, NodeId),
+    #[error("sequence not found: {0}")]
+    SequenceNotFound(String),
+    #[error("marker not found: {0}")]
+    MarkerNotFound(MarkerId),
+    #[error("node is locked: {0}")]
+    NodeLocked(NodeId),
+    #[error("track is locked: {0}")]
+    TrackLocked(TrackId),
+    #[error("timeline graph version {found} is newer than this build supports ({supported})")]
+    UnsupportedVersion { found: u16, supported: u16 },
     #[error("history empty: {0}")]
     HistoryEmpty(&'static str),
 }
@@ -125,4 +152,17 @@ impl Sequence {
     pub fn add_track(&mut self, track: Track) {
         self.tracks.push(track);
     }
+
+    /// Rebuild the legacy tracks and duration from the graph, after editing
+    /// the graph, so readers of `tracks` see the same timeline.
+    pub fn sync_tracks_from_graph(&mut self) {
+        self.tracks = tracks_from_graph(&self.graph, self.fps);
+        self.duration_in_frames = self
+            .tracks
+            .iter()
+            .flat_map(|t| &t.items)
+            .map(|item| item.from + item.duration_in_frames)
+            .max()
+            .unwrap_or(0);
+    }
 }
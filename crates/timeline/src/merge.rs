@@ -0,0 +1,587 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::{
+    LaneId, MarkerId, NodeId, TimelineCommand, TimelineEdge, TimelineGraph, TimelineNode, TrackId,
+};
+
+/// Commands that turn `from` into `to` when applied in order, for example
+/// as one [`TimelineCommand::Batch`].
+///
+/// Nodes, tracks, lanes, markers and nested sequences are matched by id.
+/// Changed nodes and lanes are replaced whole, changed tracks are upserted
+/// with their full binding and then moved into `to`'s order. The order of
+/// edges and lanes is not meaningful and is not reproduced, and neither are
+/// the graph's own `version` and `metadata`.
+pub fn diff_graphs(from: &TimelineGraph, to: &TimelineGraph) -> Vec<TimelineCommand> {
+    let mut commands = Vec::new();
+
+    for edge in from.edges.iter().filter(|e| !to.edges.contains(e)) {
+        commands.push(TimelineCommand::RemoveEdge { edge: edge.clone() });
+    }
+    for lane in &from.automation {
+        if !to.automation.iter().any(|l| l.id == lane.id) {
+            commands.push(TimelineCommand::RemoveAutomationLane { lane_id: lane.id });
+        }
+    }
+    for marker in &from.markers {
+        if !to.markers.iter().any(|m| m.id == marker.id) {
+            commands.push(TimelineCommand::RemoveMarker {
+                marker_id: marker.id,
+            });
+        }
+    }
+    for node_id in sorted_node_ids(from) {
+        if !to.nodes.contains_key(&node_id) {
+            commands.push(TimelineCommand::RemoveNode { node_id });
+        }
+    }
+    for track in &from.tracks {
+        if !to.tracks.iter().any(|t| t.id == track.id) {
+            commands.push(TimelineCommand::RemoveTrack { track_id: track.id });
+        }
+    }
+    // Compound clips using a sequence are gone by now, so it can be removed.
+    for sequence_id in sorted_keys(&from.sequences) {
+        if !to.sequences.contains_key(sequence_id) {
+            commands.push(TimelineCommand::RemoveSequence {
+                sequence_id: sequence_id.clone(),
+            });
+        }
+    }
+
+    for sequence_id in sorted_keys(&to.sequences) {
+        let inner = &to.sequences[sequence_id];
+        if from.sequences.get(sequence_id) != Some(inner) {
+            commands.push(TimelineCommand::UpsertSequence {
+                sequence_id: sequence_id.clone(),
+                graph: Box::new(inner.clone()),
+            });
+        }
+    }
+    for node_id in sorted_node_ids(to) {
+        let node = &to.nodes[&node_id];
+        match from.nodes.get(&node_id) {
+            None => commands.push(TimelineCommand::InsertNode {
+                node: node.clone(),
+                placements: Vec::new(),
+                edges: Vec::new(),
+            }),
+            Some(previous) if previous != node => {
+                commands.push(TimelineCommand::UpdateNode { node: node.clone() })
+            }
+            Some(_) => {}
+        }
+    }
+    // Upserting appends new tracks, so replay the moves against the order
+    // the graph will have at that point.
+    let mut order: Vec<TrackId> = from
+        .tracks
+        .iter()
+        .map(|t| t.id)
+        .filter(|id| to.tracks.iter().any(|t| t.id == *id))
+        .collect();
+    for track in &to.tracks {
+        match from.tracks.iter().find(|t| t.id == track.id) {
+            Some(previous) if previous == track => {}
+            Some(_) => commands.push(TimelineCommand::UpsertTrack {
+                track: track.clone(),
+            }),
+            None => {
+                order.push(track.id);
+                commands.push(TimelineCommand::UpsertTrack {
+                    track: track.clone(),
+                });
+            }
+        }
+    }
+    for (index, track) in to.tracks.iter().enumerate() {
+        if order[index] != track.id {
+            let current = order.iter().position(|id| *id == track.id).unwrap_or(index);
+            let id = order.remove(current);
+            order.insert(index, id);
+            commands.push(TimelineCommand::MoveTrack {
+                track_id: track.id,
+                index,
+            });
+        }
+    }
+    for edge in to.edges.iter().filter(|e| !from.edges.contains(e)) {
+        commands.push(TimelineCommand::AddEdge { edge: edge.clone() });
+    }
+    for lane in &to.automation {
+        match from.automation.iter().find(|l| l.id == lane.id) {
+            None => commands.push(TimelineCommand::AddAutomationLane { lane: lane.clone() }),
+            Some(previous) if previous != lane => {
+                commands.push(TimelineCommand::UpdateAutomationLane { lane: lane.clone() })
+            }
+            Some(_) => {}
+        }
+    }
+    for marker in &to.markers {
+        match from.markers.iter().find(|m| m.id == marker.id) {
+            None => commands.push(TimelineCommand::AddMarker {
+                marker: marker.clone(),
+            }),
+            Some(previous) if previous != marker => commands.push(TimelineCommand::UpdateMarker {
+                marker: marker.clone(),
+            }),
+            Some(_) => {}
+        }
+    }
+    commands
+}
+
+/// Something both sides of a merge changed in different ways. The merged
+/// graph keeps "ours" for it; the two versions can be looked up by id in the
+/// inputs to [`merge_graphs`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum MergeConflict {
+    Node(NodeId),
+    /// Both sides changed the track's name, kind or properties, one side
+    /// removed it while the other changed it, or the two sides placed nodes
+    /// over the same frames on it.
+    Track(TrackId),
+    Lane(LaneId),
+    Marker(MarkerId),
+    Sequence(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct MergeResult {
+    pub graph: TimelineGraph,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+impl MergeResult {
+    pub fn is_clean(&self) -> bool {
+        self.conflicts.is_empty()
+    }
+}
+
+/// Three-way merge of two graphs that both started from `base`.
+///
+/// A change made on one side only is taken; the same change made on both
+/// sides is taken once. Track membership is merged clip by clip, so both
+/// sides can add to or remove from the same track. Edges are merged as a
+/// set, and anything left pointing at a removed node is dropped.
+pub fn merge_graphs(
+    base: &TimelineGraph,
+    ours: &TimelineGraph,
+    theirs: &TimelineGraph,
+) -> MergeResult {
+    let mut conflicts = Vec::new();
+
+    let nodes: HashMap<NodeId, TimelineNode> = merge_keyed(
+        sorted_node_ids(base)
+            .into_iter()
+            .map(|id| (id, &base.nodes[&id])),
+        sorted_node_ids(ours)
+            .into_iter()
+            .map(|id| (id, &ours.nodes[&id])),
+        sorted_node_ids(theirs)
+            .into_iter()
+            .map(|id| (id, &theirs.nodes[&id])),
+        |id| conflicts.push(MergeConflict::Node(id)),
+    )
+    .into_iter()
+    .collect();
+
+    let mut tracks = Vec::new();
+    let mut track_ids: Vec<TrackId> = ours.tracks.iter().map(|t| t.id).collect();
+    for track in &theirs.tracks {
+        if !track_ids.contains(&track.id) {
+            track_ids.push(track.id);
+        }
+    }
+    for track_id in track_ids {
+        let find = |g: &TimelineGraph| g.tracks.iter().find(|t| t.id == track_id).cloned();
+        let (b, o, t) = (find(base), find(ours), find(theirs));
+        let (Some(mut merged), Some(other)) = (o.clone(), t.clone()) else {
+            // Present on one side only: taken unless that side changed a
+            // track the other side removed.
+            match pick(b.as_ref(), o.as_ref(), t.as_ref()) {
+                Some(track) => tracks.extend(track.cloned()),
+                None => {
+                    conflicts.push(MergeConflict::Track(track_id));
+                    tracks.extend(o);
+                }
+            }
+            continue;
+        };
+        let header =
+            |t: &crate::TrackBinding| (t.name.clone(), t.kind.clone(), t.properties.clone());
+        match pick(
+            b.as_ref().map(header).as_ref(),
+            Some(&header(&merged)),
+            Some(&header(&other)),
+        ) {
+            Some(Some((name, kind, properties))) => {
+                merged.name = name.clone();
+                merged.kind = kind.clone();
+                merged.properties = properties.clone();
+            }
+            Some(None) => {}
+            None => conflicts.push(MergeConflict::Track(track_id)),
+        }
+        let base_ids = b.map(|b| b.node_ids).unwrap_or_default();
+        merged
+            .node_ids
+            .retain(|id| !base_ids.contains(id) || other.node_ids.contains(id));
+        for id in &other.node_ids {
+            if !base_ids.contains(id) && !merged.node_ids.contains(id) {
+                merged.node_ids.push(*id);
+            }
+        }
+        tracks.push(merged);
+    }
+    for track in &mut tracks {
+        track.node_ids.retain(|id| nodes.contains_key(id));
+        if placed_over_each_other(track, &nodes, base, ours, theirs)
+            && !conflicts.contains(&MergeConflict::Track(track.id))
+        {
+            conflicts.push(MergeConflict::Track(track.id));
+        }
+    }
+
+    let automation = merge_keyed(
+        base.automation.iter().map(|l| (l.id, l)),
+        ours.automation.iter().map(|l| (l.id, l)),
+        theirs.automation.iter().map(|l| (l.id, l)),
+        |id| conflicts.push(MergeConflict::Lane(id)),
+    )
+    .into_iter()
+    .map(|(_, lane)| lane)
+    .filter(|lane| nodes.contains_key(&lane.target.node))
+    .collect();
+
+    let mut markers: Vec<_> = merge_keyed(
+        base.markers.iter().map(|m| (m.id, m)),
+        ours.markers.iter().map(|m| (m.id, m)),
+        theirs.markers.iter().map(|m| (m.id, m)),
+        |id| conflicts.push(MergeConflict::Marker(id)),
+    )
+    .into_iter()
+    .map(|(_, marker)| marker)
+    .collect();
+    markers.sort_by_key(|m| m.frame);
+
+    let sequences = merge_keyed(
+        sorted_keys(&base.sequences)
+            .into_iter()
+            .map(|id| (id.clone(), &base.sequences[id])),
+        sorted_keys(&ours.sequences)
+            .into_iter()
+            .map(|id| (id.clone(), &ours.sequences[id])),
+        sorted_keys(&theirs.sequences)
+            .into_iter()
+            .map(|id| (id.clone(), &theirs.sequences[id])),
+        |id| conflicts.push(MergeConflict::Sequence(id)),
+    )
+    .into_iter()
+    .collect();
+
+    let edges = merge_edges(&base.edges, &ours.edges, &theirs.edges)
+        .into_iter()
+        .filter(|e| nodes.contains_key(&e.from) && nodes.contains_key(&e.to))
+        .collect();
+
+    let metadata = pick(
+        Some(&base.metadata),
+        Some(&ours.metadata),
+        Some(&theirs.metadata),
+    )
+    .flatten()
+    .unwrap_or(&ours.metadata)
+    .clone();
+
+    MergeResult {
+        graph: TimelineGraph {
+            version: ours.version,
+            nodes,
+            edges,
+            tracks,
+            automation,
+            markers,
+            sequences,
+            metadata,
+            index: Default::default(),
+        },
+        conflicts,
+    }
+}
+
+/// The three-way choice for one item: `Some(value)` when the sides agree or
+/// only one of them changed it, `None` when both changed it differently.
+/// Absent means the item does not exist on that side.
+fn pick<'a, T: PartialEq>(
+    base: Option<&'a T>,
+    ours: Option<&'a T>,
+    theirs: Option<&'a T>,
+) -> Option<Option<&'a T>> {
+    if ours == theirs || theirs == base {
+        Some(ours)
+    } else if ours == base {
+        Some(theirs)
+    } else {
+        None
+    }
+}
+
+/// Merge id-keyed items, keeping ours' order followed by items only theirs
+/// has. Conflicts are reported through `on_conflict` and resolved to ours.
+fn merge_keyed<'a, K, T>(
+    base: impl IntoIterator<Item = (K, &'a T)>,
+    ours: impl IntoIterator<Item = (K, &'a T)>,
+    theirs: impl IntoIterator<Item = (K, &'a T)>,
+    mut on_conflict: impl FnMut(K),
+) -> Vec<(K, T)>
+where
+    K: Eq + Hash + Clone,
+    T: PartialEq + Clone + 'a,
+{
+    let base: HashMap<K, &T> = base.into_iter().collect();
+    let ours: Vec<(K, &T)> = ours.into_iter().collect();
+    let theirs: Vec<(K, &T)> = theirs.into_iter().collect();
+    let ours_by_key: HashMap<K, &T> = ours.iter().cloned().collect();
+    let theirs_by_key: HashMap<K, &T> = theirs.iter().cloned().collect();
+    let keys = ours.iter().map(|(k, _)| k).chain(
+        theirs
+            .iter()
+            .map(|(k, _)| k)
+            .filter(|k| !ours_by_key.contains_key(*k)),
+    );
+    let mut merged = Vec::new();
+    for key in keys {
+        let (o, t) = (
+            ours_by_key.get(key).copied(),
+            theirs_by_key.get(key).copied(),
+        );
+        let chosen = match pick(base.get(key).copied(), o, t) {
+            Some(chosen) => chosen,
+            None => {
+                on_conflict(key.clone());
+                o
+            }
+        };
+        if let Some(value) = chosen {
+            merged.push((key.clone(), value.clone()));
+        }
+    }
+    merged
+}
+
+/// Set merge: an edge survives if both sides have it, or if one side added
+/// it and the other did not have it in the base either.
+fn merge_edges(
+    base: &[TimelineEdge],
+    ours: &[TimelineEdge],
+    theirs: &[TimelineEdge],
+) -> Vec<TimelineEdge> {
+    let mut merged: Vec<TimelineEdge> = ours
+        .iter()
+        .filter(|e| theirs.contains(e) || !base.contains(e))
+        .cloned()
+        .collect();
+    for edge in theirs {
+        if !base.contains(edge) && !merged.contains(edge) {
+            merged.push(edge.clone());
+        }
+    }
+    merged
+}
+
+/// Whether a node only ours changed and a node only theirs changed now
+/// overlap on `track`.
+fn placed_over_each_other(
+    track: &crate::TrackBinding,
+    nodes: &HashMap<NodeId, TimelineNode>,
+    base: &TimelineGraph,
+    ours: &TimelineGraph,
+    theirs: &TimelineGraph,
+) -> bool {
+    let changed_by =
+        |side: &TimelineGraph, other: &TimelineGraph| -> Vec<(crate::Frame, crate::Frame)> {
+            track
+                .node_ids
+                .iter()
+                .filter(|id| {
+                    let node = side.nodes.get(id);
+                    node != base.nodes.get(id) && node != other.nodes.get(id)
+                })
+                .filter_map(|id| nodes.get(id)?.timeline_range())
+                .map(|r| (r.start, r.end()))
+                .collect()
+        };
+    let ours_spans = changed_by(ours, theirs);
+    let theirs_spans = changed_by(theirs, ours);
+    ours_spans
+        .iter()
+        .any(|(s, e)| theirs_spans.iter().any(|(ts, te)| s < te && ts < e))
+}
+
+fn sorted_node_ids(graph: &TimelineGraph) -> Vec<NodeId> {
+    let mut ids: Vec<NodeId> = graph.nodes.keys().copied().collect();
+    ids.sort_by_key(|id| id.0);
+    ids
+}
+
+fn sorted_keys<V>(map: &HashMap<String, V>) -> Vec<&String> {
+    let mut keys: Vec<&String> = map.keys().collect();
+    keys.sort();
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        apply_command, AutomationKeyframe, AutomationLane, AutomationTarget, ClipNode, EdgeKind,
+        FrameRange, TimelineNodeKind, TrackBinding, TrackKind,
+    };
+    use serde_json::Value;
+
+    fn clip(start: crate::Frame, duration: crate::Frame) -> TimelineNode {
+        TimelineNode {
+            id: NodeId::new(),
+            label: None,
+            kind: TimelineNodeKind::Clip(ClipNode {
+                asset_id: Some("a.mov".to_string()),
+                media_range: FrameRange::new(0, duration),
+                timeline_range: FrameRange::new(start, duration),
+                playback_rate: 1.0,
+                reverse: false,
+                time_remap: None,
+                markers: Vec::new(),
+                metadata: Value::Null,
+            }),
+            locked: false,
+            metadata: Value::Null,
+        }
+    }
+
+    fn track(name: &str, kind: TrackKind, nodes: &[&TimelineNode]) -> TrackBinding {
+        TrackBinding {
+            id: TrackId::new(),
+            name: name.to_string(),
+            kind,
+            node_ids: nodes.iter().map(|n| n.id).collect(),
+            properties: Default::default(),
+        }
+    }
+
+    fn moved(node: &TimelineNode, start: crate::Frame) -> TimelineNode {
+        let mut node = node.clone();
+        if let TimelineNodeKind::Clip(clip) = &mut node.kind {
+            clip.timeline_range.start = start;
+        }
+        node
+    }
+
+    fn lane(node: &TimelineNode, value: f64) -> AutomationLane {
+        AutomationLane {
+            id: LaneId::new(),
+            target: AutomationTarget {
+                node: node.id,
+                parameter: "opacity".to_string(),
+            },
+            interpolation: Default::default(),
+            keyframes: vec![AutomationKeyframe {
+                frame: 0,
+                value,
+                easing: Default::default(),
+            }],
+        }
+    }
+
+    fn base() -> (TimelineGraph, [TimelineNode; 3]) {
+        let nodes = [clip(0, 50), clip(50, 50), clip(100, 50)];
+        let mut graph = TimelineGraph {
+            nodes: nodes.iter().map(|n| (n.id, n.clone())).collect(),
+            tracks: vec![
+                track("V1", TrackKind::Video, &[&nodes[0], &nodes[1], &nodes[2]]),
+                track("A1", TrackKind::Audio, &[]),
+            ],
+            ..TimelineGraph::default()
+        };
+        graph.automation.push(lane(&nodes[0], 1.0));
+        (graph, nodes)
+    }
+
+    #[test]
+    fn diff_replays_one_graph_into_another() {
+        let (from, [a, b, c]) = base();
+        let mut to = from.clone();
+        let d = clip(200, 10);
+        to.nodes.remove(&a.id);
+        to.nodes.insert(b.id, moved(&b, 60));
+        to.nodes.insert(d.id, d.clone());
+        to.tracks[0].node_ids = vec![b.id, c.id, d.id];
+        to.tracks[1].name = "Dialogue".to_string();
+        to.tracks.swap(0, 1);
+        to.tracks.push(track("V2", TrackKind::Video, &[]));
+        to.edges.push(crate::TimelineEdge {
+            from: c.id,
+            to: d.id,
+            kind: EdgeKind::Sequential,
+        });
+        to.automation = vec![lane(&c, 0.5)];
+
+        let mut graph = from.clone();
+        apply_command(
+            &mut graph,
+            TimelineCommand::Batch {
+                commands: diff_graphs(&from, &to),
+            },
+        )
+        .unwrap();
+        assert_eq!(graph, to);
+        assert!(diff_graphs(&to, &graph).is_empty());
+    }
+
+    #[test]
+    fn merge_takes_one_sided_changes_and_reports_conflicts() {
+        let (base, [a, b, c]) = base();
+        let lane_id = base.automation[0].id;
+        let (x, y) = (clip(300, 20), clip(310, 20));
+
+        let mut ours = base.clone();
+        ours.nodes.insert(a.id, moved(&a, 5));
+        ours.nodes.insert(c.id, moved(&c, 110));
+        ours.nodes.insert(x.id, x.clone());
+        ours.tracks[0].node_ids.push(x.id);
+        ours.automation[0].keyframes[0].value = 0.25;
+
+        let mut theirs = base.clone();
+        theirs.nodes.remove(&b.id);
+        theirs.tracks[0].node_ids.retain(|id| *id != b.id);
+        theirs.nodes.insert(c.id, moved(&c, 120));
+        theirs.nodes.insert(y.id, y.clone());
+        theirs.tracks[0].node_ids.push(y.id);
+        theirs.tracks[1].properties.muted = true;
+        theirs.automation[0].keyframes[0].value = 0.75;
+
+        let merged = merge_graphs(&base, &ours, &theirs);
+        let graph = &merged.graph;
+        assert_eq!(graph.nodes[&a.id], moved(&a, 5));
+        assert!(!graph.nodes.contains_key(&b.id));
+        assert_eq!(graph.nodes[&c.id], moved(&c, 110));
+        assert_eq!(graph.tracks[0].node_ids, vec![a.id, c.id, x.id, y.id]);
+        assert!(graph.tracks[1].properties.muted);
+        assert_eq!(
+            merged.conflicts,
+            vec![
+                MergeConflict::Node(c.id),
+                MergeConflict::Track(base.tracks[0].id),
+                MergeConflict::Lane(lane_id),
+            ]
+        );
+
+        // Without the overlapping additions and competing edits it is clean.
+        let mut theirs = base.clone();
+        theirs.tracks[1].properties.muted = true;
+        let merged = merge_graphs(&base, &ours, &theirs);
+        assert!(merged.is_clean());
+        assert_eq!(merged.graph.nodes, ours.nodes);
+    }
+}
@@ -0,0 +1,218 @@
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer};
+use serde_json::{json, Map, Value};
+
+use crate::{TimelineError, TimelineGraph};
+
+/// Version written by this build. Graphs with a lower version are upgraded
+/// through [`MIGRATIONS`] when they are deserialized.
+pub const GRAPH_VERSION: u16 = 2;
+
+/// One upgrade step, from `from` to `from + 1`.
+///
+/// Steps work on the serialized form so they keep compiling when the graph
+/// types move on. They only touch their own graph: nested sequences carry
+/// their own version and are migrated when they are deserialized.
+pub struct Migration {
+    pub from: u16,
+    pub description: &'static str,
+    pub upgrade: fn(&mut Map<String, Value>) -> Result<(), TimelineError>,
+}
+
+/// Every upgrade step in order. Add a step here, with a fixture test, when
+/// bumping [`GRAPH_VERSION`].
+pub const MIGRATIONS: &[Migration] = &[Migration {
+    from: 1,
+    description: "link picture and sound clips that were paired by position",
+    upgrade: link_positional_av_pairs,
+}];
+
+/// Upgrade a serialized graph in place to [`GRAPH_VERSION`], one step at a
+/// time. Refuses graphs written by a newer build.
+pub fn migrate_graph_value(value: &mut Value) -> Result<(), TimelineError> {
+    let graph = value
+        .as_object_mut()
+        .ok_or_else(|| TimelineError::InvalidOp("timeline graph is not an object".into()))?;
+    let mut version = graph
+        .get("version")
+        .and_then(Value::as_u64)
+        .and_then(|v| u16::try_from(v).ok())
+        .ok_or_else(|| TimelineError::InvalidOp("timeline graph has no valid version".into()))?;
+    if version > GRAPH_VERSION {
+        return Err(TimelineError::UnsupportedVersion {
+            found: version,
+            supported: GRAPH_VERSION,
+        });
+    }
+    while version < GRAPH_VERSION {
+        let step = MIGRATIONS
+            .iter()
+            .find(|m| m.from == version)
+            .ok_or_else(|| {
+                TimelineError::InvalidOp(format!("no migration from graph version {}", version))
+            })?;
+        (step.upgrade)(graph)?;
+        version += 1;
+        graph.insert("version".into(), json!(version));
+    }
+    Ok(())
+}
+
+impl<'de> Deserialize<'de> for TimelineGraph {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut value = Value::deserialize(deserializer)?;
+        migrate_graph_value(&mut value).map_err(D::Error::custom)?;
+        GraphFields::deserialize(value).map_err(D::Error::custom)
+    }
+}
+
+/// Field layout of [`TimelineGraph`] for the derived half of its
+/// deserializer, used once the value has been migrated.
+#[derive(Deserialize)]
+#[serde(remote = "TimelineGraph")]
+struct GraphFields {
+    version: u16,
+    nodes: std::collections::HashMap<crate::NodeId, crate::TimelineNode>,
+    edges: Vec<crate::TimelineEdge>,
+    #[serde(default)]
+    tracks: Vec<crate::TrackBinding>,
+    #[serde(default)]
+    automation: Vec<crate::AutomationLane>,
+    #[serde(default)]
+    markers: Vec<crate::Marker>,
+    #[serde(default)]
+    sequences: std::collections::HashMap<String, TimelineGraph>,
+    #[serde(default)]
+    metadata: Value,
+    #[serde(skip)]
+    index: crate::index::IndexCache,
+}
+
+/// v1 → v2. Version 1 graphs relied on the editor pairing an audio clip
+/// with the picture clip of the same asset at the same place. Version 2
+/// needs a link edge for that, so add one for each such pair whose clips
+/// are not linked yet.
+fn link_positional_av_pairs(graph: &mut Map<String, Value>) -> Result<(), TimelineError> {
+    let edges = graph.get("edges").and_then(Value::as_array);
+    let linked: Vec<&Value> = edges
+        .into_iter()
+        .flatten()
+        .filter(|e| e.get("kind").and_then(Value::as_str) == Some("link"))
+        .flat_map(|e| [&e["from"], &e["to"]])
+        .collect();
+    let nodes = graph.get("nodes").and_then(Value::as_object);
+    let mut pictures = Vec::new();
+    let mut sounds = Vec::new();
+    for track in graph
+        .get("tracks")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        let audio = track.get("kind").and_then(Value::as_str) == Some("audio");
+        for id in track
+            .get("node_ids")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            if linked.contains(&id) {
+                continue;
+            }
+            let Some(kind) = id
+                .as_str()
+                .and_then(|id| nodes?.get(id))
+                .map(|node| &node["kind"])
+            else {
+                continue;
+            };
+            if kind["type"] != "clip" || kind["asset_id"].is_null() {
+                continue;
+            }
+            let entry = (id.clone(), &kind["asset_id"], &kind["timeline_range"]);
+            if audio {
+                sounds.push(entry);
+            } else {
+                pictures.push(entry);
+            }
+        }
+    }
+    let mut links = Vec::new();
+    for (audio_id, asset_id, range) in sounds {
+        if let Some(pos) = pictures
+            .iter()
+            .position(|(_, a, r)| *a == asset_id && *r == range)
+        {
+            let (video_id, _, _) = pictures.remove(pos);
+            links.push(json!({ "from": video_id, "to": audio_id, "kind": "link" }));
+        }
+    }
+    match graph.entry("edges").or_insert_with(|| json!([])) {
+        Value::Array(edges) => edges.extend(links),
+        _ => {
+            return Err(TimelineError::InvalidOp(
+                "graph edges are not a list".into(),
+            ))
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EdgeKind, NodeId};
+
+    fn node(id: &str) -> NodeId {
+        NodeId(uuid::Uuid::parse_str(id).unwrap())
+    }
+
+    #[test]
+    fn v1_gains_links_for_positional_av_pairs() {
+        let graph: TimelineGraph =
+            serde_json::from_str(include_str!("../fixtures/graph_v1.json")).unwrap();
+        assert_eq!(graph.version, GRAPH_VERSION);
+        let links: Vec<_> = graph
+            .edges
+            .iter()
+            .filter(|e| e.kind == EdgeKind::Link)
+            .map(|e| (e.from, e.to))
+            .collect();
+        // Matching pairs gain a link, the already-linked pair keeps its
+        // single edge and the clip that moved off its sound stays apart.
+        assert_eq!(
+            links,
+            vec![
+                (
+                    node("00000000-0000-0000-0000-0000000000a2"),
+                    node("00000000-0000-0000-0000-0000000000b2")
+                ),
+                (
+                    node("00000000-0000-0000-0000-0000000000a3"),
+                    node("00000000-0000-0000-0000-0000000000b3")
+                ),
+                (
+                    node("00000000-0000-0000-0000-0000000000a1"),
+                    node("00000000-0000-0000-0000-0000000000b1")
+                ),
+            ]
+        );
+        let nested = &graph.sequences["nested"];
+        assert_eq!(nested.version, GRAPH_VERSION);
+        assert_eq!(nested.edges.len(), 1);
+    }
+
+    #[test]
+    fn refuses_graphs_from_newer_builds() {
+        let json = format!(
+            r#"{{"version": {}, "nodes": {{}}, "edges": []}}"#,
+            GRAPH_VERSION + 1
+        );
+        let err = serde_json::from_str::<TimelineGraph>(&json).unwrap_err();
+        assert!(err.to_string().contains("newer"), "{}", err);
+
+        let current = serde_json::to_string(&TimelineGraph::default()).unwrap();
+        let graph: TimelineGraph = serde_json::from_str(&current).unwrap();
+        assert_eq!(graph, TimelineGraph::default());
+    }
+}
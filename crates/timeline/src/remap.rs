@@ -0,0 +1,392 @@
+use serde::{Deserialize, Serialize};
+
+use crate::automation::sample_curve;
+use crate::{
+    AutomationInterpolation, AutomationKeyframe, ClipNode, Frame, FrameRange, KeyframeEasing,
+};
+
+/// Speed ramp for a clip: maps frames into the clip (0 is the clip's first
+/// timeline frame) to fractional media frames. While set it replaces
+/// `playback_rate` and `reverse`.
+///
+/// Keyframes use the automation curve model, so easing works the same way.
+/// Two neighbouring keyframes with the same value hold that frame, which is
+/// how freeze frames are expressed; a falling curve plays backwards.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TimeRemap {
+    #[serde(default)]
+    pub interpolation: AutomationInterpolation,
+    pub keyframes: Vec<AutomationKeyframe>,
+}
+
+impl TimeRemap {
+    /// Constant speed starting at `media_start`, covering `duration` clip frames.
+    pub fn constant(media_start: f64, duration: Frame, rate: f64) -> Self {
+        Self {
+            interpolation: AutomationInterpolation::Linear,
+            keyframes: vec![
+                key(0, media_start),
+                key(duration, media_start + duration as f64 * rate),
+            ],
+        }
+    }
+
+    /// Curve reproducing the clip's current constant-speed mapping, as a
+    /// starting point for adding ramps or holds.
+    pub fn from_clip(clip: &ClipNode) -> Self {
+        let duration = clip.timeline_range.duration;
+        Self {
+            interpolation: AutomationInterpolation::Linear,
+            keyframes: vec![
+                key(0, clip.constant_media_frame(0)),
+                key(duration, clip.constant_media_frame(duration)),
+            ],
+        }
+    }
+
+    /// Media frame at `offset` frames into the clip, or `None` without keyframes.
+    pub fn media_frame_at(&self, offset: Frame) -> Option<f64> {
+        sample_curve(&self.interpolation, &self.keyframes, offset)
+    }
+
+    /// Clip length the curve describes: the offset of its last keyframe.
+    pub fn duration(&self) -> Frame {
+        self.keyframes
+            .iter()
+            .map(|k| k.frame)
+            .max()
+            .unwrap_or(0)
+            .max(1)
+    }
+
+    /// Media frames read while playing the first `duration` frames of the clip.
+    pub fn media_range(&self, duration: Frame) -> FrameRange {
+        let mut lo = f64::MAX;
+        let mut hi = f64::MIN;
+        for offset in 0..duration.max(1) {
+            if let Some(value) = self.media_frame_at(offset) {
+                lo = lo.min(value);
+                hi = hi.max(value);
+            }
+        }
+        if lo > hi {
+            return FrameRange::new(0, 0);
+        }
+        let start = lo.floor() as Frame;
+        FrameRange::new(start, hi.floor() as Frame + 1 - start)
+    }
+
+    /// Hold the frame shown at `offset` for `hold` frames, pushing the rest of
+    /// the curve later by the same amount.
+    pub fn insert_freeze(&mut self, offset: Frame, hold: Frame) {
+        let Some(value) = self.media_frame_at(offset) else {
+            return;
+        };
+        self.keyframes.retain(|k| k.frame != offset);
+        for keyframe in &mut self.keyframes {
+            if keyframe.frame > offset {
+                keyframe.frame += hold;
+            }
+        }
+        self.keyframes.push(key(offset, value));
+        self.keyframes.push(key(offset + hold, value));
+        self.keyframes.sort_by_key(|k| k.frame);
+    }
+
+    pub(crate) fn shift_frames(&mut self, delta: Frame) {
+        for keyframe in &mut self.keyframes {
+            keyframe.frame += delta;
+        }
+    }
+
+    pub(crate) fn shift_media(&mut self, delta: f64) {
+        for keyframe in &mut self.keyframes {
+            keyframe.value += delta;
+        }
+    }
+}
+
+impl ClipNode {
+    /// Media frame shown at timeline `frame`, or `None` outside the clip.
+    ///
+    /// Without a remap curve the clip advances `playback_rate` media frames
+    /// per timeline frame from the head of `media_range`, or from its tail
+    /// when reversed.
+    pub fn media_time_at(&self, frame: Frame) -> Option<f64> {
+        if frame < self.timeline_range.start || frame >= self.timeline_range.end() {
+            return None;
+        }
+        let offset = frame - self.timeline_range.start;
+        match &self.time_remap {
+            Some(remap) => remap.media_frame_at(offset),
+            None => Some(self.constant_media_frame(offset)),
+        }
+    }
+
+    /// Install or clear a remap curve. A curve decides the clip's timeline
+    /// duration and `media_range`; clearing one leaves both as they are.
+    pub fn set_time_remap(&mut self, remap: Option<TimeRemap>) {
+        if let Some(remap) = &remap {
+            self.timeline_range.duration = remap.duration();
+            self.media_range = remap.media_range(self.timeline_range.duration);
+        }
+        self.time_remap = remap;
+    }
+
+    fn constant_media_frame(&self, offset: Frame) -> f64 {
+        let rate = self.playback_rate as f64;
+        if self.reverse {
+            self.media_range.end() as f64 - (offset + 1) as f64 * rate
+        } else {
+            self.media_range.start as f64 + offset as f64 * rate
+        }
+    }
+}
+
+fn key(frame: Frame, value: f64) -> AutomationKeyframe {
+    AutomationKeyframe {
+        frame,
+        value,
+        easing: KeyframeEasing::Linear,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        apply_command, NodeId, TimelineCommand, TimelineGraph, TimelineNode, TimelineNodeKind,
+        TrackBinding, TrackId, TrackKind, TrimEdge,
+    };
+    use serde_json::Value;
+
+    fn clip_node(start: Frame, duration: Frame, media_start: Frame) -> TimelineNode {
+        TimelineNode {
+            id: NodeId::new(),
+            label: None,
+            kind: TimelineNodeKind::Clip(ClipNode {
+                asset_id: None,
+                media_range: FrameRange::new(media_start, duration),
+                timeline_range: FrameRange::new(start, duration),
+                playback_rate: 1.0,
+                reverse: false,
+                time_remap: None,
+                markers: Vec::new(),
+                metadata: Value::Null,
+            }),
+            locked: false,
+            metadata: Value::Null,
+        }
+    }
+
+    fn clip_of(node: &TimelineNode) -> &ClipNode {
+        match &node.kind {
+            TimelineNodeKind::Clip(clip) => clip,
+            _ => panic!("not a clip"),
+        }
+    }
+
+    #[test]
+    fn constant_mapping_matches_rate_and_reverse() {
+        let node = clip_node(100, 10, 20);
+        let mut clip = clip_of(&node).clone();
+        assert_eq!(clip.media_time_at(99), None);
+        assert_eq!(clip.media_time_at(100), Some(20.0));
+        assert_eq!(clip.media_time_at(109), Some(29.0));
+        assert_eq!(clip.media_time_at(110), None);
+
+        clip.reverse = true;
+        assert_eq!(clip.media_time_at(100), Some(29.0));
+        assert_eq!(clip.media_time_at(109), Some(20.0));
+        let remap = TimeRemap::from_clip(&clip);
+        assert_eq!(remap.media_frame_at(9), Some(20.0));
+        assert_eq!(remap.media_range(10), FrameRange::new(20, 10));
+    }
+
+    #[test]
+    fn clip_markers_follow_speed_reverse_and_remap() {
+        let node = clip_node(100, 10, 20);
+        let mut clip = clip_of(&node).clone();
+        let marker = crate::Marker::new(24, crate::MarkerKind::Standard);
+        assert_eq!(clip.marker_timeline_frame(&marker), Some(104));
+
+        clip.reverse = true;
+        assert_eq!(clip.marker_timeline_frame(&marker), Some(105));
+
+        // At double speed odd media frames are passed over, not dropped.
+        clip.reverse = false;
+        clip.playback_rate = 2.0;
+        assert_eq!(clip.marker_timeline_frame(&marker), Some(102));
+        let skipped = crate::Marker::new(25, crate::MarkerKind::Standard);
+        assert_eq!(clip.marker_timeline_frame(&skipped), Some(103));
+
+        let mut remap = TimeRemap::from_clip(clip_of(&node));
+        remap.insert_freeze(2, 5);
+        clip.set_time_remap(Some(remap));
+        assert_eq!(clip.marker_timeline_frame(&marker), Some(109));
+        let beyond = crate::Marker::new(40, crate::MarkerKind::Standard);
+        assert_eq!(clip.marker_timeline_frame(&beyond), None);
+    }
+
+    #[test]
+    fn constant_speed_markers_match_a_frame_scan() {
+        let node = clip_node(100, 12, 20);
+        for reverse in [false, true] {
+            for rate in [0.5, 1.0, 1.5, 2.0, 3.0] {
+                let mut clip = clip_of(&node).clone();
+                clip.reverse = reverse;
+                clip.playback_rate = rate;
+                clip.media_range.duration = (12.0 * rate as f64).ceil() as Frame;
+                for frame in 10..70 {
+                    let marker = crate::Marker::new(frame, crate::MarkerKind::Standard);
+                    let target = frame as f64;
+                    let mut prev: Option<f64> = None;
+                    let scanned = (100..112).find(|t| {
+                        let media = clip.media_time_at(*t).unwrap();
+                        let hit = media.floor() == target
+                            || prev.is_some_and(|p| {
+                                (p < target && target < media) || (media < target && target < p)
+                            });
+                        prev = Some(media);
+                        hit
+                    });
+                    assert_eq!(
+                        clip.marker_timeline_frame(&marker),
+                        scanned,
+                        "rate {rate} reverse {reverse} marker {frame}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn trims_and_splits_keep_constant_speed_frames() {
+        for (rate, reverse) in [(2.0, false), (1.0, true), (2.0, true)] {
+            let mut node = clip_node(100, 10, 20);
+            if let TimelineNodeKind::Clip(clip) = &mut node.kind {
+                clip.playback_rate = rate;
+                clip.reverse = reverse;
+                clip.media_range.duration = (10.0 * rate) as Frame;
+            }
+            let original = clip_of(&node).clone();
+            let mut graph = TimelineGraph::default();
+            graph.tracks.push(TrackBinding {
+                id: TrackId::new(),
+                name: "V1".to_string(),
+                kind: TrackKind::Video,
+                node_ids: vec![node.id],
+                properties: Default::default(),
+            });
+            graph.nodes.insert(node.id, node.clone());
+
+            apply_command(
+                &mut graph,
+                TimelineCommand::SplitNode {
+                    node_id: node.id,
+                    frame: 104,
+                },
+            )
+            .unwrap();
+            let right_id = *graph.nodes.keys().find(|id| **id != node.id).unwrap();
+            apply_command(
+                &mut graph,
+                TimelineCommand::RippleTrim {
+                    node_id: right_id,
+                    edge: TrimEdge::End,
+                    delta: -2,
+                },
+            )
+            .unwrap();
+            let left = clip_of(&graph.nodes[&node.id]).clone();
+            let right = clip_of(&graph.nodes[&right_id]).clone();
+            for frame in 100..108 {
+                let piece = if frame < 104 { &left } else { &right };
+                assert_eq!(
+                    piece.media_time_at(frame),
+                    original.media_time_at(frame),
+                    "rate {rate} reverse {reverse} frame {frame}"
+                );
+            }
+
+            // A head trim keeps the start, so each frame shows the next one's media.
+            apply_command(
+                &mut graph,
+                TimelineCommand::RippleTrim {
+                    node_id: node.id,
+                    edge: TrimEdge::Start,
+                    delta: 1,
+                },
+            )
+            .unwrap();
+            let left = clip_of(&graph.nodes[&node.id]).clone();
+            for frame in 100..103 {
+                assert_eq!(
+                    left.media_time_at(frame),
+                    original.media_time_at(frame + 1),
+                    "rate {rate} reverse {reverse} frame {frame}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn freeze_and_ramp_derive_ranges_and_survive_trims() {
+        let node = clip_node(0, 10, 50);
+        let mut remap = TimeRemap::from_clip(clip_of(&node));
+        remap.insert_freeze(4, 6);
+        assert_eq!(remap.duration(), 16);
+        assert_eq!(remap.media_frame_at(4), Some(54.0));
+        assert_eq!(remap.media_frame_at(9), Some(54.0));
+        assert_eq!(remap.media_frame_at(11), Some(55.0));
+
+        let mut graph = TimelineGraph::default();
+        graph.tracks.push(TrackBinding {
+            id: TrackId::new(),
+            name: "V1".to_string(),
+            kind: TrackKind::Video,
+            node_ids: vec![node.id],
+            properties: Default::default(),
+        });
+        graph.nodes.insert(node.id, node.clone());
+        let inverse = apply_command(
+            &mut graph,
+            TimelineCommand::SetTimeRemap {
+                node_id: node.id,
+                remap: Some(remap),
+            },
+        )
+        .unwrap();
+        let clip = clip_of(&graph.nodes[&node.id]).clone();
+        assert_eq!(clip.timeline_range, FrameRange::new(0, 16));
+        assert_eq!(clip.media_range, FrameRange::new(50, 10));
+        assert_eq!(clip.media_time_at(12), Some(56.0));
+
+        // Trimming the head keeps every remaining frame on the same media.
+        apply_command(
+            &mut graph,
+            TimelineCommand::RippleTrim {
+                node_id: node.id,
+                edge: TrimEdge::Start,
+                delta: 6,
+            },
+        )
+        .unwrap();
+        let trimmed = clip_of(&graph.nodes[&node.id]).clone();
+        assert_eq!(trimmed.timeline_range, FrameRange::new(0, 10));
+        assert_eq!(trimmed.media_range, FrameRange::new(54, 6));
+        assert_eq!(trimmed.media_time_at(6), Some(56.0));
+
+        apply_command(
+            &mut graph,
+            TimelineCommand::RippleTrim {
+                node_id: node.id,
+                edge: TrimEdge::Start,
+                delta: -6,
+            },
+        )
+        .unwrap();
+        apply_command(&mut graph, inverse).unwrap();
+        assert_eq!(graph.nodes[&node.id], node);
+    }
+}
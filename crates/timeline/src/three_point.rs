@@ -0,0 +1,103 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{Frame, FrameRange};
+
+/// In and out points for a three-point edit. Any three of the four are
+/// enough; the missing one follows from the others.
+///
+/// When both source points are set they decide the duration and the record
+/// out point is ignored. With only one source point and no record out the
+/// edit runs to the end of the source.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct EditPoints {
+    pub source_in: Option<Frame>,
+    pub source_out: Option<Frame>,
+    pub record_in: Option<Frame>,
+    pub record_out: Option<Frame>,
+}
+
+impl EditPoints {
+    /// Source media range and the timeline frame it lands on, or `None` if
+    /// too few points are set or they describe an empty or negative range.
+    /// `source_len` is the length of the source media in frames.
+    pub fn resolve(&self, source_len: Frame) -> Option<(FrameRange, Frame)> {
+        let duration = match (
+            self.source_in,
+            self.source_out,
+            self.record_in,
+            self.record_out,
+        ) {
+            (Some(src_in), Some(src_out), _, _) => src_out - src_in,
+            (_, _, Some(rec_in), Some(rec_out)) => rec_out - rec_in,
+            (Some(src_in), None, _, _) => source_len - src_in,
+            (None, Some(src_out), _, _) => src_out,
+            (None, None, _, _) => source_len,
+        };
+        if duration < 1 {
+            return None;
+        }
+        let source_in = self
+            .source_in
+            .or_else(|| self.source_out.map(|out| out - duration))
+            .unwrap_or(0);
+        let record_in = self
+            .record_in
+            .or_else(|| self.record_out.map(|out| out - duration))?;
+        if source_in < 0 || record_in < 0 {
+            return None;
+        }
+        Some((FrameRange::new(source_in, duration), record_in))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn any_three_points_resolve_the_fourth() {
+        let source_pair = EditPoints {
+            source_in: Some(10),
+            source_out: Some(40),
+            record_in: Some(100),
+            record_out: None,
+        };
+        assert_eq!(
+            source_pair.resolve(500),
+            Some((FrameRange::new(10, 30), 100))
+        );
+
+        // Backtimed: the clip ends on the record out point.
+        let backtimed = EditPoints {
+            source_in: Some(10),
+            source_out: Some(40),
+            record_in: None,
+            record_out: Some(100),
+        };
+        assert_eq!(backtimed.resolve(500), Some((FrameRange::new(10, 30), 70)));
+
+        let record_pair = EditPoints {
+            source_in: None,
+            source_out: Some(60),
+            record_in: Some(0),
+            record_out: Some(20),
+        };
+        assert_eq!(record_pair.resolve(500), Some((FrameRange::new(40, 20), 0)));
+
+        let to_end = EditPoints {
+            source_in: Some(450),
+            record_in: Some(5),
+            ..EditPoints::default()
+        };
+        assert_eq!(to_end.resolve(500), Some((FrameRange::new(450, 50), 5)));
+
+        assert_eq!(EditPoints::default().resolve(500), None);
+        let inverted = EditPoints {
+            source_in: Some(40),
+            source_out: Some(10),
+            record_in: Some(0),
+            record_out: None,
+        };
+        assert_eq!(inverted.resolve(500), None);
+    }
+}
@@ -0,0 +1,498 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Frame, LaneId, NodeId, TimelineGraph, TimelineNodeKind, TrackId};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticSeverity {
+    Warning,
+    Error,
+}
+
+/// A single integrity problem found by [`TimelineGraph::validate`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "issue", rename_all = "snake_case")]
+pub enum GraphDiagnostic {
+    DanglingEdge {
+        from: NodeId,
+        to: NodeId,
+    },
+    MissingTrackNode {
+        track_id: TrackId,
+        node_id: NodeId,
+    },
+    DuplicateTrackNode {
+        track_id: TrackId,
+        node_id: NodeId,
+    },
+    DuplicateTrackId {
+        track_id: TrackId,
+    },
+    OverlappingNodes {
+        track_id: TrackId,
+        first: NodeId,
+        second: NodeId,
+        overlap: Frame,
+    },
+    InvalidRange {
+        node_id: NodeId,
+        reason: String,
+    },
+    DanglingAutomation {
+        lane_id: LaneId,
+        node_id: NodeId,
+    },
+    UnsortedKeyframes {
+        lane_id: LaneId,
+    },
+    DuplicateKeyframe {
+        lane_id: LaneId,
+        frame: Frame,
+    },
+    MissingSequence {
+        node_id: NodeId,
+        sequence_id: String,
+    },
+    /// A node with a timeline range that no track lists, so it never plays.
+    OrphanNode {
+        node_id: NodeId,
+    },
+    /// A problem inside the nested graph a compound clip shows.
+    InSequence {
+        sequence_id: String,
+        diagnostic: Box<GraphDiagnostic>,
+    },
+}
+
+impl GraphDiagnostic {
+    pub fn severity(&self) -> DiagnosticSeverity {
+        match self {
+            GraphDiagnostic::InSequence { diagnostic, .. } => diagnostic.severity(),
+            GraphDiagnostic::OverlappingNodes { .. }
+            | GraphDiagnostic::UnsortedKeyframes { .. }
+            | GraphDiagnostic::DuplicateTrackNode { .. }
+            | GraphDiagnostic::OrphanNode { .. } => DiagnosticSeverity::Warning,
+            _ => DiagnosticSeverity::Error,
+        }
+    }
+
+    /// Whether [`TimelineGraph::repair`] fixes this problem. Only fixes that
+    /// drop references to things that no longer exist, or restore ordering,
+    /// are considered safe; overlaps and bad ranges need a human.
+    pub fn is_repairable(&self) -> bool {
+        if let GraphDiagnostic::InSequence { diagnostic, .. } = self {
+            return diagnostic.is_repairable();
+        }
+        matches!(
+            self,
+            GraphDiagnostic::DanglingEdge { .. }
+                | GraphDiagnostic::MissingTrackNode { .. }
+                | GraphDiagnostic::DuplicateTrackNode { .. }
+                | GraphDiagnostic::DanglingAutomation { .. }
+                | GraphDiagnostic::UnsortedKeyframes { .. }
+                | GraphDiagnostic::DuplicateKeyframe { .. }
+        )
+    }
+}
+
+impl fmt::Display for GraphDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GraphDiagnostic::DanglingEdge { from, to } => {
+                write!(f, "edge {} -> {} references a missing node", from, to)
+            }
+            GraphDiagnostic::MissingTrackNode { track_id, node_id } => {
+                write!(f, "track {} lists missing node {}", track_id, node_id)
+            }
+            GraphDiagnostic::DuplicateTrackNode { track_id, node_id } => {
+                write!(
+                    f,
+                    "track {} lists node {} more than once",
+                    track_id, node_id
+                )
+            }
+            GraphDiagnostic::DuplicateTrackId { track_id } => {
+                write!(f, "track id {} is used by more than one track", track_id)
+            }
+            GraphDiagnostic::OverlappingNodes {
+                track_id,
+                first,
+                second,
+                overlap,
+            } => write!(
+                f,
+                "nodes {} and {} overlap by {} frames on track {}",
+                first, second, overlap, track_id
+            ),
+            GraphDiagnostic::InvalidRange { node_id, reason } => {
+                write!(f, "node {} has an invalid range: {}", node_id, reason)
+            }
+            GraphDiagnostic::DanglingAutomation { lane_id, node_id } => write!(
+                f,
+                "automation lane {} targets missing node {}",
+                lane_id, node_id
+            ),
+            GraphDiagnostic::UnsortedKeyframes { lane_id } => {
+                write!(f, "automation lane {} has keyframes out of order", lane_id)
+            }
+            GraphDiagnostic::DuplicateKeyframe { lane_id, frame } => write!(
+                f,
+                "automation lane {} has several keyframes at frame {}",
+                lane_id, frame
+            ),
+            GraphDiagnostic::MissingSequence {
+                node_id,
+                sequence_id,
+            } => write!(
+                f,
+                "compound clip {} references missing sequence {}",
+                node_id, sequence_id
+            ),
+            GraphDiagnostic::OrphanNode { node_id } => {
+                write!(f, "node {} is not on any track", node_id)
+            }
+            GraphDiagnostic::InSequence {
+                sequence_id,
+                diagnostic,
+            } => write!(f, "in sequence {}: {}", sequence_id, diagnostic),
+        }
+    }
+}
+
+impl TimelineGraph {
+    /// Check the graph's internal references and track layout, and those of
+    /// every nested sequence graph.
+    pub fn validate(&self) -> Vec<GraphDiagnostic> {
+        let mut out = self.validate_graph(self);
+        let mut sequence_ids: Vec<&String> = self.sequences.keys().collect();
+        sequence_ids.sort();
+        for sequence_id in sequence_ids {
+            let nested = self.sequences[sequence_id].validate_graph(self);
+            out.extend(nested.into_iter().map(|d| GraphDiagnostic::InSequence {
+                sequence_id: sequence_id.clone(),
+                diagnostic: Box::new(d),
+            }));
+        }
+        out
+    }
+
+    /// Checks for one graph. Compound clips resolve against `root`, whose
+    /// sequence map is the only one consulted.
+    fn validate_graph(&self, root: &TimelineGraph) -> Vec<GraphDiagnostic> {
+        let mut out = Vec::new();
+
+        for edge in &self.edges {
+            if !self.nodes.contains_key(&edge.from) || !self.nodes.contains_key(&edge.to) {
+                out.push(GraphDiagnostic::DanglingEdge {
+                    from: edge.from,
+                    to: edge.to,
+                });
+            }
+        }
+
+        let mut track_ids = HashSet::new();
+        for track in &self.tracks {
+            if !track_ids.insert(track.id) {
+                out.push(GraphDiagnostic::DuplicateTrackId { track_id: track.id });
+            }
+            let mut seen = HashSet::new();
+            let mut ranges = Vec::new();
+            for node_id in &track.node_ids {
+                let Some(node) = self.nodes.get(node_id) else {
+                    out.push(GraphDiagnostic::MissingTrackNode {
+                        track_id: track.id,
+                        node_id: *node_id,
+                    });
+                    continue;
+                };
+                if !seen.insert(*node_id) {
+                    out.push(GraphDiagnostic::DuplicateTrackNode {
+                        track_id: track.id,
+                        node_id: *node_id,
+                    });
+                    continue;
+                }
+                if let Some(range) = node.timeline_range() {
+                    ranges.push((range.start, range.end(), *node_id));
+                }
+            }
+            // Compare each node with the furthest-reaching one before it so a
+            // long clip is caught overlapping several short ones.
+            ranges.sort_by_key(|&(start, end, _)| (start, end));
+            let mut reach: Option<(Frame, NodeId)> = None;
+            for &(start, end, node_id) in &ranges {
+                if let Some((reach_end, owner)) = reach {
+                    if start < reach_end {
+                        out.push(GraphDiagnostic::OverlappingNodes {
+                            track_id: track.id,
+                            first: owner,
+                            second: node_id,
+                            overlap: reach_end.min(end) - start,
+                        });
+                    }
+                }
+                if reach.is_none_or(|(reach_end, _)| end > reach_end) {
+                    reach = Some((end, node_id));
+                }
+            }
+        }
+
+        let placed: HashSet<&NodeId> = self.tracks.iter().flat_map(|t| &t.node_ids).collect();
+        let mut node_ids: Vec<&NodeId> = self.nodes.keys().collect();
+        node_ids.sort_by_key(|id| id.0);
+        for node_id in node_ids {
+            let node = &self.nodes[node_id];
+            if let Some(range) = node.timeline_range() {
+                if !placed.contains(node_id) {
+                    out.push(GraphDiagnostic::OrphanNode { node_id: *node_id });
+                }
+                if range.duration < 1 {
+                    out.push(GraphDiagnostic::InvalidRange {
+                        node_id: *node_id,
+                        reason: format!("duration {}", range.duration),
+                    });
+                }
+            }
+            match &node.kind {
+                TimelineNodeKind::Clip(clip) if clip.media_range.start < 0 => {
+                    out.push(GraphDiagnostic::InvalidRange {
+                        node_id: *node_id,
+                        reason: format!("media starts at {}", clip.media_range.start),
+                    });
+                }
+                TimelineNodeKind::Compound(compound)
+                    if !root.sequences.contains_key(&compound.sequence_id) =>
+                {
+                    out.push(GraphDiagnostic::MissingSequence {
+                        node_id: *node_id,
+                        sequence_id: compound.sequence_id.clone(),
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        for lane in &self.automation {
+            if !self.nodes.contains_key(&lane.target.node) {
+                out.push(GraphDiagnostic::DanglingAutomation {
+                    lane_id: lane.id,
+                    node_id: lane.target.node,
+                });
+            }
+            if lane.keyframes.windows(2).any(|w| w[0].frame > w[1].frame) {
+                out.push(GraphDiagnostic::UnsortedKeyframes { lane_id: lane.id });
+            }
+            let mut frames = HashSet::new();
+            for keyframe in &lane.keyframes {
+                if !frames.insert(keyframe.frame) {
+                    out.push(GraphDiagnostic::DuplicateKeyframe {
+                        lane_id: lane.id,
+                        frame: keyframe.frame,
+                    });
+                }
+            }
+        }
+
+        out
+    }
+
+    /// Fix every repairable diagnostic in place and return the ones fixed.
+    /// Duplicate keyframes keep the last one listed, matching how
+    /// `InsertAutomationKeyframe` replaces an existing frame.
+    pub fn repair(&mut self) -> Vec<GraphDiagnostic> {
+        let fixed: Vec<GraphDiagnostic> = self
+            .validate()
+            .into_iter()
+            .filter(GraphDiagnostic::is_repairable)
+            .collect();
+        if fixed.is_empty() {
+            return fixed;
+        }
+
+        self.repair_graph();
+        for nested in self.sequences.values_mut() {
+            nested.repair_graph();
+        }
+        fixed
+    }
+
+    fn repair_graph(&mut self) {
+        let nodes = &self.nodes;
+        self.edges
+            .retain(|e| nodes.contains_key(&e.from) && nodes.contains_key(&e.to));
+        for track in &mut self.tracks {
+            let mut seen = HashSet::new();
+            track
+                .node_ids
+                .retain(|id| nodes.contains_key(id) && seen.insert(*id));
+        }
+        self.automation
+            .retain(|lane| nodes.contains_key(&lane.target.node));
+        for lane in &mut self.automation {
+            // Stable sort keeps the listed order within a frame, so reversing
+            // before dedup leaves the last keyframe for each frame.
+            lane.keyframes.sort_by_key(|k| k.frame);
+            lane.keyframes.reverse();
+            lane.keyframes.dedup_by_key(|k| k.frame);
+            lane.keyframes.reverse();
+        }
+        self.invalidate_index();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        AutomationKeyframe, AutomationLane, AutomationTarget, ClipNode, EdgeKind, FrameRange,
+        KeyframeEasing, TimelineEdge, TimelineNode, TrackBinding, TrackKind,
+    };
+    use serde_json::Value;
+
+    fn clip(start: Frame, duration: Frame) -> TimelineNode {
+        TimelineNode {
+            id: NodeId::new(),
+            label: None,
+            kind: TimelineNodeKind::Clip(ClipNode {
+                asset_id: None,
+                media_range: FrameRange::new(0, duration),
+                timeline_range: FrameRange::new(start, duration),
+                playback_rate: 1.0,
+                reverse: false,
+                time_remap: None,
+                markers: Vec::new(),
+                metadata: Value::Null,
+            }),
+            locked: false,
+            metadata: Value::Null,
+        }
+    }
+
+    fn keyframe(frame: Frame, value: f64) -> AutomationKeyframe {
+        AutomationKeyframe {
+            frame,
+            value,
+            easing: KeyframeEasing::Linear,
+        }
+    }
+
+    #[test]
+    fn validate_reports_and_repair_fixes_safe_problems() {
+        let a = clip(0, 20);
+        let b = clip(10, 20);
+        let ghost = NodeId::new();
+        let track_id = TrackId::new();
+        let lane_id = LaneId::new();
+        let mut graph = TimelineGraph {
+            nodes: [(a.id, a.clone()), (b.id, b.clone())].into_iter().collect(),
+            edges: vec![TimelineEdge {
+                from: a.id,
+                to: ghost,
+                kind: EdgeKind::Sequential,
+            }],
+            tracks: vec![TrackBinding {
+                id: track_id,
+                name: "V1".to_string(),
+                kind: TrackKind::Video,
+                node_ids: vec![a.id, ghost, b.id, a.id],
+                properties: Default::default(),
+            }],
+            automation: vec![
+                AutomationLane {
+                    id: lane_id,
+                    target: AutomationTarget {
+                        node: a.id,
+                        parameter: "opacity".to_string(),
+                    },
+                    interpolation: Default::default(),
+                    keyframes: vec![keyframe(10, 1.0), keyframe(0, 0.0), keyframe(10, 0.5)],
+                },
+                AutomationLane {
+                    id: LaneId::new(),
+                    target: AutomationTarget {
+                        node: ghost,
+                        parameter: "volume".to_string(),
+                    },
+                    interpolation: Default::default(),
+                    keyframes: Vec::new(),
+                },
+            ],
+            ..TimelineGraph::default()
+        };
+
+        let issues = graph.validate();
+        assert!(issues.contains(&GraphDiagnostic::OverlappingNodes {
+            track_id,
+            first: a.id,
+            second: b.id,
+            overlap: 10,
+        }));
+        assert!(issues.contains(&GraphDiagnostic::DuplicateKeyframe { lane_id, frame: 10 }));
+        assert_eq!(issues.len(), 7);
+
+        // A clip no track lists is reported but left alone.
+        let orphan = clip(40, 5);
+        graph.nodes.insert(orphan.id, orphan.clone());
+        let issues = graph.validate();
+        assert!(issues.contains(&GraphDiagnostic::OrphanNode { node_id: orphan.id }));
+        assert_eq!(issues.len(), 8);
+
+        let fixed = graph.repair();
+        assert_eq!(fixed.len(), 6);
+        assert!(graph.nodes.contains_key(&orphan.id));
+        graph.nodes.remove(&orphan.id);
+        assert!(graph.edges.is_empty());
+        assert_eq!(graph.tracks[0].node_ids, vec![a.id, b.id]);
+        assert_eq!(graph.automation.len(), 1);
+        assert_eq!(
+            graph.automation[0].keyframes,
+            vec![keyframe(0, 0.0), keyframe(10, 0.5)]
+        );
+        // Only the overlap is left for the user to resolve.
+        assert_eq!(graph.validate().len(), 1);
+        assert!(graph.repair().is_empty());
+    }
+
+    #[test]
+    fn nested_sequences_are_validated_and_repaired() {
+        let inner_clip = clip(0, 10);
+        let ghost = NodeId::new();
+        let inner_track = TrackId::new();
+        let inner = TimelineGraph {
+            nodes: [(inner_clip.id, inner_clip.clone())].into_iter().collect(),
+            tracks: vec![TrackBinding {
+                id: inner_track,
+                name: "V1".to_string(),
+                kind: TrackKind::Video,
+                node_ids: vec![inner_clip.id, ghost],
+                properties: Default::default(),
+            }],
+            ..TimelineGraph::default()
+        };
+        let mut graph = TimelineGraph {
+            sequences: [("nested".to_string(), inner)].into_iter().collect(),
+            ..TimelineGraph::default()
+        };
+
+        let missing = GraphDiagnostic::InSequence {
+            sequence_id: "nested".to_string(),
+            diagnostic: Box::new(GraphDiagnostic::MissingTrackNode {
+                track_id: inner_track,
+                node_id: ghost,
+            }),
+        };
+        assert_eq!(graph.validate(), vec![missing.clone()]);
+        assert_eq!(missing.severity(), DiagnosticSeverity::Error);
+        assert!(missing.is_repairable());
+
+        assert_eq!(graph.repair(), vec![missing]);
+        assert_eq!(
+            graph.sequences["nested"].tracks[0].node_ids,
+            vec![inner_clip.id]
+        );
+        assert!(graph.validate().is_empty());
+    }
+}